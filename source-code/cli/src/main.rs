@@ -1,17 +1,20 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use colored::Colorize;
-use hl_core::diagnostics::{parse_error_to_diag, DiagRenderer, DiagSummary, lint_source, lint_gen};
+use hl_core::diagnostics::{parse_error_to_diag, DiagRenderer, DiagSummary, lint_source, lint_gen, check_types, check_empty_bodies, check_unreachable};
+use hl_core::bench::{collect_benches, run_benches, BenchOptions};
+use hl_core::coverage::Coverage;
 use hl_core::env::Env;
+use hl_core::executor::OutputLimit;
 use hl_core::{check_source, run_source, cmd_clean_cache};
-use hl_core::{HL_MAX_GEN, HL_DEFAULT_GEN, parse_source_with_meta};
+use hl_core::{HL_MAX_GEN, HL_DEFAULT_GEN, parse_source, parse_source_with_meta};
 use hl_core::{
     cmd_env_create, cmd_env_enter, cmd_env_exit,
     cmd_env_remove, cmd_env_list, cmd_env_status, cmd_env_help,
-    load_config, config_path, get_active_env,
 };
 use hl_shell::{run_interactive, run_as_shell};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use tracing_subscriber::{EnvFilter, fmt};
 
 const HL_SCRIPTS_DIR: &str = "/usr/share/HackerOS/Scripts/Bin";
@@ -55,6 +58,7 @@ hl run --jit plik.hl Uruchom przez JIT pipeline (eksperymentalny)
 hl run plik.bc       Uruchom bytecode bezpośrednio przez JIT
 hl compile plik.hl   Kompiluj .hl → .bc (do katalogu źródłowego)
 hl clean             Wyczyść cache .bc (~/.hackeros/hacker-lang/cache/)
+hl cache-prune --older-than 7d  Usuń z cache wpisy .bc i .ast starsze niż 7 dni
 
 PRZYKŁADY:
 hl run skrypt.hl
@@ -76,6 +80,10 @@ struct Cli {
     #[arg(short, long, global = true)]
     verbose: bool,
 
+    /// Bez kolorów ANSI (jak NO_COLOR=1; domyślnie także gdy wyjście nie jest terminalem)
+    #[arg(long, global = true)]
+    no_color: bool,
+
     #[arg(short = 'c', long = "code", value_name = "CODE")]
     inline_code: Option<String>,
 }
@@ -88,6 +96,43 @@ enum Commands {
         /// Użyj JIT pipeline zamiast tree-walk (eksperymentalny)
         #[arg(long)]
         jit: bool,
+        /// Zapisz pokrycie linii w formacie LCOV (tylko tree-walk)
+        #[arg(long, value_name = "PLIK")]
+        coverage: Option<PathBuf>,
+        /// Limit bajtów wypisanych przez `~>`, np. 1m (jak HL_MAX_OUTPUT)
+        #[arg(long, value_name = "ROZMIAR")]
+        max_output: Option<String>,
+        /// Przepełnienie i dzielenie przez zero w `$( )` jako ArithmeticError (jak HL_CHECKED=1)
+        #[arg(long)]
+        checked: bool,
+        /// Przerwij pętlę `?~` / `@ x in` po N iteracjach z błędem (jak HL_MAX_LOOP)
+        #[arg(long, value_name = "N")]
+        max_loop: Option<u64>,
+        /// Dodatkowy katalog, w którym szukać `<< plik` / `<* katalog` (można powtarzać; jak HL_IMPORT_PATH)
+        #[arg(short = 'I', long = "import-path", value_name = "KATALOG")]
+        import_path: Vec<PathBuf>,
+        /// Zabroń komend pasujących do wzorca (`*`, `?`), np. `reboot` — błąd przed uruchomieniem (można powtarzać; jak HL_DENY)
+        #[arg(long, value_name = "WZORZEC")]
+        deny: Vec<String>,
+        /// `=>` zmienia prywatną kopię środowiska (widzą ją komendy potomne), nie środowisko procesu hl (jak HL_FROZEN_ENV=1)
+        #[arg(long)]
+        frozen_env: bool,
+        /// VM bytecode: przed każdą instrukcją wypisz ją na stderr z rejestrami i zmiennymi (jak HL_TRACE=1);
+        /// plik .hl idzie wtedy przez VM, jak z --jit
+        #[arg(long)]
+        trace: bool,
+        /// Jak --trace, ale czekaj na Enter po każdej instrukcji; `c` + Enter wyłącza pauzy (jak HL_TRACE=step)
+        #[arg(long)]
+        step: bool,
+        /// Po zakończeniu wypisz szczytową pamięć (RSS) procesu i komend potomnych
+        #[arg(long)]
+        profile_memory: bool,
+        /// Obserwuj skrypt i jego importy, uruchamiaj ponownie po każdej zmianie
+        #[arg(long)]
+        watch: bool,
+        /// Wypisz klucz cache .bc i jego wejścia (oraz klucze importów), bez uruchamiania
+        #[arg(long)]
+        dump_cache_key: bool,
         #[arg(last = true)]
         args: Vec<String>,
     },
@@ -99,6 +144,21 @@ enum Commands {
         shared: bool,
         #[arg(short, long)]
         output: Option<PathBuf>,
+        /// Wypisz IR bytecode przed optymalizacją
+        #[arg(long)]
+        dump_ir: bool,
+        /// Wypisz IR bytecode po optymalizacji
+        #[arg(long)]
+        dump_ir_after_opt: bool,
+        /// Powtarzalny .bc: czas kompilacji z SOURCE_DATE_EPOCH albo 0
+        #[arg(long)]
+        reproducible: bool,
+        /// Wypisz tabelę czasów faz kompilacji (parse, lower, optimize, write)
+        #[arg(long)]
+        measure: bool,
+        /// Zamiast .bc wypisz JSON: `metadata` — funkcje, zmienne globalne, importy, zależności
+        #[arg(long, value_name = "RODZAJ", value_parser = ["metadata"])]
+        emit: Option<String>,
     },
 
     /// Uruchom skrypt z /usr/share/HackerOS/Scripts/Bin/ po nazwie (bez .hl)
@@ -127,10 +187,49 @@ enum Commands {
         file: PathBuf,
         #[arg(long)]
         meta: bool,
+        /// Sprawdź zgodność wartości z adnotacjami typów (`% n: int = ...`)
+        #[arg(long)]
+        strict_types: bool,
+        /// Nie ostrzegaj o pustych pętlach, warunkach, `? try` i funkcjach
+        #[arg(long)]
+        allow_empty: bool,
+    },
+
+    /// Zmierz bloki `? bench` skryptu (tree-walk)
+    Bench {
+        file: PathBuf,
+        /// Liczba mierzonych przebiegów każdego bloku
+        #[arg(long, default_value_t = 20)]
+        iterations: u32,
+        /// Przebiegi rozgrzewkowe przed pomiarem
+        #[arg(long, default_value_t = 3)]
+        warmup: u32,
     },
 
-    /// Wydrukuj AST jako JSON
-    Ast { file: PathBuf },
+    /// Wydrukuj AST jako JSON (sam parsing — importy nie są rozwiązywane)
+    Ast {
+        file: PathBuf,
+        /// Wydrukuj strumień tokenów leksera z pozycjami zamiast AST
+        #[arg(long)]
+        tokens: bool,
+        /// Format dla narzędzi: lista `{file, nodes}` z markerami SourceLine przed instrukcjami
+        #[arg(long)]
+        lines: bool,
+        /// Jak --lines, razem z plikami importowanymi przez `<<` / `<*`
+        #[arg(long)]
+        imports: bool,
+    },
+
+    /// Wypisz komendy zewnętrzne skryptu i jego importów (bez uruchamiania)
+    Audit {
+        file: PathBuf,
+        /// Importy `<<` / `<*` najwyżej N poziomów w głąb (0 = tylko skrypt; domyślnie wszystkie)
+        #[arg(long, value_name = "N")]
+        depth: Option<usize>,
+    },
+
+    /// Porównaj dwie wersje skryptu: funkcje, importy, liczba instrukcji bloku głównego
+    Diff { old: PathBuf, new: PathBuf },
 
     /// Wyczyść cache bytecode + bibliotek
     Clean,
@@ -138,6 +237,20 @@ enum Commands {
     /// Informacje o cache bytecode
     CacheInfo,
 
+    /// Usuń z cache wpisy .bc i .ast starsze niż podany wiek
+    CachePrune {
+        /// Wiek: `90s`, `30m`, `12h`, `7d`
+        #[arg(long, value_name = "WIEK", value_parser = parse_age_arg)]
+        older_than: std::time::Duration,
+    },
+
+    /// Sprawdź narzędzia, katalogi i repozytorium bit; kod 1 przy brakach krytycznych
+    Doctor {
+        /// Bez sprawdzania połączenia z repozytorium bit
+        #[arg(long)]
+        offline: bool,
+    },
+
     /// Informacje o systemie bibliotek
     Lib {
         #[command(subcommand)]
@@ -192,11 +305,50 @@ fn main() -> Result<()> {
     check_hackeros_only();
 
     let cli = Cli::parse();
+    hl_core::color::init(cli.no_color);
 
     fmt().with_env_filter(
         if cli.verbose { EnvFilter::new("debug") } else { EnvFilter::new("warn") }
     ).without_time().compact().init();
 
+    // --max-output przez zmienną procesu — czyta ją zarówno tree-walk, jak i VM bytecode
+    if let Some(Commands::Run { max_output: Some(max), .. }) = &cli.command {
+        std::env::set_var("HL_MAX_OUTPUT", max);
+    }
+    if let Some(Commands::Run { checked: true, .. }) = &cli.command {
+        std::env::set_var("HL_CHECKED", "1");
+    }
+    if let Some(Commands::Run { max_loop: Some(n), .. }) = &cli.command {
+        std::env::set_var("HL_MAX_LOOP", n.to_string());
+    }
+    if let Some(Commands::Run { deny, .. }) = &cli.command {
+        if !deny.is_empty() {
+            // Wzorce mogą zawierać spacje — HL_DENY ma jeden na linię, dopisujemy do odziedziczonych
+            let mut all = std::env::var("HL_DENY").unwrap_or_default();
+            for pat in deny {
+                if !all.is_empty() { all.push('\n'); }
+                all.push_str(pat);
+            }
+            std::env::set_var("HL_DENY", all);
+        }
+    }
+    if let Some(Commands::Run { frozen_env: true, .. }) = &cli.command {
+        std::env::set_var("HL_FROZEN_ENV", "1");
+    }
+    if let Some(Commands::Run { trace, step, .. }) = &cli.command {
+        if *step { std::env::set_var("HL_TRACE", "step"); }
+        else if *trace { std::env::set_var("HL_TRACE", "1"); }
+    }
+    if let Some(Commands::Run { import_path, .. }) = &cli.command {
+        if !import_path.is_empty() {
+            // Katalogi z -I przed tymi z HL_IMPORT_PATH
+            let inherited = hl_core::import_path::import_roots_from_env();
+            if let Ok(joined) = std::env::join_paths(import_path.iter().chain(&inherited)) {
+                std::env::set_var("HL_IMPORT_PATH", joined);
+            }
+        }
+    }
+
     match cli.command {
 
         Some(Commands::Exec { name, args }) => {
@@ -207,8 +359,20 @@ fn main() -> Result<()> {
             cmd_search(&query);
         }
 
-        Some(Commands::Compile { file, shared: _, output }) => {
-            cmd_compile(&file, output.as_deref())?;
+        Some(Commands::Compile { file, emit: Some(_), .. }) => {
+            let source = std::fs::read_to_string(&file)?;
+            match hl_core::metadata::metadata_json(&source) {
+                Ok(json) => println!("{}", json),
+                Err(e) => {
+                    eprintln!("{} {}", "BŁĄD".red().bold(), e);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Some(Commands::Compile { file, shared: _, output, dump_ir, dump_ir_after_opt, reproducible, measure, emit: None }) => {
+            let opts = hl_compiler::CompileOptions { dump_ir, dump_optimized_ir: dump_ir_after_opt, reproducible };
+            cmd_compile(&file, output.as_deref(), &opts, measure)?;
         }
 
         Some(Commands::Docs) => run_docs(),
@@ -288,9 +452,44 @@ fn main() -> Result<()> {
         // ── hl run ───────────────────────────────────────────────────────────
         // Domyślnie: tree-walk interpreter (sprawdzony, poprawnie obsługuje @VAR)
         // --jit: eksperymentalny JIT pipeline (compile→cache→bytecode)
-        Some(Commands::Run { file, jit, args }) => {
-            let exit_code = if jit && file.extension().and_then(|e| e.to_str()) != Some("bc") {
-                // JIT pipeline — tylko gdy jawnie włączony i plik nie jest .bc
+        Some(Commands::Run { file, dump_cache_key: true, .. }) => {
+            std::process::exit(cmd_dump_cache_key(&file));
+        }
+
+        Some(Commands::Run { file, jit, watch: true, coverage, args, trace, step, .. }) => {
+            if coverage.is_some() {
+                eprintln!("{} --watch nie łączy się z --coverage", "BŁĄD".red().bold());
+                std::process::exit(2);
+            }
+            // --trace / --step śledzą instrukcje VM — .hl też przez VM
+            cmd_run_watch(&file, jit || trace || step, &args, cli.verbose);
+        }
+
+        Some(Commands::Run { file, jit, coverage: Some(report), args, profile_memory, trace, step, .. }) => {
+            // Pokrycie liczy tylko tree-walk — bytecode nie ma liczników linii
+            if jit || trace || step || file.extension().and_then(|e| e.to_str()) == Some("bc") {
+                eprintln!("{} --coverage działa tylko z interpreterem tree-walk (bez --jit, --trace i .bc)",
+                          "BŁĄD".red().bold());
+                std::process::exit(2);
+            }
+            let cov = Arc::new(Mutex::new(Coverage::new()));
+            let mut env = Env::new();
+            env.coverage = Some(cov.clone());
+            env.output_limit = OutputLimit::from_env().map(Arc::new);
+            inject_args(&mut env, &args);
+            let exit_code = run_file_with_diag(&file, &mut env, cli.verbose);
+            let lcov = cov.lock().unwrap_or_else(|e| e.into_inner()).to_lcov(&file.to_string_lossy());
+            if let Err(e) = std::fs::write(&report, lcov) {
+                eprintln!("{} Zapis raportu pokrycia {:?}: {}", "BŁĄD".red().bold(), report, e);
+                std::process::exit(1);
+            }
+            if profile_memory { report_memory(); }
+            std::process::exit(exit_code);
+        }
+
+        Some(Commands::Run { file, jit, coverage: None, args, profile_memory, trace, step, .. }) => {
+            let exit_code = if (jit || trace || step) && file.extension().and_then(|e| e.to_str()) != Some("bc") {
+                // JIT pipeline — gdy jawnie włączony (albo --trace / --step) i plik nie jest .bc
                 run_file_jit(&file, &args, cli.verbose)
             } else if file.extension().and_then(|e| e.to_str()) == Some("bc") {
                 // .bc plik — zawsze przez JIT interpreter
@@ -298,13 +497,19 @@ fn main() -> Result<()> {
             } else {
                 // Tree-walk interpreter — domyślny, stabilny
                 let mut env = Env::new();
+                env.output_limit = OutputLimit::from_env().map(Arc::new);
                 inject_args(&mut env, &args);
                 run_file_with_diag(&file, &mut env, cli.verbose)
             };
+            if profile_memory { report_memory(); }
             std::process::exit(exit_code);
         }
 
-        Some(Commands::Check { file, meta: show_meta }) => {
+        Some(Commands::Bench { file, iterations, warmup }) => {
+            std::process::exit(cmd_bench(&file, &BenchOptions { warmup, iterations }));
+        }
+
+        Some(Commands::Check { file, meta: show_meta, strict_types, allow_empty }) => {
             let source = std::fs::read_to_string(&file)?;
             let fname  = file.file_name().and_then(|n| n.to_str()).unwrap_or("<unknown>");
             let renderer = DiagRenderer::new(fname, &source);
@@ -312,6 +517,9 @@ fn main() -> Result<()> {
 
             let mut lint_diags = lint_source(&source);
             lint_diags.extend(lint_gen(&source));
+            if strict_types { lint_diags.extend(check_types(&source)); }
+            if !allow_empty { lint_diags.extend(check_empty_bodies(&source)); }
+            lint_diags.extend(check_unreachable(&source));
 
             if !lint_diags.is_empty() {
                 renderer.emit_all(&lint_diags);
@@ -342,7 +550,32 @@ fn main() -> Result<()> {
             std::process::exit(exit_code);
         }
 
-        Some(Commands::Ast { file }) => {
+        Some(Commands::Ast { file, tokens: true, .. }) => {
+            let source = std::fs::read_to_string(&file)?;
+            match hl_parser::Lexer::new(&source).tokenize_spanned() {
+                Ok(toks) => {
+                    for (line, col, tok) in toks {
+                        println!("{}  {:?}", format!("{}:{}", line, col).bright_black(), tok);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{} {}", "✗".red(), e);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Some(Commands::Ast { file, lines, imports, .. }) if lines || imports => {
+            match hl_core::ast_json::analyze_to_json(&file, imports) {
+                Ok(json) => println!("{}", json),
+                Err(errors) => {
+                    for e in errors { eprintln!("{} {}", "BŁĄD".red().bold(), e); }
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Some(Commands::Ast { file, .. }) => {
             let source = std::fs::read_to_string(&file)?;
             match check_source(&source) {
                 Ok(nodes) => println!("{}", serde_json::to_string_pretty(&nodes)?),
@@ -354,6 +587,20 @@ fn main() -> Result<()> {
             }
         }
 
+        Some(Commands::Audit { file, depth }) => {
+            match hl_core::audit::extract_commands_to_depth(&file, depth) {
+                Ok(sites) => print!("{}", hl_core::audit::render(&sites)),
+                Err(errors) => {
+                    for e in errors { eprintln!("{} {}", "BŁĄD".red().bold(), e); }
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Some(Commands::Diff { old, new }) => {
+            std::process::exit(cmd_diff(&old, &new));
+        }
+
         Some(Commands::Clean) => {
             cmd_clean_cache();
             match hl_compiler::cache::cache_clean_all() {
@@ -367,6 +614,18 @@ fn main() -> Result<()> {
             hl_jit::runner::print_cache_stats();
         }
 
+        Some(Commands::CachePrune { older_than }) => {
+            match hl_compiler::cache::cache_prune(older_than) {
+                Ok(n) if n > 0 => println!("{} Usunięto {} plików z cache.", "✓".green(), n),
+                Ok(_)          => println!("{}", "Brak wpisów do usunięcia.".bright_black()),
+                Err(e)         => { eprintln!("{} Błąd czyszczenia cache: {}", "✗".red(), e); std::process::exit(1); }
+            }
+        }
+
+        Some(Commands::Doctor { offline }) => {
+            std::process::exit(cmd_doctor(offline));
+        }
+
         Some(Commands::Lib { .. }) => {
             println!();
             println!("{}", "  Hacker Lang — system bibliotek".bright_cyan().bold());
@@ -417,9 +676,96 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// `--profile-memory` — na stderr, żeby nie mieszać się z wyjściem skryptu
+fn report_memory() {
+    eprintln!("{} {}", "[hl]".bright_black(), hl_core::memstat::peak_memory().render());
+}
+
+// ── hl diff ───────────────────────────────────────────────────────────────────
+
+/// 0 — bez zmian, 1 — są różnice, 2 — któregoś pliku nie da się wczytać
+fn cmd_diff(old: &Path, new: &Path) -> i32 {
+    let load = |path: &Path| -> Option<hl_core::outline::Outline> {
+        let source = match std::fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) => { eprintln!("{} {}: {}", "BŁĄD".red().bold(), path.display(), e); return None; }
+        };
+        match check_source(&source) {
+            Ok(nodes) => Some(hl_core::outline::outline(&nodes)),
+            Err(e) => {
+                let fname = path.file_name().and_then(|n| n.to_str()).unwrap_or("<unknown>");
+                DiagRenderer::new(fname, &source).emit(&parse_error_to_diag(&e));
+                None
+            }
+        }
+    };
+    let (Some(a), Some(b)) = (load(old), load(new)) else { return 2 };
+    let diff = hl_core::outline::diff_outline(&a, &b);
+    for line in diff.render().lines() {
+        match line.chars().next() {
+            Some('+') => println!("{}", line.green()),
+            Some('-') => println!("{}", line.red()),
+            Some('~') => println!("{}", line.yellow()),
+            _         => println!("{}", line.bright_black()),
+        }
+    }
+    if diff.is_empty() { 0 } else { 1 }
+}
+
+// ── hl doctor ─────────────────────────────────────────────────────────────────
+
+fn cmd_doctor(offline: bool) -> i32 {
+    use hl_core::doctor::{self, Status};
+    let checks = doctor::run_all(offline);
+    println!("{}", "=== hl doctor ===".bright_cyan().bold());
+    for c in &checks {
+        let mark = match c.status {
+            Status::Ok   => "✓".green(),
+            Status::Warn => "!".yellow(),
+            Status::Fail => "✗".red(),
+        };
+        println!("  {} {:<18} {}", mark, c.name, c.detail.bright_black());
+        if let Some(hint) = &c.hint {
+            println!("      {} {}", "→".bright_black(), hint);
+        }
+    }
+    let fails = checks.iter().filter(|c| c.status == Status::Fail).count();
+    let warns = checks.iter().filter(|c| c.status == Status::Warn).count();
+    if doctor::has_failures(&checks) {
+        println!("{} {} krytycznych, {} ostrzeżeń", "BŁĄD".red().bold(), fails, warns);
+        1
+    } else {
+        println!("{} {} ostrzeżeń", "OK".green().bold(), warns);
+        0
+    }
+}
+
 // ── hl compile ────────────────────────────────────────────────────────────────
 
-fn cmd_compile(file: &Path, output: Option<&Path>) -> Result<()> {
+/// `--older-than` dla clap — błąd zamiast cichego zera przy złym formacie
+fn parse_age_arg(s: &str) -> Result<std::time::Duration, String> {
+    hl_compiler::cache::parse_age(s).ok_or_else(|| format!("zły wiek '{}' — użyj np. 90s, 30m, 12h, 7d", s))
+}
+
+/// `hl run --dump-cache-key` — skąd bierze się nazwa pliku w cache
+fn cmd_dump_cache_key(file: &Path) -> i32 {
+    let source = match std::fs::read_to_string(file) {
+        Ok(s)  => s,
+        Err(e) => { eprintln!("{} {}: {}", "BŁĄD".red().bold(), file.display(), e); return 1; }
+    };
+    let key = hl_compiler::CacheKey::new(&source, file, &hl_compiler::CompileOptions::default());
+    print!("{}", key.render());
+    // Importy nie wchodzą do .bc, ale mają własne wpisy w cache AST
+    for path in hl_core::watch::watched_paths(file).iter().skip(1) {
+        match std::fs::read_to_string(path) {
+            Ok(src) => println!("import:       {} (ast {:016x})", path.display(), hl_core::ast_cache::key(&src)),
+            Err(_)  => println!("import:       {} (brak pliku)", path.display()),
+        }
+    }
+    0
+}
+
+fn cmd_compile(file: &Path, output: Option<&Path>, opts: &hl_compiler::CompileOptions, measure: bool) -> Result<()> {
     if !file.exists() {
         eprintln!("{} Plik nie istnieje: {}", "BŁĄD".red().bold(), file.display());
         std::process::exit(1);
@@ -434,13 +780,15 @@ fn cmd_compile(file: &Path, output: Option<&Path>) -> Result<()> {
                       file.display().to_string().bright_white());
 
             let t0 = std::time::Instant::now();
-            match hl_compiler::compile_hl_to_bc(file, output) {
+            let mut timings = hl_compiler::PhaseTimings::new();
+            match hl_compiler::compile_hl_to_bc_timed(file, output, opts, &mut timings) {
                 Ok(bc_path) => {
                     let elapsed = t0.elapsed();
                     println!("{} {} ({:.1}ms)",
                              "✓".green().bold(),
                              bc_path.display().to_string().bright_white(),
                              elapsed.as_secs_f64() * 1000.0);
+                    if measure { eprint!("{}", timings.render()); }
                 }
                 Err(e) => {
                     eprintln!("{} {}", "BŁĄD kompilacji:".red().bold(), e);
@@ -491,6 +839,48 @@ fn run_file_jit(file: &Path, args: &[String], _verbose: bool) -> i32 {
     }
 }
 
+// ── hl run --watch ────────────────────────────────────────────────────────────
+
+/// Każdy przebieg to osobny proces `hl run` — `exit N`, panika ani stan zmiennych
+/// ze skryptu nie przerywają pętli. Błąd parsowania wypisuje dziecko, my czekamy dalej.
+fn cmd_run_watch(file: &Path, jit: bool, args: &[String], verbose: bool) -> ! {
+    use hl_core::watch::FileWatcher;
+
+    let exe = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("hl"));
+    let mut last_source: Option<String> = None;
+    let mut run_once = |changed: &[PathBuf]| {
+        for p in changed {
+            eprintln!("{} {}", "zmiana:".bright_yellow().bold(), p.display());
+        }
+        // Wpis cache dla poprzedniej treści jest już nieaktualny — nie zostawiamy go
+        if let Some(src) = last_source.take() {
+            if let Err(e) = hl_compiler::cache_evict(&src, file) {
+                tracing::warn!("cache_evict: {}", e);
+            }
+        }
+        if jit { last_source = std::fs::read_to_string(file).ok(); }
+
+        let mut cmd = std::process::Command::new(&exe);
+        if verbose { cmd.arg("--verbose"); }
+        // Dziecko dziedziczy terminal, ale nie decyzję o kolorach z flagi
+        if !hl_core::color::enabled() { cmd.arg("--no-color"); }
+        cmd.arg("run");
+        if jit { cmd.arg("--jit"); }
+        cmd.arg(file).arg("--").args(args);
+        let code = match cmd.status() {
+            Ok(st) => st.code().unwrap_or(1),
+            Err(e) => { eprintln!("{} Uruchomienie {:?}: {}", "BŁĄD".red().bold(), exe, e); 1 }
+        };
+        let status = if code == 0 { "OK".green().bold() } else { format!("exit {}", code).red().bold() };
+        eprintln!("{} {} — czekam na zmiany (Ctrl+C kończy)", "[watch]".bright_magenta().bold(), status);
+    };
+
+    let mut watcher = FileWatcher::new(file);
+    run_once(&[]);
+    watcher.rescan();
+    watcher.run(&mut run_once)
+}
+
 // ── hl exec ───────────────────────────────────────────────────────────────────
 
 fn cmd_exec(name: &str, args: &[String], verbose: bool) -> i32 {
@@ -650,11 +1040,48 @@ fn run_source_with_diag(fname: &str, source: &str, env: &mut Env) -> i32 {
     }
 }
 
-fn inject_args(env: &mut Env, args: &[String]) {
-    env.set_var("argc", hl_core::Value::Number(args.len() as f64));
-    for (i, arg) in args.iter().enumerate() {
-        env.set_var(&format!("arg{}", i), hl_core::Value::String(arg.clone()));
+fn cmd_bench(file: &Path, opts: &BenchOptions) -> i32 {
+    let source = match std::fs::read_to_string(file) {
+        Ok(s)  => s,
+        Err(e) => { eprintln!("{} {}: {}", "BŁĄD".red().bold(), file.display(), e); return 1; }
+    };
+    let nodes = match parse_source(&source) {
+        Ok(n)  => n,
+        Err(e) => {
+            let fname = file.file_name().and_then(|n| n.to_str()).unwrap_or("<unknown>");
+            DiagRenderer::new(fname, &source).emit(&parse_error_to_diag(&e));
+            return 2;
+        }
+    };
+    if collect_benches(&nodes).is_empty() {
+        eprintln!("{} Brak bloków `? bench` w {}", "hl bench:".bright_magenta().bold(), file.display());
+        return 1;
     }
+    let mut env = Env::new();
+    let reports = match run_benches(&nodes, &mut env, opts) {
+        Ok(r)  => r,
+        Err(e) => { eprintln!("{} {}", "BŁĄD".red().bold(), e); return 1; }
+    };
+
+    println!("{}", format!("=== Benchmarki: {} ===", file.display()).bright_cyan().bold());
+    println!("  {} przebiegów, {} rozgrzewkowych", opts.iterations.max(1), opts.warmup);
+    println!();
+    let ms = |d: std::time::Duration| format!("{:.3} ms", d.as_secs_f64() * 1000.0);
+    let mut failed = false;
+    for r in &reports {
+        println!("  {:<24} średnia {}  mediana {}  min {}",
+                 r.name.bright_white().bold(), ms(r.mean()).bright_green(),
+                 ms(r.median()), ms(r.min()));
+        if r.last_exit != 0 {
+            failed = true;
+            println!("  {:<24} {}", "", format!("ostatni przebieg zakończony kodem {}", r.last_exit).yellow());
+        }
+    }
+    if failed { 1 } else { 0 }
+}
+
+fn inject_args(env: &mut Env, args: &[String]) {
+    env.set_script_args(args);
 }
 
 fn inject_args_env(env: &mut Env, args: &[String]) {
@@ -707,7 +1134,8 @@ fn print_version() {
     println!("  hl run plik.bc        -- uruchom .bc przez JIT");
     println!("  hl run --jit plik.hl  -- JIT pipeline (eksperymentalny)");
     println!("  hl clean              -- wyczyść cache .bc");
-    println!("  hl cache-info         -- statystyki cache .bc");
+    println!("  hl cache-info         -- statystyki cache .bc i .ast");
+    println!("  hl cache-prune --older-than 7d -- usuń starsze wpisy .bc i .ast");
     println!();
     println!("{}", "Arena Functions (gen 2):".bright_yellow());
     println!("  {}  -- zdefiniuj z areną 4k", ":: fn <4k> def ... done".bright_cyan());
@@ -20,6 +20,11 @@ pub struct ConstPool {
     pub strings:      Vec<String>,
     pub numbers:      Vec<f64>,
     pub bools:        Vec<bool>,
+    /// Stringi przeniesione do wspólnej puli cache (patrz `string_pool`).
+    /// Puste poza plikami zapisanymi z HL_SHARED_POOL=1.
+    pub shared_refs:  Vec<SharedStrRef>,
+    /// Wersja puli wspólnej, względem której zapisano `shared_refs` (`strings-<id>.pool`)
+    pub shared_pool_id: u64,
     // Indeksy do szybkiej deduplikacji — nie serializowane (rebuilt przy load)
     #[serde(skip)]
     str_index:    std::collections::HashMap<String, ConstIdx>,
@@ -27,6 +32,15 @@ pub struct ConstPool {
     num_index:    std::collections::HashMap<u64, ConstIdx>,
 }
 
+/// Slot lokalnej puli, którego treść leży we wspólnej puli stringów
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedStrRef {
+    pub local:  ConstIdx,
+    pub shared: u32,
+    /// Hash treści — wykrywa pulę nadpisaną przez inny proces
+    pub hash:   u64,
+}
+
 impl ConstPool {
     /// Dodaj lub zdeduplikuj string — O(1) amortyzowane
     pub fn add_str(&mut self, s: impl Into<String>) -> ConstIdx {
@@ -113,13 +127,23 @@ pub struct HlBcHeader {
     pub hl_version:  String,
 }
 
+/// SOURCE_DATE_EPOCH (reproducible-builds.org) — jeśli ustawione, zastępuje zegar
+pub fn source_date_epoch() -> Option<u64> {
+    std::env::var("SOURCE_DATE_EPOCH").ok()?.trim().parse().ok()
+}
+
+/// Czas kompilacji do nagłówka: SOURCE_DATE_EPOCH albo zegar systemowy
+pub fn compile_time() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    source_date_epoch().unwrap_or_else(|| SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or(0))
+}
+
 impl HlModule {
     pub fn new(source_path: &str, gen: u32) -> Self {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
+        let now = compile_time();
         Self {
             header: HlBcHeader {
                 hl_gen: gen,
@@ -170,6 +194,12 @@ pub enum Instruction {
     Div  { dst: Reg, a: Reg, b: Reg },
     Mod  { dst: Reg, a: Reg, b: Reg },
     Neg  { dst: Reg, src: Reg },
+    /// `& | ^ << >>` — operandy całkowite (przesunięcie 0..63), inaczej TypeError
+    BitAnd { dst: Reg, a: Reg, b: Reg },
+    BitOr  { dst: Reg, a: Reg, b: Reg },
+    BitXor { dst: Reg, a: Reg, b: Reg },
+    Shl    { dst: Reg, a: Reg, b: Reg },
+    Shr    { dst: Reg, a: Reg, b: Reg },
 
     // ── Porównania ───────────────────────────────────────────────
     CmpEq  { dst: Reg, a: Reg, b: Reg },
@@ -206,6 +236,8 @@ pub enum Instruction {
     CallFunc    { name: ConstIdx },
     /// wywołaj quick-function (::upper itd.)
     CallQuick   { name: ConstIdx, arg: Reg, dst: Reg },
+    /// funkcja matematyczna z `$( sqrt(@x) )` (hl_parser::math); błąd dziedziny → nil
+    MathCall    { name: ConstIdx, args: Vec<Reg>, dst: Reg },
 
     // ── Komendy systemowe ────────────────────────────────────────
     /// uruchom komendę; dst = exit_code (i32 jako f64)
@@ -214,6 +246,9 @@ pub enum Instruction {
         mode: CmdMode,
         dst:  Reg,      // exit_code
     },
+    /// `>` ze stałym tekstem — argv podzielony raz przy kompilacji; `cmd` to pełny
+    /// tekst komendy (dla `--deny` i komunikatów), dst = exit_code
+    ExecArgv    { cmd: ConstIdx, argv: Vec<ConstIdx>, dst: Reg },
     /// jak ExecCmd ale przechwytuje stdout → dst_out
     ExecCapture {
         cmd:     Reg,
@@ -221,14 +256,42 @@ pub enum Instruction {
         dst_ec:  Reg,
         dst_out: Reg,
     },
+    /// `@dane |> cmd` — string z `input` na stdin komendy (tryb jak `>>`);
+    /// dst_out = None → stdout komendy idzie na terminal
+    ExecFeed {
+        cmd:     Reg,
+        input:   Reg,
+        dst_ec:  Reg,
+        dst_out: Option<Reg>,
+    },
     /// wypisz na stdout
     Print       { src: Reg },
 
     // ── Pętle ────────────────────────────────────────────────────
-    /// for-in: iteruj po słowach w src; iterator state w rejestrze iter_reg
-    ForInStart  { iter_reg: Reg, src: Reg },
+    /// for-in: iteruj po słowach (albo znakach, gdy `chars`) w src; iterator state w rejestrze iter_reg
+    ForInStart  { iter_reg: Reg, src: Reg, chars: bool },
     /// for-in next: dst = następne słowo lub skocz do end_off
     ForInNext   { iter_reg: Reg, dst: Reg, end_off: InsnOff },
+    /// początek iteracji pętli: counter += 1, powyżej limitu (HL_MAX_LOOP) błąd z `line`;
+    /// counter zeruje LoadNum przed pętlą
+    LoopGuard   { counter: Reg, line: u32 },
+
+    // ── Katalog tymczasowy ───────────────────────────────────────
+    /// `? tempdir @d` — nowy katalog tymczasowy jako cwd, ścieżka → zmienna `name`
+    TempDirEnter { name: ConstIdx },
+    /// koniec bloku `? tempdir` — poprzedni cwd wraca, katalog jest usuwany
+    TempDirLeave,
+
+    // ── Try/catch ────────────────────────────────────────────────
+    /// `? try` — błąd do końca bloku (TryLeave) przenosi wykonanie pod `handler`
+    TryEnter     { handler: InsnOff },
+    /// koniec ciała `? try` bez błędu — zdejmij handler
+    TryLeave,
+    /// ramię `? catch kind @var`: błąd innego rodzaju → skocz do `next`;
+    /// pasujący ląduje w zmiennej `var` (i zawsze w `_error` / `_error_kind`)
+    CatchArm     { kind: Option<ConstIdx>, var: Option<ConstIdx>, next: InsnOff },
+    /// żadne ramię nie pasowało — zgłoś złapany błąd dalej
+    Rethrow,
 
     // ── HackerOS API ─────────────────────────────────────────────
     /// wywołaj narzędzie HackerOS; args_reg = string argumentów
@@ -251,4 +314,6 @@ pub enum CmdMode {
     WithVars,
     WithVarsSudo,
     WithVarsIsolated,
+    /// `>` — argv bez powłoki, bez rozwijania `$VAR`/glob/`|`
+    Raw,
 }
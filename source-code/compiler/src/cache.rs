@@ -1,5 +1,9 @@
 use anyhow::Result;
-use std::path::PathBuf;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::{CompileOptions, BC_VERSION};
+use crate::string_pool::{gc_pool_versions, pool_version_id, POOL_GC_GRACE};
 
 pub const CACHE_MAX_FILES: usize = 30;
 pub const CACHE_DIR_NAME: &str = ".hackeros/hacker-lang/cache";
@@ -14,6 +18,86 @@ pub fn bc_cache_path(hash: &str) -> PathBuf {
     cache_dir().join(format!("{}.bc", hash))
 }
 
+/// Plik obok `<hash>.bc` ze ścieżką źródła — żeby `hl cache-info` mogło pokazać,
+/// czyj to wpis (z samego hasha nie da się tego odtworzyć)
+pub fn sidecar_path(bc_path: &Path) -> PathBuf {
+    bc_path.with_extension("src")
+}
+
+pub fn write_sidecar(bc_path: &Path, source_path: &Path) -> Result<()> {
+    std::fs::write(sidecar_path(bc_path), source_path.display().to_string())?;
+    Ok(())
+}
+
+/// Usuń wpis cache razem z jego plikiem `.src`
+pub fn remove_entry(bc_path: &Path) -> Result<()> {
+    std::fs::remove_file(bc_path)?;
+    let _ = std::fs::remove_file(sidecar_path(bc_path));
+    Ok(())
+}
+
+/// Wszystko, od czego zależy treść .bc w cache — `hl run --dump-cache-key` to wypisuje.
+///
+/// Opcje tylko diagnostyczne (`--dump-ir`, `--verbose`) celowo tu nie trafiają:
+/// ten sam skrypt z nimi i bez nich ma trafiać w ten sam wpis.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    pub source_hash:  u64,
+    pub source_path:  String,
+    pub bc_version:   u32,
+    /// `HL_SHARED_POOL=1` — stałe przeniesione do wspólnej puli, inny plik
+    pub shared_pool:  bool,
+    pub reproducible: bool,
+}
+
+impl CacheKey {
+    pub fn new(source: &str, source_path: &Path, opts: &CompileOptions) -> Self {
+        CacheKey {
+            source_hash:  fnv1a(FNV_OFFSET, source.as_bytes()),
+            source_path:  source_path.display().to_string(),
+            bc_version:   BC_VERSION,
+            shared_pool:  crate::shared_pool_enabled(),
+            reproducible: opts.reproducible,
+        }
+    }
+
+    /// FNV-1a po wszystkich wejściach — stabilny między procesami
+    pub fn hash(&self) -> u64 {
+        let mut h = fnv1a(FNV_OFFSET, &self.source_hash.to_le_bytes());
+        h = fnv1a(h, self.source_path.as_bytes());
+        h = fnv1a(h, &self.bc_version.to_le_bytes());
+        fnv1a(h, &[self.shared_pool as u8, self.reproducible as u8])
+    }
+
+    pub fn path(&self) -> PathBuf {
+        bc_cache_path(&format!("{:016x}", self.hash()))
+    }
+
+    /// Klucz i jego wejścia, po jednym w linii
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "klucz:        {:016x}", self.hash());
+        let _ = writeln!(out, "plik:         {}", self.path().display());
+        let _ = writeln!(out, "source_hash:  {:016x}", self.source_hash);
+        let _ = writeln!(out, "source_path:  {}", self.source_path);
+        let _ = writeln!(out, "bc_version:   {}", self.bc_version);
+        let _ = writeln!(out, "shared_pool:  {}", self.shared_pool);
+        let _ = writeln!(out, "reproducible: {}", self.reproducible);
+        out
+    }
+}
+
+const FNV_OFFSET: u64 = 14695981039346656037;
+const FNV_PRIME:  u64 = 1099511628211;
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
 pub fn ensure_cache_dir() -> Result<()> {
     let dir = cache_dir();
     if !dir.exists() {
@@ -22,12 +106,21 @@ pub fn ensure_cache_dir() -> Result<()> {
     Ok(())
 }
 
-/// Jeśli liczba plików .bc w cache > CACHE_MAX_FILES, usuń najstarsze
+/// Jeśli liczba plików .bc w cache > CACHE_MAX_FILES, usuń najstarsze.
+/// Wersje puli wspólnej, których nie wskazuje już żaden .bc, idą razem z nimi.
 pub fn cache_cleanup_if_needed() -> Result<()> {
-    let dir = cache_dir();
+    cleanup_dir(&cache_dir(), CACHE_MAX_FILES, POOL_GC_GRACE)
+}
+
+fn cleanup_dir(dir: &Path, max_files: usize, pool_grace: Duration) -> Result<()> {
     if !dir.exists() { return Ok(()); }
+    trim_dir(dir, max_files)?;
+    gc_pool_versions(dir, pool_grace)?;
+    Ok(())
+}
 
-    let mut entries: Vec<(std::time::SystemTime, PathBuf)> = std::fs::read_dir(&dir)?
+fn trim_dir(dir: &Path, max_files: usize) -> Result<()> {
+    let mut entries: Vec<(std::time::SystemTime, PathBuf)> = std::fs::read_dir(dir)?
     .flatten()
     .filter_map(|e| {
         let path = e.path();
@@ -37,17 +130,17 @@ pub fn cache_cleanup_if_needed() -> Result<()> {
     })
     .collect();
 
-    if entries.len() <= CACHE_MAX_FILES {
+    if entries.len() <= max_files {
         return Ok(());
     }
 
     // Posortuj od najstarszych
     entries.sort_by_key(|(t, _)| *t);
 
-    let to_remove = entries.len() - CACHE_MAX_FILES;
+    let to_remove = entries.len() - max_files;
     for (_, path) in entries.iter().take(to_remove) {
         tracing::debug!("cache cleanup: usuwam {:?}", path);
-        let _ = std::fs::remove_file(path);
+        let _ = remove_entry(path);
     }
 
     tracing::info!("cache cleanup: usunięto {} starych plików .bc", to_remove);
@@ -56,46 +149,239 @@ pub fn cache_cleanup_if_needed() -> Result<()> {
 
 /// Wyczyść cały cache
 pub fn cache_clean_all() -> Result<usize> {
-    let dir = cache_dir();
+    clean_dir(&cache_dir())
+}
+
+/// Usuń katalog cache (z cache AST i pulą wspólną); zwraca liczbę usuniętych plików .bc
+fn clean_dir(dir: &Path) -> Result<usize> {
     if !dir.exists() { return Ok(0); }
+    let count = list_dir(dir)?.iter().filter(|e| e.kind == CacheKind::Bytecode).count();
+    std::fs::remove_dir_all(dir)?;
+    Ok(count)
+}
 
-    let count = std::fs::read_dir(&dir)?
-    .flatten()
-    .filter(|e| {
-        e.path().extension().and_then(|x| x.to_str()) == Some("bc")
-    })
-    .count();
+/// `hl cache-prune --older-than 7d` — usuń wpisy .bc i .ast starsze niż `older_than`
+/// oraz wersje puli, których nie wskazuje już żaden .bc; zwraca liczbę usuniętych plików
+pub fn cache_prune(older_than: Duration) -> Result<usize> {
+    prune_dir(&cache_dir(), older_than)
+}
 
-    std::fs::remove_dir_all(&dir)?;
-    Ok(count)
+fn prune_dir(dir: &Path, older_than: Duration) -> Result<usize> {
+    let mut removed = 0;
+    for entry in list_dir(dir)? {
+        if entry.age() <= older_than { continue; }
+        match entry.kind {
+            CacheKind::Bytecode => remove_entry(&entry.path)?,
+            CacheKind::Ast      => std::fs::remove_file(&entry.path)?,
+            // Starą wersję może nadal wskazywać świeży .bc — o tym decyduje gc niżej
+            CacheKind::PoolVersion => continue,
+        }
+        removed += 1;
+    }
+    removed += gc_pool_versions(dir, POOL_GC_GRACE)?;
+    Ok(removed)
 }
 
 /// Wylistuj pliki cache ze statystykami
 pub fn cache_list() -> Result<Vec<CacheEntry>> {
-    let dir = cache_dir();
+    list_dir(&cache_dir())
+}
+
+/// Wpisy .bc, wersje puli `strings-<id>.pool` i drzewa AST z `ast/*.ast`, od najnowszych
+fn list_dir(dir: &Path) -> Result<Vec<CacheEntry>> {
     if !dir.exists() { return Ok(vec![]); }
 
-    let mut entries: Vec<CacheEntry> = std::fs::read_dir(&dir)?
+    let mut entries = scan_dir(dir, |path| {
+        if path.extension().and_then(|x| x.to_str()) == Some("bc") { return Some(CacheKind::Bytecode); }
+        pool_version_id(path).map(|_| CacheKind::PoolVersion)
+    })?;
+    let ast = dir.join("ast");
+    if ast.is_dir() {
+        entries.extend(scan_dir(&ast, |path| {
+            (path.extension().and_then(|x| x.to_str()) == Some("ast")).then_some(CacheKind::Ast)
+        })?);
+    }
+
+    entries.sort_by_key(|e| std::cmp::Reverse(e.modified));
+    Ok(entries)
+}
+
+fn scan_dir(dir: &Path, kind_of: impl Fn(&Path) -> Option<CacheKind>) -> Result<Vec<CacheEntry>> {
+    Ok(std::fs::read_dir(dir)?
     .flatten()
     .filter_map(|e| {
         let path = e.path();
-        if path.extension().and_then(|x| x.to_str()) != Some("bc") { return None; }
+        let kind = kind_of(&path)?;
         let meta = e.metadata().ok()?;
         let mtime = meta.modified().ok()?;
         Some(CacheEntry {
+            source:   (kind == CacheKind::Bytecode).then(|| std::fs::read_to_string(sidecar_path(&path)).ok()).flatten(),
             path,
-            size: meta.len(),
-             modified: mtime,
+            kind,
+            size:     meta.len(),
+            modified: mtime,
         })
     })
-    .collect();
+    .collect())
+}
 
-    entries.sort_by(|a, b| b.modified.cmp(&a.modified));
-    Ok(entries)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheKind {
+    /// `<hash>.bc` (z plikiem `.src` obok)
+    Bytecode,
+    /// `strings-<id>.pool` — niezmienna wersja puli wspólnej
+    PoolVersion,
+    /// `ast/<hash>.ast` — drzewo importu z hl_core::ast_cache
+    Ast,
 }
 
 pub struct CacheEntry {
     pub path:     PathBuf,
+    pub kind:     CacheKind,
     pub size:     u64,
     pub modified: std::time::SystemTime,
+    /// Ścieżka źródła z pliku `.src` (None dla wpisów sprzed jego wprowadzenia i innych niż .bc)
+    pub source:   Option<String>,
+}
+
+impl CacheEntry {
+    pub fn age(&self) -> Duration {
+        self.modified.elapsed().unwrap_or_default()
+    }
+}
+
+/// Wiek jak `90s`, `30m`, `12h`, `7d`
+pub fn parse_age(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let unit = s.chars().last()?;
+    let n: u64 = s[..s.len() - unit.len_utf8()].parse().ok()?;
+    let per_unit = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        _   => return None,
+    };
+    n.checked_mul(per_unit).map(Duration::from_secs)
+}
+
+/// Wiek w największej pełnej jednostce: `45s`, `3m`, `5h`, `2d`
+pub fn format_age(age: Duration) -> String {
+    match age.as_secs() {
+        s if s < 60    => format!("{}s", s),
+        s if s < 3600  => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h", s / 3600),
+        s              => format!("{}d", s / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hl_test_support::TestDir;
+
+    fn entry(dir: &Path, name: &str, age: Duration) -> PathBuf {
+        let path = dir.join(format!("{}.bc", name));
+        std::fs::write(&path, b"HLBC").unwrap();
+        write_sidecar(&path, Path::new(&format!("/skrypty/{}.hl", name))).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(std::time::SystemTime::now() - age).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_prune_keeps_fresh_entries() {
+        let dir = TestDir::new("cache-prune");
+        let old   = entry(&dir, "stary", Duration::from_secs(10 * 86400));
+        let fresh = entry(&dir, "swiezy", Duration::from_secs(60));
+
+        assert_eq!(prune_dir(&dir, parse_age("7d").unwrap()).unwrap(), 1);
+        assert!(!old.exists() && !sidecar_path(&old).exists());
+        let left = list_dir(&dir).unwrap();
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].path, fresh);
+        assert_eq!(left[0].source.as_deref(), Some("/skrypty/swiezy.hl"));
+
+        assert_eq!(clean_dir(&dir).unwrap(), 1);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_prune_covers_ast_and_pool_versions() {
+        let dir = TestDir::new("cache-prune-ast");
+        let month = Duration::from_secs(30 * 86400);
+        let ast_dir = dir.join("ast");
+        std::fs::create_dir(&ast_dir).unwrap();
+        let old_ast = ast_dir.join("00000000000000aa.ast");
+        std::fs::write(&old_ast, b"HLAST").unwrap();
+        std::fs::File::options().write(true).open(&old_ast).unwrap()
+        .set_modified(std::time::SystemTime::now() - month).unwrap();
+        let fresh_ast = ast_dir.join("00000000000000bb.ast");
+        std::fs::write(&fresh_ast, b"HLAST").unwrap();
+        // Wersja puli bez żadnego .bc, który by ją wskazywał
+        let orphan = dir.join("strings-00000000000000cc.pool");
+        std::fs::write(&orphan, b"HLSP").unwrap();
+        std::fs::File::options().write(true).open(&orphan).unwrap()
+        .set_modified(std::time::SystemTime::now() - month).unwrap();
+
+        let listed = list_dir(&dir).unwrap();
+        assert_eq!(listed.len(), 3);
+        assert_eq!(listed.iter().filter(|e| e.kind == CacheKind::Ast).count(), 2);
+        assert_eq!(listed[0].path, fresh_ast);
+
+        assert_eq!(prune_dir(&dir, parse_age("7d").unwrap()).unwrap(), 2);
+        assert!(!old_ast.exists(), "Stary .ast powinien zostać usunięty");
+        assert!(!orphan.exists(), "Nieużywana wersja puli powinna zostać usunięta");
+        assert!(fresh_ast.exists());
+    }
+
+    #[test]
+    fn test_pool_versions_stay_bounded() {
+        use crate::string_pool::{pool_version_id, shared_pool_version_path, SharedStringPool, POOL_FILE_NAME};
+        use crate::serialize::{read_shared_pool_id, write_bc_file};
+
+        let dir = TestDir::new("pool-gc");
+        let pool_path = dir.join(POOL_FILE_NAME);
+        let n = CACHE_MAX_FILES + 10;
+        let now = std::time::SystemTime::now();
+        // Jak compile_to_cache z HL_SHARED_POOL=1: sprzątanie, kompilacja, publikacja wersji
+        for i in 0..n {
+            cleanup_dir(&dir, CACHE_MAX_FILES, Duration::ZERO).unwrap();
+            let src = format!("~> moduł {}\n> ls /wspolne\n", i);
+            let mut m = crate::lower_ast(&hl_parser::parse_source(&src).unwrap(), Path::new("m.hl"), 2);
+            let mut pool = SharedStringPool::load(&pool_path).unwrap();
+            pool.externalize(&mut m);
+            m.consts.shared_pool_id = pool.publish(&pool_path).unwrap();
+            let bc = dir.join(format!("{:016x}.bc", i));
+            write_bc_file(&m, &bc).unwrap();
+            let f = std::fs::File::options().write(true).open(&bc).unwrap();
+            f.set_modified(now - Duration::from_secs((n - i) as u64)).unwrap();
+        }
+
+        let files: Vec<PathBuf> = std::fs::read_dir(&dir).unwrap().flatten().map(|e| e.path()).collect();
+        let bcs: Vec<&PathBuf> = files.iter().filter(|p| p.extension().is_some_and(|x| x == "bc")).collect();
+        let versions: Vec<&PathBuf> = files.iter().filter(|p| pool_version_id(p).is_some()).collect();
+        assert!(bcs.len() <= CACHE_MAX_FILES + 1, "{} plików .bc", bcs.len());
+        assert!(versions.len() <= bcs.len(), "{} wersji puli na {} plików .bc", versions.len(), bcs.len());
+        // Każda wersja jest prefiksem najnowszej puli — łącznie nie więcej niż kopia na .bc
+        let latest = std::fs::metadata(&pool_path).unwrap().len();
+        let total: u64 = versions.iter().map(|p| std::fs::metadata(p).unwrap().len()).sum();
+        assert!(total <= bcs.len() as u64 * latest, "{} B wersji puli", total);
+        // Pozostałe .bc nadal mają swoją wersję
+        for bc in bcs {
+            let id = read_shared_pool_id(bc).unwrap().expect("moduł z pulą wspólną");
+            assert!(shared_pool_version_path(&pool_path, id).exists(), "brak wersji dla {:?}", bc);
+        }
+    }
+
+    #[test]
+    fn test_parse_and_format_age() {
+        assert_eq!(parse_age("7d"), Some(Duration::from_secs(7 * 86400)));
+        assert_eq!(parse_age("30m"), Some(Duration::from_secs(1800)));
+        assert_eq!(parse_age("7"), None);
+        assert_eq!(parse_age("7w"), None);
+        assert_eq!(parse_age(&format!("{}d", u64::MAX / 1000)), None);
+        assert_eq!(format_age(Duration::from_secs(2 * 86400 + 5)), "2d");
+        assert_eq!(format_age(Duration::from_secs(59)), "59s");
+    }
 }
@@ -0,0 +1,99 @@
+use crate::bytecode::*;
+use std::fmt::Write;
+
+/// Tekstowy listing modułu: funkcje, potem instrukcje z rozwiniętymi stałymi.
+/// Format jest do czytania przez człowieka — nie parsować.
+pub fn disassemble(module: &HlModule) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "; {} (gen {}), {} instrukcji, {} rejestrów",
+                     module.header.source_path, module.header.hl_gen,
+                     module.instructions.len(), module.main_regs);
+    for f in &module.funcs.entries {
+        let _ = writeln!(out, "; func {} @{:04} ({} instrukcji)", f.name, f.start_insn, f.insn_count);
+    }
+    for i in 0..module.instructions.len() {
+        if let Some(f) = module.funcs.entries.iter().find(|f| f.start_insn as usize == i) {
+            let _ = writeln!(out, "{}:", f.name);
+        }
+        out.push_str(&disassemble_insn(module, i));
+        out.push('\n');
+    }
+    out
+}
+
+/// Jedna linia listingu: offset, instrukcja i notka ze stałą albo celem skoku
+pub fn disassemble_insn(module: &HlModule, i: usize) -> String {
+    let Some(insn) = module.instructions.get(i) else { return format!("{:04}  <poza modułem>", i) };
+    let mut line = format!("{:04}  {:?}", i, insn);
+    if let Some(note) = const_note(module, insn).or_else(|| jump_note(module, insn)) {
+        let _ = write!(line, "  ; {}", note);
+    }
+    line
+}
+
+/// Podgląd stałej, do której odwołuje się instrukcja
+fn const_note(module: &HlModule, insn: &Instruction) -> Option<String> {
+    let s = |idx: ConstIdx| module.consts.strings.get(idx as usize).map(|s| format!("{:?}", s));
+    match insn {
+        Instruction::LoadStr { idx, .. } => s(*idx),
+        Instruction::LoadNum { idx, .. } => module.consts.numbers.get(*idx as usize).map(|n| n.to_string()),
+        Instruction::GetVar { name, .. }
+        | Instruction::SetVar { name, .. }
+        | Instruction::SetEnv { name, .. }
+        | Instruction::CallFunc { name }
+        | Instruction::CallQuick { name, .. }
+        | Instruction::MathCall { name, .. }
+        | Instruction::TempDirEnter { name } => s(*name),
+        Instruction::HackerOsCall { tool, .. } => s(*tool),
+        Instruction::ExecArgv { cmd, .. } => s(*cmd),
+        _ => None,
+    }
+}
+
+/// Cel skoku (offsety są bezwzględne) — z nazwą funkcji, gdy to jej początek
+fn jump_note(module: &HlModule, insn: &Instruction) -> Option<String> {
+    let target = match insn {
+        Instruction::JumpIfFalse { offset, .. }
+        | Instruction::JumpIfTrue { offset, .. }
+        | Instruction::Jump { offset }         => *offset,
+        Instruction::ForInNext { end_off, .. } => *end_off,
+        Instruction::TryEnter { handler }      => *handler,
+        Instruction::CatchArm { next, .. }     => *next,
+        _ => return None,
+    };
+    Some(match module.funcs.entries.iter().find(|f| f.start_insn == target) {
+        Some(f) => format!("-> {:04} ({})", target, f.name),
+        None    => format!("-> {:04}", target),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lower_ast, optimize_module};
+
+    #[test]
+    fn test_optimized_ir_differs_after_folding() {
+        let nodes = hl_parser::parse_source("$( 2 + 3 ) -> @x").unwrap();
+        let mut module = lower_ast(&nodes, std::path::Path::new("fold.hl"), 2);
+        let before = disassemble(&module);
+        optimize_module(&mut module);
+        let after = disassemble(&module);
+
+        assert_ne!(before, after);
+        assert!(before.contains("Add"), "{}", before);
+        assert!(!after.contains("Add"), "{}", after);
+        assert!(after.contains("; 5"), "Wynik złożenia powinien być widoczny: {}", after);
+    }
+
+    #[test]
+    fn test_conditional_jump_target_annotated() {
+        let listing = crate::disassemble_source("> ls\n? ok\n  % x = 1\ndone\n% y = 2\n", std::path::Path::new("if.hl")).unwrap();
+        let jump = listing.lines().find(|l| l.contains("JumpIfFalse")).expect(&listing);
+        let target = jump.rsplit("-> ").next().unwrap();
+        // Skok omija ciało `? ok` i ląduje na `% y = 2`
+        let after: Vec<&str> = listing.lines().skip_while(|l| !l.starts_with(target)).collect();
+        assert!(after.iter().any(|l| l.contains("\"y\"")), "{}", listing);
+        assert!(!after.iter().any(|l| l.contains("\"x\"")), "{}", listing);
+    }
+}
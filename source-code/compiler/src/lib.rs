@@ -3,23 +3,44 @@ pub mod lower;
 pub mod optimize;
 pub mod serialize;
 pub mod cache;
+pub mod disasm;
+pub mod string_pool;
+pub mod timing;
 
 pub use bytecode::{HlModule, HlBcHeader, Instruction, ConstPool, FuncTable};
 pub use lower::lower_ast;
 pub use optimize::optimize_module;
-pub use serialize::{write_bc_file, read_bc_file, BC_MAGIC, BC_VERSION};
-pub use cache::{bc_cache_path, ensure_cache_dir, cache_cleanup_if_needed, CACHE_MAX_FILES};
+pub use disasm::{disassemble, disassemble_insn};
+pub use serialize::{write_bc_file, read_bc_file, bc_bytes, BC_MAGIC, BC_VERSION};
+pub use cache::{bc_cache_path, ensure_cache_dir, cache_cleanup_if_needed, CacheKey, CACHE_MAX_FILES};
+pub use string_pool::{SharedStringPool, shared_pool_enabled, shared_pool_path};
+pub use timing::PhaseTimings;
 
 use anyhow::Result;
 use hl_parser::{parse_source_with_meta, ParseMeta};
 use std::path::Path;
 
+/// Opcje diagnostyczne kompilacji (`hl compile --dump-ir ...`)
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    /// Wypisz IR zaraz po lowering (przed optymalizacją)
+    pub dump_ir: bool,
+    /// Wypisz IR po optymalizacji, przed zapisem .bc
+    pub dump_optimized_ir: bool,
+    /// Bajtowo powtarzalny .bc: znacznik czasu z SOURCE_DATE_EPOCH albo 0
+    pub reproducible: bool,
+}
+
 /// Główna funkcja: .hl → .bc
 /// Kompiluje plik źródłowy do zoptymalizowanego bytecode.
 /// Zwraca ścieżkę do pliku .bc.
 pub fn compile_hl_to_bc(source_path: &Path, out_path: Option<&Path>) -> Result<std::path::PathBuf> {
+    compile_hl_to_bc_with(source_path, out_path, &CompileOptions::default())
+}
+
+pub fn compile_hl_to_bc_with(source_path: &Path, out_path: Option<&Path>, opts: &CompileOptions) -> Result<std::path::PathBuf> {
     let source = std::fs::read_to_string(source_path)?;
-    compile_source_to_bc(&source, source_path, out_path)
+    compile_source_to_bc_with(&source, source_path, out_path, opts)
 }
 
 /// Kompiluj kod źródłowy (string) do .bc
@@ -28,16 +49,39 @@ pub fn compile_source_to_bc(
     source_path: &Path,
     out_path: Option<&Path>,
 ) -> Result<std::path::PathBuf> {
-    // 1. Parse
-    let meta: ParseMeta = parse_source_with_meta(source)?;
+    compile_source_to_bc_with(source, source_path, out_path, &CompileOptions::default())
+}
 
-    // 2. Lower AST → HlModule (nasz IR bytecode)
-    let mut module = lower_ast(&meta.nodes, source_path, meta.gen.number());
+pub fn compile_source_to_bc_with(
+    source: &str,
+    source_path: &Path,
+    out_path: Option<&Path>,
+    opts: &CompileOptions,
+) -> Result<std::path::PathBuf> {
+    compile_source_to_bc_timed(source, source_path, out_path, opts, &mut PhaseTimings::new())
+}
 
-    // 3. Optymalizuj
-    optimize_module(&mut module);
+/// Jak `compile_hl_to_bc_with`, ale z czasami faz (`hl compile --measure`)
+pub fn compile_hl_to_bc_timed(
+    source_path: &Path,
+    out_path: Option<&Path>,
+    opts: &CompileOptions,
+    timings: &mut PhaseTimings,
+) -> Result<std::path::PathBuf> {
+    let source = timings.phase("read", || std::fs::read_to_string(source_path))?;
+    compile_source_to_bc_timed(&source, source_path, out_path, opts, timings)
+}
+
+pub fn compile_source_to_bc_timed(
+    source: &str,
+    source_path: &Path,
+    out_path: Option<&Path>,
+    opts: &CompileOptions,
+    timings: &mut PhaseTimings,
+) -> Result<std::path::PathBuf> {
+    let module = build_module(source, source_path, opts, timings)?;
 
-    // 4. Wyznacz ścieżkę wyjściową
+    // Wyznacz ścieżkę wyjściową
     let bc_path = match out_path {
         Some(p) => p.to_path_buf(),
         None => {
@@ -48,21 +92,55 @@ pub fn compile_source_to_bc(
         }
     };
 
-    // 5. Serializuj do pliku
-    write_bc_file(&module, &bc_path)?;
+    // Serializuj do pliku
+    timings.phase("write", || write_bc_file(&module, &bc_path))?;
 
     Ok(bc_path)
 }
 
+/// Listing zoptymalizowanego bytecode dla źródła, bez zapisu .bc — stałe rozwinięte,
+/// cele skoków podane jawnie (jak `hl compile --dump-optimized-ir`, ale jako String)
+pub fn disassemble_source(source: &str, source_path: &Path) -> Result<String> {
+    let module = build_module(source, source_path, &CompileOptions::default(), &mut PhaseTimings::new())?;
+    Ok(disassemble(&module))
+}
+
+/// Parse → lower → optymalizacja
+fn build_module(source: &str, source_path: &Path, opts: &CompileOptions, timings: &mut PhaseTimings) -> Result<HlModule> {
+    build_module_at(source, source_path, opts, timings, bytecode::compile_time())
+}
+
+/// `build_module` z podanym czasem kompilacji — testy sprawdzają tak, że `--reproducible` go ignoruje
+fn build_module_at(source: &str, source_path: &Path, opts: &CompileOptions, timings: &mut PhaseTimings, now: u64) -> Result<HlModule> {
+    // 1. Parse
+    let meta: ParseMeta = timings.phase("parse", || parse_source_with_meta(source))?;
+
+    // 2. Lower AST → HlModule (nasz IR bytecode)
+    let mut module = timings.phase("lower", || lower_ast(&meta.nodes, source_path, meta.gen.number()));
+    // Reszta modułu jest deterministyczna (pula stałych i tablica funkcji w kolejności
+    // ze źródła), więc jedynym zmiennym polem jest czas kompilacji
+    module.header.compiled_at = if opts.reproducible { bytecode::source_date_epoch().unwrap_or(0) } else { now };
+    if opts.dump_ir {
+        println!(";; ── IR przed optymalizacją ──");
+        print!("{}", disassemble(&module));
+    }
+
+    // 3. Optymalizuj
+    timings.phase("optimize", || optimize_module(&mut module));
+    if opts.dump_optimized_ir {
+        println!(";; ── IR po optymalizacji ──");
+        print!("{}", disassemble(&module));
+    }
+    Ok(module)
+}
+
 /// Kompiluj do cache (~/.hackeros/hacker-lang/cache/<hash>.bc)
 /// Zwraca ścieżkę do pliku cache.
 pub fn compile_to_cache(source: &str, source_path: &Path) -> Result<std::path::PathBuf> {
     ensure_cache_dir()?;
     cache_cleanup_if_needed()?;
 
-    // Hash jakości produkcyjnej: FNV-1a zamiast DefaultHasher (stabilny między procesami)
-    let hash = fnv1a_hash_source(source, source_path);
-    let cache_path = bc_cache_path(&format!("{:016x}", hash));
+    let cache_path = CacheKey::new(source, source_path, &CompileOptions::default()).path();
 
     // Jeśli cache trafiony i plik .bc nowszy niż źródło — zwróć od razu
     if cache_path.exists() {
@@ -83,22 +161,81 @@ pub fn compile_to_cache(source: &str, source_path: &Path) -> Result<std::path::P
     }
 
     tracing::debug!("cache miss, kompiluje: {:?}", source_path);
-    compile_source_to_bc(source, source_path, Some(&cache_path))?;
+    let mut module = build_module(source, source_path, &CompileOptions::default(), &mut PhaseTimings::new())?;
+    // Pula wspólna tylko w cache — pliki z `hl compile` zostają samodzielne
+    if shared_pool_enabled() {
+        let pool_path = shared_pool_path();
+        let mut pool = SharedStringPool::load(&pool_path)?;
+        pool.externalize(&mut module);
+        module.consts.shared_pool_id = pool.publish(&pool_path)?;
+    }
+    write_bc_file(&module, &cache_path)?;
+    cache::write_sidecar(&cache_path, source_path)?;
     Ok(cache_path)
 }
 
-/// FNV-1a hash — stabilny między procesami, szybszy niż sha256 dla małych danych
-fn fnv1a_hash_source(source: &str, path: &Path) -> u64 {
-    const FNV_OFFSET: u64 = 14695981039346656037;
-    const FNV_PRIME:  u64 = 1099511628211;
-    let mut hash = FNV_OFFSET;
-    for byte in source.as_bytes() {
-        hash ^= *byte as u64;
-        hash = hash.wrapping_mul(FNV_PRIME);
+/// Usuń wpis cache dla danej treści źródła — `hl run --watch` przed ponownym uruchomieniem.
+/// Zwraca true, jeśli plik .bc istniał.
+pub fn cache_evict(source: &str, source_path: &Path) -> Result<bool> {
+    let cache_path = CacheKey::new(source, source_path, &CompileOptions::default()).path();
+    if !cache_path.exists() { return Ok(false); }
+    cache::remove_entry(&cache_path)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hl_test_support::TestDir;
+
+    #[test]
+    fn test_reproducible_build_is_byte_identical() {
+        let src = "% a = 1\n: f def\n  ~> w f @a\ndone\n: g def\n  > ls /tmp\ndone\n-- f\n$( 2 + 3 ) -> @b\n";
+        let opts = CompileOptions { reproducible: true, ..Default::default() };
+        // Dwie kompilacje "w różnych chwilach" — zegar podany wprost zamiast czekania
+        let build = |opts: &CompileOptions, now| build_module_at(src, Path::new("repro.hl"), opts, &mut PhaseTimings::new(), now).unwrap();
+        let (a, b) = (build(&opts, 1_000_000_000), build(&opts, 2_000_000_000));
+
+        assert_eq!(a.header.compiled_at, bytecode::source_date_epoch().unwrap_or(0));
+        assert_eq!(bc_bytes(&a).unwrap(), bc_bytes(&b).unwrap());
+
+        // Bez --reproducible ten sam zegar trafia do nagłówka i rozróżnia pliki
+        let plain = CompileOptions::default();
+        let (c, d) = (build(&plain, 1_000_000_000), build(&plain, 2_000_000_000));
+        assert_eq!(c.header.compiled_at, 1_000_000_000);
+        assert_ne!(bc_bytes(&c).unwrap(), bc_bytes(&d).unwrap());
+    }
+
+    #[test]
+    fn test_measure_total_is_sum_of_phases() {
+        let dir = TestDir::new("measure");
+        let src = dir.join("m.hl");
+        std::fs::write(&src, "% a = 1\n: f def\n  ~> @a\ndone\n-- f\n").unwrap();
+
+        let mut t = PhaseTimings::new();
+        compile_hl_to_bc_timed(&src, None, &CompileOptions::default(), &mut t).unwrap();
+        let names: Vec<_> = t.phases().iter().map(|(n, _)| *n).collect();
+        assert_eq!(names, ["read", "parse", "lower", "optimize", "write"]);
+
+        // Fazy nie nachodzą na siebie, więc ich suma mieści się w czasie całkowitym
+        let (sum, wall) = (t.total(), t.wall());
+        assert!(sum <= wall, "{:?} > {:?}", sum, wall);
+        assert!(t.render().lines().last().unwrap().starts_with("razem"));
     }
-    for byte in path.as_os_str().as_encoded_bytes() {
-        hash ^= *byte as u64;
-        hash = hash.wrapping_mul(FNV_PRIME);
+
+    #[test]
+    fn test_cache_key_ignores_diagnostic_options() {
+        let src  = "% a = 1\n~> @a\n";
+        let path = Path::new("klucz.hl");
+        let base = CacheKey::new(src, path, &CompileOptions::default());
+
+        // Wypisywanie IR (jak --verbose) nie zmienia wyniku kompilacji
+        let dump = CompileOptions { dump_ir: true, dump_optimized_ir: true, ..Default::default() };
+        assert_eq!(CacheKey::new(src, path, &dump).hash(), base.hash());
+
+        let repro = CompileOptions { reproducible: true, ..Default::default() };
+        assert_ne!(CacheKey::new(src, path, &repro).hash(), base.hash());
+        assert_ne!(CacheKey { shared_pool: !base.shared_pool, ..base.clone() }.hash(), base.hash());
+        assert_ne!(CacheKey::new("% a = 2\n", path, &CompileOptions::default()).hash(), base.hash());
     }
-    hash
 }
@@ -1,4 +1,5 @@
 use hl_parser::ast::*;
+use hl_parser::truth::text_equals;
 use crate::bytecode::*;
 use std::path::Path;
 
@@ -55,6 +56,34 @@ impl Lowerer {
         }
     }
 
+    /// Warunek `?~` / `when`: JumpIfFalse (do spatchowania) gdy nie jest spełniony
+    fn emit_condition_jump(&mut self, condition: &[StringPart]) -> InsnOff {
+        match condition {
+            // `@x` — prawdziwość wartości (JumpIfFalse), nie tekst warunku do ewaluacji
+            [StringPart::Var(name)] if !name.contains(':') => {
+                let cond_reg = self.alloc_reg();
+                let name_idx = self.module.consts.add_str(name.as_str());
+                self.emit(Instruction::GetVar { dst: cond_reg, name: name_idx });
+                self.emit_jump_placeholder(Some(cond_reg))
+            }
+            _ => {
+                let cond_reg = self.lower_string_parts(condition);
+                // ewaluacja warunku — truthy check
+                let bool_reg = self.alloc_reg();
+                self.emit(Instruction::Truthy { dst: bool_reg, src: cond_reg });
+                self.emit_jump_placeholder(Some(bool_reg))
+            }
+        }
+    }
+
+    /// Rejestr licznika iteracji dla LoopGuard, wyzerowany przed wejściem w pętlę
+    fn emit_loop_counter(&mut self) -> Reg {
+        let counter = self.alloc_reg();
+        let zero = self.module.consts.add_num(0.0);
+        self.emit(Instruction::LoadNum { dst: counter, idx: zero });
+        counter
+    }
+
     // ── Kompilacja węzłów ────────────────────────────────────────
 
     fn lower_nodes(&mut self, nodes: &[Node]) {
@@ -66,6 +95,7 @@ impl Lowerer {
     fn lower_node(&mut self, node: &Node) {
         match node {
             Node::LineComment(_) | Node::DocComment(_) | Node::BlockComment(_) => {}
+            Node::SourceLine(line) => self.emit(Instruction::SourceLine { line: *line as u32 }),
 
             Node::Print { parts } => {
                 let dst = self.lower_string_parts(parts);
@@ -121,20 +151,25 @@ impl Lowerer {
             }
 
             Node::Command { raw, mode, .. } => {
-                // Interpoluj @VAR w komendzie — parse_string_parts rozbija na literały i zmienne
-                let parts = hl_parser::ast::parse_string_parts(raw);
-                let cmd_reg = self.lower_string_parts(&parts);
                 let dst = self.alloc_reg();
-                let mode = lower_cmd_mode(mode);
-                self.emit(Instruction::ExecCmd { cmd: cmd_reg, mode, dst });
+                if let Some(argv) = constant_argv(raw, mode) {
+                    // `>` bez zmiennych — argv gotowy, VM nie dzieli tekstu w każdej iteracji
+                    let cmd  = self.module.consts.add_str(argv.text);
+                    let argv = argv.words.into_iter().map(|w| self.module.consts.add_str(w)).collect();
+                    self.emit(Instruction::ExecArgv { cmd, argv, dst });
+                } else {
+                    // Interpoluj @VAR w komendzie — parse_string_parts rozbija na literały i zmienne
+                    let cmd_reg = self.lower_command_text(raw);
+                    let mode = lower_cmd_mode(mode);
+                    self.emit(Instruction::ExecCmd { cmd: cmd_reg, mode, dst });
+                }
                 let le_idx = self.module.consts.add_str("_last_exit_code");
                 self.emit(Instruction::SetVar { name: le_idx, src: dst });
             }
 
             Node::PipeToVar { command, mode, var_name } => {
                 // Interpoluj @VAR w komendzie
-                let parts = hl_parser::ast::parse_string_parts(command);
-                let cmd_reg = self.lower_string_parts(&parts);
+                let cmd_reg = self.lower_command_text(command);
                 let dst_ec  = self.alloc_reg();
                 let dst_out = self.alloc_reg();
                 let mode = lower_cmd_mode(mode);
@@ -143,6 +178,21 @@ impl Lowerer {
                 self.emit(Instruction::SetVar { name: name_idx, src: dst_out });
             }
 
+            Node::Feed { source, command, var_name } => {
+                let input = self.alloc_reg();
+                let src_idx = self.module.consts.add_str(source.as_str());
+                self.emit(Instruction::GetVar { dst: input, name: src_idx });
+                let parts = hl_parser::ast::parse_string_parts(command);
+                let cmd_reg = self.lower_string_parts(&parts);
+                let dst_ec  = self.alloc_reg();
+                let dst_out = var_name.as_ref().map(|_| self.alloc_reg());
+                self.emit(Instruction::ExecFeed { cmd: cmd_reg, input, dst_ec, dst_out });
+                if let (Some(name), Some(out)) = (var_name, dst_out) {
+                    let name_idx = self.module.consts.add_str(name.as_str());
+                    self.emit(Instruction::SetVar { name: name_idx, src: out });
+                }
+            }
+
             Node::Conditional { condition, body } => {
                 // ? ok / ? err — sprawdź last_exit_code
                 let last_ec_reg = self.alloc_reg();
@@ -165,16 +215,40 @@ impl Lowerer {
                 self.patch_jump(jump_ph, after);
             }
 
-            Node::ForIn { var, iterable, body } => {
+            Node::Chain { op, node } => {
+                // Jak Conditional, ale bez zmiany _last_exit_code gdy instrukcja pominięta
+                let last_ec_reg = self.alloc_reg();
+                let le_idx = self.module.consts.add_str("_last_exit_code");
+                self.emit(Instruction::GetVar { dst: last_ec_reg, name: le_idx });
+
+                let zero_reg = self.alloc_reg();
+                let zero_idx = self.module.consts.add_num(0.0);
+                self.emit(Instruction::LoadNum { dst: zero_reg, idx: zero_idx });
+
+                let cond_reg = self.alloc_reg();
+                match op {
+                    ChainOp::And => self.emit(Instruction::CmpEq { dst: cond_reg, a: last_ec_reg, b: zero_reg }),
+                    ChainOp::Or  => self.emit(Instruction::CmpNe { dst: cond_reg, a: last_ec_reg, b: zero_reg }),
+                }
+
+                let jump_ph = self.emit_jump_placeholder(Some(cond_reg));
+                self.lower_node(node);
+                let after = self.current_offset();
+                self.patch_jump(jump_ph, after);
+            }
+
+            Node::ForIn { var, iterable, each, body, line } => {
                 let src = self.lower_string_parts(iterable);
                 let iter_reg = self.alloc_reg();
-                self.emit(Instruction::ForInStart { iter_reg, src });
+                self.emit(Instruction::ForInStart { iter_reg, src, chars: *each == ForEach::Chars });
+                let counter = self.emit_loop_counter();
 
                 let loop_start = self.current_offset();
                 let item_reg = self.alloc_reg();
                 // placeholder dla końca pętli — patched po kompilacji body
                 let end_ph_off = self.current_offset();
                 self.emit(Instruction::ForInNext { iter_reg, dst: item_reg, end_off: 0 });
+                self.emit(Instruction::LoopGuard { counter, line: *line as u32 });
 
                 let var_idx = self.module.consts.add_str(var.as_str());
                 self.emit(Instruction::SetVar { name: var_idx, src: item_reg });
@@ -192,13 +266,23 @@ impl Lowerer {
                     }
             }
 
-            Node::WhileLoop { condition, body } => {
+            Node::WhileLoop { condition, body, line } => {
+                // Stały warunek (tak jak eval_condition_fast w tree-walk): `?~ false`
+                // ma martwe ciało — nie emitujemy nic, więc jego komendy nie mogą się wykonać
+                let literal = literal_text(condition).as_deref().and_then(hl_parser::truth::literal_condition);
+                if literal == Some(false) { return; }
+                let counter = self.emit_loop_counter();
+                let guard = Instruction::LoopGuard { counter, line: *line as u32 };
+                if literal == Some(true) {
+                    let loop_start = self.current_offset();
+                    self.emit(guard);
+                    self.lower_nodes(body);
+                    self.emit(Instruction::Jump { offset: loop_start });
+                    return;
+                }
                 let loop_start = self.current_offset();
-                let cond_reg = self.lower_string_parts(condition);
-                // ewaluacja warunku — truthy check
-                let bool_reg = self.alloc_reg();
-                self.emit(Instruction::Truthy { dst: bool_reg, src: cond_reg });
-                let exit_ph = self.emit_jump_placeholder(Some(bool_reg));
+                self.emit(guard);
+                let exit_ph = self.emit_condition_jump(condition);
                 self.lower_nodes(body);
                 self.emit(Instruction::Jump { offset: loop_start });
                 let after = self.current_offset();
@@ -206,6 +290,15 @@ impl Lowerer {
             }
 
             Node::MatchExpr { subject, arms } => {
+                // Stały subject i wzorce bez @zmiennych i `when` — wybór ramienia znany w czasie kompilacji
+                if let Some(subj) = literal_text(subject) {
+                    if arms.iter().all(|a| !a.pattern.contains('@') && a.guard.is_none()) {
+                        let taken = arms.iter().find(|a| a.pattern.trim() != "*" && text_equals(a.pattern.trim(), &subj))
+                        .or_else(|| arms.iter().find(|a| a.pattern.trim() == "*"));
+                        if let Some(arm) = taken { self.lower_nodes(&arm.body); }
+                        return;
+                    }
+                }
                 let subj_reg = self.lower_string_parts(subject);
                 let mut exit_jumps: Vec<InsnOff> = Vec::new();
 
@@ -213,22 +306,25 @@ impl Lowerer {
                 let (wildcards, normals): (Vec<_>, Vec<_>) =
                 arms.iter().partition(|a| a.pattern.trim() == "*");
 
-                for arm in &normals {
-                    let pat_reg = self.alloc_reg();
-                    let pat_idx = self.module.consts.add_str(arm.pattern.trim());
-                    self.emit(Instruction::LoadStr { dst: pat_reg, idx: pat_idx });
-                    let match_reg = self.alloc_reg();
-                    self.emit(Instruction::CmpEq { dst: match_reg, a: subj_reg, b: pat_reg });
-                    let skip_ph = self.emit_jump_placeholder(Some(match_reg));
+                // Ramię: [wzorzec] [when] ciało, skok na koniec; niespełniony test → następne ramię
+                for arm in normals.iter().chain(&wildcards) {
+                    let mut skips = Vec::new();
+                    if arm.pattern.trim() != "*" {
+                        let pat_reg = self.alloc_reg();
+                        let pat_idx = self.module.consts.add_str(arm.pattern.trim());
+                        self.emit(Instruction::LoadStr { dst: pat_reg, idx: pat_idx });
+                        let match_reg = self.alloc_reg();
+                        self.emit(Instruction::CmpEq { dst: match_reg, a: subj_reg, b: pat_reg });
+                        skips.push(self.emit_jump_placeholder(Some(match_reg)));
+                    }
+                    if let Some(guard) = &arm.guard {
+                        skips.push(self.emit_condition_jump(guard));
+                    }
                     self.lower_nodes(&arm.body);
                     exit_jumps.push(self.current_offset());
                     self.emit(Instruction::Jump { offset: 0 }); // placeholder exit
                     let after_body = self.current_offset();
-                    self.patch_jump(skip_ph, after_body);
-                }
-
-                for wc in &wildcards {
-                    self.lower_nodes(&wc.body);
+                    for ph in skips { self.patch_jump(ph, after_body); }
                 }
 
                 let after_match = self.current_offset();
@@ -241,11 +337,14 @@ impl Lowerer {
                 }
             }
 
-            Node::FuncDef { name, body } => {
+            Node::FuncDef { name, body, .. } => {
+                // Ciało leży w miejscu definicji — kod główny je przeskakuje
+                let skip = self.emit_jump_placeholder(None);
                 let start = self.current_offset();
                 self.lower_nodes(body);
                 self.emit(Instruction::Return { src: None });
                 let end = self.current_offset();
+                self.patch_jump(skip, end);
                 self.module.funcs.entries.push(FuncEntry {
                     name:       name.clone(),
                                                start_insn: start,
@@ -258,6 +357,17 @@ impl Lowerer {
                 self.emit(Instruction::CallFunc { name: name_idx });
             }
 
+            // -- @f args — referencja rozwiązywana w runtime, przekazujemy jako
+            // quick-call "call" z nazwą zmiennej i argumentami
+            Node::FuncRefCall { var, args } => {
+                let mut parts = vec![StringPart::Literal(format!("{} ", var))];
+                parts.extend(args.iter().cloned());
+                let arg_reg  = self.lower_string_parts(&parts);
+                let dst      = self.alloc_reg();
+                let name_idx = self.module.consts.add_str("call");
+                self.emit(Instruction::CallQuick { name: name_idx, arg: arg_reg, dst });
+            }
+
             Node::QuickCall { name, args } => {
                 let arg_reg  = self.lower_string_parts(args);
                 let dst      = self.alloc_reg();
@@ -317,6 +427,99 @@ impl Lowerer {
                 }
             }
 
+            // Bench jest tylko dla tree-walk `hl bench` — w bytecode go nie ma
+            Node::Bench { .. } => {}
+
+            Node::TempDir { var, body } => {
+                let name = self.module.consts.add_str(var.as_str());
+                self.emit(Instruction::TempDirEnter { name });
+                self.lower_nodes(body);
+                self.emit(Instruction::TempDirLeave);
+            }
+
+            Node::Try { body, catches } => {
+                // TryEnter H; body; TryLeave; Jump END
+                // H: CatchArm(next → kolejne ramię); ciało; Jump END ... Rethrow; END
+                let enter_off = self.current_offset();
+                self.emit(Instruction::TryEnter { handler: 0 });
+                self.lower_nodes(body);
+                self.emit(Instruction::TryLeave);
+                let mut exit_jumps = vec![self.emit_jump_placeholder(None)];
+
+                let handler = self.current_offset();
+                if let Instruction::TryEnter { handler: h } = &mut self.module.instructions[enter_off as usize] {
+                    *h = handler;
+                }
+                for arm in catches {
+                    let arm_off = self.current_offset();
+                    let kind = arm.kind.as_ref().map(|k| self.module.consts.add_str(k.as_str()));
+                    let var  = arm.var.as_ref().map(|v| self.module.consts.add_str(v.as_str()));
+                    self.emit(Instruction::CatchArm { kind, var, next: 0 });
+                    self.lower_nodes(&arm.body);
+                    exit_jumps.push(self.emit_jump_placeholder(None));
+                    let next_arm = self.current_offset();
+                    if let Instruction::CatchArm { next, .. } = &mut self.module.instructions[arm_off as usize] {
+                        *next = next_arm;
+                    }
+                }
+                self.emit(Instruction::Rethrow);
+
+                let end = self.current_offset();
+                for ph in exit_jumps {
+                    self.patch_jump(ph, end);
+                }
+            }
+
+            Node::Retry { count, delay_ms, body } => {
+                // attempt = 0
+                // loop: attempt += 1; _last_exit_code = 0; body
+                //       if _last_exit_code == 0 → koniec; if attempt >= count → koniec
+                //       sleep delay; goto loop
+                let attempt_reg = self.alloc_reg();
+                let limit_reg   = self.alloc_reg();
+                let zero_reg    = self.alloc_reg();
+                let one_reg     = self.alloc_reg();
+                let zero_idx    = self.module.consts.add_num(0.0);
+                let one_idx     = self.module.consts.add_num(1.0);
+                let limit_idx   = self.module.consts.add_num(*count as f64);
+                self.emit(Instruction::LoadNum { dst: attempt_reg, idx: zero_idx });
+                self.emit(Instruction::LoadNum { dst: zero_reg,    idx: zero_idx });
+                self.emit(Instruction::LoadNum { dst: one_reg,     idx: one_idx });
+                self.emit(Instruction::LoadNum { dst: limit_reg,   idx: limit_idx });
+                let attempts_idx = self.module.consts.add_str("_retry_attempts");
+                let le_idx       = self.module.consts.add_str("_last_exit_code");
+
+                let loop_start = self.current_offset();
+                self.emit(Instruction::Add { dst: attempt_reg, a: attempt_reg, b: one_reg });
+                self.emit(Instruction::SetVar { name: attempts_idx, src: attempt_reg });
+                self.emit(Instruction::SetVar { name: le_idx, src: zero_reg });
+
+                self.lower_nodes(body);
+
+                let ec_reg   = self.alloc_reg();
+                let fail_reg = self.alloc_reg();
+                self.emit(Instruction::GetVar { dst: ec_reg, name: le_idx });
+                self.emit(Instruction::CmpNe { dst: fail_reg, a: ec_reg, b: zero_reg });
+                let ok_ph = self.emit_jump_placeholder(Some(fail_reg));
+
+                let more_reg = self.alloc_reg();
+                self.emit(Instruction::CmpLt { dst: more_reg, a: attempt_reg, b: limit_reg });
+                let exhausted_ph = self.emit_jump_placeholder(Some(more_reg));
+
+                if *delay_ms > 0 {
+                    let sleep_idx = self.module.consts.add_str(format!("sleep {}.{:03}", delay_ms / 1000, delay_ms % 1000));
+                    let sleep_reg = self.alloc_reg();
+                    let sleep_ec  = self.alloc_reg();
+                    self.emit(Instruction::LoadStr { dst: sleep_reg, idx: sleep_idx });
+                    self.emit(Instruction::ExecCmd { cmd: sleep_reg, mode: CmdMode::Plain, dst: sleep_ec });
+                }
+                self.emit(Instruction::Jump { offset: loop_start });
+
+                let after = self.current_offset();
+                self.patch_jump(ok_ph, after);
+                self.patch_jump(exhausted_ph, after);
+            }
+
             Node::HshCommand { raw } => {
                 // Interpoluj @VAR, dodaj prefix hsh -c
                 let inner_parts = hl_parser::ast::parse_string_parts(raw);
@@ -407,10 +610,12 @@ impl Lowerer {
             // ArenaFuncDef: kompilujemy ciało jak zwykłą funkcję.
             // Arena allocation dzieje się w runtime (executor), nie w bytecode.
             Node::ArenaFuncDef { name, body, .. } => {
+                let skip = self.emit_jump_placeholder(None);
                 let start = self.current_offset();
                 self.lower_nodes(body);
                 self.emit(Instruction::Return { src: None });
                 let end = self.current_offset();
+                self.patch_jump(skip, end);
                 self.module.funcs.entries.push(FuncEntry {
                     name:       format!("__arena__{}", name),
                                                start_insn: start,
@@ -465,6 +670,29 @@ impl Lowerer {
 
     // ── Pomocniki ────────────────────────────────────────────────
 
+    /// Tekst komendy: `@VAR` jak w lower_string_parts, `{VAR}` przez quick "shquote"
+    /// (hl_parser::cmd_template — ta sama reguła co w tree-walk)
+    fn lower_command_text(&mut self, raw: &str) -> Reg {
+        use hl_parser::cmd_template::{split_placeholders, CmdSegment};
+        let segments = split_placeholders(raw);
+        if let [CmdSegment::Text(text)] = segments.as_slice() {
+            return self.lower_string_parts(&parse_string_parts(text));
+        }
+        let regs: Vec<Reg> = segments.iter().map(|seg| match seg {
+            CmdSegment::Text(text) => self.lower_string_parts(&parse_string_parts(text)),
+            CmdSegment::Quoted(name) => {
+                let arg = self.lower_string_part(&StringPart::Var(name.to_string()));
+                let dst = self.alloc_reg();
+                let name_idx = self.module.consts.add_str("shquote");
+                self.emit(Instruction::CallQuick { name: name_idx, arg, dst });
+                dst
+            }
+        }).collect();
+        let dst = self.alloc_reg();
+        self.emit(Instruction::Concat { dst, parts: regs });
+        dst
+    }
+
     fn lower_string_parts(&mut self, parts: &[StringPart]) -> Reg {
         if parts.is_empty() {
             let dst = self.alloc_reg();
@@ -492,9 +720,20 @@ impl Lowerer {
                 self.emit(Instruction::LoadStr { dst, idx });
                 dst
             }
+            // @env:NAZWA — zmienna procesu z pominięciem zmiennych HL, przez `::env`
+            StringPart::Var(name) if name.starts_with("env:") => {
+                let arg = self.alloc_reg();
+                let idx = self.module.consts.add_str(&name["env:".len()..]);
+                self.emit(Instruction::LoadStr { dst: arg, idx });
+                let dst = self.alloc_reg();
+                let quick = self.module.consts.add_str("env");
+                self.emit(Instruction::CallQuick { name: quick, arg, dst });
+                dst
+            }
             StringPart::Var(name) => {
+                let name = name.strip_prefix("local:").unwrap_or(name);
                 let dst = self.alloc_reg();
-                let name_idx = self.module.consts.add_str(name.as_str());
+                let name_idx = self.module.consts.add_str(name);
                 self.emit(Instruction::GetVar { dst, name: name_idx });
                 let str_dst = self.alloc_reg();
                 self.emit(Instruction::ToString { dst: str_dst, src: dst });
@@ -555,6 +794,27 @@ impl Lowerer {
                 });
                 dst_out
             }
+            VarValue::ExitStatus(cmd) => {
+                // Jak `>`: argv bez powłoki; ExecCmd zostawia kod wyjścia w dst. Kod jest
+                // obsłużony przez przypisanie — jak w tree-walk sama deklaracja kończy się sukcesem
+                let parts   = hl_parser::ast::parse_string_parts(cmd);
+                let cmd_reg = self.lower_string_parts(&parts);
+                let dst     = self.alloc_reg();
+                self.emit(Instruction::ExecCmd { cmd: cmd_reg, mode: lower_cmd_mode(&CommandMode::Plain), dst });
+                let ok      = self.alloc_reg();
+                let zero    = self.module.consts.add_num(0.0);
+                self.emit(Instruction::LoadNum { dst: ok, idx: zero });
+                let le_name = self.module.consts.add_str("_last_exit_code");
+                self.emit(Instruction::SetVar { name: le_name, src: ok });
+                dst
+            }
+            VarValue::FuncRef { name, bound } => {
+                let text = if bound.is_empty() { format!("&{}", name) } else { format!("&{}({})", name, bound.join(" ")) };
+                let dst = self.alloc_reg();
+                let idx = self.module.consts.add_str(&text);
+                self.emit(Instruction::LoadStr { dst, idx });
+                dst
+            }
             VarValue::List(_) | VarValue::Map(_) => {
                 // Uproszczenie: listy/mapy traktuj jak pusty string w BC (nie używane w gen 2 skryptach)
                 let dst = self.alloc_reg();
@@ -591,9 +851,8 @@ impl Lowerer {
             return Some(dst);
         }
 
-        // Zmienna @name
-        if expr.starts_with('@') {
-            let name = &expr[1..];
+        // Zmienna @name (samo `@n - 1` to już wyrażenie, nie nazwa "n - 1")
+        if let Some(name) = expr.strip_prefix('@').filter(|n| !n.is_empty() && n.chars().all(|c| c.is_alphanumeric() || c == '_')) {
             let dst = self.alloc_reg();
             let name_idx = self.module.consts.add_str(name);
             self.emit(Instruction::GetVar { dst, name: name_idx });
@@ -607,6 +866,21 @@ impl Lowerer {
             return self.try_compile_arith_expr(&expr[1..expr.len()-1]);
         }
 
+        // Operatory bitowe wiążą najsłabiej — przed `+ -`
+        if let Some((left, op, right)) = hl_parser::math::split_bitwise(expr) {
+            let l = self.try_compile_arith_expr(left)?;
+            let r = self.try_compile_arith_expr(right)?;
+            let dst = self.alloc_reg();
+            self.emit(match op {
+                "&"  => Instruction::BitAnd { dst, a: l, b: r },
+                "|"  => Instruction::BitOr  { dst, a: l, b: r },
+                "^"  => Instruction::BitXor { dst, a: l, b: r },
+                "<<" => Instruction::Shl    { dst, a: l, b: r },
+                _    => Instruction::Shr    { dst, a: l, b: r },
+            });
+            return Some(dst);
+        }
+
         // Szukaj operatora na najniższym poziomie priorytetu (additive)
         if let Some((left, op, right)) = find_binary_op_split(expr) {
             let l = self.try_compile_arith_expr(left)?;
@@ -624,6 +898,23 @@ impl Lowerer {
             return Some(dst);
         }
 
+        // Unarny minus: `-sqrt(x)`, `-@n`, `-(a + b)`
+        if let Some(rest) = expr.strip_prefix('-') {
+            let src = self.try_compile_arith_expr(rest)?;
+            let dst = self.alloc_reg();
+            self.emit(Instruction::Neg { dst, src });
+            return Some(dst);
+        }
+
+        // sqrt(x), max(a, b) ...
+        if let Some((name, args)) = hl_parser::math::split_math_call(expr) {
+            let args = args.iter().map(|a| self.try_compile_arith_expr(a)).collect::<Option<Vec<_>>>()?;
+            let dst = self.alloc_reg();
+            let name = self.module.consts.add_str(name);
+            self.emit(Instruction::MathCall { name, args, dst });
+            return Some(dst);
+        }
+
         None
     }
 }
@@ -674,9 +965,19 @@ fn find_binary_op_split(expr: &str) -> Option<(&str, char, &str)> {
     None
 }
 
+/// Treść części stringu, jeśli nie ma w nich żadnej zmiennej
+fn literal_text(parts: &[StringPart]) -> Option<String> {
+    parts.iter().map(|p| match p {
+        StringPart::Literal(s) => Some(s.as_str()),
+        _ => None,
+    }).collect()
+}
+
 fn lower_cmd_mode(mode: &CommandMode) -> CmdMode {
     match mode {
-        CommandMode::Plain            => CmdMode::Plain,
+        // `>` z AST nigdy nie idzie przez powłokę; CmdMode::Plain zostaje dla
+        // komend generowanych przez lower (sleep w retry, tło itd.)
+        CommandMode::Plain            => CmdMode::Raw,
         CommandMode::Sudo             => CmdMode::Sudo,
         CommandMode::Isolated         => CmdMode::Isolated,
         CommandMode::IsolatedSudo     => CmdMode::IsolatedSudo,
@@ -686,6 +987,27 @@ fn lower_cmd_mode(mode: &CommandMode) -> CmdMode {
     }
 }
 
+struct ConstArgv {
+    text:  String,
+    words: Vec<String>,
+}
+
+/// Komenda `>` (argv bez powłoki) bez `@zmiennych` i `{placeholderów}` — podzielona na słowa
+/// tak samo, jak VM podzieliłby ją w runtime. `@@` jest już zamienione na `@`.
+fn constant_argv(raw: &str, mode: &CommandMode) -> Option<ConstArgv> {
+    use hl_parser::cmd_template::{split_placeholders, split_words, CmdSegment};
+    if *mode != CommandMode::Plain { return None; }
+    let [CmdSegment::Text(text)] = split_placeholders(raw)[..] else { return None };
+    let text = match parse_string_parts(text).as_slice() {
+        []                          => String::new(),
+        [StringPart::Literal(lit)]  => lit.clone(),
+        _                           => return None,
+    };
+    let words = split_words(&text);
+    if words.is_empty() { return None; }
+    Some(ConstArgv { text, words })
+}
+
 /// Główna funkcja lowering
 pub fn lower_ast(nodes: &[Node], source_path: &Path, gen: u32) -> HlModule {
     let path_str = source_path.display().to_string();
@@ -695,3 +1017,49 @@ pub fn lower_ast(nodes: &[Node], source_path: &Path, gen: u32) -> HlModule {
     lowerer.module.main_regs = lowerer.reg_alloc;
     lowerer.module
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lower(src: &str) -> HlModule {
+        lower_ast(&hl_parser::parse_source(src).unwrap(), Path::new("test.hl"), 2)
+    }
+
+    fn exec_count(m: &HlModule) -> usize {
+        m.instructions.iter().filter(|i| matches!(i, Instruction::ExecCmd { .. } | Instruction::ExecArgv { .. })).count()
+    }
+
+    #[test]
+    fn test_constant_false_while_emits_no_body() {
+        let m = lower("?~ false\n  > rm x\ndone\n");
+        assert_eq!(exec_count(&m), 0, "{:?}", m.instructions);
+        assert!(!m.consts.strings.iter().any(|s| s.contains("rm x")));
+        assert!(!m.instructions.iter().any(|i| matches!(i, Instruction::JumpIfFalse { .. })));
+    }
+
+    #[test]
+    fn test_constant_raw_command_is_split_at_compile_time() {
+        let m = lower("> printf '%s|' \"a b\" user@@host\n");
+        let Some(Instruction::ExecArgv { cmd, argv, .. }) = m.instructions.first() else { panic!("{:?}", m.instructions) };
+        let words: Vec<&str> = argv.iter().map(|i| m.consts.strings[*i as usize].as_str()).collect();
+        assert_eq!(words, ["printf", "%s|", "a b", "user@host"]);
+        assert_eq!(m.consts.strings[*cmd as usize], "printf '%s|' \"a b\" user@host");
+        // Zmienna, placeholder albo inny tryb — tekst składany w runtime
+        for src in ["> ls @dir\n", "> rm {plik}\n", ">> ls -la\n"] {
+            let m = lower(src);
+            assert!(m.instructions.iter().any(|i| matches!(i, Instruction::ExecCmd { .. })), "{}", src);
+            assert!(!m.instructions.iter().any(|i| matches!(i, Instruction::ExecArgv { .. })), "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_constant_switch_keeps_only_taken_arm() {
+        let m = lower("? switch b\n| a\n  > rm a\n| b\n  > ls\n| *\n  > rm c\ndone\n");
+        assert_eq!(exec_count(&m), 1, "{:?}", m.instructions);
+        assert!(!m.instructions.iter().any(|i| matches!(i, Instruction::CmpEq { .. })));
+        // Subject ze zmienną — zwykłe porównania w runtime
+        let m = lower("? switch @x\n| a\n  > rm a\n| *\n  > ls\ndone\n");
+        assert!(m.instructions.iter().any(|i| matches!(i, Instruction::CmpEq { .. })));
+    }
+}
@@ -2,6 +2,7 @@ use crate::bytecode::*;
 
 pub fn optimize_module(module: &mut HlModule) {
     pass_constant_folding(module);
+    pass_inline_small_funcs(module);
     pass_nop_elimination(module);
     pass_source_line_strip(module);
     // Deduplacja stałych jest już wbudowana w ConstPool
@@ -22,8 +23,7 @@ fn pass_constant_folding(module: &mut HlModule) {
                 reg_consts.insert(dst, val);
             }
             Instruction::Add { dst, a, b } => {
-                if let (Some(&va), Some(&vb)) = (reg_consts.get(&a), reg_consts.get(&b)) {
-                    let result = va + vb;
+                if let Some(result) = fold(&reg_consts, a, b, |va, vb| va + vb) {
                     let new_idx = module.consts.add_num(result);
                     module.instructions[i] = Instruction::LoadNum { dst, idx: new_idx };
                     reg_consts.insert(dst, result);
@@ -32,8 +32,7 @@ fn pass_constant_folding(module: &mut HlModule) {
                 }
             }
             Instruction::Sub { dst, a, b } => {
-                if let (Some(&va), Some(&vb)) = (reg_consts.get(&a), reg_consts.get(&b)) {
-                    let result = va - vb;
+                if let Some(result) = fold(&reg_consts, a, b, |va, vb| va - vb) {
                     let new_idx = module.consts.add_num(result);
                     module.instructions[i] = Instruction::LoadNum { dst, idx: new_idx };
                     reg_consts.insert(dst, result);
@@ -42,8 +41,7 @@ fn pass_constant_folding(module: &mut HlModule) {
                 }
             }
             Instruction::Mul { dst, a, b } => {
-                if let (Some(&va), Some(&vb)) = (reg_consts.get(&a), reg_consts.get(&b)) {
-                    let result = va * vb;
+                if let Some(result) = fold(&reg_consts, a, b, |va, vb| va * vb) {
                     let new_idx = module.consts.add_num(result);
                     module.instructions[i] = Instruction::LoadNum { dst, idx: new_idx };
                     reg_consts.insert(dst, result);
@@ -52,8 +50,7 @@ fn pass_constant_folding(module: &mut HlModule) {
                 }
             }
             Instruction::Div { dst, a, b } => {
-                if let (Some(&va), Some(&vb)) = (reg_consts.get(&a), reg_consts.get(&b)) {
-                    let result = if vb == 0.0 { 0.0 } else { va / vb };
+                if let Some(result) = fold(&reg_consts, a, b, |va, vb| if vb == 0.0 { f64::NAN } else { va / vb }) {
                     let new_idx = module.consts.add_num(result);
                     module.instructions[i] = Instruction::LoadNum { dst, idx: new_idx };
                     reg_consts.insert(dst, result);
@@ -62,8 +59,25 @@ fn pass_constant_folding(module: &mut HlModule) {
                 }
             }
             Instruction::Mod { dst, a, b } => {
-                if let (Some(&va), Some(&vb)) = (reg_consts.get(&a), reg_consts.get(&b)) {
-                    let result = if vb == 0.0 { 0.0 } else { (va as i64 % vb as i64) as f64 };
+                if let Some(result) = fold(&reg_consts, a, b, |va, vb| if vb == 0.0 { f64::NAN } else { (va as i64 % vb as i64) as f64 }) {
+                    let new_idx = module.consts.add_num(result);
+                    module.instructions[i] = Instruction::LoadNum { dst, idx: new_idx };
+                    reg_consts.insert(dst, result);
+                } else {
+                    reg_consts.remove(&dst);
+                }
+            }
+            // Zły operand (`1.5 & 1`) zostaje do runtime — tam TypeError
+            Instruction::BitAnd { dst, a, b } | Instruction::BitOr { dst, a, b } | Instruction::BitXor { dst, a, b }
+            | Instruction::Shl { dst, a, b } | Instruction::Shr { dst, a, b } => {
+                let op = match module.instructions[i] {
+                    Instruction::BitAnd { .. } => "&",
+                    Instruction::BitOr { .. }  => "|",
+                    Instruction::BitXor { .. } => "^",
+                    Instruction::Shl { .. }    => "<<",
+                    _                          => ">>",
+                };
+                if let Some(result) = fold(&reg_consts, a, b, |va, vb| hl_parser::math::eval_bitwise(op, va, vb).unwrap_or(f64::NAN)) {
                     let new_idx = module.consts.add_num(result);
                     module.instructions[i] = Instruction::LoadNum { dst, idx: new_idx };
                     reg_consts.insert(dst, result);
@@ -82,6 +96,13 @@ fn pass_constant_folding(module: &mut HlModule) {
     }
 }
 
+/// Wynik operacji na dwóch znanych stałych. Dzielenie przez zero i wyniki poza
+/// zakresem i64 zostają w runtime — w trybie `HL_CHECKED=1` to ArithmeticError.
+fn fold(consts: &std::collections::HashMap<Reg, f64>, a: Reg, b: Reg, op: impl Fn(f64, f64) -> f64) -> Option<f64> {
+    let result = op(*consts.get(&a)?, *consts.get(&b)?);
+    (result.is_finite() && result.abs() < i64::MAX as f64).then_some(result)
+}
+
 /// Usuń Nop — przepisz instrukcje pomijając Nopy i popraw offsety skoków
 fn pass_nop_elimination(module: &mut HlModule) {
     // Zbuduj mapę starych offsetów → nowych offsetów
@@ -99,20 +120,8 @@ fn pass_nop_elimination(module: &mut HlModule) {
 
     // Przepisz offsety w instrukcjach skoku
     for insn in &mut new_instructions {
-        match insn {
-            Instruction::JumpIfFalse { offset, .. } => {
-                *offset = offset_map[(*offset as usize).min(old_len)];
-            }
-            Instruction::JumpIfTrue { offset, .. } => {
-                *offset = offset_map[(*offset as usize).min(old_len)];
-            }
-            Instruction::Jump { offset } => {
-                *offset = offset_map[(*offset as usize).min(old_len)];
-            }
-            Instruction::ForInNext { end_off, .. } => {
-                *end_off = offset_map[(*end_off as usize).min(old_len)];
-            }
-            _ => {}
+        if let Some(target) = jump_target_mut(insn) {
+            *target = offset_map[(*target as usize).min(old_len)];
         }
     }
 
@@ -124,6 +133,95 @@ fn pass_nop_elimination(module: &mut HlModule) {
     module.instructions = new_instructions;
 }
 
+/// Offset docelowy instrukcji skoku (także handler `? try` i wyjście z `@ x in`)
+fn jump_target_mut(insn: &mut Instruction) -> Option<&mut InsnOff> {
+    match insn {
+        Instruction::JumpIfFalse { offset, .. }
+        | Instruction::JumpIfTrue { offset, .. }
+        | Instruction::Jump { offset }          => Some(offset),
+        Instruction::ForInNext { end_off, .. }  => Some(end_off),
+        Instruction::TryEnter { handler }       => Some(handler),
+        Instruction::CatchArm { next, .. }      => Some(next),
+        _ => None,
+    }
+}
+
+/// Najdłuższe ciało funkcji (bez końcowego Return) wstawiane w miejsce `CallFunc`
+pub const INLINE_MAX_INSNS: u32 = 16;
+
+/// Ciało funkcji `name` do wstawienia: krótkie, bez `CallFunc` (więc bez rekurencji)
+/// i bez `? try` — handler zdejmowany przy powrocie z funkcji zostałby na stosie
+fn inline_candidate(module: &HlModule, name: ConstIdx) -> Option<(usize, usize)> {
+    let entry = module.funcs.find(module.consts.strings.get(name as usize)?)?;
+    let (start, count) = (entry.start_insn as usize, entry.insn_count as usize);
+    if count == 0 || count - 1 > INLINE_MAX_INSNS as usize { return None; }
+    let body = module.instructions.get(start..start + count)?;
+    let leaf = body.iter().all(|i| !matches!(i, Instruction::CallFunc { .. } | Instruction::TryEnter { .. }));
+    leaf.then_some((start, count))
+}
+
+/// Inlining małych funkcji: `CallFunc` → kopia ciała funkcji.
+/// Rejestry są wspólne dla modułu, a zmienne żyją w env, więc kopia nie koliduje
+/// z wołającym ani z inną kopią. `Return` staje się skokiem za wstawione ciało.
+fn pass_inline_small_funcs(module: &mut HlModule) {
+    use std::collections::HashMap;
+    let mut bodies: HashMap<ConstIdx, Option<(usize, usize)>> = HashMap::new();
+    for insn in &module.instructions {
+        if let Instruction::CallFunc { name } = *insn {
+            bodies.entry(name).or_insert_with(|| inline_candidate(module, name));
+        }
+    }
+    if bodies.values().all(Option::is_none) { return; }
+
+    let old = std::mem::take(&mut module.instructions);
+    let old_len = old.len();
+    let mut offset_map = vec![0u32; old_len + 1];
+    let mut out = Vec::with_capacity(old_len);
+    // Kopie ciał mają już docelowe offsety — pomijane przy przepisywaniu skoków
+    let mut copied = Vec::with_capacity(old_len);
+
+    for (old_off, insn) in old.iter().enumerate() {
+        offset_map[old_off] = out.len() as u32;
+        let body = match insn {
+            Instruction::CallFunc { name } => bodies.get(name).copied().flatten(),
+            _ => None,
+        };
+        let Some((start, count)) = body else {
+            out.push(insn.clone());
+            copied.push(false);
+            continue;
+        };
+        let base = out.len() as u32;
+        let end  = base + count as u32;
+        for body_insn in &old[start..start + count] {
+            let mut insn = body_insn.clone();
+            if let Instruction::Return { .. } = insn {
+                let next = out.len() as u32 + 1;
+                insn = if next == end { Instruction::Nop } else { Instruction::Jump { offset: end } };
+            } else if let Some(target) = jump_target_mut(&mut insn) {
+                *target = *target - start as u32 + base;
+            }
+            out.push(insn);
+            copied.push(true);
+        }
+    }
+    offset_map[old_len] = out.len() as u32;
+
+    for (insn, copied) in out.iter_mut().zip(&copied) {
+        if *copied { continue; }
+        if let Some(target) = jump_target_mut(insn) {
+            *target = offset_map[(*target as usize).min(old_len)];
+        }
+    }
+    for entry in &mut module.funcs.entries {
+        let start = (entry.start_insn as usize).min(old_len);
+        let end   = (start + entry.insn_count as usize).min(old_len);
+        entry.start_insn = offset_map[start];
+        entry.insn_count = offset_map[end] - offset_map[start];
+    }
+    module.instructions = out;
+}
+
 /// Usuń SourceLine markers — nie potrzebne w release
 fn pass_source_line_strip(module: &mut HlModule) {
     for insn in &mut module.instructions {
@@ -146,7 +244,7 @@ mod tests {
     use crate::bytecode::HlModule;
 
     fn make_module_with(insns: Vec<Instruction>, nums: Vec<f64>) -> HlModule {
-        use std::path::Path;
+        
         let mut m = HlModule::new("test.hl", 2);
         for n in nums { m.consts.add_num(n); }
         m.instructions = insns;
@@ -170,6 +268,29 @@ mod tests {
                 "Add powinien być wyeliminowany");
     }
 
+    #[test]
+    fn test_inline_small_function_removes_call() {
+        let src = "% x = 1\n-- inc\n?~ @x < 5\n    -- inc\ndone\n: inc def\n    $( @x + 1 ) -> @x\ndone\n";
+        let nodes = hl_parser::parse_source(src).unwrap();
+        let mut m = crate::lower_ast(&nodes, std::path::Path::new("test.hl"), 2);
+        assert_eq!(m.instructions.iter().filter(|i| matches!(i, Instruction::CallFunc { .. })).count(), 2);
+        optimize_module(&mut m);
+        assert!(!m.instructions.iter().any(|i| matches!(i, Instruction::CallFunc { .. })), "{:?}", m.instructions);
+        // Ciało funkcji zostaje (i nadal kończy się Return) — `-- @f` woła ją po nazwie w runtime
+        let inc = m.funcs.find("inc").unwrap();
+        let last = (inc.start_insn + inc.insn_count - 1) as usize;
+        assert!(matches!(m.instructions[last], Instruction::Return { .. }));
+    }
+
+    #[test]
+    fn test_inline_skips_recursive_function() {
+        let src = ": loop def\n    -- loop\ndone\n-- loop\n";
+        let nodes = hl_parser::parse_source(src).unwrap();
+        let mut m = crate::lower_ast(&nodes, std::path::Path::new("test.hl"), 2);
+        optimize_module(&mut m);
+        assert_eq!(m.instructions.iter().filter(|i| matches!(i, Instruction::CallFunc { .. })).count(), 2);
+    }
+
     #[test]
     fn test_nop_elimination_preserves_jumps() {
         let mut m = make_module_with(vec![
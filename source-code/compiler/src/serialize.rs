@@ -1,15 +1,35 @@
 use anyhow::{bail, Context, Result};
 use crate::bytecode::{HlBcHeader, HlModule};
+use crate::string_pool::SharedStringPool;
 use std::path::Path;
 
 pub const BC_MAGIC: &[u8; 4] = b"HLBC";
-pub const BC_VERSION: u32 = 3; // bump: fix @VAR interpolation in commands
+pub const BC_VERSION: u32 = 19; // bump: ConstPool::shared_pool_id (wersjonowana pula wspólna)
 
 /// Shebang dla pliku .bc — `hl run` uruchamia bytecode przez JIT
 const BC_SHEBANG: &str = "#!/usr/bin/env -S /usr/bin/hl run\n";
 
 pub fn write_bc_file(module: &HlModule, path: &Path) -> Result<()> {
+    let buf = bc_bytes(module)?;
 
+    // Zapisz
+    std::fs::write(path, &buf).with_context(|| format!("Zapis .bc: {:?}", path))?;
+
+    // Ustaw bit wykonywalny
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = std::fs::metadata(path)?.permissions();
+        perms.set_mode(0o755);
+        std::fs::set_permissions(path, perms)?;
+    }
+
+    tracing::debug!("Zapisano .bc ({} bajtów): {:?}", buf.len(), path);
+    Ok(())
+}
+
+/// Pełna zawartość pliku .bc (shebang + magic + wersja + nagłówek + moduł)
+pub fn bc_bytes(module: &HlModule) -> Result<Vec<u8>> {
     let mut buf: Vec<u8> = Vec::with_capacity(4096);
 
     // Shebang (musi być pierwszy żeby plik był wykonywalny bezpośrednio)
@@ -32,21 +52,7 @@ pub fn write_bc_file(module: &HlModule, path: &Path) -> Result<()> {
     let module_bytes = bincode::serialize(module)
     .context("Serializacja modułu .bc")?;
     buf.extend_from_slice(&module_bytes);
-
-    // Zapisz
-    std::fs::write(path, &buf).with_context(|| format!("Zapis .bc: {:?}", path))?;
-
-    // Ustaw bit wykonywalny
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let mut perms = std::fs::metadata(path)?.permissions();
-        perms.set_mode(0o755);
-        std::fs::set_permissions(path, perms)?;
-    }
-
-    tracing::debug!("Zapisano .bc ({} bajtów): {:?}", buf.len(), path);
-    Ok(())
+    Ok(buf)
 }
 
 pub fn read_bc_file(path: &Path) -> Result<HlModule> {
@@ -57,6 +63,31 @@ pub fn read_bc_file(path: &Path) -> Result<HlModule> {
 }
 
 pub fn parse_bc_bytes(raw: &[u8], path: &Path) -> Result<HlModule> {
+    let mut module = decode_bc_bytes(raw, path)?;
+
+    // Stringi z puli wspólnej cache
+    if !module.consts.shared_refs.is_empty() {
+        SharedStringPool::load_version(module.consts.shared_pool_id)
+        .and_then(|pool| pool.resolve(&mut module))
+        .with_context(|| format!("Rozwiązywanie puli stringów dla {:?}", path))?;
+    }
+
+    // Odbuduj indeksy HashMap w ConstPool (pola z #[serde(skip)] są puste po deserializacji)
+    module.consts.rebuild_index();
+
+    Ok(module)
+}
+
+/// Wersja puli wspólnej, której potrzebuje plik .bc (None — moduł samodzielny)
+pub fn read_shared_pool_id(path: &Path) -> Result<Option<u64>> {
+    let raw = std::fs::read(path)
+    .with_context(|| format!("Odczyt .bc: {:?}", path))?;
+    let module = decode_bc_bytes(&raw, path)?;
+    Ok((!module.consts.shared_refs.is_empty()).then_some(module.consts.shared_pool_id))
+}
+
+/// Nagłówek + moduł bez rozwiązywania puli wspólnej
+fn decode_bc_bytes(raw: &[u8], path: &Path) -> Result<HlModule> {
     let mut pos = 0usize;
 
     // Pomiń shebang jeśli jest
@@ -101,13 +132,7 @@ pub fn parse_bc_bytes(raw: &[u8], path: &Path) -> Result<HlModule> {
     pos += header_len;
 
     // Bincode module
-    let mut module: HlModule = bincode::deserialize(&raw[pos..])
-    .context("Deserializacja modułu .bc")?;
-
-    // Odbuduj indeksy HashMap w ConstPool (pola z #[serde(skip)] są puste po deserializacji)
-    module.consts.rebuild_index();
-
-    Ok(module)
+    bincode::deserialize(&raw[pos..]).context("Deserializacja modułu .bc")
 }
 
 /// Sprawdź czy plik to poprawny .bc (szybkie sprawdzenie bez pełnego parsowania)
@@ -0,0 +1,298 @@
+use anyhow::{bail, Context, Result};
+use crate::bytecode::{ConstIdx, HlModule, SharedStrRef};
+use crate::cache::cache_dir;
+use crate::serialize::read_shared_pool_id;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub const POOL_MAGIC: &[u8; 4] = b"HLSP";
+pub const POOL_FILE_NAME: &str = "strings.pool";
+
+/// Dłuższe stringi (np. całe bloki extern) są zwykle specyficzne dla programu —
+/// zostają w lokalnej puli modułu.
+pub const SHARED_MAX_LEN: usize = 256;
+
+/// Wspólna pula stringów dla całego cache .bc.
+///
+/// Pula jest tylko dopisywana — raz nadany indeks globalny nigdy się nie zmienia.
+/// `strings.pool` to tylko punkt startowy dla kolejnych kompilacji; moduł wskazuje
+/// niezmienny plik `strings-<id>.pool` z dokładnie tą pulą, względem której go zapisano.
+/// Dwa równoległe procesy tworzą więc dwie wersje zamiast nadpisywać sobie wpisy.
+/// Każda referencja niesie też hash stringu — uszkodzona pula daje błąd, nie zły string.
+#[derive(Debug, Default)]
+pub struct SharedStringPool {
+    strings: Vec<String>,
+    index:   HashMap<String, u32>,
+}
+
+/// Włączane przez HL_SHARED_POOL=1 — domyślnie każdy .bc jest samodzielny
+pub fn shared_pool_enabled() -> bool {
+    matches!(std::env::var("HL_SHARED_POOL").as_deref(), Ok("1") | Ok("true"))
+}
+
+pub fn shared_pool_path() -> PathBuf {
+    cache_dir().join(POOL_FILE_NAME)
+}
+
+/// Niezmienna wersja puli o danym id — obok `strings.pool`
+pub fn shared_pool_version_path(pool_path: &Path, id: u64) -> PathBuf {
+    pool_path.with_file_name(format!("strings-{:016x}.pool", id))
+}
+
+/// Id wersji z nazwy `strings-<id>.pool`
+pub fn pool_version_id(path: &Path) -> Option<u64> {
+    let name = path.file_name()?.to_str()?;
+    let hex = name.strip_prefix("strings-")?.strip_suffix(".pool")?;
+    u64::from_str_radix(hex, 16).ok()
+}
+
+/// Świeżo opublikowana wersja może jeszcze czekać, aż inny proces zapisze wskazujący ją .bc
+pub const POOL_GC_GRACE: Duration = Duration::from_secs(60);
+
+/// Usuń wersje puli z `dir`, których nie wskazuje żaden plik .bc (i starsze niż `grace`).
+/// Każda wersja to pełna kopia puli — bez tego cache rośnie z każdą kompilacją.
+/// Zwraca liczbę usuniętych plików.
+pub fn gc_pool_versions(dir: &Path, grace: Duration) -> Result<usize> {
+    let Ok(read) = std::fs::read_dir(dir) else { return Ok(0); };
+    let (mut versions, mut bcs) = (Vec::new(), Vec::new());
+    for path in read.flatten().map(|e| e.path()) {
+        if let Some(id) = pool_version_id(&path) {
+            versions.push((path, id));
+        } else if path.extension().and_then(|x| x.to_str()) == Some("bc") {
+            bcs.push(path);
+        }
+    }
+    if versions.is_empty() { return Ok(0); }
+
+    // Nieczytelny .bc i tak zostanie odrzucony przy uruchomieniu — jego wersja nie jest potrzebna
+    let used: HashSet<u64> = bcs.iter().filter_map(|bc| read_shared_pool_id(bc).ok().flatten()).collect();
+    let mut removed = 0;
+    for (path, id) in versions {
+        if used.contains(&id) { continue; }
+        let age = std::fs::metadata(&path).and_then(|m| m.modified()).ok()
+        .and_then(|t| t.elapsed().ok()).unwrap_or_default();
+        if age < grace { continue; }
+        tracing::debug!("cache cleanup: usuwam nieużywaną wersję puli {:?}", path);
+        if std::fs::remove_file(&path).is_ok() { removed += 1; }
+    }
+    Ok(removed)
+}
+
+impl SharedStringPool {
+    /// Wczytaj pulę z dysku; brak pliku = pusta pula
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = match std::fs::read(path) {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).with_context(|| format!("Odczyt puli stringów: {:?}", path)),
+        };
+        if raw.len() < 4 || &raw[..4] != POOL_MAGIC {
+            bail!("Nieprawidłowy magic puli stringów: {:?}", path);
+        }
+        let strings: Vec<String> = bincode::deserialize(&raw[4..])
+        .context("Deserializacja puli stringów")?;
+        let index = strings.iter().enumerate().map(|(i, s)| (s.clone(), i as u32)).collect();
+        Ok(Self { strings, index })
+    }
+
+    /// Wczytaj wersję puli, względem której zapisano moduł z cache
+    pub fn load_version(id: u64) -> Result<Self> {
+        Self::load_version_at(&shared_pool_path(), id)
+    }
+
+    fn load_version_at(pool_path: &Path, id: u64) -> Result<Self> {
+        let path = shared_pool_version_path(pool_path, id);
+        if !path.exists() {
+            bail!("Brak wersji {:016x} puli stringów: {:?}", id, path);
+        }
+        let pool = Self::load(&path)?;
+        if pool.id() != id {
+            bail!("Pula stringów {:?} nie zgadza się z wersją {:016x}", path, id);
+        }
+        Ok(pool)
+    }
+
+    /// Zapisz niezmienną wersję puli i zaktualizuj `strings.pool`; zwraca id wersji
+    pub fn publish(&self, pool_path: &Path) -> Result<u64> {
+        let id = self.id();
+        let version = shared_pool_version_path(pool_path, id);
+        if !version.exists() {
+            self.save(&version)?;
+        }
+        self.save(pool_path)?;
+        Ok(id)
+    }
+
+    /// Id wersji — hash wszystkich wpisów w kolejności
+    pub fn id(&self) -> u64 {
+        self.strings.iter().fold(FNV_OFFSET, |h, s| fnv1a(fnv1a(h, s.as_bytes()), &[0]))
+    }
+
+    /// Zapis atomowy (plik tymczasowy + rename) — czytelnik nigdy nie widzi połowy pliku
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut buf = Vec::with_capacity(4096);
+        buf.extend_from_slice(POOL_MAGIC);
+        buf.extend_from_slice(&bincode::serialize(&self.strings).context("Serializacja puli stringów")?);
+        let tmp = path.with_extension(format!("pool.{}.tmp", std::process::id()));
+        std::fs::write(&tmp, &buf).with_context(|| format!("Zapis puli stringów: {:?}", tmp))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("Zapis puli stringów: {:?}", path))?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize { self.strings.len() }
+    pub fn is_empty(&self) -> bool { self.strings.is_empty() }
+    pub fn get(&self, idx: u32) -> Option<&str> { self.strings.get(idx as usize).map(|s| s.as_str()) }
+
+    /// Dodaj lub zdeduplikuj string, zwróć indeks globalny
+    pub fn intern(&mut self, s: &str) -> u32 {
+        if let Some(&i) = self.index.get(s) {
+            return i;
+        }
+        let i = self.strings.len() as u32;
+        self.index.insert(s.to_string(), i);
+        self.strings.push(s.to_string());
+        i
+    }
+
+    /// Przenieś krótkie stringi modułu do puli wspolnej.
+    /// Ich sloty w lokalnej puli zostają puste, a moduł dostaje listę referencji.
+    /// Moduł po tej operacji nadaje się tylko do zapisu — wykonanie wymaga `resolve`.
+    pub fn externalize(&mut self, module: &mut HlModule) {
+        let consts = &mut module.consts;
+        for (i, s) in consts.strings.iter_mut().enumerate() {
+            if s.is_empty() || s.len() > SHARED_MAX_LEN { continue; }
+            let shared = self.intern(s);
+            consts.shared_refs.push(SharedStrRef { local: i as ConstIdx, shared, hash: str_hash(s) });
+            s.clear();
+        }
+    }
+
+    /// Odtwórz lokalną pulę modułu z referencji do puli wspolnej
+    pub fn resolve(&self, module: &mut HlModule) -> Result<()> {
+        let consts = &mut module.consts;
+        for r in &consts.shared_refs {
+            let Some(s) = self.get(r.shared) else {
+                bail!("Brak wpisu {} w puli stringów (pula ma {} wpisów)", r.shared, self.len());
+            };
+            if str_hash(s) != r.hash {
+                bail!("Wpis {} puli stringów nie zgadza się z modułem — pula nadpisana?", r.shared);
+            }
+            let Some(slot) = consts.strings.get_mut(r.local as usize) else {
+                bail!("Referencja do nieistniejącego slotu {} lokalnej puli", r.local);
+            };
+            *slot = s.to_string();
+        }
+        consts.shared_refs.clear();
+        Ok(())
+    }
+}
+
+const FNV_OFFSET: u64 = 14695981039346656037;
+const FNV_PRIME:  u64 = 1099511628211;
+
+/// FNV-1a — ten sam hash co klucz cache
+fn str_hash(s: &str) -> u64 {
+    fnv1a(FNV_OFFSET, s.as_bytes())
+}
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hl_test_support::TestDir;
+    use crate::lower_ast;
+    use hl_parser::parse_source;
+
+    fn module(src: &str, name: &str) -> HlModule {
+        lower_ast(&parse_source(src).unwrap(), Path::new(name), 2)
+    }
+
+    fn shared_idx_of(pool: &SharedStringPool, m: &HlModule, s: &str) -> Option<u32> {
+        m.consts.shared_refs.iter().map(|r| r.shared).find(|&g| pool.get(g) == Some(s))
+    }
+
+    #[test]
+    fn test_common_string_shares_entry() {
+        let mut pool = SharedStringPool::default();
+        let mut a = module("> echo wspolne\n~> tylko a", "a.hl");
+        let mut b = module("~> tylko b\n> echo wspolne", "b.hl");
+        pool.externalize(&mut a);
+        pool.externalize(&mut b);
+
+        let in_a: Vec<u32> = a.consts.shared_refs.iter().map(|r| r.shared)
+        .filter(|&g| pool.get(g).is_some_and(|s| s.contains("wspolne"))).collect();
+        let in_b: Vec<u32> = b.consts.shared_refs.iter().map(|r| r.shared)
+        .filter(|&g| pool.get(g).is_some_and(|s| s.contains("wspolne"))).collect();
+        assert!(!in_a.is_empty(), "Wspólny string powinien trafić do puli");
+        assert_eq!(in_a, in_b, "Oba moduły powinny wskazywać ten sam wpis puli");
+        assert!(shared_idx_of(&pool, &a, "tylko a").is_some());
+        assert!(shared_idx_of(&pool, &b, "tylko a").is_none());
+    }
+
+    #[test]
+    fn test_resolve_roundtrip() {
+        let original = module("> echo x\n~> hello", "r.hl");
+        let mut m = original.clone();
+        let mut pool = SharedStringPool::default();
+        pool.externalize(&mut m);
+        assert!(m.consts.strings.iter().all(|s| s.is_empty()));
+        pool.resolve(&mut m).unwrap();
+        assert_eq!(m.consts.strings, original.consts.strings);
+        assert!(m.consts.shared_refs.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_rejects_rewritten_pool() {
+        let mut m = module("~> hello", "r.hl");
+        let mut pool = SharedStringPool::default();
+        pool.externalize(&mut m);
+        // Inny proces zapisał pulę w tej samej kolejności, ale z innym stringiem
+        let mut other = SharedStringPool::default();
+        for i in 0..pool.len() { other.intern(&format!("inne-{}", i)); }
+        assert!(other.resolve(&mut m).is_err());
+    }
+
+    #[test]
+    fn test_concurrent_writers_keep_their_versions() {
+        let dir = TestDir::new("pool-race");
+        let path = dir.join(POOL_FILE_NAME);
+        // Dwa procesy wczytały tę samą (pustą) pulę i dopisały różne stringi
+        let (mut pa, mut pb) = (SharedStringPool::load(&path).unwrap(), SharedStringPool::load(&path).unwrap());
+        let mut a = module("~> tylko a", "a.hl");
+        let mut b = module("~> tylko b", "b.hl");
+        pa.externalize(&mut a);
+        pb.externalize(&mut b);
+        a.consts.shared_pool_id = pa.publish(&path).unwrap();
+        b.consts.shared_pool_id = pb.publish(&path).unwrap();
+        // `strings.pool` należy do B, ale moduł A nadal czyta swoją wersję
+        assert!(SharedStringPool::load(&path).unwrap().resolve(&mut a.clone()).is_err());
+        SharedStringPool::load_version_at(&path, a.consts.shared_pool_id).unwrap().resolve(&mut a).unwrap();
+        SharedStringPool::load_version_at(&path, b.consts.shared_pool_id).unwrap().resolve(&mut b).unwrap();
+        assert!(a.consts.strings.iter().any(|s| s.contains("tylko a")));
+        assert!(b.consts.strings.iter().any(|s| s.contains("tylko b")));
+        let err = SharedStringPool::load_version_at(&path, 0xdead).unwrap_err();
+        assert!(err.to_string().contains("Brak wersji"), "{}", err);
+    }
+
+    #[test]
+    fn test_save_load() {
+        let dir = TestDir::new("pool");
+        let path = dir.join(POOL_FILE_NAME);
+        let mut pool = SharedStringPool::default();
+        pool.intern("a");
+        pool.intern("b");
+        pool.save(&path).unwrap();
+        let loaded = SharedStringPool::load(&path).unwrap();
+        assert_eq!(loaded.get(1), Some("b"));
+        let mut loaded = loaded;
+        assert_eq!(loaded.intern("a"), 0);
+    }
+}
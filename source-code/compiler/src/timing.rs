@@ -0,0 +1,63 @@
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// Czasy faz kompilacji dla `hl compile --measure`.
+///
+/// Każda faza mierzy tylko własne domknięcie. Suma faz nie obejmuje tego, co dzieje się
+/// między nimi (np. `--dump-ir`); `wall` liczy od utworzenia do końca ostatniej fazy.
+pub struct PhaseTimings {
+    started: Instant,
+    last:    Instant,
+    phases:  Vec<(&'static str, Duration)>,
+}
+
+impl Default for PhaseTimings {
+    fn default() -> Self { Self::new() }
+}
+
+impl PhaseTimings {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self { started: now, last: now, phases: Vec::new() }
+    }
+
+    /// Zmierz `f` jako fazę `name`
+    pub fn phase<T>(&mut self, name: &'static str, f: impl FnOnce() -> T) -> T {
+        let t0 = Instant::now();
+        let out = f();
+        self.last = Instant::now();
+        self.phases.push((name, self.last - t0));
+        out
+    }
+
+    pub fn phases(&self) -> &[(&'static str, Duration)] {
+        &self.phases
+    }
+
+    /// Suma faz
+    pub fn total(&self) -> Duration {
+        self.phases.iter().map(|(_, d)| *d).sum()
+    }
+
+    /// Czas od utworzenia do końca ostatniej fazy
+    pub fn wall(&self) -> Duration {
+        self.last - self.started
+    }
+
+    /// Tabela: faza, ms, udział w sumie
+    pub fn render(&self) -> String {
+        let total = self.total();
+        let mut out = String::new();
+        let _ = writeln!(out, "{:<12} {:>10} {:>7}", "faza", "ms", "%");
+        for (name, d) in &self.phases {
+            let pct = if total.is_zero() { 0.0 } else { d.as_secs_f64() / total.as_secs_f64() * 100.0 };
+            let _ = writeln!(out, "{:<12} {:>10.3} {:>6.1}%", name, ms(*d), pct);
+        }
+        let _ = writeln!(out, "{:<12} {:>10.3} {:>6.1}%", "razem", ms(total), 100.0);
+        out
+    }
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
@@ -28,19 +28,23 @@ impl Arena {
         Self { ptr, capacity: size, used: Cell::new(0), layout }
     }
 
-    /// Zaalokuj `size` bajtów wyrównanych do `align`
+    /// Zaalokuj `size` bajtów wyrównanych do `align` (potęga dwójki)
     /// Zwraca None jeśli brak miejsca (executor powinien fallback do heap)
     #[inline]
     pub fn alloc_bytes(&self, size: usize, align: usize) -> Option<*mut u8> {
+        if !align.is_power_of_two() { return None; }
         let used = self.used.get();
-        // Wyrównaj do `align`
-        let aligned = (used + align - 1) & !(align - 1);
-        let new_used = aligned + size;
+        // Wyrównujemy adres, nie offset — blok ma wyrównanie tylko 8, więc dla align > 8
+        // wyrównany offset nie daje wyrównanego wskaźnika
+        let addr    = (self.ptr as usize).checked_add(used)?;
+        let aligned = addr.checked_add(align - 1)? & !(align - 1);
+        let start   = aligned - self.ptr as usize;
+        let new_used = start.checked_add(size)?;
         if new_used > self.capacity {
             return None; // Arena pełna → fallback
         }
         self.used.set(new_used);
-        Some(unsafe { self.ptr.add(aligned) })
+        Some(unsafe { self.ptr.add(start) })
     }
 
     /// Zaalokuj String w arenie — kopiuje bajty do areny, zwraca &str
@@ -148,3 +152,36 @@ impl std::fmt::Display for ArenaStats {
                if self.overflowed { " [OVERFLOW→heap]" } else { "" })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alloc_bytes_aligned_up_to_the_end() {
+        let arena = Arena::new(64);
+        assert!(arena.alloc_bytes(3, 1).is_some());
+        let mut last = None;
+        while let Some(p) = arena.alloc_bytes(8, 8) {
+            assert_eq!(p as usize % 8, 0);
+            last = Some(p as usize);
+        }
+        // Ostatni ośmiobajtowy obiekt kończy się dokładnie na końcu areny
+        assert_eq!(last.unwrap() + 8, arena.ptr as usize + arena.capacity());
+        assert_eq!(arena.remaining(), 0);
+    }
+
+    #[test]
+    fn test_alloc_bytes_over_alignment_and_oversize() {
+        let arena = Arena::new(256);
+        arena.alloc_bytes(1, 1).unwrap();
+        let p = arena.alloc_bytes(16, 64).unwrap();
+        assert_eq!(p as usize % 64, 0);
+        // Nie mieści się — None zamiast wskaźnika za blokiem; stan areny bez zmian
+        let used = arena.used();
+        assert!(arena.alloc_bytes(1024, 8).is_none());
+        assert!(arena.alloc_bytes(usize::MAX, 8).is_none());
+        assert!(arena.alloc_bytes(8, 3).is_none());
+        assert_eq!(arena.used(), used);
+    }
+}
@@ -0,0 +1,151 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use anyhow::Result;
+use hl_parser::Node;
+
+/// Cache AST importowanych plików (`<<`, `<*`, `# <lib>`) — osobny od cache .bc.
+///
+/// Klucz to hash treści pliku, wersji parsera i `AST_CACHE_VERSION`, więc zmiana źródła
+/// albo nowy parser sam unieważnia wpis, a ta sama biblioteka w dwóch projektach dzieli jeden. Zapis: magic + wersja + bincode `Vec<Node>`.
+/// Uszkodzony wpis albo inna wersja formatu — parsujemy od nowa i nadpisujemy.
+/// `HL_AST_CACHE=0` wyłącza cache (np. przy debugowaniu parsera).
+pub const AST_CACHE_MAGIC: &[u8; 4] = b"HLA\0";
+/// Podbij przy każdej zmianie `Node` — stare wpisy przestaną pasować
+pub const AST_CACHE_VERSION: u32 = 7;
+
+pub struct AstCache {
+    dir: Option<PathBuf>,
+    /// Ile razy naprawdę parsowano (cache miss) — do testów i `--verbose`
+    parses: AtomicUsize,
+}
+
+impl AstCache {
+    pub fn at(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: Some(dir.into()), parses: AtomicUsize::new(0) }
+    }
+
+    /// Wspólny cache procesu w `~/.hackeros/hacker-lang/cache/ast`
+    pub fn global() -> &'static AstCache {
+        static GLOBAL: OnceLock<AstCache> = OnceLock::new();
+        GLOBAL.get_or_init(|| {
+            let dir = match std::env::var("HL_AST_CACHE").as_deref() {
+                Ok("0") | Ok("false") => None,
+                _ => Some(crate::libs::hl_cache_dir().join("ast")),
+            };
+            Self { dir, parses: AtomicUsize::new(0) }
+        })
+    }
+
+    pub fn parse_count(&self) -> usize {
+        self.parses.load(Ordering::Relaxed)
+    }
+
+    pub fn parse(&self, source: &str) -> Result<Vec<Node>> {
+        let Some(dir) = &self.dir else { return self.parse_fresh(source) };
+        let path = dir.join(format!("{:016x}.ast", key(source)));
+        if let Some(nodes) = std::fs::read(&path).ok().and_then(|b| decode(&b)) {
+            return Ok(nodes);
+        }
+        let nodes = self.parse_fresh(source)?;
+        // Cache to tylko przyspieszenie — błąd zapisu nie psuje uruchomienia
+        if let Err(e) = std::fs::create_dir_all(dir).and_then(|_| write_atomic(&path, &encode(&nodes))) {
+            tracing::debug!("ast cache: zapis {:?}: {}", path, e);
+        }
+        Ok(nodes)
+    }
+
+    fn parse_fresh(&self, source: &str) -> Result<Vec<Node>> {
+        self.parses.fetch_add(1, Ordering::Relaxed);
+        Ok(hl_parser::parse_source(source)?)
+    }
+}
+
+/// Zapis atomowy (plik tymczasowy w tym samym katalogu + rename) — równoległy `hl`
+/// czyta stary wpis albo cały nowy, nigdy połowę. Numer w nazwie rozdziela wątki procesu.
+fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    static SEQ: AtomicUsize = AtomicUsize::new(0);
+    let tmp = path.with_extension(format!("ast.{}-{}.tmp", std::process::id(), SEQ.fetch_add(1, Ordering::Relaxed)));
+    if let Err(e) = std::fs::write(&tmp, bytes).and_then(|_| std::fs::rename(&tmp, path)) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Parsuj importowany plik przez globalny cache
+pub fn parse_import(source: &str) -> Result<Vec<Node>> {
+    AstCache::global().parse(source)
+}
+
+/// Klucz wpisu — hash wersji formatu, wersji parsera i treści
+/// (`hl run --dump-cache-key` pokazuje go dla importów)
+pub fn key(source: &str) -> u64 {
+    key_for(AST_CACHE_VERSION, hl_parser::PARSER_VERSION, source)
+}
+
+fn key_for(cache_version: u32, parser_version: &str, source: &str) -> u64 {
+    let mut hash: u64 = 14695981039346656037;
+    let parts: [&[u8]; 4] = [&cache_version.to_le_bytes(), parser_version.as_bytes(), &[0], source.as_bytes()];
+    for byte in parts.into_iter().flatten() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(1099511628211);
+    }
+    hash
+}
+
+fn encode(nodes: &[Node]) -> Vec<u8> {
+    let mut out = AST_CACHE_MAGIC.to_vec();
+    out.extend_from_slice(&AST_CACHE_VERSION.to_le_bytes());
+    out.extend(bincode::serialize(nodes).unwrap_or_default());
+    out
+}
+
+fn decode(bytes: &[u8]) -> Option<Vec<Node>> {
+    let body = bytes.strip_prefix(AST_CACHE_MAGIC.as_slice())?;
+    let (ver, body) = body.split_first_chunk::<4>()?;
+    if u32::from_le_bytes(*ver) != AST_CACHE_VERSION { return None; }
+    bincode::deserialize(body).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hl_test_support::TestDir;
+
+    #[test]
+    fn test_unchanged_library_is_not_reparsed() {
+        let dir = TestDir::new("ast-cache");
+        let lib = "% x = 1\n: f def\n    ~> @x\ndone\n? ok\n    ~> ok\ndone\n";
+
+        let first = AstCache::at(dir.path());
+        let nodes = first.parse(lib).unwrap();
+        assert_eq!(first.parse_count(), 1);
+
+        // Nowy proces = nowy licznik; ta sama treść czytana z dysku
+        let second = AstCache::at(dir.path());
+        assert_eq!(format!("{:?}", second.parse(lib).unwrap()), format!("{:?}", nodes));
+        assert_eq!(second.parse_count(), 0);
+
+        // Zmiana treści — nowy klucz, ponowne parsowanie
+        second.parse("% x = 2\n").unwrap();
+        assert_eq!(second.parse_count(), 1);
+
+        // Zapis przez rename — żadnych plików tymczasowych w katalogu
+        let mut names: Vec<String> = std::fs::read_dir(&dir).unwrap().flatten()
+        .map(|e| e.file_name().to_string_lossy().into_owned()).collect();
+        names.sort();
+        let mut expected = vec![format!("{:016x}.ast", key(lib)), format!("{:016x}.ast", key("% x = 2\n"))];
+        expected.sort();
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn test_key_depends_on_versions() {
+        let src = "% x = 1\n";
+        let base = key_for(AST_CACHE_VERSION, hl_parser::PARSER_VERSION, src);
+        assert_eq!(key(src), base);
+        assert_ne!(key_for(AST_CACHE_VERSION + 1, hl_parser::PARSER_VERSION, src), base);
+        assert_ne!(key_for(AST_CACHE_VERSION, "0.0.0-inny", src), base);
+    }
+}
@@ -0,0 +1,61 @@
+use std::path::{Path, PathBuf};
+use anyhow::anyhow;
+use serde::Serialize;
+use hl_parser::Node;
+
+/// AST jednego pliku dla narzędzi (LSP, formatery) — `hl ast --lines` / `--imports`.
+/// W przeciwieństwie do zwykłego `hl ast` zawiera markery `SourceLine` przed instrukcjami.
+#[derive(Debug, Serialize)]
+pub struct FileAst {
+    pub file:  PathBuf,
+    pub nodes: Vec<Node>,
+}
+
+/// Ładnie sformatowany JSON: lista `{ "file", "nodes" }`, pierwszy jest skrypt.
+/// Z `resolve_imports` dochodzą pliki `<<` / `<*` (te same ścieżki co `hl run --watch`).
+/// Błąd wczytania albo parsowania któregokolwiek pliku — brak wyniku, wszystkie błędy na liście.
+pub fn analyze_to_json(path: &Path, resolve_imports: bool) -> Result<String, Vec<anyhow::Error>> {
+    let paths = if resolve_imports { crate::watch::watched_paths(path) } else { vec![path.to_path_buf()] };
+    let mut files  = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        match std::fs::read_to_string(&path) {
+            Ok(src) => match hl_parser::parse_source_with_lines(&src) {
+                Ok((nodes, _)) => files.push(FileAst { file: path, nodes }),
+                Err(e)         => errors.push(anyhow!("{}: {}", path.display(), e)),
+            },
+            Err(e) => errors.push(anyhow!("{}: {}", path.display(), e)),
+        }
+    }
+    if !errors.is_empty() { return Err(errors); }
+    serde_json::to_string_pretty(&files).map_err(|e| vec![e.into()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hl_test_support::TestDir;
+
+    #[test]
+    fn test_json_with_lines_and_imports() {
+        let dir = TestDir::new("ast-json");
+        let lib = dir.join("lib.hl");
+        std::fs::write(&lib, ": pomocnik def\n    ~> lib\ndone\n").unwrap();
+        let main = dir.join("main.hl");
+        std::fs::write(&main, format!("<< {}\n\n: powitanie def\n    ~> hej\ndone\n", lib.display())).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&analyze_to_json(&main, true).unwrap()).unwrap();
+        let files = json.as_array().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0]["file"], main.display().to_string());
+        let nodes = files[0]["nodes"].to_string();
+        assert!(nodes.contains(r#""name":"powitanie""#), "{}", nodes);
+        assert!(nodes.contains(r#"{"SourceLine":3}"#), "{}", nodes);
+        assert!(files[1]["nodes"].to_string().contains("pomocnik"));
+
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&analyze_to_json(&main, false).unwrap())
+                       .unwrap().as_array().unwrap().len(), 1);
+        std::fs::write(&lib, "? ok\n").unwrap();
+        assert!(analyze_to_json(&main, true).is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use std::path::{Path, PathBuf};
+use anyhow::anyhow;
+use hl_parser::{CommandMode, Node, StringPart, VarValue};
+use hl_parser::cmd_template::{split_placeholders, CmdSegment};
+
+/// `hl audit` — każda zewnętrzna komenda, którą skrypt i jego importy mogą uruchomić,
+/// bez wykonywania czegokolwiek. Wchodzi też do ciał funkcji, pętli, `? try` itd.,
+/// więc raport pokazuje komendy możliwe, nie tylko te, które na pewno się wykonają.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandSite {
+    pub file:         PathBuf,
+    /// Linia instrukcji (0, gdy nieznana)
+    pub line:         usize,
+    /// Operator jak w źródle: `>`, `^>>`, `> |>`, `? >`, `*>`, `&`, `|>`, `||`, `_>`, `//`
+    pub op:           &'static str,
+    pub command:      String,
+    pub sudo:         bool,
+    pub isolated:     bool,
+    /// Tekst komendy zawiera `@zmienną` albo `{zmienną}` — ostateczna postać znana dopiero w runtime
+    pub substitution: bool,
+}
+
+/// Skrypt + importy `<<` / `<*` (te same ścieżki co `hl run --watch`).
+/// Plik, którego nie da się wczytać albo sparsować, trafia do listy błędów — raport
+/// z pominiętym plikiem byłby niepełny, więc wtedy nie ma wyniku.
+pub fn extract_commands(script: &Path) -> Result<Vec<CommandSite>, Vec<anyhow::Error>> {
+    extract_commands_to_depth(script, None)
+}
+
+/// `hl audit --depth N` — importy najwyżej N poziomów w głąb (0 = tylko skrypt)
+pub fn extract_commands_to_depth(script: &Path, max_depth: Option<usize>) -> Result<Vec<CommandSite>, Vec<anyhow::Error>> {
+    let mut sites = Vec::new();
+    let mut errors = Vec::new();
+    for path in crate::watch::import_paths(script, max_depth) {
+        match std::fs::read_to_string(&path) {
+            Ok(src) => match commands_in_source(&src, &path) {
+                Ok(found) => sites.extend(found),
+                Err(e)    => errors.push(anyhow!("{}: {}", path.display(), e)),
+            },
+            Err(e) => errors.push(anyhow!("{}: {}", path.display(), e)),
+        }
+    }
+    if errors.is_empty() { Ok(sites) } else { Err(errors) }
+}
+
+pub fn commands_in_source(source: &str, file: &Path) -> Result<Vec<CommandSite>, hl_parser::ParseError> {
+    let (nodes, _) = hl_parser::parse_source_with_lines(source)?;
+    let mut walker = Walker { file, line: 0, sites: Vec::new() };
+    walker.nodes(&nodes);
+    Ok(walker.sites)
+}
+
+struct Walker<'a> {
+    file:  &'a Path,
+    /// Ostatni marker SourceLine — linia bieżącej instrukcji
+    line:  usize,
+    sites: Vec<CommandSite>,
+}
+
+impl Walker<'_> {
+    fn nodes(&mut self, nodes: &[Node]) {
+        for n in nodes { self.node(n); }
+    }
+
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::SourceLine(line) => self.line = *line,
+            Node::Command { raw, mode, .. } => {
+                let (op, sudo, isolated) = mode_op(mode);
+                self.site(op, raw, sudo, isolated);
+            }
+            Node::PipeToVar { command, mode, .. } => {
+                let (op, sudo, isolated) = match mode {
+                    CommandMode::Sudo | CommandMode::WithVarsSudo => ("^> |>", true, false),
+                    CommandMode::WithVars                         => (">> |>", false, false),
+                    _                                             => ("> |>", false, false),
+                };
+                self.site(op, command, sudo, isolated);
+            }
+            Node::VarDecl { value: VarValue::ExitStatus(cmd), .. } => self.site("? >", cmd, false, false),
+            Node::HshCommand { raw }     => self.site("*>", raw, false, false),
+            Node::Background { raw }     => self.site("&", raw, false, false),
+            Node::Feed { command, .. } if !command.trim_start().starts_with("::") => {
+                self.site("|>", command, false, false);
+            }
+            Node::HackerOsApi { tool, args } => {
+                let cmd = format!("{} {}", tool.binary_name(), parts_text(args));
+                self.site("||", cmd.trim_end(), false, false);
+            }
+            // `// bin [pakiet]` — brak binarki to (po potwierdzeniu) instalacja przez sudo
+            Node::Dependency { name, apt_package } => {
+                let pkg = apt_package.as_deref().unwrap_or(name);
+                self.site("//", &format!("apt-get -y install {}", pkg.trim()), true, false);
+            }
+            Node::ExternDef { file, body, .. } => {
+                self.site("_>", file, false, false);
+                self.nodes(body);
+            }
+
+            Node::Conditional { body, .. } | Node::ForIn { body, .. } | Node::WhileLoop { body, .. }
+            | Node::RepeatN { body, .. } | Node::Retry { body, .. } | Node::Bench { body, .. }
+            | Node::TempDir { body, .. } | Node::Goroutine { body, .. } | Node::Block(body)
+            | Node::FuncDef { body, .. } | Node::ArenaFuncDef { body, .. } => self.nodes(body),
+            Node::MatchExpr { arms, .. } => for arm in arms { self.nodes(&arm.body) },
+            Node::Try { body, catches } => {
+                self.nodes(body);
+                for arm in catches { self.nodes(&arm.body); }
+            }
+            Node::Chain { node, .. } => self.node(node),
+            _ => {}
+        }
+    }
+
+    fn site(&mut self, op: &'static str, command: &str, sudo: bool, isolated: bool) {
+        self.sites.push(CommandSite {
+            file: self.file.to_path_buf(),
+            line: self.line,
+            op,
+            command: command.trim().to_string(),
+            sudo,
+            isolated,
+            substitution: has_substitution(command),
+        });
+    }
+}
+
+/// Operator, sudo, izolacja
+fn mode_op(mode: &CommandMode) -> (&'static str, bool, bool) {
+    match mode {
+        CommandMode::Plain            => (">",   false, false),
+        CommandMode::Sudo             => ("^>",  true,  false),
+        CommandMode::Isolated         => ("->",  false, true),
+        CommandMode::IsolatedSudo     => ("^->", true,  true),
+        CommandMode::WithVars         => (">>",  false, false),
+        CommandMode::WithVarsSudo     => ("^>>", true,  false),
+        CommandMode::WithVarsIsolated => ("->>", false, true),
+    }
+}
+
+fn has_substitution(raw: &str) -> bool {
+    hl_parser::ast::parse_string_parts(raw).iter().any(|p| !matches!(p, StringPart::Literal(_)))
+        || split_placeholders(raw).iter().any(|s| matches!(s, CmdSegment::Quoted(_)))
+}
+
+fn parts_text(parts: &[StringPart]) -> String {
+    parts.iter().map(|p| match p {
+        StringPart::Literal(s) => s.clone(),
+        StringPart::Var(v)     => format!("@{}", v),
+        StringPart::DynVar(inner) => format!("@{{{}}}", parts_text(inner)),
+    }).collect()
+}
+
+/// Raport: `plik:linia  op  [sudo] [izolacja] [zmienne]  komenda`, na końcu podsumowanie
+pub fn render(sites: &[CommandSite]) -> String {
+    let mut out = String::new();
+    for s in sites {
+        let mut flags = Vec::new();
+        if s.sudo         { flags.push("sudo"); }
+        if s.isolated     { flags.push("izolacja"); }
+        if s.substitution { flags.push("zmienne"); }
+        let flags = if flags.is_empty() { String::new() } else { format!("[{}]", flags.join(", ")) };
+        out.push_str(&format!("{}:{}  {:<5} {:<24} {}\n", s.file.display(), s.line, s.op, flags, s.command));
+    }
+    let sudo = sites.iter().filter(|s| s.sudo).count();
+    out.push_str(&format!("{} komend, {} przez sudo\n", sites.len(), sudo));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hl_test_support::TestDir;
+
+    #[test]
+    fn test_nested_command_sites() {
+        let src = "> ls -la\n\
+                   ? ok\n    ^> apt-get update\ndone\n\
+                   @ f in a b\n    ^>> rm -f {f}\n    ->> cat @f\ndone\n\
+                   : pomoc def\n    *> hpkg list\ndone\n\
+                   > whoami |> @kto\n\
+                   // ninja [ninja-build]\n";
+        let sites = commands_in_source(src, Path::new("s.hl")).unwrap();
+        let got: Vec<_> = sites.iter().map(|s| (s.line, s.op, s.command.as_str(), s.sudo, s.substitution)).collect();
+        assert_eq!(got, vec![
+            (1,  ">",     "ls -la",         false, false),
+            (3,  "^>",    "apt-get update", true,  false),
+            (6,  "^>>",   "rm -f {f}",      true,  true),
+            (7,  "->>",   "cat @f",         false, true),
+            (10, "*>",    "hpkg list",      false, false),
+            (12, "> |>",  "whoami",         false, false),
+            (13, "//",    "apt-get -y install ninja-build", true, false),
+        ]);
+        assert!(sites[3].isolated);
+        assert!(render(&sites).ends_with("7 komend, 3 przez sudo\n"));
+    }
+
+    #[test]
+    fn test_import_depth_limits_scanned_files() {
+        let dir = TestDir::new("audit-depth");
+        let deep = dir.join("deep.hl");
+        std::fs::write(&deep, "^> rm -rf /var/cache/x\n").unwrap();
+        let lib = dir.join("lib.hl");
+        std::fs::write(&lib, format!("<< {}\n> ls\n", deep.display())).unwrap();
+        let main = dir.join("main.hl");
+        std::fs::write(&main, format!("<< {}\n> whoami\n", lib.display())).unwrap();
+
+        let files = |depth| -> Vec<PathBuf> {
+            let mut f: Vec<_> = extract_commands_to_depth(&main, depth).unwrap().into_iter().map(|s| s.file).collect();
+            f.dedup();
+            f
+        };
+        assert_eq!(files(Some(0)), vec![main.clone()]);
+        assert_eq!(files(Some(1)), vec![main.clone(), lib.clone()]);
+        assert_eq!(files(None), vec![main.clone(), lib.clone(), deep.clone()]);
+        // Komenda z importu zgłoszona z plikiem i linią biblioteki
+        let all = extract_commands(&main).unwrap();
+        let rm = all.iter().find(|s| s.command.starts_with("rm -rf")).unwrap();
+        assert_eq!((rm.file.as_path(), rm.line, rm.sudo), (deep.as_path(), 1, true));
+
+        // main → a → b, main → b, b → c: b jest na głębokości 1, więc c (2) wchodzi do Some(2)
+        let c = dir.join("c.hl");
+        std::fs::write(&c, "> id\n").unwrap();
+        let b = dir.join("b.hl");
+        std::fs::write(&b, format!("<< {}\n", c.display())).unwrap();
+        let a = dir.join("a.hl");
+        std::fs::write(&a, format!("<< {}\n", b.display())).unwrap();
+        let main2 = dir.join("main2.hl");
+        std::fs::write(&main2, format!("<< {}\n<< {}\n", a.display(), b.display())).unwrap();
+        let mut scanned: Vec<_> = extract_commands_to_depth(&main2, Some(2)).unwrap().into_iter().map(|s| s.file).collect();
+        scanned.dedup();
+        assert_eq!(scanned, vec![c.clone()]);
+        assert_eq!(crate::watch::import_paths(&main2, Some(2)), vec![main2.clone(), a, b, c]);
+    }
+}
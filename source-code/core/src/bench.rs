@@ -0,0 +1,100 @@
+use anyhow::Result;
+use std::time::{Duration, Instant};
+use hl_parser::ast::Node;
+use crate::env::Env;
+use crate::executor::exec_nodes;
+
+/// Parametry `hl bench`
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// Przebiegi rozgrzewkowe — nie wliczane do wyniku (cache FS, page cache, JIT CPU)
+    pub warmup:     u32,
+    pub iterations: u32,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self { Self { warmup: 3, iterations: 20 } }
+}
+
+/// Wynik jednego bloku `? bench`
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    pub name:      String,
+    /// Czasy kolejnych mierzonych przebiegów (bez rozgrzewki)
+    pub samples:   Vec<Duration>,
+    /// Kod wyjścia ostatniego przebiegu — ≠ 0 znaczy, że mierzymy błąd
+    pub last_exit: i32,
+}
+
+impl BenchReport {
+    pub fn mean(&self) -> Duration {
+        if self.samples.is_empty() { return Duration::ZERO; }
+        self.samples.iter().sum::<Duration>() / self.samples.len() as u32
+    }
+
+    pub fn median(&self) -> Duration {
+        let mut s = self.samples.clone();
+        s.sort();
+        match s.len() {
+            0 => Duration::ZERO,
+            n if n % 2 == 1 => s[n / 2],
+            n => (s[n / 2 - 1] + s[n / 2]) / 2,
+        }
+    }
+
+    pub fn min(&self) -> Duration {
+        self.samples.iter().min().copied().unwrap_or(Duration::ZERO)
+    }
+}
+
+/// Bloki `? bench` z najwyższego poziomu skryptu
+pub fn collect_benches(nodes: &[Node]) -> Vec<(&str, &[Node])> {
+    nodes.iter().filter_map(|n| match n {
+        Node::Bench { name, body } => Some((name.as_str(), body.as_slice())),
+        _ => None,
+    }).collect()
+}
+
+/// Wykonaj skrypt raz (definicje funkcji, zmienne — executor pomija bloki bench),
+/// potem zmierz każdy blok `? bench` w tym samym Env.
+pub fn run_benches(nodes: &[Node], env: &mut Env, opts: &BenchOptions) -> Result<Vec<BenchReport>> {
+    exec_nodes(nodes, env)?;
+    let mut reports = Vec::new();
+    for (name, body) in collect_benches(nodes) {
+        for _ in 0..opts.warmup {
+            exec_nodes(body, env)?;
+        }
+        let mut samples = Vec::with_capacity(opts.iterations as usize);
+        let mut last_exit = 0;
+        for _ in 0..opts.iterations.max(1) {
+            let t0 = Instant::now();
+            last_exit = exec_nodes(body, env)?.exit_code;
+            samples.push(t0.elapsed());
+        }
+        reports.push(BenchReport { name: name.to_string(), samples, last_exit });
+    }
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_block_runs_and_reports_duration() {
+        let src = "% licznik = 0\n? bench liczenie\n  $( @licznik + 1 ) -> @licznik\n  > sleep 0.001\ndone\n";
+        let nodes = hl_parser::parse_source(src).unwrap();
+        let mut env = Env::new();
+        let opts = BenchOptions { warmup: 1, iterations: 3 };
+        let reports = run_benches(&nodes, &mut env, &opts).unwrap();
+
+        assert_eq!(reports.len(), 1);
+        let r = &reports[0];
+        assert_eq!(r.name, "liczenie");
+        assert_eq!(r.samples.len(), 3);
+        assert!(r.min() > Duration::ZERO && r.min() <= r.median() && r.median() <= r.samples.iter().max().copied().unwrap());
+        assert!(r.mean() > Duration::ZERO);
+        // Każdy przebieg (rozgrzewka + pomiary) wykonał ciało, zwykły przebieg skryptu — nie
+        assert_eq!(env.get_var("licznik").to_string_val(), "4");
+    }
+}
@@ -0,0 +1,24 @@
+use std::io::IsTerminal;
+
+/// Czy wypisywać kolory — jedna decyzja dla `colored` i surowych sekwencji ANSI.
+///
+/// Wyłączone przy `--no-color`, niepustym `NO_COLOR` (no-color.org) albo gdy stdout
+/// lub stderr nie jest terminalem (przekierowanie do pliku, log CI).
+/// `CLICOLOR_FORCE` wymusza kolory mimo braku terminala, ale nie mimo `NO_COLOR`.
+pub fn init(no_color_flag: bool) {
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let forced = std::env::var("CLICOLOR_FORCE").is_ok_and(|v| v != "0");
+    let tty = std::io::stdout().is_terminal() && std::io::stderr().is_terminal();
+    if no_color_flag || no_color_env || (!tty && !forced) {
+        colored::control::set_override(false);
+    }
+}
+
+pub fn enabled() -> bool {
+    colored::control::SHOULD_COLORIZE.should_colorize()
+}
+
+/// Surowa sekwencja ANSI albo "" gdy kolory są wyłączone
+pub fn ansi(code: &'static str) -> &'static str {
+    if enabled() { code } else { "" }
+}
@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Licznik wykonań linii źródła dla `hl run --coverage`.
+///
+/// Linie wykonywalne rejestruje parser (markery Node::SourceLine), więc linia
+/// z licznikiem 0 to instrukcja, do której program nigdy nie doszedł — a nie
+/// komentarz czy pusta linia.
+#[derive(Debug, Default, Clone)]
+pub struct Coverage {
+    lines: BTreeMap<usize, u64>,
+}
+
+impl Coverage {
+    pub fn new() -> Self { Self::default() }
+
+    /// Zarejestruj linie wykonywalne (bez zerowania już zliczonych)
+    pub fn register(&mut self, lines: &[usize]) {
+        for &l in lines {
+            self.lines.entry(l).or_insert(0);
+        }
+    }
+
+    #[inline]
+    pub fn hit(&mut self, line: usize) {
+        *self.lines.entry(line).or_insert(0) += 1;
+    }
+
+    /// None = linia niewykonywalna (nie zarejestrowana)
+    pub fn hits(&self, line: usize) -> Option<u64> {
+        self.lines.get(&line).copied()
+    }
+
+    /// Raport w formacie LCOV (tracefile) — czytany przez genhtml, Codecov itd.
+    pub fn to_lcov(&self, source_path: &str) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "TN:");
+        let _ = writeln!(out, "SF:{}", source_path);
+        for (line, hits) in &self.lines {
+            let _ = writeln!(out, "DA:{},{}", line, hits);
+        }
+        let found = self.lines.len();
+        let hit   = self.lines.values().filter(|&&h| h > 0).count();
+        let _ = writeln!(out, "LF:{}", found);
+        let _ = writeln!(out, "LH:{}", hit);
+        out.push_str("end_of_record\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lcov_counts_found_and_hit() {
+        let mut c = Coverage::new();
+        c.register(&[1, 2, 4]);
+        c.hit(1);
+        c.hit(1);
+        c.hit(4);
+        let lcov = c.to_lcov("a.hl");
+        assert!(lcov.contains("SF:a.hl\n"));
+        assert!(lcov.contains("DA:1,2\nDA:2,0\nDA:4,1\n"), "{}", lcov);
+        assert!(lcov.contains("LF:3\nLH:2\nend_of_record"), "{}", lcov);
+    }
+}
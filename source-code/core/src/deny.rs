@@ -0,0 +1,103 @@
+//! Lista zabronionych komend (`hl run --deny <wzorzec>`, HL_DENY).
+//!
+//! Sprawdzana po podstawieniu zmiennych, tuż przed uruchomieniem procesu — w tree-walk
+//! i w VM bytecode. Wzorce to proste globy: `*` (dowolny ciąg), `?` (jeden znak).
+
+/// Zabronione wzorce z HL_DENY — jeden na linię (wzorzec może zawierać spacje)
+pub fn deny_from_env() -> DenyList {
+    std::env::var("HL_DENY").map(|v| DenyList::new(v.lines())).unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DenyList {
+    patterns: Vec<String>,
+}
+
+impl DenyList {
+    pub fn new<S: AsRef<str>>(patterns: impl IntoIterator<Item = S>) -> Self {
+        let patterns = patterns.into_iter()
+            .map(|p| p.as_ref().trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect();
+        Self { patterns }
+    }
+
+    pub fn is_empty(&self) -> bool { self.patterns.is_empty() }
+
+    /// Pierwszy wzorzec pasujący do którejś prostej komendy w `cmd`.
+    ///
+    /// `cmd` dzielony jest na `;`, `&&`, `||`, `|` i nowe linie; każdy kawałek pasuje gdy wzorzec
+    /// obejmuje go całego albo samą nazwę programu (bez ścieżki i wiodącego `sudo`).
+    pub fn matching(&self, cmd: &str) -> Option<&str> {
+        if self.patterns.is_empty() { return None; }
+        let segments: Vec<&str> = cmd.split([';', '&', '|', '\n']).map(str::trim).filter(|s| !s.is_empty()).collect();
+        self.patterns.iter().map(String::as_str).find(|pat| {
+            segments.iter().any(|seg| glob_match(pat, seg) || glob_match(pat, program_name(seg)))
+        })
+    }
+
+    /// Err gdy komenda jest zabroniona — wywołać przed spawnem
+    pub fn check(&self, cmd: &str) -> anyhow::Result<()> {
+        match self.matching(cmd) {
+            Some(pat) => Err(anyhow::anyhow!("Komenda zabroniona przez --deny '{}': {}", pat, cmd.trim())),
+            None      => Ok(()),
+        }
+    }
+}
+
+fn program_name(segment: &str) -> &str {
+    let mut words = segment.split_whitespace();
+    let mut prog = words.next().unwrap_or("");
+    if prog == "sudo" { prog = words.next().unwrap_or(""); }
+    prog.rsplit('/').next().unwrap_or(prog)
+}
+
+/// Glob `*` / `?` dopasowany do całego tekstu
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    // Pozycja ostatniej `*` i miejsce w tekście, od którego ją rozciągamy
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("reboot", "reboot"));
+        assert!(glob_match("rm -rf *", "rm -rf /tmp/x"));
+        assert!(glob_match("shut*", "shutdown"));
+        assert!(glob_match("h?lt", "halt"));
+        assert!(!glob_match("reboot", "rebooter"));
+    }
+
+    #[test]
+    fn test_matches_program_and_chained_commands() {
+        let deny = DenyList::new(["reboot", "rm -rf /*"]);
+        assert_eq!(deny.matching("sudo /sbin/reboot now"), Some("reboot"));
+        assert_eq!(deny.matching("true && reboot"), Some("reboot"));
+        assert_eq!(deny.matching("rm -rf /home"), Some("rm -rf /*"));
+        assert_eq!(deny.matching("rm -rf ./build"), None);
+        assert_eq!(deny.matching("ls reboot"), None);
+        assert_eq!(deny.matching("echo ok\nreboot"), Some("reboot"));
+    }
+}
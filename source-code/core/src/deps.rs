@@ -1,6 +1,8 @@
+use colored::Colorize;
 use anyhow::{Context, Result};
 use std::process::Command;
 use tracing::{info, warn};
+use crate::prompt::Prompter;
 
 pub fn is_installed(name: &str) -> bool { which::which(name).is_ok() }
 
@@ -34,9 +36,20 @@ pub fn install_package(apt_name: &str) -> Result<bool> {
 ///   // curl              → bin_name="curl", apt_package=None   → apt install curl
 ///   // ninja [ninja-build] → bin_name="ninja", apt_package=Some("ninja-build") → apt install ninja-build
 ///   // python3 [python3] → jawne (oba nazwy takie same)
-pub fn resolve_dependency(bin_name: &str, apt_package: Option<&str>) -> Result<DependencyResult> {
+/// Instalacja (sudo) tylko po potwierdzeniu przez `prompter`.
+pub fn resolve_dependency(bin_name: &str, apt_package: Option<&str>, prompter: &dyn Prompter) -> Result<DependencyResult> {
+    resolve_dependency_with(bin_name, apt_package, prompter, install_package)
+}
+
+/// Jak `resolve_dependency`, z podanym instalatorem (testy nie wołają sudo)
+pub fn resolve_dependency_with(
+    bin_name:    &str,
+    apt_package: Option<&str>,
+    prompter:    &dyn Prompter,
+    install:     impl FnOnce(&str) -> Result<bool>,
+) -> Result<DependencyResult> {
     let bin = bin_name.trim();
-    
+
     // Binarki już zainstalowana → OK bez instalacji
     if is_installed(bin) {
         return Ok(DependencyResult::AlreadyInstalled(bin.to_string()));
@@ -45,29 +58,36 @@ pub fn resolve_dependency(bin_name: &str, apt_package: Option<&str>) -> Result<D
     // Wybierz nazwę pakietu apt: jawna [pakiet] lub fallback = nazwa binarki
     let pkg = apt_package.unwrap_or(bin);
 
+    if !prompter.confirm(&format!("'{bin}' nie znalezione — zainstalować pakiet '{pkg}' (sudo apt-get / lpm)?")) {
+        eprintln!("{} '{bin}' nie zainstalowane (odmowa).", "[hl dep]".red());
+        return Ok(DependencyResult::Failed(bin.to_string()));
+    }
+
     eprintln!(
-        "\x1b[33m[hl dep]\x1b[0m '{bin}' nie znalezione. \
-        Próbuję: apt install {pkg}..."
+        "{} '{bin}' nie znalezione. \
+        Próbuję: apt install {pkg}...",
+        "[hl dep]".yellow()
     );
 
-    match install_package(pkg) {
+    match install(pkg) {
         Ok(true) => {
             // Sprawdź ponownie czy binarka teraz dostępna
             if is_installed(bin) {
-                eprintln!("\x1b[32m[hl dep]\x1b[0m '{bin}' zainstalowane ({pkg}).");
+                eprintln!("{} '{bin}' zainstalowane ({pkg}).", "[hl dep]".green());
                 Ok(DependencyResult::Installed(bin.to_string()))
             } else {
                 // Pakiet zainstalowany ale binarka wciąż nie widoczna (np. inna nazwa)
                 eprintln!(
-                    "\x1b[33m[hl dep]\x1b[0m Pakiet '{pkg}' zainstalowany, \
+                    "{} Pakiet '{pkg}' zainstalowany, \
                     ale binarka '{bin}' nadal nie widoczna. \
-                    Może wymaga innej ścieżki lub restartu powłoki."
+                    Może wymaga innej ścieżki lub restartu powłoki.",
+                    "[hl dep]".yellow()
                 );
                 Ok(DependencyResult::Installed(bin.to_string()))
             }
         }
         Ok(false) => {
-            eprintln!("\x1b[31m[hl dep]\x1b[0m Nie udało się zainstalować '{pkg}'.");
+            eprintln!("{} Nie udało się zainstalować '{pkg}'.", "[hl dep]".red());
             Ok(DependencyResult::Failed(bin.to_string()))
         }
         Err(e) => Err(e),
@@ -86,3 +106,32 @@ impl DependencyResult {
         matches!(self, DependencyResult::AlreadyInstalled(_) | DependencyResult::Installed(_))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt::ScriptedPrompter;
+
+    const MISSING: &str = "hl-test-brak-takiej-binarki";
+
+    #[test]
+    fn test_install_gated_by_prompter() {
+        let deny = ScriptedPrompter::new([false]);
+        let mut ran = false;
+        let r = resolve_dependency_with(MISSING, None, &deny, |_| { ran = true; Ok(true) }).unwrap();
+        assert!(!ran, "odmowa — instalator nie może się uruchomić");
+        assert!(!r.is_available());
+        assert_eq!(deny.asked().len(), 1);
+        assert!(deny.asked()[0].contains(MISSING));
+
+        let approve = ScriptedPrompter::new([true]);
+        let mut pkg = String::new();
+        resolve_dependency_with(MISSING, Some("pakiet-apt"), &approve, |p| { pkg = p.to_string(); Ok(false) }).unwrap();
+        assert_eq!(pkg, "pakiet-apt");
+
+        // Zainstalowana binarka — bez pytania
+        let silent = ScriptedPrompter::default();
+        assert!(resolve_dependency_with("sh", None, &silent, |_| Ok(false)).unwrap().is_available());
+        assert!(silent.asked().is_empty());
+    }
+}
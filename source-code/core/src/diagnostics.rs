@@ -15,12 +15,12 @@ impl DiagLevel {
         }
     }
     fn gutter_color(&self) -> &'static str {
-        match self {
+        crate::color::ansi(match self {
             DiagLevel::Error   => "\x1b[31m",
             DiagLevel::Warning => "\x1b[33m",
             DiagLevel::Hint    => "\x1b[36m",
             DiagLevel::Note    => "\x1b[90m",
-        }
+        })
     }
     fn marker(&self) -> &'static str {
         match self { DiagLevel::Error=>"^", DiagLevel::Warning=>"~", DiagLevel::Hint=>"-", DiagLevel::Note=>"." }
@@ -54,28 +54,35 @@ impl<'a> DiagRenderer<'a> {
         Self { filename, lines: source.lines().collect() }
     }
     pub fn emit(&self, diag: &Diag) {
-        let gc = diag.level.gutter_color(); let reset = "\x1b[0m";
-        eprintln!("{}: {}", diag.level.label(), diag.message.white().bold());
+        eprint!("{}", self.render(diag));
+    }
+    /// Tekst diagnostyki tak jak trafia na stderr (z ANSI tylko gdy kolory włączone)
+    pub fn render(&self, diag: &Diag) -> String {
+        use std::fmt::Write;
+        let mut out = String::new();
+        let gc = diag.level.gutter_color(); let reset = crate::color::ansi("\x1b[0m");
+        let _ = writeln!(out, "{}: {}", diag.level.label(), diag.message.white().bold());
         if let Some(ref span) = diag.span {
-            eprintln!("  {} {}:{}:{}", "-->".bright_black(), self.filename.bright_white(), span.line, span.col);
+            let _ = writeln!(out, "  {} {}:{}:{}", "-->".bright_black(), self.filename.bright_white(), span.line, span.col);
             let line_idx = span.line.saturating_sub(1);
             let line_num_w = format!("{}", span.line).len().max(2);
-            if line_idx > 0 { if let Some(prev) = self.lines.get(line_idx - 1) { eprintln!("{}{:>w$} |{} {}", gc, span.line-1, reset, prev.bright_black(), w=line_num_w); } }
+            if line_idx > 0 { if let Some(prev) = self.lines.get(line_idx - 1) { let _ = writeln!(out, "{}{:>w$} |{} {}", gc, span.line-1, reset, prev.bright_black(), w=line_num_w); } }
             if let Some(src_line) = self.lines.get(line_idx) {
-                eprintln!("{}{:>w$} |{} {}", gc, span.line, reset, src_line, w=line_num_w);
+                let _ = writeln!(out, "{}{:>w$} |{} {}", gc, span.line, reset, src_line, w=line_num_w);
                 let col0 = span.col.saturating_sub(1);
                 let marker_len = if span.len == 0 { src_line.trim_start().len().max(1) } else { span.len };
                 let spaces = " ".repeat(line_num_w + 3 + col0);
-                eprintln!("{}{}{}{}", spaces, gc, diag.level.marker().repeat(marker_len), reset);
+                let _ = writeln!(out, "{}{}{}{}", spaces, gc, diag.level.marker().repeat(marker_len), reset);
             }
-            if let Some(next) = self.lines.get(line_idx + 1) { eprintln!("{}{:>w$} |{} {}", gc, span.line+1, reset, next.bright_black(), w=line_num_w); }
-            eprintln!("{}{:>w$} |{}", gc, "", reset, w=line_num_w);
+            if let Some(next) = self.lines.get(line_idx + 1) { let _ = writeln!(out, "{}{:>w$} |{} {}", gc, span.line+1, reset, next.bright_black(), w=line_num_w); }
+            let _ = writeln!(out, "{}{:>w$} |{}", gc, "", reset, w=line_num_w);
         } else {
-            eprintln!("  {} {}", "-->".bright_black(), self.filename.bright_white());
+            let _ = writeln!(out, "  {} {}", "-->".bright_black(), self.filename.bright_white());
         }
-        if let Some(ref sug) = diag.suggestion { eprintln!("  {} {}", "help:".bright_cyan().bold(), sug.bright_white()); }
-        for note in &diag.notes { eprintln!("  {} {}", "note:".bright_black().bold(), note.bright_black()); }
-        eprintln!();
+        if let Some(ref sug) = diag.suggestion { let _ = writeln!(out, "  {} {}", "help:".bright_cyan().bold(), sug.bright_white()); }
+        for note in &diag.notes { let _ = writeln!(out, "  {} {}", "note:".bright_black().bold(), note.bright_black()); }
+        out.push('\n');
+        out
     }
     pub fn emit_all(&self, diags: &[Diag]) { for d in diags { self.emit(d); } }
 }
@@ -113,17 +120,23 @@ pub fn lint_source(source: &str) -> Vec<Diag> {
         let line_no = idx + 1;
         let trimmed = raw_line.trim();
 
-        // echo zakazane w blokach >
+        // echo zakazane w blokach > — na poczatku i po &&, ||, ; lub | (poza cudzyslowem)
         if let Some(rest) = strip_cmd_prefix(trimmed, ">") {
-            let rest = rest.trim();
-            if rest.starts_with("echo ") || rest == "echo" {
-                let msg = rest.trim_start_matches("echo").trim();
-                let col = raw_line.find('>').map(|c| c+1).unwrap_or(1);
-                diags.push(Diag::error("`echo` jest zabronione w blokach komend HL")
-                .with_span(Span::new(line_no, col, trimmed.len()))
-                .with_suggestion(if msg.is_empty() { "uzyj: `~>`".into() } else { format!("zamien na: `~> {}`", msg) })
-                .with_note("operator `~>` to jedyny sposob wypisywania tekstu w HL"));
+            let base = raw_line.len() - raw_line.trim_start().len() + 1;
+            for (off, msg, mid) in raw_echo_uses(rest) {
+                let what = if msg.is_empty() { "`~>`".to_string() } else { format!("`~> {}`", msg) };
+                let d = if mid {
+                    Diag::error("`echo` w srodku komendy `>` — tryb surowy nie uruchamia powloki")
+                    .with_suggestion(format!("przenies wypisywanie do osobnej linii: {} albo uzyj `>>` zamiast `>`", what))
+                    .with_note("`>` przekazuje `&&`, `||`, `;` i `|` jako zwykle argumenty")
+                } else {
+                    Diag::error("`echo` jest zabronione w blokach komend HL")
+                    .with_suggestion(format!("zamien na: {} albo uzyj `>>`, jesli wynik idzie do `|> @zmienna`", what))
+                    .with_note("operator `~>` to jedyny sposob wypisywania tekstu w HL")
+                };
+                diags.push(d.with_span(Span::new(line_no, base + off + 1, "echo".len())));
             }
+            let rest = rest.trim();
             // sudo zamiast ^>
             if rest.starts_with("sudo ") {
                 let actual_cmd = rest.trim_start_matches("sudo").trim();
@@ -168,7 +181,7 @@ fn check_missing_dep_fast(line: &str, line_no: usize, declared: &HashSet<&str>,
     else if let Some(r) = strip_cmd_prefix(line, "^>")  { r.to_string() }
     else { return };
 
-    let first_word = cmd_content.trim().split_whitespace().next().unwrap_or("");
+    let first_word = cmd_content.split_whitespace().next().unwrap_or("");
     if let Some(&tool) = WATCHED.iter().find(|&&t| t == first_word) {
         if !declared.contains(tool) {
             diags.push(Diag::hint(format!("narzedzie `{}` uzyte bez deklaracji `// {}`", tool, tool))
@@ -181,12 +194,42 @@ fn check_missing_dep_fast(line: &str, line_no: usize, declared: &HashSet<&str>,
     }
 }
 
+/// Wystapienia `echo` jako nazwy komendy w treści `>`: (offset bajtowy, argumenty, czy w srodku).
+/// Slowa w cudzyslowie sie nie licza — `> grep "echo" plik` to nie echo.
+fn raw_echo_uses(body: &str) -> Vec<(usize, String, bool)> {
+    const SEPS: &[&str] = &["&&", "||", ";", "|"];
+    // (start, slowo) z pominieciem tresci w '...' i "..."
+    let mut words: Vec<(usize, &str)> = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut start: Option<usize> = None;
+    for (i, c) in body.char_indices() {
+        match quote {
+            Some(q) => if c == q { quote = None },
+            None if c == '"' || c == '\'' => { quote = Some(c); start.get_or_insert(i); }
+            None if c.is_whitespace() => if let Some(s) = start.take() { words.push((s, &body[s..i])); },
+            None => { start.get_or_insert(i); }
+        }
+    }
+    if let Some(s) = start { words.push((s, &body[s..])); }
+
+    let mut out = Vec::new();
+    for (n, &(off, w)) in words.iter().enumerate() {
+        let first = n == 0;
+        let after_sep = n > 0 && { let prev = words[n - 1].1; SEPS.contains(&prev) || prev.ends_with(';') };
+        if w != "echo" || !(first || after_sep) { continue; }
+        let args: Vec<&str> = words[n + 1..].iter().map(|&(_, w)| w)
+        .take_while(|w| !SEPS.contains(w) && *w != "|>").collect();
+        out.push((off, args.join(" "), !first));
+    }
+    out
+}
+
 fn strip_cmd_prefix<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
     let line = line.trim();
     if prefix == ">>" && (line.starts_with("^>>") || line.starts_with("->>")) { return None; }
     if prefix == ">"  && (line.starts_with(">>") || line.starts_with("^>") || line.starts_with("->") || line.starts_with("*>")) { return None; }
     if prefix == "->" && line.starts_with("^->") { return None; }
-    if line.starts_with(prefix) { Some(&line[prefix.len()..]) } else { None }
+    line.strip_prefix(prefix)
 }
 
 #[derive(Default)]
@@ -219,8 +262,11 @@ use hl_parser::lexer::LexError;
 pub fn parse_error_to_diag(err: &ParseError) -> Diag {
     match err {
         ParseError::Lex(e) => lex_error_to_diag(e),
-        ParseError::UnexpectedToken(pos, tok) => Diag::error(format!("nieoczekiwany token `{}` (pozycja {})", tok, pos))
-        .with_suggestion("sprawdz skladnie — kazda linia powinna zaczynac sie od operatora"),
+        ParseError::UnexpectedToken { line, col, found, expected } => {
+            let d = Diag::error(format!("nieoczekiwany {}, oczekiwano: {}", found, expected))
+            .with_suggestion("sprawdz skladnie — kazda linia powinna zaczynac sie od operatora");
+            if *line > 0 { d.with_span(Span::new(*line, *col, 0)) } else { d }
+        }
         ParseError::MissingDone => Diag::error("brakujace `done` — blok nie jest zamkniety")
         .with_suggestion("dodaj `done` na koncu bloku"),
         ParseError::MissingDef  => Diag::error("brakujace `def` po nazwie funkcji")
@@ -270,3 +316,336 @@ pub fn lint_gen(source: &str) -> Vec<Diag> {
     }
     diags
 }
+
+// ─────────────────────────────────────────────────────────────
+// --strict-types: zgodnosc wartosci z adnotacjami `% n: typ = ...`
+// ─────────────────────────────────────────────────────────────
+
+/// Typ wywnioskowany z literalu. `Num` = wynik `$( ... )` (int albo float).
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Inferred { Str, Int, Float, Num, Bool, Func, Declared(VarTypeTag) }
+
+/// Kopia `VarType` bez `Any` — `Any` oznacza wartosc dynamiczna i nie trafia do tablicy
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum VarTypeTag { Str, Int, Float, Bool, List, Map }
+
+impl VarTypeTag {
+    fn from_annotation(typ: &str) -> Option<Self> {
+        use hl_parser::VarType;
+        match VarType::from_str(typ) {
+            VarType::String => Some(Self::Str),
+            VarType::Int    => Some(Self::Int),
+            VarType::Float  => Some(Self::Float),
+            VarType::Bool   => Some(Self::Bool),
+            VarType::List   => Some(Self::List),
+            VarType::Map    => Some(Self::Map),
+            VarType::Any    => None,
+        }
+    }
+    fn name(self) -> &'static str {
+        match self { Self::Str=>"str", Self::Int=>"int", Self::Float=>"float", Self::Bool=>"bool", Self::List=>"list", Self::Map=>"map" }
+    }
+    fn accepts(self, v: Inferred) -> bool {
+        match (self, v) {
+            (t, Inferred::Declared(u)) => t == u || (t == Self::Float && u == Self::Int),
+            (Self::Str,   Inferred::Str) => true,
+            (Self::Int,   Inferred::Int | Inferred::Num) => true,
+            (Self::Float, Inferred::Float | Inferred::Int | Inferred::Num) => true,
+            (Self::Bool,  Inferred::Bool) => true,
+            // list/map nie maja literalow — pasuje tylko zmienna tego samego typu
+            // (pierwsze ramie) albo wartosc dynamiczna, ktorej nie sprawdzamy
+            _ => false,
+        }
+    }
+}
+
+impl Inferred {
+    fn name(self) -> &'static str {
+        match self {
+            Inferred::Str => "str", Inferred::Int => "int", Inferred::Float => "float",
+            Inferred::Num => "liczba", Inferred::Bool => "bool", Inferred::Func => "func",
+            Inferred::Declared(t) => t.name(),
+        }
+    }
+}
+
+/// None = wartosc dynamiczna (wyjscie komendy, nieotypowana zmienna) — nie sprawdzamy
+fn infer_value(value: &str, declared: &std::collections::HashMap<String, VarTypeTag>) -> Option<Inferred> {
+    let v = value.trim();
+    if v.is_empty() { return None; }
+    if v.len() >= 2 && v.starts_with('"') && v.ends_with('"') { return Some(Inferred::Str); }
+    if v.starts_with("$(") && v.ends_with(')') { return Some(Inferred::Num); }
+    if v.starts_with('&') { return Some(Inferred::Func); }
+    if v == "true" || v == "false" { return Some(Inferred::Bool); }
+    if v.parse::<i64>().is_ok() { return Some(Inferred::Int); }
+    if v.parse::<f64>().is_ok() { return Some(Inferred::Float); }
+    if let Some(name) = v.strip_prefix('@') {
+        if name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return declared.get(name).map(|t| Inferred::Declared(*t));
+        }
+        return Some(Inferred::Str);
+    }
+    // Goly tekst (bez @zmiennych w srodku) to string
+    if v.contains('@') { None } else { Some(Inferred::Str) }
+}
+
+/// Sprawdz typy przypisan wzgledem adnotacji. Wartosci bez adnotacji zostaja dynamiczne,
+/// ale raz otypowana zmienna zachowuje typ przy kolejnych przypisaniach.
+pub fn check_types(source: &str) -> Vec<Diag> {
+    use hl_parser::{Lexer, Token};
+    let mut diags = Vec::new();
+    // Bledy leksera zglasza parser — tu po prostu nic nie sprawdzamy
+    let Ok(tokens) = Lexer::new(source).tokenize_spanned() else { return diags; };
+    let mut declared: std::collections::HashMap<String, VarTypeTag> = std::collections::HashMap::new();
+
+    for (line, col, tok) in tokens {
+        let (name, annotation, inferred) = match tok {
+            Token::VarDecl { name, typ, value } => {
+                let inferred = infer_value(&value, &declared);
+                (name, typ, inferred)
+            }
+            Token::Arithmetic { assign_to: Some(name), .. } => (name, String::new(), Some(Inferred::Num)),
+            _ => continue,
+        };
+        let annotated = VarTypeTag::from_annotation(&annotation);
+        let slot = annotated.or_else(|| declared.get(&name).copied());
+
+        if let (Some(t), Some(prev)) = (annotated, declared.get(&name)) {
+            if t != *prev {
+                diags.push(Diag::error(format!("zmienna `{}` byla zadeklarowana jako `{}`, teraz jako `{}`", name, prev.name(), t.name()))
+                .with_span(Span::new(line, col, 0))
+                .with_suggestion("uzyj nowej nazwy zmiennej zamiast zmieniac jej typ"));
+            }
+        }
+        if let (Some(t), Some(v)) = (slot, inferred) {
+            if !t.accepts(v) {
+                let mut d = Diag::error(format!("niezgodny typ: `{}` oczekuje `{}`, a wartosc jest typu `{}`", name, t.name(), v.name()))
+                .with_span(Span::new(line, col, 0));
+                if annotated.is_none() {
+                    d = d.with_note(format!("typ `{}` pochodzi z wczesniejszej deklaracji `{}`", t.name(), name));
+                }
+                diags.push(d);
+            }
+        }
+        if let Some(t) = annotated {
+            declared.entry(name).or_insert(t);
+        }
+    }
+    diags
+}
+
+// ─────────────────────────────────────────────────────────────
+// Puste ciala blokow (wylaczane przez `hl check --allow-empty`)
+// ─────────────────────────────────────────────────────────────
+
+/// Pusta petla, warunek, `? try` albo funkcja — prawie zawsze zapomniane cialo.
+/// Blok z samym komentarzem (`;; celowo puste`) jest zamierzony i nie jest zglaszany.
+pub fn check_empty_bodies(source: &str) -> Vec<Diag> {
+    let mut diags = Vec::new();
+    // Bledy skladni zglasza parser
+    let Ok((nodes, _)) = hl_parser::parse_source_with_lines(source) else { return diags; };
+    empty_bodies(&nodes, &mut 0, &mut diags);
+    diags
+}
+
+fn empty_bodies(nodes: &[hl_parser::Node], line: &mut usize, diags: &mut Vec<Diag>) {
+    use hl_parser::Node;
+    for node in nodes {
+        let (what, body): (String, &[Node]) = match node {
+            Node::SourceLine(l) => { *line = *l; continue; }
+            Node::Chain { node, .. } => { empty_bodies(std::slice::from_ref(&**node), line, diags); continue; }
+            Node::ForIn { body, .. }        => ("petla `@ ... in`".into(), body),
+            Node::WhileLoop { body, .. }    => ("petla `?~`".into(), body),
+            Node::Conditional { body, .. }  => ("blok warunkowy `?`".into(), body),
+            Node::Try { body, catches }     => {
+                for arm in catches { empty_bodies(&arm.body, line, diags); }
+                ("blok `? try`".into(), body)
+            }
+            Node::FuncDef { name, body, .. } | Node::ArenaFuncDef { name, body, .. } => {
+                (format!("funkcja `{}`", name), body)
+            }
+            Node::MatchExpr { arms, .. } => { for arm in arms { empty_bodies(&arm.body, line, diags); } continue; }
+            Node::RepeatN { body, .. } | Node::Retry { body, .. } | Node::Bench { body, .. }
+            | Node::TempDir { body, .. } | Node::Goroutine { body, .. } | Node::Block(body)
+            | Node::ExternDef { body, .. } => { empty_bodies(body, line, diags); continue; }
+            _ => continue,
+        };
+        // Markery linii sie nie licza; komentarz tak — oznacza celowo pusty blok
+        if body.iter().all(|n| matches!(n, Node::SourceLine(_))) {
+            let d = Diag::warning(format!("{} ma puste cialo", what))
+            .with_suggestion("dodaj instrukcje albo komentarz `;; celowo puste`, jesli blok ma nic nie robic");
+            diags.push(if *line > 0 { d.with_span(Span::line_only(*line)) } else { d });
+        }
+        empty_bodies(body, line, diags);
+    }
+}
+
+// ─────────────────────────────────────────────────────────────
+// Martwy kod po `exit`
+// ─────────────────────────────────────────────────────────────
+
+/// Instrukcje po `> exit` w tym samym bloku nigdy sie nie wykonaja — zwykle blad logiki.
+/// `? switch` konczy blok tylko z ramieniem `| *` bez `when` i gdy kazde ramie konczy sie
+/// `exit`; `? ok` / `? err`, petle i lancuchy `&&` / `||` nigdy (moga sie nie wykonac).
+pub fn check_unreachable(source: &str) -> Vec<Diag> {
+    let mut diags = Vec::new();
+    let Ok((nodes, _)) = hl_parser::parse_source_with_lines(source) else { return diags; };
+    block_exits(&nodes, &mut 0, &mut diags);
+    diags
+}
+
+/// Czy blok na pewno konczy proces; zglasza pierwsza martwa instrukcje w kazdym bloku
+fn block_exits(nodes: &[hl_parser::Node], line: &mut usize, diags: &mut Vec<Diag>) -> bool {
+    use hl_parser::Node;
+    let mut exited = false;
+    for node in nodes {
+        match node {
+            Node::SourceLine(l) => { *line = *l; continue; }
+            Node::LineComment(_) | Node::DocComment(_) | Node::BlockComment(_) => continue,
+            _ if exited => {
+                let d = Diag::warning("instrukcja po `exit` nigdy sie nie wykona")
+                .with_suggestion("usun martwy kod albo przenies `exit` na koniec bloku");
+                diags.push(if *line > 0 { d.with_span(Span::line_only(*line)) } else { d });
+                return true;
+            }
+            _ => {}
+        }
+        exited = match node {
+            Node::Command { raw, .. } => is_exit(raw),
+            Node::Block(body) | Node::TempDir { body, .. } => block_exits(body, line, diags),
+            // `exit` konczy proces — `? catch` go nie przechwyci
+            Node::Try { body, catches } => {
+                let body_exits = block_exits(body, line, diags);
+                for arm in catches { block_exits(&arm.body, line, diags); }
+                body_exits
+            }
+            Node::MatchExpr { arms, .. } => {
+                let mut all = true;
+                for arm in arms { all &= block_exits(&arm.body, line, diags); }
+                all && arms.iter().any(|a| a.pattern.trim() == "*" && a.guard.is_none())
+            }
+            Node::Chain { node, .. } => { block_exits(std::slice::from_ref(&**node), line, diags); false }
+            Node::Conditional { body, .. } | Node::ForIn { body, .. } | Node::WhileLoop { body, .. }
+            | Node::RepeatN { body, .. } | Node::Retry { body, .. } | Node::Bench { body, .. }
+            | Node::Goroutine { body, .. } | Node::FuncDef { body, .. } | Node::ArenaFuncDef { body, .. }
+            | Node::ExternDef { body, .. } => { block_exits(body, line, diags); false }
+            _ => false,
+        };
+    }
+    exited
+}
+
+/// `exit` / `exit N` — wykonywane przez hl, nie przez powloke (jak w executorze)
+fn is_exit(raw: &str) -> bool {
+    let t = raw.trim();
+    t == "exit" || t.starts_with("exit ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(src: &str) -> Vec<String> {
+        check_types(src).into_iter().map(|d| d.message).collect()
+    }
+
+    #[test]
+    fn test_strict_types_mismatch() {
+        let errs = check_types("~> start\n% n: int = \"abc\"\n");
+        assert_eq!(errs.len(), 1);
+        assert!(errs[0].message.contains("`int`") && errs[0].message.contains("`str`"));
+        assert_eq!(errs[0].span.as_ref().map(|s| s.line), Some(2));
+    }
+
+    #[test]
+    fn test_empty_loop_body_warns() {
+        let diags = check_empty_bodies("~> start\n@ f in a b\ndone\n");
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].level, DiagLevel::Warning);
+        assert!(diags[0].message.contains("@ ... in"), "{}", diags[0].message);
+        assert_eq!(diags[0].span.as_ref().map(|s| s.line), Some(2));
+
+        assert!(check_empty_bodies("@ f in a b\n    ~> @f\ndone\n").is_empty());
+        assert!(check_empty_bodies("@ f in a b\n    ;; celowo puste\ndone\n").is_empty());
+        // Zagniezdzone: pusty `? ok` w ciele funkcji, pusta funkcja
+        let msgs: Vec<String> = check_empty_bodies(": f def\n    ? ok\n    done\ndone\n: g def\ndone\n")
+            .into_iter().map(|d| d.message).collect();
+        assert_eq!(msgs, vec!["blok warunkowy `?` ma puste cialo", "funkcja `g` ma puste cialo"]);
+    }
+
+    #[test]
+    fn test_code_after_exit_warns() {
+        let diags = check_unreachable("~> start\n> exit 1\n;; komentarz\n~> nigdy\n~> tez nie\n");
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].level, DiagLevel::Warning);
+        assert_eq!(diags[0].span.as_ref().map(|s| s.line), Some(4));
+        // `? switch`, w ktorym kazde ramie (z `| *`) konczy sie `exit`
+        let diags = check_unreachable("? switch @x\n| a\n    > exit 1\n| *\n    > exit 0\ndone\n~> po\n");
+        assert_eq!(diags.iter().map(|d| d.span.as_ref().map(|s| s.line)).collect::<Vec<_>>(), vec![Some(7)]);
+    }
+
+    #[test]
+    fn test_exit_in_one_branch_is_not_dead_code() {
+        assert!(check_unreachable("? ok\n    > exit 1\ndone\n~> dalej\n").is_empty());
+        assert!(check_unreachable("? switch @x\n| a\n    > exit 1\n| *\n    ~> inne\ndone\n~> po\n").is_empty());
+        assert!(check_unreachable("? switch @x\n| a\n    > exit 1\ndone\n~> po\n").is_empty());
+        assert!(check_unreachable("> true\n&& > exit 0\n~> po\n").is_empty());
+    }
+
+    #[test]
+    fn test_strict_types_list_map_reject_literals() {
+        let errs = errors("% xs: list = \"a b\"\n% m: map = klucz\n% s: str = x\n% ys: list = @s\n");
+        assert_eq!(errs.len(), 3, "{:?}", errs);
+        assert!(errs[0].contains("`list`") && errs[0].contains("`str`"), "{}", errs[0]);
+        assert!(errs[1].contains("`map`"), "{}", errs[1]);
+        // Zmienna tego samego typu albo wartosc dynamiczna — bez bledu
+        assert!(errors("% xs: list = @wejscie\n% ys: list = @xs\n% m: map = @_cfg\n").is_empty());
+    }
+
+    #[test]
+    fn test_strict_types_ok() {
+        assert!(errors("% n: int = 42\n% f: float = @n\n% s: str = \"x @n\"\n$( @n + 1 ) -> @n\n% x = cokolwiek\n").is_empty());
+    }
+
+    #[test]
+    fn test_strict_types_reassignment_keeps_slot_type() {
+        let errs = errors("% n: int = 1\n% n = tekst\n");
+        assert_eq!(errs.len(), 1, "{:?}", errs);
+        let errs = errors("% n: int = 1\n% n: str = \"a\"\n");
+        assert_eq!(errs.len(), 1, "{:?}", errs);
+        // Zmienna z wyjscia komendy jest dynamiczna
+        assert!(errors("% n: int = 1\n% m: str = @n_nieznana\n").is_empty());
+    }
+
+    #[test]
+    fn test_no_color_render_has_no_ansi() {
+        // Flaga zamiast globalnego NO_COLOR — inne testy w tym procesie czytają środowisko
+        crate::color::init(true);
+        let src = "> echo hej\n";
+        let out = DiagRenderer::new("t.hl", src).render(&lint_source(src)[0]);
+        assert!(out.contains("`echo`") && out.contains("^^^^"), "{}", out);
+        assert!(!out.contains('\x1b'), "ANSI mimo NO_COLOR: {:?}", out);
+        let mut env = crate::env::Env::new();
+        crate::libs::resolve_import("main/colors", None, &mut env).ok();
+        assert_eq!(env.get_var_str("COLOR_RED"), "");
+    }
+
+    #[test]
+    fn test_raw_echo_help_and_span() {
+        let d = lint_source("  > echo gotowe\n");
+        assert_eq!(d.len(), 1, "{:?}", d);
+        let span = d[0].span.as_ref().unwrap();
+        assert_eq!((span.line, span.col, span.len), (1, 5, 4));
+        let help = d[0].suggestion.as_deref().unwrap();
+        assert!(help.contains("`~> gotowe`") && help.contains("`>>`"), "{}", help);
+
+        let d = lint_source("> make all && echo zbudowano\n");
+        assert_eq!(d.len(), 1, "{:?}", d);
+        assert!(d[0].message.contains("w srodku"), "{}", d[0].message);
+        assert_eq!(d[0].span.as_ref().map(|s| s.col), Some(15));
+        assert!(d[0].suggestion.as_deref().unwrap().contains("`~> zbudowano`"));
+
+        // echo w cudzyslowie i w trybie >> nie jest bledem
+        assert!(lint_source("> grep \"&& echo\" plik\n>> echo $HOME |> @h\n").is_empty());
+    }
+}
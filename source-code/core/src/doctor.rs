@@ -0,0 +1,153 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// `hl doctor` — czego brakuje w systemie, zanim skrypt padnie z niejasnym błędem.
+///
+/// Sprawdzenia są osobnymi funkcjami bez wypisywania; raport składa CLI.
+/// `Fail` tylko dla braków, bez których HL nie działa (powłoka, cache) — reszta to `Warn`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name:   String,
+    pub status: Status,
+    pub detail: String,
+    /// Co zrobić, gdy status nie jest Ok
+    pub hint:   Option<String>,
+}
+
+impl Check {
+    fn ok(name: &str, detail: impl Into<String>) -> Self {
+        Self { name: name.into(), status: Status::Ok, detail: detail.into(), hint: None }
+    }
+
+    fn bad(name: &str, status: Status, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self { name: name.into(), status, detail: detail.into(), hint: Some(hint.into()) }
+    }
+}
+
+/// Narzędzie zewnętrzne i do czego HL go używa
+struct Tool {
+    bin:      &'static str,
+    critical: bool,
+    purpose:  &'static str,
+}
+
+const TOOLS: &[Tool] = &[
+    Tool { bin: "sh",      critical: true,  purpose: "komendy VM bytecode z |, ; i $" },
+    Tool { bin: "bash",    critical: true,  purpose: ">> i komendy wymagające powłoki" },
+    Tool { bin: "git",     critical: false, purpose: "bit install, importy # <github/...>" },
+    Tool { bin: "curl",    critical: false, purpose: "bit update (lista pakietów)" },
+    Tool { bin: "jq",      critical: false, purpose: "bit (lock file, lista pakietów)" },
+    Tool { bin: "sudo",    critical: false, purpose: "^> i ^>>" },
+    Tool { bin: "unshare", critical: false, purpose: "-> i ->> (izolacja namespace)" },
+    Tool { bin: "hsh",     critical: false, purpose: "*> (HackerOS shell)" },
+    Tool { bin: "bit",     critical: false, purpose: "manager pakietów" },
+];
+
+/// Lista pakietów bit — ten sam adres co BIT_REPO_RAW w bit.hl
+pub const BIT_REPO_RAW: &str = "https://raw.githubusercontent.com/bit-io/repository/main/bit-repo/repo-list.json";
+
+/// Wszystkie sprawdzenia; `offline` pomija połączenie z repozytorium bit
+pub fn run_all(offline: bool) -> Vec<Check> {
+    let mut checks = check_tools(std::env::var_os("PATH").as_deref());
+    checks.push(check_dir("cache", &crate::libs::hl_cache_dir(), true, true));
+    checks.push(check_dir("biblioteki bit", &crate::libs::bit_base_dir(), true, false));
+    checks.push(check_dir("biblioteki main", Path::new(crate::libs::MAIN_LIBS_DIR), false, false));
+    if !offline {
+        checks.push(check_registry(BIT_REPO_RAW));
+    }
+    checks
+}
+
+/// Narzędzia z `TOOLS` szukane w podanym PATH (None — bez PATH, nic nie zostanie znalezione)
+pub fn check_tools(path: Option<&OsStr>) -> Vec<Check> {
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+    TOOLS.iter().map(|t| match which::which_in(t.bin, path, &cwd) {
+        Ok(found) => Check::ok(t.bin, found.display().to_string()),
+        Err(_) => Check::bad(
+            t.bin,
+            if t.critical { Status::Fail } else { Status::Warn },
+            format!("brak w PATH — potrzebny dla: {}", t.purpose),
+            format!("zainstaluj pakiet '{}' (lpm install {} albo apt install {})", t.bin, t.bin, t.bin),
+        ),
+    }).collect()
+}
+
+/// Katalog istnieje (i, gdy `write`, da się w nim pisać). Brakujący katalog z zapisem
+/// jest w porządku, jeśli da się go utworzyć — HL i bit robią to same przy pierwszym użyciu.
+pub fn check_dir(name: &str, dir: &Path, write: bool, critical: bool) -> Check {
+    let fail = if critical { Status::Fail } else { Status::Warn };
+    let shown = dir.display().to_string();
+    if !dir.exists() {
+        let parent = dir.ancestors().skip(1).find(|p| p.exists());
+        return match parent {
+            Some(p) if write && is_writable(p) => Check::ok(name, format!("{} (zostanie utworzony)", shown)),
+            _ if write => Check::bad(name, fail, format!("{} nie istnieje i nie da się go utworzyć", shown),
+                                     format!("mkdir -p {} i nadaj prawa zapisu", shown)),
+            _ => Check::bad(name, fail, format!("{} nie istnieje", shown),
+                            "zainstaluj biblioteki standardowe Hacker Lang (pakiet hacker-lang)"),
+        };
+    }
+    if !dir.is_dir() {
+        return Check::bad(name, fail, format!("{} nie jest katalogiem", shown), format!("usuń plik {}", shown));
+    }
+    if write && !is_writable(dir) {
+        return Check::bad(name, fail, format!("{} bez prawa zapisu", shown),
+                          format!("chown -R $USER {}", shown));
+    }
+    Check::ok(name, shown)
+}
+
+/// Połączenie z repozytorium bit przez curl (5 s); brak curl — ostrzeżenie, nie błąd
+pub fn check_registry(url: &str) -> Check {
+    let status = Command::new("curl")
+        .args(["-fsSI", "--max-time", "5", url])
+        .stdout(Stdio::null()).stderr(Stdio::null())
+        .status();
+    match status {
+        Ok(s) if s.success() => Check::ok("repozytorium bit", url),
+        Ok(_)  => Check::bad("repozytorium bit", Status::Warn, format!("{} nieosiągalne", url),
+                             "sprawdź połączenie sieciowe albo uruchom hl doctor --offline"),
+        Err(_) => Check::bad("repozytorium bit", Status::Warn, "nie sprawdzono — brak curl",
+                             "zainstaluj curl"),
+    }
+}
+
+pub fn has_failures(checks: &[Check]) -> bool {
+    checks.iter().any(|c| c.status == Status::Fail)
+}
+
+fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(".hl-doctor-{}", std::process::id()));
+    let ok = std::fs::write(&probe, b"").is_ok();
+    let _ = std::fs::remove_file(&probe);
+    ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hl_test_support::TestDir;
+
+    #[test]
+    fn test_missing_tools_reported() {
+        let empty = TestDir::new("doctor-path");
+        let checks = check_tools(Some(empty.as_os_str()));
+        let bash = checks.iter().find(|c| c.name == "bash").unwrap();
+        assert_eq!(bash.status, Status::Fail);
+        assert!(bash.hint.as_deref().unwrap().contains("bash"));
+        assert_eq!(checks.iter().find(|c| c.name == "unshare").unwrap().status, Status::Warn);
+        assert!(has_failures(&checks));
+
+        assert_eq!(check_dir("tmp", &empty, true, true).status, Status::Ok);
+        assert_eq!(check_dir("nowy", &empty.join("a/b"), true, true).status, Status::Ok);
+        assert_eq!(check_dir("brak", &empty.join("c"), false, false).status, Status::Warn);
+    }
+}
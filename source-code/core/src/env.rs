@@ -1,6 +1,11 @@
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use rustc_hash::FxHashMap;
 use hl_parser::ast::{Node, StringPart, ArenaSize};
+use crate::coverage::Coverage;
+use crate::deny::{DenyList, deny_from_env};
+use crate::executor::OutputLimit;
+use crate::prompt::{Prompter, TtyPrompter};
 
 #[derive(Debug, Clone)]
 pub enum Value {
@@ -8,6 +13,8 @@ pub enum Value {
     Number(f64),
     Bool(bool),
     List(Vec<Value>),
+    /// Referencja do funkcji (&nazwa) z argumentami związanymi przez partial application
+    Func { name: String, bound: Vec<String> },
     Nil,
 }
 
@@ -19,6 +26,9 @@ impl Value {
             Value::Number(n) => if n.fract() == 0.0 { format!("{}", *n as i64) } else { format!("{}", n) },
             Value::Bool(b)   => b.to_string(),
             Value::List(v)   => v.iter().map(|x| x.to_string_val()).collect::<Vec<_>>().join(" "),
+            Value::Func { name, bound } => {
+                if bound.is_empty() { format!("&{}", name) } else { format!("&{}({})", name, bound.join(" ")) }
+            }
             Value::Nil       => String::new(),
         }
     }
@@ -31,7 +41,7 @@ impl Value {
         match self {
             Value::Number(n) => *n,
             Value::String(s) => s.parse().unwrap_or(0.0),
-            Value::Bool(b)   => if *b { 1.0 } else { 0.0 },
+            Value::Bool(true) => 1.0,
             _                => 0.0,
         }
     }
@@ -40,8 +50,9 @@ impl Value {
         match self {
             Value::Bool(b)   => *b,
             Value::Number(n) => *n != 0.0,
-            Value::String(s) => !s.is_empty() && s != "false" && s != "0",
+            Value::String(s) => hl_parser::truth::text_is_truthy(s),
             Value::List(v)   => !v.is_empty(),
+            Value::Func { .. } => true,
             Value::Nil       => false,
         }
     }
@@ -49,6 +60,30 @@ impl Value {
 
 pub type FuncBody = Arc<Vec<Node>>;
 
+/// HL_CHECKED=1 — domyślny tryb arytmetyki dla nowych Env (VM czyta tę samą zmienną)
+pub fn checked_from_env() -> bool {
+    matches!(std::env::var("HL_CHECKED").as_deref(), Ok("1") | Ok("true"))
+}
+
+/// HL_MAX_LOOP=N — limit iteracji jednego wejścia w pętlę `?~` / `@ x in` (brak albo 0 = bez limitu)
+pub fn loop_limit_from_env() -> Option<u64> {
+    std::env::var("HL_MAX_LOOP").ok()?.trim().parse().ok().filter(|&n| n > 0)
+}
+
+/// HL_FROZEN_ENV=1 — `export` zmienia prywatną kopię środowiska zamiast środowiska procesu
+/// (hl run --frozen-env). Kopia powstaje z bieżącego środowiska przy tworzeniu Env.
+/// VM bytecode trzyma tę samą kopię w RuntimeState.
+pub fn frozen_env_from_env() -> Option<FxHashMap<String, String>> {
+    if !matches!(std::env::var("HL_FROZEN_ENV").as_deref(), Ok("1") | Ok("true")) { return None; }
+    Some(std::env::vars().collect())
+}
+
+/// Przekroczony limit iteracji — ten sam komunikat w tree-walk i VM bytecode
+pub fn loop_limit_exceeded(line: usize, limit: u64) -> anyhow::Error {
+    let at = if line > 0 { format!(" w linii {}", line) } else { String::new() };
+    anyhow::anyhow!("Pętla{}: przekroczono limit {} iteracji (--max-loop / HL_MAX_LOOP)", at, limit)
+}
+
 /// Wpis arena function w rejestrze
 #[derive(Clone)]
 pub struct ArenaFuncEntry {
@@ -56,12 +91,42 @@ pub struct ArenaFuncEntry {
     pub arena_size: ArenaSize,
 }
 
+
 pub struct Env {
     pub vars:        FxHashMap<String, Value>,
     pub functions:   FxHashMap<String, FuncBody>,
+    /// Moduł (indeks w `modules` + 1), z którego pochodzi funkcja `: pub` — wykonuje się w jego kontekście
+    pub func_origins: FxHashMap<String, usize>,
+    /// Prywatne funkcje modułów z `pub`, po (moduł, nazwa) — nie zasłaniają funkcji importera
+    pub private_funcs: FxHashMap<(usize, String), FuncBody>,
+    /// Ścieżki zaimportowanych modułów z `pub` (do komunikatów błędów)
+    pub modules:     Vec<String>,
+    /// Moduł, którego kod właśnie się wykonuje (0 = skrypt główny)
+    pub current_module: usize,
     /// Rejestr arena functions (gen 2): :: nazwa <rozmiar> def
     pub arena_funcs: FxHashMap<String, ArenaFuncEntry>,
     pub last_exit:   i32,
+    /// Licznik linii dla `hl run --coverage` — wspólny dla goroutines i arena functions
+    pub coverage:    Option<Arc<Mutex<Coverage>>>,
+    /// Limit wyjścia `~>` (hl run --max-output) — wspólny licznik dla całego przebiegu
+    pub output_limit: Option<Arc<OutputLimit>>,
+    /// Arytmetyka z kontrolą przepełnienia i dzielenia przez zero (HL_CHECKED=1, hl run --checked)
+    pub checked:     bool,
+    /// Limit iteracji pętli (HL_MAX_LOOP, hl run --max-loop) — None = bez limitu
+    pub loop_limit:  Option<u64>,
+    /// Zabronione komendy (HL_DENY, hl run --deny) — sprawdzane przed każdym spawnem
+    pub deny:        Arc<DenyList>,
+    /// Prywatne środowisko (HL_FROZEN_ENV, hl run --frozen-env) — cel `export` i jedyne
+    /// środowisko komend potomnych; None = `export` zmienia środowisko procesu
+    pub frozen_env:  Option<Arc<Mutex<FxHashMap<String, String>>>>,
+    /// Potwierdzenia akcji z bramką (instalacja zależności); w testach ScriptedPrompter
+    pub prompter:    Arc<dyn Prompter>,
+    /// Korzenie importów `<<` / `<*` (HL_IMPORT_PATH, hl run -I) — po katalogu importera i cwd
+    pub import_roots: Vec<PathBuf>,
+    /// Katalog pliku, którego kod właśnie się wykonuje (None = źródło spoza pliku, np. `hl -c`)
+    pub source_dir:  Option<PathBuf>,
+    /// Katalog, w którym `? tempdir` tworzy katalogi tymczasowe (domyślnie $TMPDIR)
+    pub tempdir_base: PathBuf,
     interp_buf:      String,
 }
 
@@ -78,8 +143,22 @@ impl Env {
         Self {
             vars,
             functions:   FxHashMap::default(),
+            func_origins: FxHashMap::default(),
+            private_funcs: FxHashMap::default(),
+            modules:     Vec::new(),
+            current_module: 0,
             arena_funcs: FxHashMap::default(),
             last_exit:   0,
+            coverage:    None,
+            output_limit: None,
+            checked:     checked_from_env(),
+            loop_limit:  loop_limit_from_env(),
+            deny:        Arc::new(deny_from_env()),
+            frozen_env:  frozen_env_from_env().map(|m| Arc::new(Mutex::new(m))),
+            prompter:    Arc::new(TtyPrompter),
+            import_roots: crate::import_path::import_roots_from_env(),
+            source_dir:  None,
+            tempdir_base: std::env::temp_dir(),
             interp_buf:  String::with_capacity(256),
         }
     }
@@ -91,25 +170,85 @@ impl Env {
         Self {
             vars:        parent.vars.clone(),
             functions:   parent.functions.clone(),
+            func_origins: parent.func_origins.clone(),
+            private_funcs: parent.private_funcs.clone(),
+            modules:     parent.modules.clone(),
+            current_module: parent.current_module,
             arena_funcs: parent.arena_funcs.clone(),
             last_exit:   parent.last_exit,
+            coverage:    parent.coverage.clone(),
+            output_limit: parent.output_limit.clone(),
+            checked:     parent.checked,
+            loop_limit:  parent.loop_limit,
+            deny:        parent.deny.clone(),
+            frozen_env:  parent.frozen_env.clone(),
+            prompter:    parent.prompter.clone(),
+            import_roots: parent.import_roots.clone(),
+            source_dir:  parent.source_dir.clone(),
+            tempdir_base: parent.tempdir_base.clone(),
             interp_buf:  String::with_capacity(256),
         }
     }
 
+    /// Argumenty skryptu (argv po ścieżce pliku): @argc, @arg0.., lista @args
+    pub fn set_script_args(&mut self, args: &[String]) {
+        self.set_var("argc", Value::Number(args.len() as f64));
+        for (i, arg) in args.iter().enumerate() {
+            self.set_var(&format!("arg{}", i), Value::String(arg.clone()));
+        }
+        self.set_var("args", Value::List(args.iter().cloned().map(Value::String).collect()));
+    }
+
     #[inline]
     pub fn set_var(&mut self, name: &str, val: Value) {
         self.vars.insert(name.to_string(), val);
     }
 
+    /// Zmienna środowiskowa — z prywatnej kopii w trybie --frozen-env, inaczej z procesu
+    pub fn env_var(&self, name: &str) -> Option<String> {
+        match &self.frozen_env {
+            Some(map) => map.lock().unwrap().get(name).cloned(),
+            None      => std::env::var(name).ok(),
+        }
+    }
+
+    /// `=> NAZWA = wartość` — widoczne dla kolejnych komend potomnych
+    pub fn export_env(&mut self, name: &str, value: String) {
+        match &self.frozen_env {
+            Some(map) => { map.lock().unwrap().insert(name.to_string(), value.clone()); }
+            None      => std::env::set_var(name, &value),
+        }
+        self.set_var(name, Value::String(value));
+    }
+
+    /// Ustaw środowisko procesu potomnego: w trybie --frozen-env tylko prywatna kopia
+    pub fn apply_env(&self, cmd: &mut std::process::Command) {
+        if let Some(map) = &self.frozen_env {
+            cmd.env_clear().envs(map.lock().unwrap().iter());
+        }
+    }
+
+    /// Kolejność rozwiązywania: `env:X` — tylko środowisko procesu, `local:X` — tylko
+    /// zmienne HL, gołe `X` — zmienna HL, a gdy jej brak, środowisko procesu.
+    /// Dzięki temu `% HOME = ...` nie zasłania na stałe `$HOME` — zostaje `@env:HOME`.
     pub fn get_var_str(&self, name: &str) -> String {
+        if let Some(n) = name.strip_prefix("env:") {
+            return self.env_var(n).unwrap_or_default();
+        }
+        if let Some(n) = name.strip_prefix("local:") {
+            return self.vars.get(n).map(|v| v.to_string_val()).unwrap_or_default();
+        }
         if let Some(v) = self.vars.get(name) {
             return v.to_string_val();
         }
-        std::env::var(name).unwrap_or_default()
+        self.env_var(name).unwrap_or_default()
     }
 
     pub fn get_var_owned(&self, name: &str) -> Value {
+        if let Some(n) = name.strip_prefix("env:") {
+            return self.env_var(n).map(Value::String).unwrap_or(Value::Nil);
+        }
+        let name = name.strip_prefix("local:").unwrap_or(name);
         self.vars.get(name).cloned().unwrap_or(Value::Nil)
     }
 
@@ -125,22 +264,17 @@ impl Env {
             match part {
                 StringPart::Literal(s) => self.interp_buf.push_str(s),
                 StringPart::Var(v) => {
-                    let val = if let Some(val) = self.vars.get(v.as_str()) {
-                        val.to_string_val()
-                    } else {
-                        std::env::var(v).unwrap_or_default()
-                    };
+                    let val = self.get_var_str(v);
                     self.interp_buf.push_str(&val);
                 }
                 // DynVar: @{arg@_i} lub @arg@_i — najpierw rozwiąż nazwę, potem lookup
                 // np. @{arg@_i} z _i=1 → resolve("arg" + get_var("_i")) = resolve("arg1") → get_var("arg1")
                 StringPart::DynVar(inner_parts) => {
+                    // Rekurencja czyści interp_buf — odłóż dotychczasowy wynik na bok
+                    let outer = std::mem::take(&mut self.interp_buf);
                     let var_name = self.resolve_string_parts(inner_parts);
-                    let val = if let Some(val) = self.vars.get(var_name.as_str()) {
-                        val.to_string_val()
-                    } else {
-                        std::env::var(&var_name).unwrap_or_default()
-                    };
+                    self.interp_buf = outer;
+                    let val = self.get_var_str(&var_name);
                     self.interp_buf.push_str(&val);
                 }
             }
@@ -166,6 +300,59 @@ impl Env {
         self.functions.get(name).cloned()
     }
 
+    /// `: [pub] nazwa def` — w module z `pub` funkcja prywatna trafia do `private_funcs`,
+    /// publiczna do wspólnej przestrzeni nazw z zapamiętanym modułem.
+    pub fn define_function_in_module(&mut self, name: String, body: Vec<Node>, public: bool) {
+        let module = self.current_module;
+        if module == 0 {
+            self.func_origins.remove(&name);
+        } else if public {
+            self.private_funcs.remove(&(module, name.clone()));
+            self.func_origins.insert(name.clone(), module);
+        } else {
+            self.private_funcs.insert((module, name), Arc::new(body));
+            return;
+        }
+        self.define_function(name, body);
+    }
+
+    /// Funkcja widoczna z bieżącego miejsca + moduł, w którym ma się wykonać.
+    /// Najpierw prywatne funkcje bieżącego modułu, potem wspólna przestrzeń nazw.
+    pub fn resolve_function(&self, name: &str) -> anyhow::Result<Option<(FuncBody, Option<usize>)>> {
+        if let Some(body) = self.private_funcs.get(&(self.current_module, name.to_string())) {
+            return Ok(Some((body.clone(), Some(self.current_module))));
+        }
+        if let Some(body) = self.get_function(name) {
+            return Ok(Some((body, self.func_origins.get(name).copied())));
+        }
+        match self.private_funcs.keys().find(|(_, n)| n == name) {
+            Some(&(module, _)) => Err(crate::fault::raise(crate::fault::FaultKind::Name, format!(
+                "Funkcja '{}' jest prywatna w module '{}' (oznacz ją `: pub {} def`, żeby ją eksportować)",
+                name, self.module_name(module), name
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn module_name(&self, module: usize) -> &str {
+        module.checked_sub(1).and_then(|i| self.modules.get(i)).map(|s| s.as_str()).unwrap_or("<main>")
+    }
+
+    /// Wykonaj `f` jako kod modułu (`None` = bez zmiany)
+    pub fn in_module<T>(&mut self, module: Option<usize>, f: impl FnOnce(&mut Env) -> T) -> T {
+        let Some(module) = module else { return f(self) };
+        let prev = std::mem::replace(&mut self.current_module, module);
+        let out = f(self);
+        self.current_module = prev;
+        out
+    }
+
+    /// Zarejestruj importowany moduł z `pub` i zwróć jego numer
+    pub fn register_module(&mut self, path: &str) -> usize {
+        self.modules.push(path.to_string());
+        self.modules.len()
+    }
+
     // ── Arena functions (gen 2): :: nazwa <size> def ─────────────────────────
 
     /// Zarejestruj arena function
@@ -368,54 +368,44 @@ pub fn cmd_env_help() {
     println!();
     println!("  {}:", "Komendy".bright_yellow().bold());
     println!(
-        "    {:<40} {}",
-        "hl env create <nazwa>".bright_cyan(),
-        "Utwórz nowe środowisko"
+        "    {:<40} Utwórz nowe środowisko",
+        "hl env create <nazwa>".bright_cyan()
     );
     println!(
-        "    {:<40} {}",
-        "hl env create <pełna/ścieżka/nazwa>".bright_cyan(),
-        "Utwórz w konkretnej lokalizacji"
+        "    {:<40} Utwórz w konkretnej lokalizacji",
+        "hl env create <pełna/ścieżka/nazwa>".bright_cyan()
     );
     println!(
-        "    {:<40} {}",
-        "hl env enter".bright_cyan(),
-        "Wejdź do środowiska w bieżącym katalogu"
+        "    {:<40} Wejdź do środowiska w bieżącym katalogu",
+        "hl env enter".bright_cyan()
     );
     println!(
-        "    {:<40} {}",
-        "hl env enter <nazwa>".bright_cyan(),
-        "Wejdź do środowiska po nazwie"
+        "    {:<40} Wejdź do środowiska po nazwie",
+        "hl env enter <nazwa>".bright_cyan()
     );
     println!(
-        "    {:<40} {}",
-        "hl env enter <pełna/ścieżka>".bright_cyan(),
-        "Wejdź do środowiska po ścieżce"
+        "    {:<40} Wejdź do środowiska po ścieżce",
+        "hl env enter <pełna/ścieżka>".bright_cyan()
     );
     println!(
-        "    {:<40} {}",
-        "hl env exit".bright_cyan(),
-        "Opuść środowisko (wróć do globalnego)"
+        "    {:<40} Opuść środowisko (wróć do globalnego)",
+        "hl env exit".bright_cyan()
     );
     println!(
-        "    {:<40} {}",
-        "hl env remove <nazwa>".bright_cyan(),
-        "Usuń środowisko (z wszystkimi paczkami)"
+        "    {:<40} Usuń środowisko (z wszystkimi paczkami)",
+        "hl env remove <nazwa>".bright_cyan()
     );
     println!(
-        "    {:<40} {}",
-        "hl env list".bright_cyan(),
-        "Lista wszystkich środowisk"
+        "    {:<40} Lista wszystkich środowisk",
+        "hl env list".bright_cyan()
     );
     println!(
-        "    {:<40} {}",
-        "hl env status".bright_cyan(),
-        "Status aktywnego środowiska"
+        "    {:<40} Status aktywnego środowiska",
+        "hl env status".bright_cyan()
     );
     println!(
-        "    {:<40} {}",
-        "hl env help".bright_cyan(),
-        "Ta wiadomość"
+        "    {:<40} Ta wiadomość",
+        "hl env help".bright_cyan()
     );
     println!();
     println!("  {}:", "Jak działa izolacja".bright_yellow().bold());
@@ -427,7 +417,7 @@ pub fn cmd_env_help() {
     println!("  {}:", "Config".bright_yellow().bold());
     println!(
         "    {}",
-        format!("~/.config/hackeros/hacker-lang/config.hk").bright_black()
+        "~/.config/hackeros/hacker-lang/config.hk".to_string().bright_black()
     );
     println!("    Sekcja [env] przechowuje aktywne środowisko.");
     println!("    bit i hl czytają tę sekcję automatycznie.");
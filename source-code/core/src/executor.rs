@@ -1,29 +1,36 @@
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use anyhow::{Result, bail};
+use colored::Colorize;
 use smallvec::SmallVec;
 use tracing::debug;
 use hl_parser::ast::*;
+use hl_parser::truth::{compare_text, text_equals};
 use crate::env::{Env, Value};
 use crate::deps::resolve_dependency;
 use crate::libs::resolve_import;
 use crate::quick::exec_quick;
 use crate::arena::ArenaContext;
 use crate::extern_runner::exec_extern_def;
+use crate::fault::{self, FaultKind};
 
 pub struct ExecResult {
     pub exit_code: i32,
     pub stdout:    Option<String>,
+    /// Przechwycone wyjście przekroczyło limit (obcięte lub zrzucone do pliku)
+    pub truncated: bool,
 }
 
 impl ExecResult {
-    #[inline] pub fn ok()              -> Self { Self { exit_code: 0, stdout: None } }
-    #[inline] pub fn err(n: i32)       -> Self { Self { exit_code: n, stdout: None } }
-    #[inline] pub fn err_or_ok(code: i32) -> Self { Self { exit_code: code, stdout: None } }
+    #[inline] pub fn ok()              -> Self { Self { exit_code: 0, stdout: None, truncated: false } }
+    #[inline] pub fn err(n: i32)       -> Self { Self { exit_code: n, stdout: None, truncated: false } }
+    #[inline] pub fn err_or_ok(code: i32) -> Self { Self { exit_code: code, stdout: None, truncated: false } }
     #[inline] pub fn is_ok(&self) -> bool { self.exit_code == 0 }
 }
 
+/// `exit [N]` — kod zakończenia skryptu (nieliczbowe N → 1); wspólne z VM
 #[inline]
-fn try_builtin_exit(cmd: &str) -> Option<i32> {
+pub fn try_builtin_exit(cmd: &str) -> Option<i32> {
     let t = cmd.trim();
     if t == "exit" { return Some(0); }
     if let Some(rest) = t.strip_prefix("exit ") {
@@ -32,6 +39,15 @@ fn try_builtin_exit(cmd: &str) -> Option<i32> {
     None
 }
 
+/// `echo` w bloku komendy jest zabroniony — tekst wypisuje `~>`; wspólne z VM
+pub fn reject_echo(cmd: &str) -> Result<()> {
+    let t = cmd.trim();
+    if t.starts_with("echo ") || t == "echo" {
+        bail!("'echo' jest zabroniony. Użyj '~>'.");
+    }
+    Ok(())
+}
+
 /// Wbudowany `test` — poprawna obsługa pustych stringów.
 /// Zastępuje /usr/bin/test który ma bug: `test -n` (bez argumentu) → exit 0 (true!)
 /// Nasze wbudowane: jeśli brak argumentu po -n, traktujemy jako pusty string → false.
@@ -142,11 +158,12 @@ fn needs_shell(cmd: &str) -> bool {
             b'&' | b';' | b'`' => return true,
             b'|' if i + 1 < b.len() && b[i+1] != b'>' => return true,
             b'>' if i + 1 < b.len() && (b[i+1] == b'>' || b[i+1] == b' ') => return true,
+            // Przekierowanie deskryptora: 2>/dev/null, 2>&1
+            b'>' if i > 0 && b[i-1].is_ascii_digit() => return true,
             b'<' if i + 1 < b.len() && b[i+1] == b' ' => return true,
             b'$' if i + 1 < b.len() && b[i+1] == b'(' => return true,
-            b'*' if i + 1 < b.len() => {
-                if i + 2 < b.len() && b[i+1] != b'/' { return true; }
-            }
+            b'*' if i + 1 < b.len()
+                && i + 2 < b.len() && b[i+1] != b'/' => { return true; }
             _ => {}
         }
         i += 1;
@@ -161,8 +178,18 @@ fn shell_words(s: &str) -> SmallVec<[String; 8]> {
     let mut cur   = String::with_capacity(32);
     let (mut in_s, mut in_d) = (false, false);
     let mut had_quote = false;
-    for c in s.chars() {
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
         match c {
+            // Jak w sh: w "..." backslash zabezpiecza tylko $ ` " \ i nową linię, inny zostaje dosłownie
+            '\\' if in_d => match chars.next() {
+                Some('\n') => {}
+                Some(n) if "$`\"\\".contains(n) => cur.push(n),
+                Some(n) => { cur.push('\\'); cur.push(n); }
+                None    => cur.push('\\'),
+            },
+            // Poza cudzysłowami zabezpiecza każdy następny znak
+            '\\' if !in_s => { if let Some(n) = chars.next() { cur.push(n); } }
             '\'' if !in_d => { in_s = !in_s; had_quote = true; }
             '"'  if !in_s => { in_d = !in_d; had_quote = true; }
             ' ' | '\t' if !in_s && !in_d => {
@@ -180,7 +207,20 @@ fn shell_words(s: &str) -> SmallVec<[String; 8]> {
 }
 
 fn run_command(raw: &str, sudo: bool, isolated: bool, interpolate: bool, env: &mut Env, capture: bool) -> Result<ExecResult> {
-    let expanded = if interpolate || raw.contains('@') {
+    run_command_impl(raw, sudo, isolated, interpolate, true, env, capture)
+}
+
+/// Tryb `>`: argv bez powłoki — `$HOME`, glob, `|` i `$(..)` trafiają do programu dosłownie.
+/// Podstawianie `@zmiennych` zostaje, bo to składnia HL, nie powłoki.
+fn run_raw_command(raw: &str, env: &mut Env, capture: bool) -> Result<ExecResult> {
+    run_command_impl(raw, false, false, false, false, env, capture)
+}
+
+fn run_command_impl(raw: &str, sudo: bool, isolated: bool, interpolate: bool, shell: bool,
+                    env: &mut Env, capture: bool) -> Result<ExecResult> {
+    let expanded = if raw.contains('{') {
+        expand_command(raw, env)
+    } else if interpolate || raw.contains('@') {
         env.interpolate(raw)
     } else {
         raw.to_string()
@@ -191,6 +231,7 @@ fn run_command(raw: &str, sudo: bool, isolated: bool, interpolate: bool, env: &m
     if let Some(code) = try_builtin_exit(trimmed) {
         std::process::exit(code);
     }
+    env.deny.check(trimmed)?;
 
     // Wbudowany `test` — poprawna obsługa pustych stringów bez subprocess.
     // Kluczowe: "test -n " (pusty var) → parts=["test","-n"] → test -n → exit 0 (BUG w /usr/bin/test)
@@ -199,51 +240,277 @@ fn run_command(raw: &str, sudo: bool, isolated: bool, interpolate: bool, env: &m
         return Ok(ExecResult::err_or_ok(code));
     }
 
-    if needs_shell(trimmed) { return run_via_shell(trimmed, sudo, isolated, capture); }
+    if shell && needs_shell(trimmed) { return run_via_shell(trimmed, sudo, isolated, capture, env); }
     let parts = shell_words(trimmed);
     if parts.is_empty() { return Ok(ExecResult::ok()); }
-    build_and_run(parts, sudo, isolated, capture)
+    build_and_run(parts, sudo, isolated, capture, env)
+}
+
+/// `@zmienne` wklejane dosłownie, `{zmienne}` jako zacytowane słowo (hl_parser::cmd_template)
+fn expand_command(raw: &str, env: &mut Env) -> String {
+    use hl_parser::cmd_template::{split_placeholders, shell_quote, CmdSegment};
+    let mut out = String::with_capacity(raw.len());
+    for seg in split_placeholders(raw) {
+        match seg {
+            CmdSegment::Text(text)    => out.push_str(&env.interpolate(text)),
+            CmdSegment::Quoted(name)  => out.push_str(&shell_quote(&env.get_var_str(name))),
+        }
+    }
+    out
 }
 
-fn run_via_shell(cmd: &str, sudo: bool, isolated: bool, capture: bool) -> Result<ExecResult> {
+fn run_via_shell(cmd: &str, sudo: bool, isolated: bool, capture: bool, env: &Env) -> Result<ExecResult> {
     let (prog, args): (String, Vec<String>) = match (sudo, isolated) {
         (false, false) => ("bash".into(), vec!["-c".into(), cmd.into()]),
         (true,  false) => ("sudo".into(), vec!["bash".into(), "-c".into(), cmd.into()]),
         (false, true)  => ("unshare".into(), vec!["--mount".into(),"--pid".into(),"--net".into(),"--fork".into(),"--".into(),"bash".into(),"-c".into(),cmd.into()]),
         (true,  true)  => ("sudo".into(), vec!["unshare".into(),"--mount".into(),"--pid".into(),"--net".into(),"--fork".into(),"--".into(),"bash".into(),"-c".into(),cmd.into()]),
     };
-    exec_process(prog, args, capture)
+    exec_process(prog, args, capture, env)
 }
 
-fn build_and_run(parts: SmallVec<[String; 8]>, sudo: bool, isolated: bool, capture: bool) -> Result<ExecResult> {
+fn build_and_run(parts: SmallVec<[String; 8]>, sudo: bool, isolated: bool, capture: bool, env: &Env) -> Result<ExecResult> {
     let (prog, args): (String, Vec<String>) = match (sudo, isolated) {
         (false, false) => { let mut it = parts.into_iter(); let p = it.next().unwrap(); (p, it.collect()) }
         (true,  false) => ("sudo".into(), parts.into_iter().collect()),
         (false, true)  => { let mut a = vec!["--mount".into(),"--pid".into(),"--net".into(),"--fork".into(),"--".into()]; a.extend(parts); ("unshare".into(), a) }
         (true,  true)  => { let mut iso = vec!["--mount".into(),"--pid".into(),"--net".into(),"--fork".into(),"--".into()]; iso.extend(parts); let mut a = vec!["unshare".into()]; a.extend(iso); ("sudo".into(), a) }
     };
-    exec_process(prog, args, capture)
+    exec_process(prog, args, capture, env)
 }
 
-fn exec_process(prog: String, args: Vec<String>, capture: bool) -> Result<ExecResult> {
+/// Kod wyjścia procesu; zabity sygnałem N daje 128+N, jak w powłoce (SIGKILL → 137)
+pub fn status_code(status: std::process::ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    status.code().or_else(|| status.signal().map(|sig| 128 + sig)).unwrap_or(1)
+}
+
+fn exec_process(prog: String, args: Vec<String>, capture: bool, env: &Env) -> Result<ExecResult> {
     let mut cmd = Command::new(&prog);
     cmd.args(&args);
+    env.apply_env(&mut cmd);
     if capture {
         // WAZONE: stdin musi byc null — inherit blokuje gdy subproces czeka na input z TTY
         // (bug powodujacy wiszenie >> bash -c "..." |> @var)
         cmd.stdin(Stdio::null())
            .stdout(Stdio::piped())
            .stderr(Stdio::inherit());
-        let out = cmd.output()?;
-        return Ok(ExecResult {
-            exit_code: out.status.code().unwrap_or(1),
-            stdout:    Some(String::from_utf8_lossy(&out.stdout).into_owned()),
-        });
+        return capture_output(cmd, &CaptureLimit::from_env(), None);
     }
     cmd.stdin(Stdio::inherit())
        .stdout(Stdio::inherit())
        .stderr(Stdio::inherit());
-    Ok(ExecResult { exit_code: cmd.status()?.code().unwrap_or(1), stdout: None })
+    Ok(ExecResult { exit_code: status_code(cmd.status()?), stdout: None, truncated: false })
+}
+
+// ── Limit przechwytywania (|> @var, %x = $(cmd)) ──────────────────────────────
+
+/// Domyślny limit przechwyconego stdout: 64 MiB
+pub const DEFAULT_MAX_CAPTURE: usize = 64 * 1024 * 1024;
+
+/// Co zrobić gdy wyjście przekroczy limit
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureOverflow {
+    /// Zachowaj pierwsze `max_bytes`, dopisz znacznik i zabij proces
+    Truncate,
+    /// Strumieniuj całość do pliku w prywatnym katalogu w $TMPDIR; stdout = ścieżka pliku,
+    /// który usuwa skrypt
+    TempFile,
+}
+
+#[derive(Debug, Clone)]
+pub struct CaptureLimit {
+    pub max_bytes: usize,
+    pub overflow:  CaptureOverflow,
+}
+
+impl CaptureLimit {
+    /// HL_MAX_CAPTURE=64m (składnia jak rozmiar areny), HL_CAPTURE_OVERFLOW=truncate|file
+    pub fn from_env() -> Self {
+        let max_bytes = std::env::var("HL_MAX_CAPTURE").ok()
+            .map(|s| ArenaSize::parse(&s).bytes())
+            .unwrap_or(DEFAULT_MAX_CAPTURE);
+        let overflow = match std::env::var("HL_CAPTURE_OVERFLOW").as_deref() {
+            Ok("file") | Ok("tempfile") => CaptureOverflow::TempFile,
+            _                           => CaptureOverflow::Truncate,
+        };
+        Self { max_bytes, overflow }
+    }
+}
+
+// ── Limit wyjścia `~>` (hl run --max-output) ─────────────────────────────────
+
+/// Limit bajtów wypisanych przez `~>` w jednym przebiegu skryptu.
+/// Po przekroczeniu dalsze linie są pomijane, a na stderr trafia jedna notka —
+/// pętla z `~>` nie zaleje terminala ani logów, ale skrypt działa dalej.
+#[derive(Debug)]
+pub struct OutputLimit {
+    max_bytes: usize,
+    written:   AtomicUsize,
+    truncated: AtomicBool,
+}
+
+impl OutputLimit {
+    pub fn new(max_bytes: usize) -> Self {
+        Self { max_bytes, written: AtomicUsize::new(0), truncated: AtomicBool::new(false) }
+    }
+
+    /// HL_MAX_OUTPUT=1m (składnia jak rozmiar areny); brak = bez limitu
+    pub fn from_env() -> Option<Self> {
+        std::env::var("HL_MAX_OUTPUT").ok().map(|s| Self::new(ArenaSize::parse(&s).bytes()))
+    }
+
+    /// Czy linia o długości `len` (bez \n) mieści się w limicie
+    pub fn admit(&self, len: usize) -> bool {
+        if self.truncated.load(Ordering::Relaxed) { return false; }
+        let before = self.written.fetch_add(len + 1, Ordering::Relaxed);
+        if before + len < self.max_bytes { return true; }
+        if !self.truncated.swap(true, Ordering::Relaxed) {
+            eprintln!("{} wyjście obcięte po {} B (--max-output / HL_MAX_OUTPUT)", "[hl]".yellow(), self.max_bytes);
+        }
+        false
+    }
+
+    pub fn truncated(&self) -> bool { self.truncated.load(Ordering::Relaxed) }
+}
+
+/// `~>` z uwzględnieniem limitu wyjścia; obcięcie ustawia @_output_truncated
+fn print_line(line: &str, env: &mut Env) {
+    if let Some(limit) = &env.output_limit {
+        if !limit.admit(line.len()) {
+            env.set_var("_output_truncated", Value::Bool(true));
+            return;
+        }
+    }
+    println!("{}", line);
+}
+
+/// Znacznik dopisywany do obciętego wyjścia
+pub fn truncation_marker(max_bytes: usize) -> String {
+    format!("\n[hl: wyjście obcięte do {} B]", max_bytes)
+}
+
+/// `input` — dane dla stdin (cmd musi mieć `Stdio::piped()`); pisane z osobnego wątku,
+/// żeby duże wejście nie zakleszczyło się z pełnym pipe stdout. Wspólne z VM (hl_jit).
+pub fn capture_output(mut cmd: Command, limit: &CaptureLimit, input: Option<String>) -> Result<ExecResult> {
+    use std::io::{Read, Write};
+    let mut child = cmd.spawn()?;
+    let writer = input.map(|data| feed_stdin(&mut child, data));
+    let mut pipe  = child.stdout.take().expect("stdout ustawione na piped");
+    let mut buf   = Vec::with_capacity(4096);
+    let mut chunk = [0u8; 8192];
+    let mut truncated = false;
+    loop {
+        let n = pipe.read(&mut chunk)?;
+        if n == 0 { break; }
+        if buf.len() + n > limit.max_bytes {
+            truncated = true;
+            match limit.overflow {
+                CaptureOverflow::Truncate => {
+                    buf.extend_from_slice(&chunk[..limit.max_bytes - buf.len()]);
+                    let _ = child.kill();
+                }
+                CaptureOverflow::TempFile => {
+                    let (path, mut file) = create_overflow_file(&capture_dir()?)?;
+                    file.write_all(&buf)?;
+                    file.write_all(&chunk[..n])?;
+                    std::io::copy(&mut pipe, &mut file)?;
+                    buf = path.display().to_string().into_bytes();
+                }
+            }
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    drop(pipe);
+    let status = child.wait()?;
+    if let Some(w) = writer { let _ = w.join(); }
+    let mut stdout = String::from_utf8_lossy(&buf).into_owned();
+    if truncated && limit.overflow == CaptureOverflow::Truncate {
+        stdout.push_str(&truncation_marker(limit.max_bytes));
+    }
+    // Proces zabity przez nas po obcięciu — nie traktuj tego jako błędu komendy
+    Ok(ExecResult {
+        exit_code: if truncated && limit.overflow == CaptureOverflow::Truncate { 0 } else { status_code(status) },
+        stdout:    Some(stdout),
+        truncated,
+    })
+}
+
+/// Prywatny katalog (0700) na pliki przepełnienia — jeden na proces, tworzony
+/// na wyłączność w $TMPDIR pod nieprzewidywalną nazwą; odtwarzany, gdy ktoś go usunął
+fn capture_dir() -> Result<std::path::PathBuf> {
+    use std::os::unix::fs::DirBuilderExt;
+    static DIR: std::sync::Mutex<Option<std::path::PathBuf>> = std::sync::Mutex::new(None);
+    let mut dir = DIR.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(d) = dir.as_ref().filter(|d| d.is_dir()) { return Ok(d.clone()); }
+    let base = std::env::temp_dir();
+    let path = loop {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos()).unwrap_or(0);
+        let p = base.join(format!("hl-capture-{}-{:08x}", std::process::id(), nanos));
+        match std::fs::DirBuilder::new().mode(0o700).create(&p) {
+            Ok(()) => break p,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => bail!("HL_CAPTURE_OVERFLOW: nie można utworzyć katalogu w {}: {}", base.display(), e),
+        }
+    };
+    *dir = Some(path.clone());
+    Ok(path)
+}
+
+/// Nowy plik `capture-<n>.out` w `dir`, otwarty z `create_new` — istniejący plik
+/// ani symlink pod tą nazwą nie zostanie nadpisany, bierzemy kolejny numer.
+/// Plik należy do skryptu (dostaje jego ścieżkę jako wynik przechwycenia) i to
+/// skrypt go usuwa; hl go nie kasuje, bo nie wie, kiedy wartość przestaje być potrzebna.
+fn create_overflow_file(dir: &std::path::Path) -> Result<(std::path::PathBuf, std::fs::File)> {
+    for n in 0.. {
+        let path = dir.join(format!("capture-{}.out", n));
+        match std::fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => bail!("HL_CAPTURE_OVERFLOW: {}: {}", path.display(), e),
+        }
+    }
+    unreachable!()
+}
+
+fn feed_stdin(child: &mut std::process::Child, data: String) -> std::thread::JoinHandle<()> {
+    use std::io::Write;
+    let mut stdin = child.stdin.take().expect("stdin ustawione na piped");
+    // Błąd zapisu (np. komenda nie czyta stdin i już wyszła) nie jest błędem skryptu
+    std::thread::spawn(move || { let _ = stdin.write_all(data.as_bytes()); })
+}
+
+/// `@dane |> cmd` — komenda jak `>>` (interpolacja, powłoka gdy potrzebna), `input` na stdin
+fn run_feed_command(raw: &str, input: String, env: &mut Env, capture: bool) -> Result<ExecResult> {
+    let expanded = env.interpolate(raw);
+    let trimmed = expanded.trim();
+    debug!("feed: {}", trimmed);
+    env.deny.check(trimmed)?;
+    let mut cmd = if needs_shell(trimmed) {
+        let mut c = Command::new("bash");
+        c.args(["-c", trimmed]);
+        c
+    } else {
+        let parts = shell_words(trimmed);
+        let Some((prog, args)) = parts.split_first() else { return Ok(ExecResult::ok()) };
+        let mut c = Command::new(prog);
+        c.args(args);
+        c
+    };
+    env.apply_env(&mut cmd);
+    cmd.stdin(Stdio::piped()).stderr(Stdio::inherit());
+    if capture {
+        cmd.stdout(Stdio::piped());
+        return capture_output(cmd, &CaptureLimit::from_env(), Some(input));
+    }
+    cmd.stdout(Stdio::inherit());
+    let mut child = cmd.spawn()?;
+    let writer = feed_stdin(&mut child, input);
+    let status = child.wait()?;
+    let _ = writer.join();
+    Ok(ExecResult::err_or_ok(status_code(status)))
 }
 
 fn resolve_export_value(val: &ExportValue, env: &mut Env) -> String {
@@ -255,6 +522,115 @@ fn resolve_export_value(val: &ExportValue, env: &mut Env) -> String {
 
 // ── Główna pętla wykonania ─────────────────────────────────────────────────────
 
+/// `? tempdir @d` — świeży katalog w `base` (Env::tempdir_base, domyślnie $TMPDIR) jako cwd na czas bloku.
+/// Drop przywraca poprzedni cwd i usuwa katalog, więc sprząta też przy błędzie ciała
+/// (tylko `exit N` kończy proces bez sprzątania). Używa go też VM (TempDirEnter).
+pub struct TempDirGuard {
+    path:  std::path::PathBuf,
+    saved: Option<std::path::PathBuf>,
+}
+
+impl TempDirGuard {
+    pub fn enter(base: &std::path::Path) -> Result<Self> {
+        static SEQ: AtomicUsize = AtomicUsize::new(0);
+        let path = loop {
+            let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos()).unwrap_or(0);
+            let p = base.join(format!("hl-tmp-{}-{}-{:08x}", std::process::id(), SEQ.fetch_add(1, Ordering::Relaxed), nanos));
+            match std::fs::create_dir(&p) {
+                Ok(()) => break p,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => bail!("? tempdir: nie można utworzyć katalogu w {}: {}", base.display(), e),
+            }
+        };
+        let saved = std::env::current_dir().ok();
+        if let Err(e) = std::env::set_current_dir(&path) {
+            std::fs::remove_dir_all(&path).ok();
+            bail!("? tempdir: cd {}: {}", path.display(), e);
+        }
+        Ok(Self { path, saved })
+    }
+
+    pub fn path(&self) -> &std::path::Path { &self.path }
+}
+
+impl Drop for TempDirGuard {
+    fn drop(&mut self) {
+        if let Some(d) = &self.saved { std::env::set_current_dir(d).ok(); }
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            tracing::warn!("? tempdir: nie usunięto {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Proces tła (`& cmd`) do odebrania przez `:: wait`
+enum BgJob {
+    Running(std::process::Child),
+    /// Zakończony i odebrany przez system (nie zombie) — czeka już tylko kod wyjścia
+    Done(i32),
+}
+
+/// Procesy tła czekające na `:: wait` — klucz to PID.
+/// Globalne, bo goroutines, arena functions i VM mają własny stan.
+static BG_JOBS: std::sync::OnceLock<std::sync::Mutex<std::collections::HashMap<u32, BgJob>>> =
+    std::sync::OnceLock::new();
+
+fn bg_jobs() -> std::sync::MutexGuard<'static, std::collections::HashMap<u32, BgJob>> {
+    BG_JOBS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Zarejestruj proces tła (tree-walk i VM). Przy okazji odbiera zakończone procesy,
+/// których nikt nie czeka — skrypt uruchamiający wiele `& cmd` bez `:: wait` nie zostawia
+/// zombie ani otwartych uchwytów; ich kody zostają dla późniejszego `:: wait`.
+pub fn register_background(child: std::process::Child) -> u32 {
+    let pid = child.id();
+    let mut jobs = bg_jobs();
+    for job in jobs.values_mut() {
+        if let BgJob::Running(c) = job {
+            if let Ok(Some(status)) = c.try_wait() { *job = BgJob::Done(status_code(status)); }
+        }
+    }
+    jobs.insert(pid, BgJob::Running(child));
+    pid
+}
+
+/// Poczekaj na proces tła uruchomiony przez `& cmd` i zwróć jego kod wyjścia
+pub fn wait_background(pid: u32) -> Result<i32> {
+    let job = bg_jobs().remove(&pid);
+    match job {
+        Some(BgJob::Done(code)) => Ok(code),
+        Some(BgJob::Running(mut child)) => {
+            let status = child.wait().map_err(|e| anyhow::anyhow!("Błąd oczekiwania na PID {}: {}", pid, e))?;
+            Ok(status_code(status))
+        }
+        None => bail!("Brak procesu tła o PID {} (już zakończony przez :: wait albo nie uruchomiony przez &)", pid),
+    }
+}
+
+/// Warunek `?~` i `when` w `? switch`: sama `@zmienna` — jej prawdziwość, inaczej porównanie / komenda
+fn condition_holds(condition: &[StringPart], env: &mut Env) -> Result<bool> {
+    match condition {
+        [StringPart::Var(name)] if !name.contains(':') => Ok(var_is_truthy(name, env)),
+        _ => {
+            let cond_str = env.resolve_string_parts(condition);
+            eval_condition_fast(&cond_str, env)
+        }
+    }
+}
+
+fn guard_holds(arm: &MatchArm, env: &mut Env) -> Result<bool> {
+    arm.guard.as_deref().map_or(Ok(true), |g| condition_holds(g, env))
+}
+
+/// Przed iteracją numer `done` (od 0): czy pętla nie przekroczyła `env.loop_limit`
+#[inline]
+fn check_loop_limit(done: u64, line: usize, env: &Env) -> Result<()> {
+    match env.loop_limit {
+        Some(limit) if done >= limit => Err(crate::env::loop_limit_exceeded(line, limit)),
+        _ => Ok(()),
+    }
+}
+
 pub fn exec_nodes(nodes: &[Node], env: &mut Env) -> Result<ExecResult> {
     let mut last = ExecResult::ok();
     for node in nodes {
@@ -268,16 +644,24 @@ pub fn exec_nodes(nodes: &[Node], env: &mut Env) -> Result<ExecResult> {
 pub fn exec_node(node: &Node, env: &mut Env) -> Result<ExecResult> {
     match node {
         Node::LineComment(_) | Node::DocComment(_) | Node::BlockComment(_) => Ok(ExecResult::ok()),
+        // Marker nie może zmieniać kodu wyjścia — `?ok` po nim widzi poprzednią komendę
+        Node::SourceLine(line) => {
+            if let Some(cov) = &env.coverage {
+                cov.lock().unwrap_or_else(|e| e.into_inner()).hit(*line);
+            }
+            Ok(ExecResult::err_or_ok(env.last_exit))
+        }
 
         Node::Print { parts } => {
-            let has_vars = parts.iter().any(|p| matches!(p, StringPart::Var(_)));
+            let has_vars = parts.iter().any(StringPart::is_ref);
             if has_vars {
-                println!("{}", env.resolve_string_parts(parts));
+                let line = env.resolve_string_parts(parts);
+                print_line(&line, env);
             } else {
                 let total: usize = parts.iter().map(|p| if let StringPart::Literal(s) = p { s.len() } else { 0 }).sum();
                 let mut out = String::with_capacity(total);
                 for p in parts { if let StringPart::Literal(s) = p { out.push_str(s); } }
-                println!("{}", out);
+                print_line(&out, env);
             }
             Ok(ExecResult::ok())
         }
@@ -310,11 +694,9 @@ pub fn exec_node(node: &Node, env: &mut Env) -> Result<ExecResult> {
         Node::Command { raw, mode, .. } => {
             let trimmed = raw.trim();
             if let Some(code) = try_builtin_exit(trimmed) { std::process::exit(code); }
-            if trimmed.starts_with("echo ") || trimmed == "echo" {
-                bail!("'echo' jest zabroniony. Użyj '~>'.");
-            }
+            reject_echo(trimmed)?;
             let (sudo, isolated, interpolate) = match mode {
-                CommandMode::Plain            => (false, false, false),
+                CommandMode::Plain            => return run_raw_command(raw, env, false),
                 CommandMode::Sudo             => (true,  false, false),
                 CommandMode::Isolated         => (false, true,  false),
                 CommandMode::IsolatedSudo     => (true,  true,  false),
@@ -327,24 +709,30 @@ pub fn exec_node(node: &Node, env: &mut Env) -> Result<ExecResult> {
 
         Node::HshCommand { raw } => {
             let expanded = env.interpolate(raw);
-            let status = Command::new("hsh")
+            env.deny.check(&expanded)?;
+            let mut cmd = Command::new("hsh");
+            env.apply_env(&mut cmd);
+            let status = cmd
             .args(["-c", expanded.trim()])
             .stdin(Stdio::inherit()).stdout(Stdio::inherit()).stderr(Stdio::inherit())
             .status()
             .map_err(|e| anyhow::anyhow!("hsh nie znaleziony: {}", e))?;
-            Ok(ExecResult { exit_code: status.code().unwrap_or(1), stdout: None })
+            Ok(ExecResult { exit_code: status_code(status), stdout: None, truncated: false })
         }
 
         Node::Background { raw } => {
             let expanded = env.interpolate(raw);
-            let child = Command::new("sh")
+            env.deny.check(&expanded)?;
+            let mut cmd = Command::new("sh");
+            env.apply_env(&mut cmd);
+            let child = cmd
             .args(["-c", expanded.trim()])
             .stdin(Stdio::null()).stdout(Stdio::inherit()).stderr(Stdio::inherit())
             .spawn()
             .map_err(|e| anyhow::anyhow!("Błąd tła: {}", e))?;
-            env.set_var("_bg_pid", Value::Number(child.id() as f64));
-            eprintln!("\x1b[90m[hl &] PID={}\x1b[0m", child.id());
-            std::mem::forget(child);
+            let pid = register_background(child);
+            env.set_var("_bg_pid", Value::Number(pid as f64));
+            eprintln!("{}", format!("[hl &] PID={}", pid).bright_black());
             Ok(ExecResult::ok())
         }
 
@@ -357,20 +745,65 @@ pub fn exec_node(node: &Node, env: &mut Env) -> Result<ExecResult> {
             Ok(last)
         }
 
+        Node::Retry { count, delay_ms, body } => {
+            let mut last = ExecResult::ok();
+            for attempt in 1..=*count {
+                env.set_var("_retry_attempts", Value::Number(attempt as f64));
+                last = exec_nodes(body, env)?;
+                env.last_exit = last.exit_code;
+                if last.exit_code == 0 { break; }
+                if attempt < *count {
+                    tracing::debug!("retry: próba {}/{} nieudana (kod {})", attempt, count, last.exit_code);
+                    if *delay_ms > 0 { std::thread::sleep(std::time::Duration::from_millis(*delay_ms)); }
+                }
+            }
+            Ok(last)
+        }
+
+        Node::TempDir { var, body } => {
+            let dir = TempDirGuard::enter(&env.tempdir_base)?;
+            env.set_var(var, Value::String(dir.path().display().to_string()));
+            exec_nodes(body, env)
+        }
+
+        Node::Try { body, catches } => {
+            let err = match exec_nodes(body, env) {
+                Ok(r)  => return Ok(r),
+                Err(e) => e,
+            };
+            let (kind, message) = fault::classify(&err);
+            let Some(arm) = catches.iter().find(|c| fault::catches(c.kind.as_deref(), kind)) else {
+                return Err(err);
+            };
+            debug!("? catch {}: {}", kind.tag(), message);
+            env.set_var("_error_kind", Value::String(kind.tag().to_string()));
+            env.set_var("_error", Value::String(message.clone()));
+            if let Some(var) = &arm.var { env.set_var(var, Value::String(message)); }
+            env.last_exit = 1;
+            exec_nodes(&arm.body, env)
+        }
+
+        // Benchmarki wykonuje tylko `hl bench` (crate::bench) — zwykły przebieg je pomija
+        Node::Bench { .. } => Ok(ExecResult::err_or_ok(env.last_exit)),
+
         Node::FileImport { path, detail } => {
             let expanded = env.interpolate(path);
             // Dodaj .hl jeśli brak rozszerzenia (gen 2: << nazwa bez końcówki)
-            let resolved = if !expanded.contains('.') && !expanded.ends_with(".hl") {
+            let wanted = if !expanded.contains('.') && !expanded.ends_with(".hl") {
                 format!("{}.hl", expanded)
             } else {
                 expanded.clone()
             };
-            if !std::path::Path::new(&resolved).exists() {
-                bail!("Import: plik nie istnieje: '{}'", resolved);
-            }
+            let resolved = find_import(&wanted, env, "Import: plik nie istnieje")?;
             let src = std::fs::read_to_string(&resolved)?;
             if let Some(d) = detail { env.set_var("_import_detail", Value::String(d.clone())); }
-            exec_nodes(&hl_parser::parse_source(&src)?, env)
+            let nodes = crate::ast_cache::parse_import(&src)?;
+            // Zagnieżdżone `<<` szukają najpierw obok importowanego pliku
+            let dir = std::fs::canonicalize(&resolved).ok().and_then(|p| p.parent().map(|d| d.to_path_buf()));
+            let saved = std::mem::replace(&mut env.source_dir, dir);
+            let result = exec_module(&nodes, &resolved.display().to_string(), env);
+            env.source_dir = saved;
+            result
         }
 
         // <* katalog — import katalogu (gen 2)
@@ -378,11 +811,8 @@ pub fn exec_node(node: &Node, env: &mut Env) -> Result<ExecResult> {
         // imports.hl jest odpowiednikiem mod.rs z Rust
         Node::DirImport { path } => {
             let expanded = env.interpolate(path);
-            let dir = std::path::Path::new(&expanded);
-
-            if !dir.exists() {
-                bail!("<* import: katalog nie istnieje: '{}'", expanded);
-            }
+            let found = find_import(&expanded, env, "<* import: katalog nie istnieje")?;
+            let dir = found.as_path();
             if !dir.is_dir() {
                 bail!("<* import: '{}' nie jest katalogiem (użyj << dla pliku)", expanded);
             }
@@ -417,10 +847,12 @@ pub fn exec_node(node: &Node, env: &mut Env) -> Result<ExecResult> {
             // Wykonaj imports.hl — wszystkie << wewnątrz są relatywne do abs_dir
             let saved_dir = std::env::current_dir().ok();
             std::env::set_current_dir(&abs_dir).ok();
+            let saved_source = env.source_dir.replace(abs_dir);
 
-            let result = exec_nodes(&hl_parser::parse_source(&src)?, env);
+            let result = crate::ast_cache::parse_import(&src).and_then(|nodes| exec_nodes(&nodes, env));
 
             // Przywróć katalog roboczy
+            env.source_dir = saved_source;
             if let Some(d) = saved_dir { std::env::set_current_dir(d).ok(); }
 
             result
@@ -429,10 +861,10 @@ pub fn exec_node(node: &Node, env: &mut Env) -> Result<ExecResult> {
         Node::Goroutine { name, body } => {
             let body_clone = body.clone();
             let name_str   = name.clone().unwrap_or_else(|| "<goroutine>".to_string());
-            let mut thread_env = Env::new();
-            for (k, v) in &env.vars { thread_env.vars.insert(k.clone(), v.clone()); }
+            // Kopia zmiennych, wspólne Arc (frozen env, deny, coverage, limity) — jak w arena functions
+            let mut thread_env = Env::new_with_parent(env);
             std::thread::spawn(move || { let _ = exec_nodes(&body_clone, &mut thread_env); });
-            eprintln!("\x1b[35m[hl :*] goroutine '{}' uruchomiona\x1b[0m", name_str);
+            eprintln!("{}", format!("[hl :*] goroutine '{}' uruchomiona", name_str).magenta());
             Ok(ExecResult::ok())
         }
 
@@ -460,8 +892,7 @@ pub fn exec_node(node: &Node, env: &mut Env) -> Result<ExecResult> {
 
         Node::Export { name, value } => {
             let resolved = resolve_export_value(value, env);
-            std::env::set_var(name, &resolved);
-            env.set_var(name, Value::String(resolved));
+            env.export_env(name, resolved);
             Ok(ExecResult::ok())
         }
 
@@ -476,9 +907,9 @@ pub fn exec_node(node: &Node, env: &mut Env) -> Result<ExecResult> {
 
         Node::Dependency { name, apt_package } => {
             let apt = apt_package.as_deref();
-            match resolve_dependency(name, apt) {
+            match resolve_dependency(name, apt, env.prompter.as_ref()) {
                 Ok(r)  => Ok(if r.is_available() { ExecResult::ok() } else { ExecResult::err(1) }),
-                Err(e) => { eprintln!("\x1b[31m[hl dep]\x1b[0m {}", e); Ok(ExecResult::err(1)) }
+                Err(e) => { eprintln!("{} {}", "[hl dep]".red(), e); Ok(ExecResult::err(1)) }
             }
         }
 
@@ -487,47 +918,65 @@ pub fn exec_node(node: &Node, env: &mut Env) -> Result<ExecResult> {
             Ok(ExecResult::ok())
         }
 
-        Node::FuncDef { name, body } => {
-            env.define_function(name.clone(), body.clone());
+        Node::FuncDef { name, public, body } => {
+            env.define_function_in_module(name.clone(), body.clone(), *public);
             Ok(ExecResult::ok())
         }
 
         Node::FuncCall { name } => {
-            match env.get_function(name) {
-                Some(body) => exec_nodes(&body, env),
-                None       => bail!("Niezdefiniowana funkcja: '{}'", name),
+            match env.resolve_function(name)? {
+                Some((body, module)) => env.in_module(module, |env| exec_nodes(&body, env)),
+                None                 => Err(fault::raise(FaultKind::Name, format!("Niezdefiniowana funkcja: '{}'", name))),
             }
         }
 
+        Node::FuncRefCall { var, args } => {
+            let func = env.get_var_owned(var);
+            let call_args: Vec<String> = env.resolve_string_parts(args)
+                .split_whitespace().map(str::to_string).collect();
+            call_func_value(&func, &call_args, env)
+        }
+
         Node::Conditional { condition, body } => {
             let run = match condition {
                 ConditionKind::Ok  => env.last_exit == 0,
                 ConditionKind::Err => env.last_exit != 0,
             };
-            if run { exec_nodes(body, env) } else { Ok(ExecResult::ok()) }
+            // Pominięty blok przenosi kod wyjścia — `? ok … done` + `? err … done` po jednej komendzie
+            if run { exec_nodes(body, env) } else { Ok(ExecResult::err_or_ok(env.last_exit)) }
         }
 
-        Node::ForIn { var, iterable, body } => {
+        Node::Chain { op, node } => {
+            let run = match op {
+                ChainOp::And => env.last_exit == 0,
+                ChainOp::Or  => env.last_exit != 0,
+            };
+            // Pominięta instrukcja przenosi dalej poprzedni kod wyjścia
+            if run { exec_node(node, env) } else { Ok(ExecResult::err_or_ok(env.last_exit)) }
+        }
+
+        Node::ForIn { var, iterable, each, body, line } => {
             let iter_str = env.resolve_string_parts(iterable);
+            let items: Vec<String> = match each {
+                ForEach::Words => iter_str.split_whitespace().map(str::to_string).collect(),
+                ForEach::Chars => iter_str.chars().map(String::from).collect(),
+            };
             let mut last = ExecResult::ok();
-            for item in iter_str.split_whitespace() {
-                env.set_var(var, Value::String(item.to_string()));
+            for (i, item) in items.into_iter().enumerate() {
+                check_loop_limit(i as u64, *line, env)?;
+                env.set_var(var, Value::String(item));
                 last = exec_nodes(body, env)?;
                 env.last_exit = last.exit_code;
             }
             Ok(last)
         }
 
-        Node::WhileLoop { condition, body } => {
-            let mut iterations = 0usize;
-            const MAX_ITER: usize = 1_000_000;
+        Node::WhileLoop { condition, body, line } => {
+            let mut iterations = 0u64;
             loop {
-                if iterations >= MAX_ITER {
-                    bail!("Pętla while: przekroczono limit {} iteracji", MAX_ITER);
-                }
+                check_loop_limit(iterations, *line, env)?;
                 iterations += 1;
-                let cond_str = env.resolve_string_parts(condition);
-                if !eval_condition_fast(&cond_str, env)? { break; }
+                if !condition_holds(condition, env)? { break; }
                 let r = exec_nodes(body, env)?;
                 env.last_exit = r.exit_code;
             }
@@ -536,31 +985,25 @@ pub fn exec_node(node: &Node, env: &mut Env) -> Result<ExecResult> {
 
         Node::MatchExpr { subject, arms } => {
             let subj = env.resolve_string_parts(subject);
-            let mut matched = false;
-            let mut wildcard_idx = None;
-            for (i, arm) in arms.iter().enumerate() {
+            // Najpierw wzorce w kolejności, potem `*` — w obu przypadkach pierwsze ramię ze spełnionym `when`
+            let (wildcards, normals): (Vec<_>, Vec<_>) = arms.iter().partition(|a| a.pattern.trim() == "*");
+            for arm in normals {
                 let pattern = arm.pattern.trim();
-                if pattern == "*" { wildcard_idx = Some(i); continue; }
-                if pattern == subj || env.interpolate(pattern) == subj {
-                    exec_nodes(&arm.body, env)?;
-                    matched = true;
-                    break;
-                }
+                if !(text_equals(pattern, &subj) || text_equals(&env.interpolate(pattern), &subj)) { continue; }
+                if guard_holds(arm, env)? { exec_nodes(&arm.body, env)?; return Ok(ExecResult::ok()); }
             }
-            if !matched {
-                if let Some(idx) = wildcard_idx {
-                    exec_nodes(&arms[idx].body, env)?;
-                }
+            for arm in wildcards {
+                if guard_holds(arm, env)? { exec_nodes(&arm.body, env)?; break; }
             }
             Ok(ExecResult::ok())
         }
 
         Node::Arithmetic { expr, assign_to } => {
             let expanded = env.interpolate(expr);
-            let result   = eval_arithmetic_fast(&expanded);
+            let result   = eval_arithmetic_mode(&expanded, env.checked)?;
             let result = result.unwrap_or_else(|| eval_arithmetic_shell(&expanded));
             if let Some(var) = assign_to {
-                env.set_var(var, Value::String(result.clone()));
+                env.set_var(var, arith_value(result.clone()));
             } else {
                 println!("{}", result);
             }
@@ -568,13 +1011,29 @@ pub fn exec_node(node: &Node, env: &mut Env) -> Result<ExecResult> {
         }
 
         Node::PipeToVar { command, mode, var_name } => {
-            let interpolate = true;
-            let sudo     = matches!(mode, CommandMode::Sudo | CommandMode::IsolatedSudo | CommandMode::WithVarsSudo);
-            let isolated = matches!(mode, CommandMode::Isolated | CommandMode::IsolatedSudo | CommandMode::WithVarsIsolated);
-            let r = run_command(command, sudo, isolated, interpolate, env, true)?;
+            let r = if *mode == CommandMode::Plain {
+                run_raw_command(command, env, true)?
+            } else {
+                let sudo     = matches!(mode, CommandMode::Sudo | CommandMode::IsolatedSudo | CommandMode::WithVarsSudo);
+                let isolated = matches!(mode, CommandMode::Isolated | CommandMode::IsolatedSudo | CommandMode::WithVarsIsolated);
+                run_command(command, sudo, isolated, true, env, true)?
+            };
             let output = r.stdout.unwrap_or_default().trim().to_string();
             env.set_var(var_name, Value::String(output));
-            Ok(ExecResult { exit_code: r.exit_code, stdout: None })
+            env.set_var("_capture_truncated", Value::Bool(r.truncated));
+            Ok(ExecResult { exit_code: r.exit_code, stdout: None, truncated: r.truncated })
+        }
+
+        Node::Feed { source, command, var_name } => {
+            // Jak `@dane` w tekście — z fallbackiem na zmienne środowiska procesu
+            let input = env.resolve_string_parts(&[StringPart::Var(source.clone())]);
+            let r = run_feed_command(command, input, env, var_name.is_some())?;
+            if let Some(var_name) = var_name {
+                let output = r.stdout.unwrap_or_default().trim().to_string();
+                env.set_var(var_name, Value::String(output));
+                env.set_var("_capture_truncated", Value::Bool(r.truncated));
+            }
+            Ok(ExecResult { exit_code: r.exit_code, stdout: None, truncated: r.truncated })
         }
 
         Node::HackerOsApi { tool, args } => {
@@ -582,7 +1041,7 @@ pub fn exec_node(node: &Node, env: &mut Env) -> Result<ExecResult> {
             let args_str = env.resolve_string_parts(args);
             let args_str = args_str.trim();
             if which::which(bin).is_err() {
-                eprintln!("\x1b[33m[hl ||]\x1b[0m Narzędzie '{}' nie jest zainstalowane.", bin);
+                eprintln!("{} Narzędzie '{}' nie jest zainstalowane.", "[hl ||]".yellow(), bin);
                 return Ok(ExecResult::err(127));
             }
             let cmd = if args_str.is_empty() { bin.to_string() } else { format!("{} {}", bin, args_str) };
@@ -593,6 +1052,55 @@ pub fn exec_node(node: &Node, env: &mut Env) -> Result<ExecResult> {
     }
 }
 
+// ── Referencje do funkcji ─────────────────────────────────────────────────────
+
+/// Wywołaj funkcję z `Value::Func`. Argumenty związane przez partial application
+/// idą pierwsze; każdy `_` zastępowany jest kolejnym argumentem wywołania,
+/// pozostałe argumenty są dopisywane na końcu. Ciało widzi je jako @_arg0.. i @_argc.
+pub fn call_func_value(func: &Value, call_args: &[String], env: &mut Env) -> Result<ExecResult> {
+    let (name, bound) = match func {
+        Value::Func { name, bound } => (name, bound),
+        other => return Err(fault::raise(FaultKind::Type, format!("Wartość '{}' nie jest referencją do funkcji", other.to_string_val()))),
+    };
+    let (body, module) = match env.resolve_function(name)? {
+        Some(found) => found,
+        None        => return Err(fault::raise(FaultKind::Name, format!("Niezdefiniowana funkcja: '{}'", name))),
+    };
+    let args = apply_partial(bound, call_args);
+    env.set_var("_argc", Value::Number(args.len() as f64));
+    for (i, arg) in args.into_iter().enumerate() {
+        env.set_var(&format!("_arg{}", i), Value::String(arg));
+    }
+    env.in_module(module, |env| exec_nodes(&body, env))
+}
+
+/// Pierwsze istniejące miejsce dla `<<` / `<*` (crate::import_path); brak — ImportError z listą sprawdzonych
+fn find_import(path: &str, env: &Env, missing: &str) -> Result<std::path::PathBuf> {
+    let candidates = crate::import_path::candidates(path, env.source_dir.as_deref(), &env.import_roots);
+    if let Some(found) = candidates.iter().find(|p| p.exists()) { return Ok(found.clone()); }
+    let searched: Vec<String> = candidates.iter().map(|p| p.display().to_string()).collect();
+    Err(fault::raise(FaultKind::Import, format!("{}: '{}' (szukano: {})", missing, path, searched.join(", "))))
+}
+
+/// Wykonaj zaimportowany plik. Jeśli definiuje choć jedną `: pub nazwa def`,
+/// staje się modułem: jego pozostałe funkcje są widoczne tylko z jego własnego kodu.
+/// Pliki bez `pub` eksportują wszystko, jak dotąd.
+pub fn exec_module(nodes: &[Node], path: &str, env: &mut Env) -> Result<ExecResult> {
+    let has_pub = nodes.iter().any(|n| matches!(n, Node::FuncDef { public: true, .. }));
+    if !has_pub { return exec_nodes(nodes, env); }
+    let module = env.register_module(path);
+    env.in_module(Some(module), |env| exec_nodes(nodes, env))
+}
+
+pub fn apply_partial(bound: &[String], call_args: &[String]) -> Vec<String> {
+    let mut rest = call_args.iter();
+    let mut args: Vec<String> = bound.iter()
+        .map(|b| if b == "_" { rest.next().cloned().unwrap_or_default() } else { b.clone() })
+        .collect();
+    args.extend(rest.cloned());
+    args
+}
+
 // ── Arena Function execution ──────────────────────────────────────────────────
 
 /// Wykonaj arena function z bump-pointer arena allocatorem
@@ -665,11 +1173,12 @@ fn eval_var_value(value: &VarValue, env: &mut Env) -> Result<Value> {
            let r = run_command(cmd, false, false, true, env, true)?;
            Value::String(r.stdout.unwrap_or_default().trim().to_string())
        }
+       VarValue::ExitStatus(cmd) => Value::Number(run_raw_command(cmd, env, false)?.exit_code as f64),
        VarValue::Arithmetic(expr) => {
            let expanded = env.interpolate(expr);
-           let result = eval_arithmetic_fast(&expanded)
+           let result = eval_arithmetic_mode(&expanded, env.checked)?
            .unwrap_or_else(|| eval_arithmetic_shell(&expanded));
-           Value::String(result)
+           arith_value(result)
        }
        VarValue::List(items) => {
            Value::List(items.iter().map(|v| match v {
@@ -682,32 +1191,80 @@ fn eval_var_value(value: &VarValue, env: &mut Env) -> Result<Value> {
            }).collect())
        }
        VarValue::Map(_) => Value::String(String::new()),
+       VarValue::FuncRef { name, bound } => Value::Func { name: name.clone(), bound: bound.clone() },
     })
 }
 
 // ── Arytmetyka natywna ────────────────────────────────────────────────────────
 
+/// Pusty wynik (błąd dziedziny, np. sqrt(-1)) to nil, nie pusty string
+fn arith_value(result: String) -> Value {
+    if result.is_empty() { Value::Nil } else { Value::String(result) }
+}
+
 pub fn eval_arithmetic_fast(expr: &str) -> Option<String> {
+    eval_arithmetic_mode(expr, false).ok().flatten()
+}
+
+/// Największa wartość bezpiecznie zapisywalna jako i64 — powyżej tryb checked zgłasza przepełnienie
+const CHECKED_LIMIT: f64 = 9_223_372_036_854_775_807.0;
+
+/// `checked` (HL_CHECKED=1): dzielenie przez zero i wynik poza zakresem i64 to ArithmeticError
+/// zamiast cichego 0 / utraty precyzji. Bez `checked` zachowanie jak dotąd.
+fn eval_arithmetic_mode(expr: &str, checked: bool) -> Result<Option<String>> {
     let e = expr.trim();
-    if e.is_empty() { return Some("0".to_string()); }
-    match eval_expr(e) {
-        Some(v) => {
+    if e.is_empty() { return Ok(Some("0".to_string())); }
+    Ok(match eval_expr_mode(e, checked) {
+        Err(ArithErr::Fault(err)) => return Err(err),
+        Err(ArithErr::Unsupported) => None,
+        Ok(v) if checked && v.is_infinite() => {
+            return Err(fault::raise(FaultKind::Arithmetic,
+                format!("$( {} ): przepełnienie albo dzielenie przez zero", e)));
+        }
+        // Błąd dziedziny funkcji matematycznej (sqrt(-1)) — nil
+        Ok(v) if v.is_nan() => Some(String::new()),
+        Ok(v) => {
             if v.fract() == 0.0 && v.abs() < 1e15 {
                 Some(format!("{}", v as i64))
             } else {
                 Some(format!("{}", v))
             }
         }
-        None => None,
-    }
+    })
 }
 
-fn eval_expr(s: &str) -> Option<f64> {
+/// Dlaczego natywna ewaluacja się nie udała
+enum ArithErr {
+    /// Poza natywną gramatyką — fallback do `sh $(( ))`
+    Unsupported,
+    /// Błąd HL (np. `1.5 & 1`) — bez fallbacku
+    Fault(anyhow::Error),
+}
+
+type Arith = std::result::Result<f64, ArithErr>;
+
+fn eval_expr_mode(s: &str, checked: bool) -> Arith {
     let s = s.trim();
-    eval_additive(s)
+    eval_bitwise(s, checked)
 }
 
-fn eval_additive(s: &str) -> Option<f64> {
+/// `| ^ & << >>` — poniżej `+ -`, tylko na liczbach całkowitych
+fn eval_bitwise(s: &str, checked: bool) -> Arith {
+    let Some((left, op, right)) = hl_parser::math::split_bitwise(s) else { return eval_additive(s, checked) };
+    let (l, r) = (eval_bitwise(left.trim(), checked)?, eval_bitwise(right.trim(), checked)?);
+    // Nieskończoność z trybu checked przechodzi dalej jako przepełnienie
+    if l.is_infinite() || r.is_infinite() { return Ok(f64::INFINITY); }
+    hl_parser::math::eval_bitwise(op, l, r).ok_or_else(|| ArithErr::Fault(fault::raise(FaultKind::Type,
+        format!("{} {} {}: operator {} wymaga liczb całkowitych (przesunięcie 0..63)", l, op, r, op))))
+}
+
+/// Tryb checked: wynik poza zakresem i64 staje się nieskończonością i tak już zostaje
+#[inline]
+fn check(v: f64, checked: bool) -> f64 {
+    if checked && v.abs() > CHECKED_LIMIT { f64::INFINITY } else { v }
+}
+
+fn eval_additive(s: &str, checked: bool) -> Arith {
     let bytes = s.as_bytes();
     let mut depth = 0i32;
     let mut last_add = None;
@@ -730,14 +1287,14 @@ fn eval_additive(s: &str) -> Option<f64> {
         _                  => None,
     };
     if let Some((pos, op)) = split_at {
-        let left  = eval_multiplicative(s[..pos].trim())?;
-        let right = eval_multiplicative(s[pos+1..].trim())?;
-        return Some(if op == '+' { left + right } else { left - right });
+        let left  = eval_multiplicative(s[..pos].trim(), checked)?;
+        let right = eval_multiplicative(s[pos+1..].trim(), checked)?;
+        return Ok(check(if op == '+' { left + right } else { left - right }, checked));
     }
-    eval_multiplicative(s)
+    eval_multiplicative(s, checked)
 }
 
-fn eval_multiplicative(s: &str) -> Option<f64> {
+fn eval_multiplicative(s: &str, checked: bool) -> Arith {
     let bytes = s.as_bytes();
     let mut depth = 0i32;
     let mut split = None;
@@ -754,32 +1311,37 @@ fn eval_multiplicative(s: &str) -> Option<f64> {
         }
     }
     if let Some((pos, op)) = split {
-        let left  = eval_unary(s[..pos].trim())?;
-        let right = eval_unary(s[pos+1..].trim())?;
-        return Some(match op {
+        let left  = eval_unary(s[..pos].trim(), checked)?;
+        let right = eval_unary(s[pos+1..].trim(), checked)?;
+        let zero  = if checked { f64::INFINITY } else { 0.0 };
+        return Ok(check(match op {
             '*' => left * right,
-            '/' => if right == 0.0 { 0.0 } else { left / right },
-            '%' => if right == 0.0 { 0.0 } else { (left as i64 % right as i64) as f64 },
+            '/' => if right == 0.0 { zero } else { left / right },
+            '%' => if right == 0.0 { zero } else { (left as i64 % right as i64) as f64 },
                     _   => 0.0,
-        });
+        }, checked));
     }
-    eval_unary(s)
+    eval_unary(s, checked)
 }
 
-fn eval_unary(s: &str) -> Option<f64> {
+fn eval_unary(s: &str, checked: bool) -> Arith {
     let s = s.trim();
-    if s.starts_with('-') { return Some(-eval_atom(s[1..].trim())?); }
-    eval_atom(s)
+    if let Some(rest) = s.strip_prefix('-') { return Ok(-eval_atom(rest.trim(), checked)?); }
+    eval_atom(s, checked)
 }
 
-fn eval_atom(s: &str) -> Option<f64> {
+fn eval_atom(s: &str, checked: bool) -> Arith {
     let s = s.trim();
-    if s.is_empty() { return None; }
+    if s.is_empty() { return Err(ArithErr::Unsupported); }
     if s.starts_with('(') && s.ends_with(')') {
-        return eval_expr(&s[1..s.len()-1]);
+        return eval_expr_mode(&s[1..s.len()-1], checked);
     }
-    if let Ok(n) = s.parse::<f64>() { return Some(n); }
-    None
+    if let Ok(n) = s.parse::<f64>() { return Ok(check(n, checked)); }
+    if let Some((name, args)) = hl_parser::math::split_math_call(s) {
+        let args = args.iter().map(|a| eval_expr_mode(a, checked)).collect::<std::result::Result<Vec<_>, _>>()?;
+        return Ok(hl_parser::math::eval_math(name, &args));
+    }
+    Err(ArithErr::Unsupported)
 }
 
 fn eval_arithmetic_shell(expr: &str) -> String {
@@ -795,58 +1357,571 @@ fn eval_arithmetic_shell(expr: &str) -> String {
 
 // ── Warunek while ─────────────────────────────────────────────────────────────
 
+/// `?~ @x` — prawdziwość samej wartości (pusta lista, 0, "" → fałsz), bez szukania
+/// w niej porównania ani komendy; zmienna spoza HL — jej tekst ze środowiska procesu
+fn var_is_truthy(name: &str, env: &Env) -> bool {
+    match env.get_var(name) {
+        Value::Nil => hl_parser::truth::text_is_truthy(&env.get_var_str(name)),
+        v          => v.is_truthy(),
+    }
+}
+
 fn eval_condition_fast(cond: &str, env: &mut Env) -> Result<bool> {
     let cond = env.interpolate(cond);
     let cond = cond.trim();
-    if cond.is_empty() { return Ok(false); }
-    if cond == "true"  { return Ok(true);  }
-    if cond == "false" { return Ok(false); }
-
-    const OPS: &[&str] = &["==", "!=", ">=", "<=", ">", "<"];
-    for op in OPS {
-        if let Some(pos) = find_operator(cond, op) {
-            let left_raw  = cond[..pos].trim();
-            let right_raw = cond[pos + op.len()..].trim().trim_matches('"');
-            let lv = if left_raw.starts_with('@') {
-                env.get_var(&left_raw[1..]).to_string_val()
-            } else {
-                left_raw.to_string()
-            };
-            return Ok(match *op {
-                "==" => lv == right_raw,
-                "!=" => lv != right_raw,
-                ">=" => lv.parse::<f64>().unwrap_or(0.0) >= right_raw.parse::<f64>().unwrap_or(0.0),
-                      "<=" => lv.parse::<f64>().unwrap_or(0.0) <= right_raw.parse::<f64>().unwrap_or(0.0),
-                      ">"  => lv.parse::<f64>().unwrap_or(0.0) >  right_raw.parse::<f64>().unwrap_or(0.0),
-                      "<"  => lv.parse::<f64>().unwrap_or(0.0) <  right_raw.parse::<f64>().unwrap_or(0.0),
-                      _    => false,
-            });
+    if let Some(b) = hl_parser::truth::literal_condition(cond) { return Ok(b); }
+
+    // `a < b < c` = `a < b && b < c` (hl_parser::truth::comparison_chain)
+    if let Some((operands, ops)) = hl_parser::truth::comparison_chain(cond) {
+        let mut lv = match operands[0].strip_prefix('@') {
+            Some(name) => env.get_var(name).to_string_val(),
+            None       => operands[0].to_string(),
+        };
+        for (op, right) in ops.iter().zip(&operands[1..]) {
+            let rv = right.trim_matches('"');
+            if !compare_text(&lv, op, rv) { return Ok(false); }
+            lv = rv.to_string();
         }
+        return Ok(true);
     }
 
-    if cond.starts_with('@') {
-        let val = env.get_var(&cond[1..]).to_string_val();
-        return Ok(!val.is_empty() && val != "false" && val != "0");
+    if let Some(name) = cond.strip_prefix('@') {
+        return Ok(env.get_var(name).is_truthy());
     }
 
-    Ok(Command::new("sh").args(["-c", cond]).status().map(|s| s.success()).unwrap_or(false))
+    env.deny.check(cond)?;
+    let mut cmd = Command::new("sh");
+    env.apply_env(&mut cmd);
+    Ok(cmd.args(["-c", cond]).status().map(|s| s.success()).unwrap_or(false))
 }
 
-fn find_operator(s: &str, op: &str) -> Option<usize> {
-    let b = s.as_bytes();
-    let op_b = op.as_bytes();
-    let op_len = op_b.len();
-    let mut i = 0;
-    while i + op_len <= b.len() {
-        if &b[i..i+op_len] == op_b {
-            let ok = match op {
-                ">" => i + 1 >= b.len() || b[i+1] != b'=',
-                "<" => i + 1 >= b.len() || b[i+1] != b'=',
-                _   => true,
-            };
-            if ok { return Some(i); }
-        }
-        i += 1;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hl_test_support::TestDir;
+
+    fn run(src: &str) -> Env {
+        let mut env = Env::new();
+        exec_nodes(&hl_parser::parse_source(src).unwrap(), &mut env).unwrap();
+        env
+    }
+
+    #[test]
+    fn test_wait_background_exit_code() {
+        let env = run("& exit 3\n:: wait @_bg_pid\n");
+        assert_eq!(env.get_var("_wait_exit").to_string_val(), "3");
+        assert_eq!(env.last_exit, 3);
+        // Drugi wait na ten sam PID to błąd
+        let pid = env.get_var("_bg_pid").to_string_val();
+        assert!(wait_background(pid.parse().unwrap()).is_err());
+
+        // Zakończony proces odebrany przy następnym `&` — kod nadal dostępny dla `:: wait`
+        let env = run("& exit 4\n% first = @_bg_pid\n> sleep 0.2\n& true\n:: wait @first\n% c1 = @_wait_exit\n:: wait @_bg_pid\n");
+        assert_eq!(env.get_var("c1").to_string_val(), "4");
+        assert_eq!(env.last_exit, 0);
+    }
+
+    #[test]
+    fn test_deny_blocks_reboot_before_spawn() {
+        let mut env = Env::new();
+        env.deny = std::sync::Arc::new(crate::deny::DenyList::new(["reboot", "shutdown*"]));
+        let nodes = hl_parser::parse_source("% cmd = reboot\n>> @cmd\n").unwrap();
+        let Err(err) = exec_nodes(&nodes, &mut env) else { panic!("reboot musi być zablokowany") };
+        assert!(err.to_string().contains("--deny 'reboot'"), "{}", err);
+        // Reszta komend przechodzi
+        let nodes = hl_parser::parse_source(">> true\n").unwrap();
+        assert!(exec_nodes(&nodes, &mut env).unwrap().is_ok());
+        // Blok extern też przechodzi przez --deny
+        let ext = Node::ExternDef { file: "reboot".into(), runtime: hl_parser::ast::ExternRuntime::Elf, body: vec![] };
+        let Err(err) = exec_nodes(&[ext], &mut env) else { panic!("extern reboot musi być zablokowany") };
+        assert!(err.to_string().contains("--deny 'reboot'"), "{}", err);
+    }
+
+    #[test]
+    fn test_frozen_env_export_does_not_leak_to_next_script() {
+        let frozen = || {
+            let mut env = Env::new();
+            env.frozen_env = Some(std::sync::Arc::new(std::sync::Mutex::new(std::env::vars().collect())));
+            env
+        };
+        let mut first = frozen();
+        let nodes = hl_parser::parse_source("=> HL_FROZEN_TEST = tak\n> printenv HL_FROZEN_TEST |> @seen\n").unwrap();
+        exec_nodes(&nodes, &mut first).unwrap();
+        // Komenda potomna widzi eksport, proces hl — nie
+        assert_eq!(first.get_var("seen").to_string_val().trim(), "tak");
+        assert!(std::env::var("HL_FROZEN_TEST").is_err());
+
+        let mut second = frozen();
+        let nodes = hl_parser::parse_source("% v = @env:HL_FROZEN_TEST\n").unwrap();
+        exec_nodes(&nodes, &mut second).unwrap();
+        assert_eq!(second.get_var("v").to_string_val(), "");
+    }
+
+    #[test]
+    fn test_goroutine_sees_frozen_env_export() {
+        let mut env = Env::new();
+        let frozen = std::sync::Arc::new(std::sync::Mutex::new(std::env::vars().collect()));
+        env.frozen_env = Some(frozen.clone());
+        let src = "=> HL_FROZEN_GO = tak\n:*\n    > printenv HL_FROZEN_GO |> @x\n    => HL_FROZEN_GO_SEEN = @x\ndone\n";
+        exec_nodes(&hl_parser::parse_source(src).unwrap(), &mut env).unwrap();
+        let seen = (0..500).find_map(|_| {
+            let v = frozen.lock().unwrap().get("HL_FROZEN_GO_SEEN").cloned();
+            if v.is_none() { std::thread::sleep(std::time::Duration::from_millis(10)); }
+            v
+        });
+        assert_eq!(seen.as_deref().map(str::trim), Some("tak"));
+        assert!(std::env::var("HL_FROZEN_GO_SEEN").is_err());
+    }
+
+    #[test]
+    fn test_frozen_env_reaches_extern_blocks() {
+        let mut env = Env::new();
+        let mut snapshot: rustc_hash::FxHashMap<String, String> = std::env::vars().collect();
+        snapshot.insert("HL_FROZEN_EXTERN".into(), "tak".into());
+        env.frozen_env = Some(std::sync::Arc::new(std::sync::Mutex::new(snapshot)));
+        // printenv kończy się 0 tylko gdy zmienna jest w środowisku potomka — jest tylko w kopii
+        let body = hl_parser::parse_source("% _arg_0 = HL_FROZEN_EXTERN\n").unwrap();
+        let ext = Node::ExternDef { file: "printenv".into(), runtime: ExternRuntime::Elf, body };
+        assert_eq!(exec_nodes(&[ext], &mut env).unwrap().exit_code, 0);
+    }
+
+    #[test]
+    fn test_chained_comparison_means_and() {
+        let mut env = Env::new();
+        assert!(eval_condition_fast("1 < 2 < 3", &mut env).unwrap());
+        assert!(!eval_condition_fast("1 < 3 < 2", &mut env).unwrap());
+        assert!(eval_condition_fast("3 > 2 >= 2 == 2", &mut env).unwrap());
+        let env = run("% i = 0\n?~ 0 <= @i < 3\n    $( @i + 1 ) -> @i\ndone\n");
+        assert_eq!(env.get_var("i").to_string_val(), "3");
+    }
+
+    #[test]
+    fn test_exit_status_binding() {
+        let env = run("% ok = ? > true\n% zle = ? > false\n% zabity = ? > sh -c \"kill -9 $$\"\n% tekst = ? >> x\n");
+        assert_eq!(env.get_var("ok").to_string_val(), "0");
+        assert_eq!(env.get_var("zle").to_string_val(), "1");
+        // Sygnał N → 128+N, jak w powłoce
+        assert_eq!(env.get_var("zabity").to_string_val(), "137");
+        assert_eq!(env.get_var("tekst").to_string_val(), "? >> x");
+    }
+
+    #[test]
+    fn test_chain_and_or() {
+        let env = run("% r = start\n> false\n&& % r = and\n|| % r = or\n");
+        assert_eq!(env.get_var("r").to_string_val(), "or");
+        let env = run("% r = start\n> true\n&& % r = and\n|| % r = or\n");
+        assert_eq!(env.get_var("r").to_string_val(), "and");
+    }
+
+    #[test]
+    fn test_retry_succeeds_on_third_attempt() {
+        let dir = TestDir::new("retry");
+        let counter = dir.join("counter");
+        let c = counter.display();
+        let src = format!(
+            "? retry 5\n>> n=$(cat {c} 2>/dev/null || echo 0); n=$((n+1)); echo $n > {c}; [ $n -ge 3 ]\ndone"
+        );
+        let env = run(&src);
+        assert_eq!(env.get_var("_retry_attempts").to_string_val(), "3");
+        assert_eq!(env.last_exit, 0);
+    }
+
+    #[test]
+    fn test_retry_exhausted_reports_failure() {
+        let env = run("? retry 2\n> false\ndone");
+        assert_eq!(env.get_var("_retry_attempts").to_string_val(), "2");
+        assert_ne!(env.last_exit, 0);
+    }
+
+    #[test]
+    fn test_raw_mode_skips_shell_expansion() {
+        let env = run("> echo $HOME |> @raw\n>> echo $HOME |> @shell");
+        assert_eq!(env.get_var("raw").to_string_val(), "$HOME");
+        assert_ne!(env.get_var("shell").to_string_val(), "$HOME");
+        let env = run("> printf '%s|%s' 'a b' \"$(x)\" |> @q");
+        assert_eq!(env.get_var("q").to_string_val(), "a b|$(x)");
+    }
+
+    #[test]
+    fn test_backslash_in_double_quotes_stays_literal() {
+        assert_eq!(shell_words(r#"bash -c "printf 'x\ny\n'""#).to_vec(), vec!["bash", "-c", r"printf 'x\ny\n'"]);
+        assert_eq!(shell_words(r#"echo "a\"b" "\$HOME" c\ d"#).to_vec(), vec!["echo", "a\"b", "$HOME", "c d"]);
+        let env = run("> bash -c \"printf 'x\\ny'\" |> @out");
+        assert_eq!(env.get_var("out").to_string_val(), "x\ny");
+    }
+
+    #[test]
+    fn test_placeholder_is_shell_quoted() {
+        let dir = TestDir::new("quote");
+        let marker = dir.join("marker");
+        let mut env = Env::new();
+        env.set_var("v", Value::String(format!("x; touch {}; rm -rf /", marker.display())));
+        let nodes = hl_parser::parse_source(">> printf '%s' {v} |> @sh\n> printf '%s' {v} |> @raw\n> printf '%s' @v{v} |> @pair").unwrap();
+        exec_nodes(&nodes, &mut env).unwrap();
+        assert!(!marker.exists(), "Wartość {{v}} nie może wykonać się jako osobna komenda");
+        let v = env.get_var("v").to_string_val();
+        assert_eq!(env.get_var("sh").to_string_val(), v);
+        assert_eq!(env.get_var("raw").to_string_val(), v);
+        assert!(env.get_var("pair").to_string_val().starts_with("x;"));
+    }
+
+    #[test]
+    fn test_script_args_quick_functions() {
+        let mut env = Env::new();
+        env.set_script_args(&["pierwszy".into(), "drugi".into()]);
+        crate::run_source(":: arg 0 |> @a\n:: arg 5 |> @missing\n:: args |> @all", &mut env).unwrap();
+        assert_eq!(env.get_var("a").to_string_val(), "pierwszy");
+        assert_eq!(env.get_var("missing").to_string_val(), "");
+        assert_eq!(env.get_var("all").to_string_val(), "pierwszy drugi");
+        assert_eq!(env.get_var("argc").to_string_val(), "2");
+    }
+
+    #[test]
+    fn test_double_at_escape_in_interpolation() {
+        let mut env = Env::new();
+        env.set_var("host", Value::String("hackeros".into()));
+        assert_eq!(env.interpolate("user@@host @host"), "user@host hackeros");
+    }
+
+    #[test]
+    fn test_feed_json_to_cat_is_captured() {
+        let mut env = Env::new();
+        env.set_var("json", Value::String(r#"{"host": "hackeros", "port": 22}"#.into()));
+        let r = crate::run_source("@json |> cat |> @echo\n@json |> grep -c port |> @n", &mut env).unwrap();
+        assert_eq!(r.exit_code, 0);
+        assert_eq!(env.get_var("echo").to_string_val(), r#"{"host": "hackeros", "port": 22}"#);
+        assert_eq!(env.get_var("n").to_string_val(), "1");
+    }
+
+    #[test]
+    fn test_skipped_ok_block_keeps_exit_for_err_block() {
+        let mut env = Env::new();
+        crate::run_source("> false\n? ok\n% gałąź = ok\ndone\n? err\n% gałąź = err\ndone", &mut env).unwrap();
+        assert_eq!(env.get_var("gałąź").to_string_val(), "err");
+    }
+
+    #[test]
+    fn test_dyn_var_keeps_preceding_text() {
+        let mut env = Env::new();
+        env.set_var("arg1", Value::String("drugi".into()));
+        env.set_var("_i", Value::String("1".into()));
+        assert_eq!(env.interpolate("przed @{arg@_i} po"), "przed drugi po");
+    }
+
+    #[test]
+    fn test_pipe_value_into_quick_call() {
+        let env = run("% s = a,b\n@s |> :: split , |> @p1\n% sep = ,\n@sep |> :: split x,y _ |> @p2\n");
+        assert_eq!(env.get_var("p1").to_string_val().trim(), "a\nb");
+        assert_eq!(env.get_var("p2").to_string_val().trim(), "x\ny");
+    }
+
+    #[test]
+    fn test_tempdir_block_removes_created_files() {
+        let base = TestDir::new("tempdir-base");
+        let mut env = Env::new();
+        env.tempdir_base = base.to_path_buf();
+        crate::run_source("? tempdir @d\n> touch plik.txt\n::exists @d/plik.txt\n% widac = @_last_bool\ndone", &mut env).unwrap();
+        assert_eq!(env.get_var("widac").to_string_val(), "true");
+        let dir = std::path::PathBuf::from(env.get_var("d").to_string_val());
+        assert!(dir.starts_with(&base), "{} poza {}", dir.display(), base.display());
+        assert!(std::fs::read_dir(&base).unwrap().next().is_none(), "{} nie usunięty", dir.display());
+
+        // Błąd w ciele też sprząta
+        let res = crate::run_source("? tempdir d\n> touch x\n<< /nie/ma/takiego.hl\ndone", &mut env);
+        assert!(res.is_err());
+        assert!(std::fs::read_dir(&base).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_import_exports_only_pub_functions() {
+        let dir = TestDir::new("pub");
+        let lib = dir.join("lib.hl");
+        std::fs::write(&lib, ": pomocnik def\n    % wynik = z pomocnika\ndone\n: pub api def\n    -- pomocnik\ndone\n").unwrap();
+
+        // Publiczna funkcja woła prywatną z własnego modułu
+        let env = run(&format!("<< {}\n-- api", lib.display()));
+        assert_eq!(env.get_var("wynik").to_string_val(), "z pomocnika");
+
+        let mut env = Env::new();
+        let err = crate::run_source(&format!("<< {}\n-- pomocnik", lib.display()), &mut env).err().expect("prywatna funkcja wywołana z importera");
+        assert!(err.to_string().contains("prywatna"), "{}", err);
+    }
+
+    #[test]
+    fn test_private_module_function_does_not_shadow_importer() {
+        let dir = TestDir::new("pub-shadow");
+        let lib = dir.join("lib.hl");
+        std::fs::write(&lib, ": helper def\n    % z_lib = tak\ndone\n: pub api def\n    -- helper\ndone\n").unwrap();
+
+        // Importer ma własny `helper` — przed importem i po nim
+        let env = run(&format!(": helper def\n    % z_main = tak\ndone\n<< {}\n-- helper\n% po_main = @z_main\n% po_lib = @z_lib\n-- api\n", lib.display()));
+        assert_eq!(env.get_var("po_main").to_string_val(), "tak");
+        assert_eq!(env.get_var("po_lib").to_string_val(), "", "`-- helper` importera wywołał funkcję modułu");
+        assert_eq!(env.get_var("z_lib").to_string_val(), "tak", "`api` powinno wołać `helper` swojego modułu");
+
+        let env = run(&format!("<< {}\n: helper def\n    % z_main = tak\ndone\n-- api\n-- helper\n", lib.display()));
+        assert_eq!(env.get_var("z_main").to_string_val(), "tak");
+        assert_eq!(env.get_var("z_lib").to_string_val(), "tak");
+    }
+
+    #[test]
+    fn test_import_resolves_via_roots_and_importer_dir() {
+        let proj = TestDir::new("import-path");
+        let (app, lib) = (proj.join("app"), proj.join("lib"));
+        std::fs::create_dir_all(&app).unwrap();
+        std::fs::create_dir_all(&lib).unwrap();
+        // a.hl importuje rodzeństwo b.hl — ani cwd, ani katalog skryptu go nie zawierają
+        std::fs::write(lib.join("a.hl"), "<< b\n% z_a = tak\n").unwrap();
+        std::fs::write(lib.join("b.hl"), "% z_b = tak\n").unwrap();
+
+        let mut env = Env::new();
+        env.import_roots = vec![lib.clone()];
+        env.source_dir = Some(app.clone());
+        crate::run_source("<< a\n", &mut env).unwrap();
+        assert_eq!(env.get_var("z_a").to_string_val(), "tak");
+        assert_eq!(env.get_var("z_b").to_string_val(), "tak");
+        assert_eq!(env.source_dir.as_deref(), Some(app.as_path()));
+
+        let err = crate::run_source("<< brak\n", &mut env).err().expect("brak pliku");
+        assert!(err.to_string().contains(&lib.join("brak.hl").display().to_string()), "{}", err);
+    }
+
+    #[test]
+    fn test_math_builtins_in_arithmetic() {
+        let env = run("$( sqrt(9.0) ) -> @a\n$( abs(-3) ) -> @b\n% c = $( max(2, 5) * 2 )\n$( sqrt(-1) ) -> @d");
+        assert_eq!(env.get_var("a").to_string_val(), "3");
+        assert_eq!(env.get_var("b").to_string_val(), "3");
+        assert_eq!(env.get_var("c").to_string_val(), "10");
+        assert!(matches!(env.get_var("d"), Value::Nil));
+    }
+
+    #[test]
+    fn test_try_catch_by_fault_kind() {
+        let env = run("% xs = [a b]\n? try\n    :: at xs 5\n    % po = tak\ndone\n? catch NameError\n    % arm = name\ndone\n? catch IndexError @e\n    ? err\n        % arm = index\n    done\ndone");
+        assert_eq!(env.get_var("arm").to_string_val(), "index");
+        assert!(matches!(env.get_var("po"), Value::Nil), "Ciało przerwane po błędzie");
+        assert_eq!(env.get_var("_error_kind").to_string_val(), "IndexError");
+        assert!(env.get_var("e").to_string_val().contains("poza zakresem"));
+
+        // Żadne ramię nie pasuje — błąd wychodzi poza blok
+        let mut env = Env::new();
+        let nodes = hl_parser::parse_source("? try\n    -- brak\ndone\n? catch IndexError\n    % x = 1\ndone").unwrap();
+        let err = exec_nodes(&nodes, &mut env).err().expect("NameError powinien przejść dalej");
+        assert_eq!(fault::classify(&err).0, FaultKind::Name);
+    }
+
+    #[test]
+    fn test_numeric_equality_ignores_notation() {
+        let env = run("% n = 3\n% wynik = nie\n?~ @n == 3.0\n    % wynik = tak\n    % n = 0\ndone\n\
+                   $( 6 / 2 ) -> @x\n% sw = nie\n? switch @x\n| 3.0\n    % sw = tak\n| *\n    % sw = inne\ndone\n");
+        assert_eq!(env.get_var("wynik").to_string_val(), "tak");
+        assert_eq!(env.get_var("sw").to_string_val(), "tak");
+    }
+
+    #[test]
+    fn test_switch_when_guard() {
+        let src = "? switch @kod\n| 0 when @verbose\n    % r = szczegoly\n| 0\n    % r = ok\n\
+                   | * when @kod > 100\n    % r = krytyczny\n| *\n    % r = blad\n| *\n    % r = drugi\ndone\n";
+        let case = |pre: &str| run(&format!("{}{}", pre, src)).get_var("r").to_string_val();
+        assert_eq!(case("% kod = 0\n% verbose = true\n"), "szczegoly");
+        assert_eq!(case("% kod = 0\n% verbose = false\n"), "ok");
+        assert_eq!(case("% kod = 500\n% verbose = true\n"), "krytyczny");
+        assert_eq!(case("% kod = 5\n% verbose = true\n"), "blad");
+    }
+
+    #[test]
+    fn test_at_negative_index() {
+        let env = run("% xs = \"1 2 3\"\n:: at xs -1 |> @last\n:: at xs -3 |> @first\n\
+                       ? try\n    :: at xs -5\ndone\n? catch IndexError\n    % poza = tak\ndone");
+        assert_eq!(env.get_var("last").to_string_val(), "3");
+        assert_eq!(env.get_var("first").to_string_val(), "1");
+        assert_eq!(env.get_var("poza").to_string_val(), "tak");
+    }
+
+    #[test]
+    fn test_checked_arithmetic_raises() {
+        let mut env = Env::new();
+        env.checked = true;
+        let src = "? try\n    $( 9223372036854775807 * 4 ) -> @a\ndone\n? catch ArithmeticError\n    % a = duzo\ndone\n\
+                   ? try\n    $( 1 / 0 ) -> @b\ndone\n? catch @e\n    % b = @_error_kind\ndone\n$( 7 / 2 ) -> @c";
+        exec_nodes(&hl_parser::parse_source(src).unwrap(), &mut env).unwrap();
+        assert_eq!(env.get_var("a").to_string_val(), "duzo");
+        assert_eq!(env.get_var("b").to_string_val(), "ArithmeticError");
+        assert_eq!(env.get_var("c").to_string_val(), "3.5");
+
+        // Bez trybu checked — stare zachowanie
+        assert_eq!(run("$( 1 / 0 ) -> @z").get_var("z").to_string_val(), "0");
+    }
+
+    #[test]
+    fn test_modulo_and_bitwise_operators() {
+        let env = run("$( 7 % 3 ) -> @m\n$( 6 & 3 ) -> @a\n$( 1 << 4 ) -> @s\n$( 8 | 6 & 3 ) -> @o\n\
+                       $( 1 << 1 + 1 ) -> @p\n? try\n    $( 1.5 & 1 ) -> @f\ndone\n? catch TypeError\n    % f = typ\ndone");
+        assert_eq!(env.get_var("m").to_string_val(), "1");
+        assert_eq!(env.get_var("a").to_string_val(), "2");
+        assert_eq!(env.get_var("s").to_string_val(), "16");
+        assert_eq!(env.get_var("o").to_string_val(), "10", "& wiąże mocniej niż |");
+        assert_eq!(env.get_var("p").to_string_val(), "4", "+ wiąże mocniej niż <<");
+        assert_eq!(env.get_var("f").to_string_val(), "typ");
+    }
+
+    #[test]
+    fn test_loop_limit_aborts_runaway_loop() {
+        let mut env = Env::new();
+        env.loop_limit = Some(3);
+        let nodes = hl_parser::parse_source("@ w in a b c\n    % ok = @w\ndone\n?~ true\n    % x = 1\ndone").unwrap();
+        let err = exec_nodes(&nodes, &mut env).err().expect("pętla bez końca musi się przerwać");
+        assert!(err.to_string().contains("Pętla w linii 4: przekroczono limit 3 iteracji"), "{}", err);
+        assert_eq!(env.get_var("ok").to_string_val(), "c", "3 iteracje mieszczą się w limicie");
+    }
+
+    #[test]
+    fn test_while_condition_truthiness() {
+        let env = run("% n = 3\n% i = 0\n?~ @n\n    $( @n - 1 ) -> @n\n    $( @i + 1 ) -> @i\ndone\n\
+                       ?~ 0\n    % zero = tak\ndone\n% s = x\n?~ @s\n    % s = 0\n    % napis = tak\ndone");
+        assert_eq!(env.get_var("i").to_string_val(), "3");
+        assert!(matches!(env.get_var("zero"), Value::Nil));
+        assert_eq!(env.get_var("napis").to_string_val(), "tak");
+        assert!(!Value::String("0.0".into()).is_truthy() && Value::String("x".into()).is_truthy());
+    }
+
+    #[test]
+    fn test_dyn_var_in_var_decl() {
+        let mut env = Env::new();
+        crate::run_source("% arg1 = drugi\n% _i = 1\n% x = przed @{arg@_i} po", &mut env).unwrap();
+        assert_eq!(env.get_var("x").to_string_val(), "przed drugi po");
+    }
+
+    #[test]
+    fn test_descriptor_redirect_needs_shell() {
+        assert!(needs_shell("ls /brak 2>/dev/null"));
+        assert!(needs_shell("cmd 2>&1"));
+        // Bez powłoki printf dostałby "2>/dev/null" jako argument
+        let env = run(">> printf %s x 2>/dev/null |> @out");
+        assert_eq!(env.get_var("out").to_string_val(), "x");
+    }
+
+    #[test]
+    fn test_env_scope_bypasses_local_shadow() {
+        let mut env = Env::new();
+        let real = std::env::var("HOME").unwrap_or_default();
+        crate::run_source("% HOME = /lokalny\n% a = @env:HOME\n% b = @HOME\n% c = @local:HOME\n% d = [@local:PATH]", &mut env).unwrap();
+        assert_eq!(env.get_var("a").to_string_val(), real);
+        assert_eq!(env.get_var("b").to_string_val(), "/lokalny");
+        assert_eq!(env.get_var("c").to_string_val(), "/lokalny");
+        assert_eq!(env.get_var("d").to_string_val(), "[]");
+    }
+
+    #[test]
+    fn test_for_chars_iterates_unicode_chars() {
+        let mut env = Env::new();
+        env.set_var("slowo", Value::String("żó w".into()));
+        crate::run_source("% n = 0\n@ c chars @slowo\n$( @n + 1 ) -> @n\ndone", &mut env).unwrap();
+        assert_eq!(env.get_var("n").to_string_val(), "4");
+        assert_eq!(env.get_var("c").to_string_val(), "w");
+    }
+
+    #[test]
+    fn test_output_limit_truncates_long_print_loop() {
+        let limit = std::sync::Arc::new(OutputLimit::new(64));
+        let mut env = Env::new();
+        env.output_limit = Some(limit.clone());
+        let r = crate::run_source("_1000000 ~> linia wyjscia\n% koniec = tak\n", &mut env).unwrap();
+        assert_eq!(r.exit_code, 0);
+        assert!(limit.truncated());
+        assert_eq!(env.get_var("_output_truncated").to_string_val(), "true");
+        assert_eq!(env.get_var("koniec").to_string_val(), "tak");
+    }
+
+    #[test]
+    fn test_coverage_marks_untaken_branch() {
+        let cov = std::sync::Arc::new(std::sync::Mutex::new(crate::coverage::Coverage::new()));
+        let mut env = Env::new();
+        env.coverage = Some(cov.clone());
+        let src = "> false\n? ok\n  ~> nigdy\ndone\n;; komentarz\n~> koniec\n";
+        crate::run_source(src, &mut env).unwrap();
+        let cov = cov.lock().unwrap();
+        assert_eq!(cov.hits(1), Some(1));
+        assert_eq!(cov.hits(2), Some(1));
+        assert_eq!(cov.hits(3), Some(0), "Gałąź ?ok nie powinna się wykonać");
+        assert_eq!(cov.hits(5), None, "Komentarz nie jest linią wykonywalną");
+        assert_eq!(cov.hits(6), Some(1));
+    }
+
+    #[test]
+    fn test_capture_truncated_over_limit() {
+        // `yes` produkuje wyjście bez końca — limit musi przerwać czytanie
+        let mut cmd = Command::new("yes");
+        cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::null());
+        let limit = CaptureLimit { max_bytes: 1024, overflow: CaptureOverflow::Truncate };
+        let r = capture_output(cmd, &limit, None).unwrap();
+        let out = r.stdout.unwrap();
+        assert!(r.truncated, "Powinna być ustawiona flaga obcięcia");
+        assert!(out.ends_with(&truncation_marker(1024)));
+        assert_eq!(out.len(), 1024 + truncation_marker(1024).len());
+    }
+
+    #[test]
+    fn test_capture_overflow_file_skips_existing() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = hl_test_support::TestDir::new("capture-overflow");
+        std::fs::write(dir.join("capture-0.out"), "stare").unwrap();
+        std::fs::write(dir.join("cel"), "nietknięty").unwrap();
+        std::os::unix::fs::symlink(dir.join("cel"), dir.join("capture-1.out")).unwrap();
+        let (path, _) = create_overflow_file(&dir).unwrap();
+        assert_eq!(path, dir.join("capture-2.out"));
+        assert_eq!(std::fs::read_to_string(dir.join("capture-0.out")).unwrap(), "stare");
+        assert_eq!(std::fs::read_to_string(dir.join("cel")).unwrap(), "nietknięty");
+
+        // Pełne przechwycenie: całe wyjście w pliku, katalog prywatny
+        let mut cmd = Command::new("seq");
+        cmd.args(["1", "1000"]).stdin(Stdio::null()).stdout(Stdio::piped());
+        let limit = CaptureLimit { max_bytes: 16, overflow: CaptureOverflow::TempFile };
+        let r = capture_output(cmd, &limit, None).unwrap();
+        let out = std::path::PathBuf::from(r.stdout.unwrap());
+        assert!(r.truncated);
+        let data = std::fs::read_to_string(&out).unwrap();
+        assert_eq!(data.lines().count(), 1000);
+        let mode = std::fs::metadata(out.parent().unwrap()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        std::fs::remove_file(&out).unwrap();
+    }
+
+    #[test]
+    fn test_capture_under_limit_untouched() {
+        let mut cmd = Command::new("printf");
+        cmd.arg("abc").stdin(Stdio::null()).stdout(Stdio::piped());
+        let limit = CaptureLimit { max_bytes: 1024, overflow: CaptureOverflow::Truncate };
+        let r = capture_output(cmd, &limit, None).unwrap();
+        assert!(!r.truncated);
+        assert_eq!(r.stdout.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn test_map_with_func_ref() {
+        let src = ": collect def\n% out = \"@out @_arg0\"\ndone\n% f = &collect\n:: map @f a b c";
+        let env = run(src);
+        assert_eq!(env.get_var("out").to_string_val().trim(), "a b c");
+    }
+
+    #[test]
+    fn test_map_with_bare_function_name() {
+        let src = ": collect def\n% out = \"@out @_arg0\"\ndone\n:: map collect x y";
+        let env = run(src);
+        assert_eq!(env.get_var("out").to_string_val().trim(), "x y");
+    }
+
+    #[test]
+    fn test_partial_application_call() {
+        let src = ": add def\n$( @_arg0 + @_arg1 ) -> @sum\ndone\n% inc = &add(1 _)\n-- @inc 41";
+        let env = run(src);
+        assert_eq!(env.get_var("sum").to_string_val(), "42");
+    }
+
+    #[test]
+    fn test_partial_application_in_map() {
+        let src = ": pair def\n% out = \"@out @_arg0-@_arg1\"\ndone\n% tag = &pair(_ x)\n:: map @tag 1 2";
+        let env = run(src);
+        assert_eq!(env.get_var("out").to_string_val().trim(), "1-x 2-x");
     }
-    None
 }
@@ -17,16 +17,14 @@ pub fn exec_extern_def(
     //    Env vars: _env_KEY=VALUE
     exec_nodes(body, env)?;
 
-    // 2. Zbierz argumenty pozycyjne z env
-    let mut args: Vec<String> = Vec::new();
-    let mut i = 0usize;
+    // 2. Zbierz argumenty pozycyjne z env (wartości — .so z hl_extern_call_v dostaje typy)
+    let mut arg_values: Vec<Value> = Vec::new();
     loop {
-        let key = format!("_arg_{}", i);
-        let val = env.get_var(&key).to_string_val();
-        if val.is_empty() { break; }
-        args.push(val);
-        i += 1;
+        let val = env.get_var(&format!("_arg_{}", arg_values.len()));
+        if val.to_string_val().is_empty() { break; }
+        arg_values.push(val.clone());
     }
+    let args: Vec<String> = arg_values.iter().map(|v| v.to_string_val()).collect();
 
     // 3. Zbierz zmienne env (_env_KEY)
     let mut extra_env: Vec<(String, String)> = Vec::new();
@@ -39,17 +37,21 @@ pub fn exec_extern_def(
     // 4. Rozwiąż ścieżkę pliku
     let resolved_path = resolve_extern_file(file, runtime, env);
 
-    // 5. Uruchom odpowiedni runtime
-    let result = match runtime {
-        ExternRuntime::Shell  => run_shell(&resolved_path, &args, &extra_env),
-        ExternRuntime::Python => run_python(&resolved_path, &args, &extra_env),
-        ExternRuntime::Java   => run_java(&resolved_path, &args, &extra_env),
-        ExternRuntime::Elf    => run_elf(&resolved_path, &args, &extra_env),
-        ExternRuntime::So     => run_so(&resolved_path, &args, &extra_env),
-    }?;
-
-    // 6. Zapisz wynik do env
-    env.set_var("_extern_result",   Value::String(result.stdout.clone().unwrap_or_default().trim().to_string()));
+    // 5. --deny — sprawdzany jak zwykła komenda: plik + argumenty
+    env.deny.check(&std::iter::once(resolved_path.as_str()).chain(args.iter().map(String::as_str)).collect::<Vec<_>>().join(" "))?;
+
+    // 6. Uruchom odpowiedni runtime
+    let (result, returned) = match runtime {
+        ExternRuntime::Shell  => (run_shell(&resolved_path, &args, &extra_env, env)?, None),
+        ExternRuntime::Python => (run_python(&resolved_path, &args, &extra_env, env)?, None),
+        ExternRuntime::Java   => (run_java(&resolved_path, &args, &extra_env, env)?, None),
+        ExternRuntime::Elf    => (run_elf(&resolved_path, &args, &extra_env, env)?, None),
+        ExternRuntime::So     => run_so(&resolved_path, &arg_values, &extra_env)?,
+    };
+
+    // 7. Zapisz wynik do env — wartość zwrócona przez hl_extern_call_v albo stdout
+    let stdout = Value::String(result.stdout.clone().unwrap_or_default().trim().to_string());
+    env.set_var("_extern_result",   returned.unwrap_or(stdout));
     env.set_var("_extern_exit",     Value::Number(result.exit_code as f64));
     env.last_exit = result.exit_code;
 
@@ -105,6 +107,7 @@ fn run_shell(
     file:      &str,
     args:      &[String],
     extra_env: &[(String, String)],
+    env:       &Env,
 ) -> Result<crate::executor::ExecResult> {
     let cfg  = load_config();
     let shell = cfg.shell_cmd().to_string();
@@ -116,26 +119,28 @@ fn run_shell(
             bail!("[extern shell] Skrypt nie istnieje: '{}'", file);
         }
         // Komenda w PATH — uruchom bezpośrednio
-        let mut cmd = build_command_with_env(file, args, extra_env);
+        let mut cmd = build_command_with_env(file, args, extra_env, env);
         let status = cmd.status()?;
         return Ok(crate::executor::ExecResult {
-            exit_code: status.code().unwrap_or(1),
+            exit_code: crate::executor::status_code(status),
             stdout: None,
+            truncated: false,
         });
     }
 
     let mut cmd = Command::new(&shell);
     if !file.is_empty() { cmd.arg(file); }
     cmd.args(args);
-    apply_env(&mut cmd, extra_env);
+    apply_env(&mut cmd, extra_env, env);
     cmd.stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit());
 
     let status = cmd.status()?;
     Ok(crate::executor::ExecResult {
-        exit_code: status.code().unwrap_or(1),
+        exit_code: crate::executor::status_code(status),
         stdout: None,
+        truncated: false,
     })
 }
 
@@ -145,6 +150,7 @@ fn run_python(
     file:      &str,
     args:      &[String],
     extra_env: &[(String, String)],
+    env:       &Env,
 ) -> Result<crate::executor::ExecResult> {
     let cfg = load_config();
     let python = cfg.python_cmd().to_string();
@@ -162,15 +168,16 @@ fn run_python(
     let mut cmd = Command::new(&python);
     if !file.is_empty() { cmd.arg(file); }
     cmd.args(args);
-    apply_env(&mut cmd, extra_env);
+    apply_env(&mut cmd, extra_env, env);
     cmd.stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit());
 
     let status = cmd.status()?;
     Ok(crate::executor::ExecResult {
-        exit_code: status.code().unwrap_or(1),
+        exit_code: crate::executor::status_code(status),
         stdout: None,
+        truncated: false,
     })
 }
 
@@ -180,6 +187,7 @@ fn run_java(
     file:      &str,
     args:      &[String],
     extra_env: &[(String, String)],
+    env:       &Env,
 ) -> Result<crate::executor::ExecResult> {
     let cfg  = load_config();
     let java = cfg.java_cmd().to_string();
@@ -208,15 +216,16 @@ fn run_java(
     }
 
     cmd.args(args);
-    apply_env(&mut cmd, extra_env);
+    apply_env(&mut cmd, extra_env, env);
     cmd.stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit());
 
     let status = cmd.status()?;
     Ok(crate::executor::ExecResult {
-        exit_code: status.code().unwrap_or(1),
+        exit_code: crate::executor::status_code(status),
         stdout: None,
+        truncated: false,
     })
 }
 
@@ -226,20 +235,22 @@ fn run_elf(
     file:      &str,
     args:      &[String],
     extra_env: &[(String, String)],
+    env:       &Env,
 ) -> Result<crate::executor::ExecResult> {
     if file.is_empty() {
         bail!("[extern elf] Brak nazwy binarki");
     }
 
-    let mut cmd = build_command_with_env(file, args, extra_env);
+    let mut cmd = build_command_with_env(file, args, extra_env, env);
     cmd.stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit());
 
     match cmd.status() {
         Ok(status) => Ok(crate::executor::ExecResult {
-            exit_code: status.code().unwrap_or(1),
+            exit_code: crate::executor::status_code(status),
             stdout: None,
+            truncated: false,
         }),
         Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
             bail!(
@@ -254,14 +265,14 @@ fn run_elf(
 
 // ── .so runtime ───────────────────────────────────────────────────────────────
 //
-// Szuka symbolu "hl_extern_call" w bibliotece .so
+// Szuka symbolu "hl_extern_call_v" (wartości HL, crate::ffi), a gdy go brak — "hl_extern_call"
 // Sygnatura: extern "C" fn hl_extern_call(argc: i32, argv: *const *const i8) -> i32
 
 fn run_so(
     file:      &str,
-    args:      &[String],
+    values:    &[Value],
     _extra_env: &[(String, String)],
-) -> Result<crate::executor::ExecResult> {
+) -> Result<(crate::executor::ExecResult, Option<Value>)> {
     if file.is_empty() {
         bail!("[extern so] Brak nazwy biblioteki .so");
     }
@@ -303,15 +314,34 @@ fn run_so(
             bail!("[extern so] dlopen('{}') failed: {}", file, err);
         }
 
-        let sym_name = CString::new("hl_extern_call").unwrap();
-        let sym_ptr  = unsafe { dlsym(handle, sym_name.as_ptr()) };
+        let symbol = |name: &str| {
+            let c_name = CString::new(name).unwrap();
+            unsafe { dlsym(handle, c_name.as_ptr()) }
+        };
+
+        let value_ptr = symbol("hl_extern_call_v");
+        if !value_ptr.is_null() {
+            let free_ptr = symbol("hl_extern_free");
+            // SAFETY: symbole o sygnaturach z crate::ffi (kontrakt biblioteki)
+            let (exit_code, value) = unsafe {
+                let func: crate::ffi::HlExternCallV = std::mem::transmute(value_ptr);
+                let free: Option<crate::ffi::HlExternFree> =
+                    (!free_ptr.is_null()).then(|| std::mem::transmute(free_ptr));
+                crate::ffi::call_value_abi(func, free, values)
+            };
+            unsafe { dlclose(handle); }
+            return Ok((crate::executor::ExecResult { exit_code, stdout: None, truncated: false }, Some(value)));
+        }
+
+        let sym_ptr = symbol("hl_extern_call");
 
         if sym_ptr.is_null() {
             unsafe { dlclose(handle); }
             bail!(
                 "[extern so] Symbol 'hl_extern_call' nie znaleziony w '{}'.\n\
                  Biblioteka musi eksportować:\n\
-                 extern \"C\" fn hl_extern_call(argc: i32, argv: *const *const i8) -> i32",
+                 extern \"C\" fn hl_extern_call(argc: i32, argv: *const *const i8) -> i32\n\
+                 albo hl_extern_call_v (wartości HL, zob. hl_core::ffi)",
                 file
             );
         }
@@ -320,8 +350,8 @@ fn run_so(
         type HlExternCallFn = unsafe extern "C" fn(c_int, *const *const c_char) -> c_int;
         let func: HlExternCallFn = unsafe { std::mem::transmute(sym_ptr) };
 
-        let c_args: Vec<CString> = args.iter()
-            .filter_map(|a| CString::new(a.as_str()).ok())
+        let c_args: Vec<CString> = values.iter()
+            .filter_map(|a| CString::new(a.to_string_val()).ok())
             .collect();
         let c_ptrs: Vec<*const c_char> = c_args.iter().map(|s| s.as_ptr()).collect();
 
@@ -331,10 +361,11 @@ fn run_so(
 
         unsafe { dlclose(handle); }
 
-        return Ok(crate::executor::ExecResult {
+        Ok((crate::executor::ExecResult {
             exit_code: exit_code as i32,
             stdout: None,
-        });
+            truncated: false,
+        }, None))
     }
 
     #[cfg(not(target_os = "linux"))]
@@ -347,14 +378,17 @@ fn build_command_with_env(
     prog:      &str,
     args:      &[String],
     extra_env: &[(String, String)],
+    env:       &Env,
 ) -> Command {
     let mut cmd = Command::new(prog);
     cmd.args(args);
-    apply_env(&mut cmd, extra_env);
+    apply_env(&mut cmd, extra_env, env);
     cmd
 }
 
-fn apply_env(cmd: &mut Command, extra_env: &[(String, String)]) {
+/// Środowisko skryptu (w trybie --frozen-env prywatna kopia) + zmienne `_env_KEY` bloku
+fn apply_env(cmd: &mut Command, extra_env: &[(String, String)], env: &Env) {
+    env.apply_env(cmd);
     for (k, v) in extra_env {
         cmd.env(k, v);
     }
@@ -0,0 +1,75 @@
+use thiserror::Error;
+
+/// Błąd wykonania z rodzajem — `? catch IndexError @e` wybiera ramię po `kind`.
+///
+/// Zwykłe błędy anyhow (bez `Fault`) mają rodzaj RuntimeError, więc stare `bail!`
+/// też da się złapać, tylko ogólnym `? catch` albo `? catch RuntimeError`.
+#[derive(Debug, Error)]
+#[error("{}: {message}", kind.tag())]
+pub struct Fault {
+    pub kind:    FaultKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultKind {
+    /// Niezdefiniowana albo prywatna funkcja
+    Name,
+    /// Wartość złego rodzaju (np. `-- @x` gdy x nie jest referencją do funkcji)
+    Type,
+    /// Indeks poza listą (`:: at`)
+    Index,
+    /// Przepełnienie albo dzielenie przez zero w trybie `HL_CHECKED=1`
+    Arithmetic,
+    /// Brak importowanego pliku lub katalogu
+    Import,
+    Runtime,
+}
+
+impl FaultKind {
+    /// Nazwa jak w `? catch` — kolejność i pisownia zgodne z `hl_parser::FAULT_KINDS`
+    pub fn tag(self) -> &'static str {
+        match self {
+            FaultKind::Name       => "NameError",
+            FaultKind::Type       => "TypeError",
+            FaultKind::Index      => "IndexError",
+            FaultKind::Arithmetic => "ArithmeticError",
+            FaultKind::Import     => "ImportError",
+            FaultKind::Runtime    => "RuntimeError",
+        }
+    }
+}
+
+/// Błąd do zwrócenia przez `Err(...)`
+pub fn raise(kind: FaultKind, message: impl Into<String>) -> anyhow::Error {
+    Fault { kind, message: message.into() }.into()
+}
+
+/// Rodzaj i komunikat (bez prefiksu rodzaju) dowolnego błędu wykonania
+pub fn classify(err: &anyhow::Error) -> (FaultKind, String) {
+    match err.downcast_ref::<Fault>() {
+        Some(f) => (f.kind, f.message.clone()),
+        None    => (FaultKind::Runtime, format!("{:#}", err)),
+    }
+}
+
+/// Czy ramię `? catch kind` łapie błąd rodzaju `fault`
+pub fn catches(kind: Option<&str>, fault: FaultKind) -> bool {
+    kind.is_none_or(|k| k == fault.tag())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags_match_parser_kinds() {
+        let kinds = [FaultKind::Name, FaultKind::Type, FaultKind::Index,
+                     FaultKind::Arithmetic, FaultKind::Import, FaultKind::Runtime];
+        let tags: Vec<_> = kinds.iter().map(|k| k.tag()).collect();
+        assert_eq!(tags, hl_parser::ast::FAULT_KINDS);
+
+        let (kind, msg) = classify(&anyhow::anyhow!("cos"));
+        assert_eq!((kind, msg.as_str()), (FaultKind::Runtime, "cos"));
+    }
+}
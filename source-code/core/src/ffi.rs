@@ -0,0 +1,156 @@
+use std::os::raw::{c_char, c_int};
+use crate::env::Value;
+
+// ── Wartości HL przez granicę C dla bibliotek `_> lib.so so def` ─────────────
+//
+// Biblioteka może eksportować, zamiast tekstowego `hl_extern_call(argc, argv)`:
+//
+// ```c
+// typedef struct { const char *ptr; size_t len; } HlStr;      // UTF-8, ptr[len] == '\0'
+// typedef struct { uint32_t tag; union { uint8_t boolean; double number; HlStr str; }; } HlValue;
+// int  hl_extern_call_v(int argc, const HlValue *argv, HlValue *ret);  // ret: wejście = nil
+// void hl_extern_free(HlValue *ret);                                    // opcjonalne
+// ```
+//
+// Własność:
+// - `argv` i napisy w nim należą do HL i są ważne tylko do powrotu z wywołania;
+//   biblioteka, która chce je zachować, musi je skopiować.
+// - Napis w `ret` należy do biblioteki. HL kopiuje go zaraz po powrocie, a potem
+//   woła `hl_extern_free(ret)`, jeśli biblioteka ją eksportuje (inaczej napis musi
+//   żyć dłużej niż wywołanie, np. być statyczny).
+// - Lista i referencja do funkcji nie mają własnego tagu — przechodzą jako napis
+//   (`to_string_val`), tak jak do `hl_extern_call`.
+
+pub const HL_NIL:    u32 = 0;
+pub const HL_BOOL:   u32 = 1;
+pub const HL_NUMBER: u32 = 2;
+pub const HL_STR:    u32 = 3;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct HlStr {
+    pub ptr: *const c_char,
+    pub len: usize,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union HlPayload {
+    pub boolean: u8,
+    pub number:  f64,
+    pub str:     HlStr,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct HlValue {
+    pub tag:     u32,
+    pub payload: HlPayload,
+}
+
+impl HlValue {
+    pub fn nil() -> Self { HlValue { tag: HL_NIL, payload: HlPayload { number: 0.0 } } }
+}
+
+pub type HlExternCallV = unsafe extern "C" fn(c_int, *const HlValue, *mut HlValue) -> c_int;
+pub type HlExternFree  = unsafe extern "C" fn(*mut HlValue);
+
+/// Argumenty w postaci C razem z buforami napisów, na które wskazują —
+/// wskaźniki w `values` są ważne, dopóki żyje ten obiekt
+pub struct FfiArgs {
+    _strings: Vec<Vec<u8>>,
+    pub values: Vec<HlValue>,
+}
+
+pub fn to_ffi(values: &[Value]) -> FfiArgs {
+    let mut strings = Vec::new();
+    let mut out = Vec::with_capacity(values.len());
+    for v in values {
+        out.push(match v {
+            Value::Nil       => HlValue::nil(),
+            Value::Bool(b)   => HlValue { tag: HL_BOOL, payload: HlPayload { boolean: *b as u8 } },
+            Value::Number(n) => HlValue { tag: HL_NUMBER, payload: HlPayload { number: *n } },
+            other => {
+                let mut bytes = other.to_string_val().into_bytes();
+                let len = bytes.len();
+                bytes.push(0);
+                // Bufor Vec nie przesuwa się przy przeniesieniu do `strings`
+                let ptr = bytes.as_ptr() as *const c_char;
+                strings.push(bytes);
+                HlValue { tag: HL_STR, payload: HlPayload { str: HlStr { ptr, len } } }
+            }
+        });
+    }
+    FfiArgs { _strings: strings, values: out }
+}
+
+/// Kopia wartości po stronie HL; nieznany tag albo pusty wskaźnik → nil
+///
+/// # Safety
+/// Dla `HL_STR` `ptr` musi wskazywać `len` bajtów ważnych na czas wywołania.
+pub unsafe fn from_ffi(v: &HlValue) -> Value {
+    match v.tag {
+        HL_BOOL   => Value::Bool(v.payload.boolean != 0),
+        HL_NUMBER => Value::Number(v.payload.number),
+        HL_STR if !v.payload.str.ptr.is_null() => {
+            let s = v.payload.str;
+            let bytes = std::slice::from_raw_parts(s.ptr as *const u8, s.len);
+            Value::String(String::from_utf8_lossy(bytes).into_owned())
+        }
+        _ => Value::Nil,
+    }
+}
+
+/// Wywołanie według reguł własności z nagłówka modułu: (kod wyjścia, zwrócona wartość)
+///
+/// # Safety
+/// `func` i `free` muszą mieć sygnatury `HlExternCallV` / `HlExternFree`.
+pub unsafe fn call_value_abi(func: HlExternCallV, free: Option<HlExternFree>, args: &[Value]) -> (i32, Value) {
+    let args = to_ffi(args);
+    let mut ret = HlValue::nil();
+    let code = func(args.values.len() as c_int, args.values.as_ptr(), &mut ret);
+    let value = from_ffi(&ret);
+    if let Some(free) = free { free(&mut ret); }
+    (code, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static FREED: AtomicUsize = AtomicUsize::new(0);
+
+    /// Biblioteka testowa: zwraca napis "<argc>:<drugi argument>" i liczbę × 2 jako kod
+    unsafe extern "C" fn describe(argc: c_int, argv: *const HlValue, ret: *mut HlValue) -> c_int {
+        let args = std::slice::from_raw_parts(argv, argc as usize);
+        let text = match from_ffi(&args[1]) { Value::String(s) => s, _ => return -1 };
+        let bytes = format!("{}:{}", argc, text).into_bytes().into_boxed_slice();
+        let len = bytes.len();
+        *ret = HlValue { tag: HL_STR, payload: HlPayload { str: HlStr { ptr: Box::into_raw(bytes) as *const c_char, len } } };
+        (args[0].payload.number * 2.0) as c_int
+    }
+
+    unsafe extern "C" fn release(ret: *mut HlValue) {
+        let s = (*ret).payload.str;
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(s.ptr as *mut u8, s.len)));
+        FREED.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_round_trip_number_and_string() {
+        let values = [Value::Number(42.0), Value::String("zażółć".into()), Value::Bool(true), Value::Nil];
+        let args = to_ffi(&values);
+        assert_eq!(args.values.iter().map(|v| v.tag).collect::<Vec<_>>(), vec![HL_NUMBER, HL_STR, HL_BOOL, HL_NIL]);
+        let back: Vec<String> = args.values.iter().map(|v| unsafe { from_ffi(v) }.to_string_val()).collect();
+        assert_eq!(back, vec!["42", "zażółć", "true", ""]);
+        // Napis zakończony NUL dla bibliotek czytających char*
+        let s = unsafe { args.values[1].payload.str };
+        assert_eq!(unsafe { *s.ptr.add(s.len) }, 0);
+
+        let (code, ret) = unsafe { call_value_abi(describe, Some(release), &values[..2]) };
+        assert_eq!(code, 84);
+        assert_eq!(ret.to_string_val(), "2:zażółć");
+        assert_eq!(FREED.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,24 @@
+use std::path::{Path, PathBuf};
+
+/// Gdzie szukać `<< plik` / `<* katalog` ze ścieżką względną, po kolei:
+/// katalog pliku, który importuje, katalog roboczy, potem korzenie z `hl run -I` / HL_IMPORT_PATH.
+/// Pierwszy istniejący kandydat wygrywa; ścieżka bezwzględna nie jest szukana.
+pub fn candidates(path: &str, importer_dir: Option<&Path>, roots: &[PathBuf]) -> Vec<PathBuf> {
+    let path = Path::new(path);
+    if path.is_absolute() { return vec![path.to_path_buf()]; }
+    importer_dir.map(|d| d.join(path)).into_iter()
+        .chain(std::iter::once(path.to_path_buf()))
+        .chain(roots.iter().map(|r| r.join(path)))
+        .collect()
+}
+
+pub fn resolve(path: &str, importer_dir: Option<&Path>, roots: &[PathBuf]) -> Option<PathBuf> {
+    candidates(path, importer_dir, roots).into_iter().find(|p| p.exists())
+}
+
+/// HL_IMPORT_PATH — korzenie importów rozdzielone jak PATH (`:` na Linuksie)
+pub fn import_roots_from_env() -> Vec<PathBuf> {
+    std::env::var_os("HL_IMPORT_PATH")
+        .map(|v| std::env::split_paths(&v).filter(|p| !p.as_os_str().is_empty()).collect())
+        .unwrap_or_default()
+}
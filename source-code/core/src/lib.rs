@@ -1,19 +1,36 @@
+pub mod ast_json;
+pub mod audit;
+pub mod bench;
+pub mod color;
+pub mod coverage;
+pub mod deny;
 pub mod deps;
 pub mod diagnostics;
+pub mod doctor;
 pub mod env;
 pub mod executor;
+pub mod fault;
+pub mod ffi;
+pub mod import_path;
 pub mod libs;
+pub mod memstat;
+pub mod metadata;
+pub mod outline;
+pub mod prompt;
 pub mod quick;
 pub mod arena;
+pub mod ast_cache;
 pub mod config;
 pub mod env_manager;
 pub mod extern_runner;
+pub mod wire;
+pub mod watch;
 
 pub use hl_parser::{
     ast, lexer, parser, gen, shebang,
     Node, StringPart, VarValue, VarType, ExportValue, CommandMode, ConditionKind,
     MatchArm, HackerOsTool,
-    parse_source, parse_source_with_meta, ParseError, LexError,
+    parse_source, parse_source_with_meta, parse_source_with_lines, ParseError, LexError,
     Gen, GenError, GenFeature, extract_gen, HL_MAX_GEN, HL_DEFAULT_GEN,
     ShebangInfo, PreprocessResult, preprocess,
     ParseMeta,
@@ -27,7 +44,15 @@ use env::Env;
 use executor::exec_nodes;
 
 pub fn run_source(source: &str, env: &mut Env) -> Result<executor::ExecResult> {
-    let nodes = parse_source(source)?;
+    // Markery linii tylko gdy ktoś zbiera pokrycie — zwykły run ich nie płaci
+    let nodes = match env.coverage.clone() {
+        Some(cov) => {
+            let (nodes, lines) = parse_source_with_lines(source)?;
+            cov.lock().unwrap_or_else(|e| e.into_inner()).register(&lines);
+            nodes
+        }
+        None => parse_source(source)?,
+    };
     exec_nodes(&nodes, env)
 }
 
@@ -53,7 +78,7 @@ pub use env::Value;
 pub use executor::ExecResult;
 pub use diagnostics::{Diag, DiagLevel, DiagRenderer, DiagSummary, Span, lint_source};
 pub use libs::{cmd_lib_list, cmd_lib_install, cmd_lib_remove, cmd_clean_cache};
-pub use diagnostics::lint_gen;
+pub use diagnostics::{lint_gen, check_types};
 pub use arena::{Arena, ArenaContext, ArenaStats};
 pub use config::{
     HlConfig, load_config, save_config, config_path,
@@ -1,6 +1,7 @@
 use anyhow::{bail, Result};
 use std::path::{Path, PathBuf};
 use tracing::info;
+use colored::Colorize;
 use crate::env::{Env, Value};
 
 pub const MAIN_LIBS_DIR: &str = "/usr/lib/HackerOS/Hacker-Lang/main-libs";
@@ -88,17 +89,19 @@ fn load_main_lib(lib: &str, detail: Option<&str>, env: &mut Env) -> Result<()> {
     if hl_file.exists() {
         info!("Laduje main lib '{}' z {:?}", lib, hl_file);
         let src   = std::fs::read_to_string(&hl_file)?;
-        let nodes = hl_parser::parse_source(&src)?;
-        crate::executor::exec_nodes(&nodes, env)?;
-        eprintln!("\x1b[36m[hl main]\x1b[0m Zaladowano main/{}", lib);
+        let nodes = crate::ast_cache::parse_import(&src)?;
+        crate::executor::exec_module(&nodes, &hl_file.display().to_string(), env)?;
+        if !crate::color::enabled() { strip_ansi_vars(env); }
+        eprintln!("{} Zaladowano main/{}", "[hl main]".cyan(), lib);
         return Ok(());
     }
     if dir_file.exists() {
         info!("Laduje main lib '{}' z {:?}", lib, dir_file);
         let src   = std::fs::read_to_string(&dir_file)?;
-        let nodes = hl_parser::parse_source(&src)?;
-        crate::executor::exec_nodes(&nodes, env)?;
-        eprintln!("\x1b[36m[hl main]\x1b[0m Zaladowano main/{}", lib);
+        let nodes = crate::ast_cache::parse_import(&src)?;
+        crate::executor::exec_module(&nodes, &dir_file.display().to_string(), env)?;
+        if !crate::color::enabled() { strip_ansi_vars(env); }
+        eprintln!("{} Zaladowano main/{}", "[hl main]".cyan(), lib);
         return Ok(());
     }
 
@@ -144,11 +147,11 @@ fn load_bit_lib(name: &str, _version: Option<&str>, env: &mut Env) -> Result<()>
             "Biblioteka bit '{}' nie jest zainstalowana.\n\
 \n\
 Aby zainstalować:\n\
-\x1b[32m  bit install {}\x1b[0m\n\
+{}\n\
 \n\
 Jeśli pakiet nie istnieje w repozytorium:\n\
-\x1b[32m  bit search {}\x1b[0m",
-name, name, name
+{}",
+name, format!("  bit install {}", name).green(), format!("  bit search {}", name).green()
         );
     }
 
@@ -164,9 +167,9 @@ name, name, name
         if candidate.exists() {
             info!("Laduje bit lib '{}' z {:?}", name, candidate);
             let src   = std::fs::read_to_string(candidate)?;
-            let nodes = hl_parser::parse_source(&src)?;
-            crate::executor::exec_nodes(&nodes, env)?;
-            eprintln!("\x1b[35m[hl bit]\x1b[0m Zaladowano bit/{}", name);
+            let nodes = crate::ast_cache::parse_import(&src)?;
+            crate::executor::exec_module(&nodes, &candidate.display().to_string(), env)?;
+            eprintln!("{} Zaladowano bit/{}", "[hl bit]".magenta(), name);
 
             // Ustaw zmienne informacyjne
             let prefix = name.to_uppercase().replace('-', "_");
@@ -184,7 +187,7 @@ name, name, name
         env.set_var(&format!("BIT_{}_LOADED", prefix), Value::Bool(true));
         env.set_var(&format!("BIT_{}_PATH", prefix),
                     Value::String(so_path.display().to_string()));
-        eprintln!("\x1b[35m[hl bit]\x1b[0m Zaladowano bit/{} (.so)", name);
+        eprintln!("{} Zaladowano bit/{} (.so)", "[hl bit]".magenta(), name);
         return Ok(());
     }
 
@@ -224,8 +227,8 @@ fn load_from_dir(dir: &Path, detail: Option<&str>, env: &mut Env, name: &str) ->
     };
     if !main_file.exists() { bail!("Brak pliku wejsciowego dla '{}' w {:?}", name, dir); }
     let src   = std::fs::read_to_string(&main_file)?;
-    let nodes = hl_parser::parse_source(&src)?;
-    crate::executor::exec_nodes(&nodes, env)?;
+    let nodes = crate::ast_cache::parse_import(&src)?;
+    crate::executor::exec_module(&nodes, &main_file.display().to_string(), env)?;
     Ok(())
 }
 
@@ -239,12 +242,23 @@ pub fn hl_cache_dir() -> PathBuf {
     .join(".hackeros/hacker-lang/cache")
 }
 
+/// Kolory wyłączone — zmienne COLOR_* z sekwencjami ANSI (main/colors.hl) stają się puste;
+/// inne zmienne skryptu zostają nietknięte, nawet gdy trzymają ANSI
+fn strip_ansi_vars(env: &mut Env) {
+    for (name, val) in env.vars.iter_mut() {
+        if !name.starts_with("COLOR_") { continue; }
+        if let Value::String(s) = val {
+            if s.contains("\x1b[") || s.contains("\\033[") { s.clear(); }
+        }
+    }
+}
+
 // ── Builtin fallbacks ─────────────────────────────────────────────────────────
 
 fn load_builtin_net(_detail: Option<&str>, env: &mut Env) -> Result<()> {
     env.set_var("NET_LOCALHOST", Value::String("127.0.0.1".into()));
     env.set_var("NET_BROADCAST", Value::String("255.255.255.255".into()));
-    eprintln!("\x1b[36m[hl main]\x1b[0m Zaladowano main/net (builtin fallback)");
+    eprintln!("{} Zaladowano main/net (builtin fallback)", "[hl main]".cyan());
     Ok(())
 }
 fn load_builtin_fs(_detail: Option<&str>, env: &mut Env) -> Result<()> {
@@ -253,7 +267,7 @@ fn load_builtin_fs(_detail: Option<&str>, env: &mut Env) -> Result<()> {
     env.set_var("FS_TMP",     Value::String("/tmp".into()));
     env.set_var("FS_ETC",     Value::String("/etc".into()));
     env.set_var("FS_VAR_LOG", Value::String("/var/log".into()));
-    eprintln!("\x1b[36m[hl main]\x1b[0m Zaladowano main/fs (builtin fallback)");
+    eprintln!("{} Zaladowano main/fs (builtin fallback)", "[hl main]".cyan());
     Ok(())
 }
 fn load_builtin_sys(_detail: Option<&str>, env: &mut Env) -> Result<()> {
@@ -261,34 +275,36 @@ fn load_builtin_sys(_detail: Option<&str>, env: &mut Env) -> Result<()> {
     env.set_var("SYS_HOSTNAME", Value::String(
         std::fs::read_to_string("/etc/hostname").unwrap_or_default().trim().into()
     ));
-    eprintln!("\x1b[36m[hl main]\x1b[0m Zaladowano main/sys (builtin fallback)");
+    eprintln!("{} Zaladowano main/sys (builtin fallback)", "[hl main]".cyan());
     Ok(())
 }
 fn load_builtin_str(_detail: Option<&str>, env: &mut Env) -> Result<()> {
     env.set_var("STR_NEWLINE", Value::String("\n".into()));
     env.set_var("STR_TAB",     Value::String("\t".into()));
-    eprintln!("\x1b[36m[hl main]\x1b[0m Zaladowano main/str (builtin fallback)");
+    eprintln!("{} Zaladowano main/str (builtin fallback)", "[hl main]".cyan());
     Ok(())
 }
 fn load_builtin_crypto(_detail: Option<&str>, env: &mut Env) -> Result<()> {
     env.set_var("CRYPTO_SHA256_CMD", Value::String("sha256sum".into()));
     env.set_var("CRYPTO_MD5_CMD",    Value::String("md5sum".into()));
-    eprintln!("\x1b[36m[hl main]\x1b[0m Zaladowano main/crypto (builtin fallback)");
+    eprintln!("{} Zaladowano main/crypto (builtin fallback)", "[hl main]".cyan());
     Ok(())
 }
 fn load_builtin_proc(_detail: Option<&str>, env: &mut Env) -> Result<()> {
     env.set_var("PROC_SELF_PID", Value::Number(std::process::id() as f64));
-    eprintln!("\x1b[36m[hl main]\x1b[0m Zaladowano main/proc (builtin fallback)");
+    eprintln!("{} Zaladowano main/proc (builtin fallback)", "[hl main]".cyan());
     Ok(())
 }
 fn load_builtin_colors(env: &mut Env) -> Result<()> {
-    env.set_var("COLOR_RED",    Value::String("\x1b[31m".into()));
-    env.set_var("COLOR_GREEN",  Value::String("\x1b[32m".into()));
-    env.set_var("COLOR_YELLOW", Value::String("\x1b[33m".into()));
-    env.set_var("COLOR_CYAN",   Value::String("\x1b[36m".into()));
-    env.set_var("COLOR_RESET",  Value::String("\x1b[0m".into()));
-    env.set_var("COLOR_BOLD",   Value::String("\x1b[1m".into()));
-    eprintln!("\x1b[36m[hl main]\x1b[0m Zaladowano main/colors (builtin fallback)");
+    // NO_COLOR / --no-color / brak terminala — zmienne puste, `~> @COLOR_RED ...` bez ANSI
+    use crate::color::ansi;
+    env.set_var("COLOR_RED",    Value::String(ansi("\x1b[31m").into()));
+    env.set_var("COLOR_GREEN",  Value::String(ansi("\x1b[32m").into()));
+    env.set_var("COLOR_YELLOW", Value::String(ansi("\x1b[33m").into()));
+    env.set_var("COLOR_CYAN",   Value::String(ansi("\x1b[36m").into()));
+    env.set_var("COLOR_RESET",  Value::String(ansi("\x1b[0m").into()));
+    env.set_var("COLOR_BOLD",   Value::String(ansi("\x1b[1m").into()));
+    eprintln!("{} Zaladowano main/colors (builtin fallback)", "[hl main]".cyan());
     Ok(())
 }
 fn load_builtin_cli(env: &mut Env) -> Result<()> {
@@ -296,29 +312,29 @@ fn load_builtin_cli(env: &mut Env) -> Result<()> {
     env.set_var("CLI_PROG_NAME", Value::String(
         std::env::args().next().unwrap_or_else(|| "hl".into())
     ));
-    eprintln!("\x1b[36m[hl main]\x1b[0m Zaladowano main/cli (builtin fallback)");
+    eprintln!("{} Zaladowano main/cli (builtin fallback)", "[hl main]".cyan());
     Ok(())
 }
 fn load_builtin_progress_bar(env: &mut Env) -> Result<()> {
     env.set_var("PROGRESS_BAR_LOADED", Value::Bool(true));
-    eprintln!("\x1b[36m[hl main]\x1b[0m Zaladowano main/progress-bar (builtin fallback)");
+    eprintln!("{} Zaladowano main/progress-bar (builtin fallback)", "[hl main]".cyan());
     Ok(())
 }
 fn load_builtin_json(env: &mut Env) -> Result<()> {
     env.set_var("JSON_LOADED", Value::Bool(true));
-    eprintln!("\x1b[36m[hl main]\x1b[0m Zaladowano main/json (builtin fallback)");
+    eprintln!("{} Zaladowano main/json (builtin fallback)", "[hl main]".cyan());
     Ok(())
 }
 fn load_builtin_hk_parser(env: &mut Env) -> Result<()> {
     env.set_var("HK_PARSER_LOADED",  Value::Bool(true));
     env.set_var("HK_PARSER_VERSION", Value::String("gen 1".into()));
-    eprintln!("\x1b[36m[hl main]\x1b[0m Zaladowano main/hk-parser (builtin fallback)");
+    eprintln!("{} Zaladowano main/hk-parser (builtin fallback)", "[hl main]".cyan());
     Ok(())
 }
 fn load_builtin_hacker(env: &mut Env) -> Result<()> {
     env.set_var("HACKER_PARSER_LOADED",  Value::Bool(true));
     env.set_var("HACKER_PARSER_VERSION", Value::String("gen 1".into()));
-    eprintln!("\x1b[36m[hl main]\x1b[0m Zaladowano main/hacker (builtin fallback)");
+    eprintln!("{} Zaladowano main/hacker (builtin fallback)", "[hl main]".cyan());
     Ok(())
 }
 
@@ -383,3 +399,18 @@ pub fn cmd_clean_cache() {
         println!("{}", "Cache jest pusty.".bright_black());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi_vars_only_touches_colors() {
+        let mut env = Env::new();
+        env.set_var("COLOR_RED", Value::String("\x1b[31m".into()));
+        env.set_var("baner",     Value::String("\x1b[1mHackerOS".into()));
+        strip_ansi_vars(&mut env);
+        assert_eq!(env.get_var_str("COLOR_RED"), "");
+        assert_eq!(env.get_var_str("baner"), "\x1b[1mHackerOS");
+    }
+}
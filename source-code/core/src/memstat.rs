@@ -0,0 +1,75 @@
+/// `hl run --profile-memory` — szczytowa pamięć rezydentna przebiegu.
+///
+/// `own` to proces `hl` (VmHWM z /proc/self/status), `children` — największy RSS
+/// spośród zakończonych komend potomnych (getrusage). Poza Linuksem — None.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeakMemory {
+    pub own:      Option<u64>,
+    pub children: Option<u64>,
+}
+
+pub fn peak_memory() -> PeakMemory {
+    PeakMemory { own: own_peak(), children: children_peak() }
+}
+
+#[cfg(target_os = "linux")]
+fn own_peak() -> Option<u64> {
+    parse_vm_hwm(&std::fs::read_to_string("/proc/self/status").ok()?)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn own_peak() -> Option<u64> { None }
+
+#[cfg(target_os = "linux")]
+fn children_peak() -> Option<u64> {
+    use nix::sys::resource::{getrusage, UsageWho};
+    // ru_maxrss na Linuksie jest w KiB
+    let kib = getrusage(UsageWho::RUSAGE_CHILDREN).ok()?.max_rss();
+    (kib > 0).then(|| kib as u64 * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn children_peak() -> Option<u64> { None }
+
+/// `VmHWM:   12345 kB` → bajty
+pub fn parse_vm_hwm(status: &str) -> Option<u64> {
+    let line = status.lines().find_map(|l| l.strip_prefix("VmHWM:"))?;
+    let kib: u64 = line.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    Some(kib * 1024)
+}
+
+fn human(bytes: Option<u64>) -> String {
+    match bytes {
+        Some(b) if b >= 1 << 30 => format!("{:.2} GiB", b as f64 / (1u64 << 30) as f64),
+        Some(b)                 => format!("{:.1} MiB", b as f64 / (1u64 << 20) as f64),
+        None                    => "niedostępne".into(),
+    }
+}
+
+impl PeakMemory {
+    pub fn render(&self) -> String {
+        format!("szczytowa pamięć (RSS): hl {}, komendy potomne {}", human(self.own), human(self.children))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peak_rss_after_large_allocation() {
+        assert_eq!(parse_vm_hwm("Name:\thl\nVmHWM:\t   2048 kB\nVmRSS:\t1 kB\n"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_vm_hwm("Name:\thl\n"), None);
+
+        // Skrypt podwaja tekst 23 razy: (7 + 1) · 2^23 - 1 bajtów ≈ 64 MiB w jednej zmiennej
+        let mut env = crate::env::Env::new();
+        crate::run_source("% s = abcdefg\n_23 % s = \"@s @s\"\n", &mut env).unwrap();
+        assert_eq!(env.get_var("s").to_string_val().len(), (64 << 20) - 1);
+        if cfg!(target_os = "linux") {
+            let own = peak_memory().own.expect("VmHWM na Linuksie");
+            assert!(((64 << 20)..(1 << 40)).contains(&own), "{}", own);
+        } else {
+            assert!(peak_memory().render().contains("niedostępne"));
+        }
+    }
+}
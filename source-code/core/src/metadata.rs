@@ -0,0 +1,111 @@
+use serde::Serialize;
+use hl_parser::{Node, VarType};
+
+/// Interfejs skryptu dla narzędzi (generatory dokumentacji, pakowanie) — `hl compile --emit metadata`.
+///
+/// Tylko najwyższy poziom pliku: funkcje, zmienne globalne, eksporty do środowiska,
+/// importy i zależności. Funkcje HL nie mają parametrów ani typu zwracanego —
+/// argumenty idą przez zmienne (`@_arena_args` dla arena functions).
+#[derive(Debug, Default, Serialize)]
+pub struct Metadata {
+    pub gen:       u32,
+    pub functions: Vec<FunctionMeta>,
+    pub globals:   Vec<GlobalMeta>,
+    /// `export NAZWA = ...` — zmienne przekazywane komendom potomnym
+    pub env:       Vec<String>,
+    /// `# <lib>`, `<< plik`, `<* katalog` w postaci jak w źródle
+    pub imports:   Vec<String>,
+    pub deps:      Vec<DepMeta>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct FunctionMeta {
+    pub name:       String,
+    /// `def` (`: nazwa def`) albo `arena` (`:: nazwa <rozmiar> def`)
+    pub kind:       &'static str,
+    /// Widoczna dla importującego: w module z choć jednym `pub` tylko funkcje `pub`
+    pub exported:   bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arena_size: Option<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct GlobalMeta {
+    pub name: String,
+    /// Adnotacja typu (`% x: int = ...`) albo `any`
+    #[serde(rename = "type")]
+    pub typ:  &'static str,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct DepMeta {
+    pub name:        String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apt_package: Option<String>,
+}
+
+pub fn metadata(nodes: &[Node], gen: u32) -> Metadata {
+    let has_pub = nodes.iter().any(|n| matches!(n, Node::FuncDef { public: true, .. }));
+    let mut meta = Metadata { gen, ..Metadata::default() };
+    for node in nodes {
+        match node {
+            Node::FuncDef { name, public, .. } => meta.functions.push(FunctionMeta {
+                name: name.clone(), kind: "def", exported: *public || !has_pub, arena_size: None,
+            }),
+            Node::ArenaFuncDef { name, arena_size, .. } => meta.functions.push(FunctionMeta {
+                name: name.clone(), kind: "arena", exported: true, arena_size: Some(arena_size.to_string()),
+            }),
+            Node::VarDecl { name, typ, .. } if !meta.globals.iter().any(|g| &g.name == name) => {
+                meta.globals.push(GlobalMeta { name: name.clone(), typ: type_name(typ) });
+            }
+            Node::Export { name, .. } if !meta.env.contains(name) => meta.env.push(name.clone()),
+            Node::Import { lib, .. }      => meta.imports.push(format!("# <{}>", lib)),
+            Node::FileImport { path, .. } => meta.imports.push(format!("<< {}", path)),
+            Node::DirImport { path }      => meta.imports.push(format!("<* {}", path)),
+            Node::Dependency { name, apt_package } => meta.deps.push(DepMeta {
+                name: name.clone(), apt_package: apt_package.clone(),
+            }),
+            _ => {}
+        }
+    }
+    meta
+}
+
+fn type_name(typ: &VarType) -> &'static str {
+    match typ {
+        VarType::String => "str",
+        VarType::Int    => "int",
+        VarType::Float  => "float",
+        VarType::Bool   => "bool",
+        VarType::List   => "list",
+        VarType::Map    => "map",
+        VarType::Any    => "any",
+    }
+}
+
+/// Ładnie sformatowany JSON metadanych źródła
+pub fn metadata_json(source: &str) -> anyhow::Result<String> {
+    let meta = hl_parser::parse_source_with_meta(source)?;
+    Ok(serde_json::to_string_pretty(&metadata(&meta.nodes, meta.gen.number()))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_functions_globals_and_deps() {
+        let src = "# <main/colors>\n// ninja [ninja-build]\n% n: int = 3\n\
+                   : pub powitanie def\n    ~> hej\ndone\n: pomocnik def\n    ~> x\ndone\n\
+                   :: licz <64k> def\n    ~> @_arena_args\ndone\n";
+        let json: serde_json::Value = serde_json::from_str(&metadata_json(src).unwrap()).unwrap();
+        assert_eq!(json["functions"], serde_json::json!([
+            { "name": "powitanie", "kind": "def",   "exported": true },
+            { "name": "pomocnik",  "kind": "def",   "exported": false },
+            { "name": "licz",      "kind": "arena", "exported": true, "arena_size": "64k" },
+        ]));
+        assert_eq!(json["globals"], serde_json::json!([{ "name": "n", "type": "int" }]));
+        assert_eq!(json["imports"], serde_json::json!(["# <main/colors>"]));
+        assert_eq!(json["deps"], serde_json::json!([{ "name": "ninja", "apt_package": "ninja-build" }]));
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::{BTreeMap, BTreeSet};
+use hl_parser::Node;
+
+/// Zarys skryptu do porównywania dwóch wersji (`hl diff`) — co się zmieniło między
+/// przebiegami, gdy cache .bc / AST zachowuje się inaczej niż oczekiwano.
+///
+/// Ciała funkcji są porównywane po ich AST bez komentarzy i numerów linii, więc zmiana
+/// samego komentarza, wcięcia albo położenia funkcji w pliku nie jest zmianą funkcji.
+#[derive(Debug, Default)]
+pub struct Outline {
+    /// `: nazwa def` i `:: nazwa <rozmiar> def` → odcisk ciała
+    pub functions:       BTreeMap<String, String>,
+    /// `# <lib>`, `<< plik`, `<* katalog` w postaci jak w źródle
+    pub imports:         BTreeSet<String>,
+    /// Instrukcje głównego bloku poza definicjami funkcji, importami i komentarzami
+    pub main_statements: usize,
+}
+
+pub fn outline(nodes: &[Node]) -> Outline {
+    let mut out = Outline::default();
+    for node in nodes {
+        match node {
+            Node::FuncDef { name, body, .. } | Node::ArenaFuncDef { name, body, .. } => {
+                out.functions.insert(name.clone(), fingerprint(body));
+            }
+            Node::Import { lib, .. }    => { out.imports.insert(format!("# <{}>", lib)); }
+            Node::FileImport { path, .. } => { out.imports.insert(format!("<< {}", path)); }
+            Node::DirImport { path }    => { out.imports.insert(format!("<* {}", path)); }
+            n if n.is_comment() || matches!(n, Node::SourceLine(_)) => {}
+            _ => out.main_statements += 1,
+        }
+    }
+    out
+}
+
+fn fingerprint(body: &[Node]) -> String {
+    format!("{:?}", without_positions(body))
+}
+
+/// Kopia bloku bez komentarzy, markerów linii i `line` pętli — na każdym poziomie zagnieżdżenia
+fn without_positions(body: &[Node]) -> Vec<Node> {
+    body.iter()
+        .filter(|n| !n.is_comment() && !matches!(n, Node::SourceLine(_)))
+        .map(|n| { let mut n = n.clone(); clear_positions(&mut n); n })
+        .collect()
+}
+
+fn clear_positions(node: &mut Node) {
+    match node {
+        Node::ForIn { body, line, .. } | Node::WhileLoop { body, line, .. } => {
+            *line = 0;
+            *body = without_positions(body);
+        }
+        Node::RepeatN { body, .. } | Node::Retry { body, .. } | Node::Bench { body, .. }
+        | Node::TempDir { body, .. } | Node::FuncDef { body, .. } | Node::ArenaFuncDef { body, .. }
+        | Node::Conditional { body, .. } | Node::Goroutine { body, .. } | Node::ExternDef { body, .. }
+        | Node::Block(body) => *body = without_positions(body),
+        Node::Try { body, catches } => {
+            *body = without_positions(body);
+            for arm in catches { arm.body = without_positions(&arm.body); }
+        }
+        Node::MatchExpr { arms, .. } => {
+            for arm in arms { arm.body = without_positions(&arm.body); }
+        }
+        Node::Chain { node, .. } => clear_positions(node),
+        _ => {}
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct OutlineDiff {
+    pub added_functions:   Vec<String>,
+    pub removed_functions: Vec<String>,
+    pub changed_functions: Vec<String>,
+    pub added_imports:     Vec<String>,
+    pub removed_imports:   Vec<String>,
+    /// (stara, nowa) liczba instrukcji głównego bloku, gdy się różni
+    pub main_statements:   Option<(usize, usize)>,
+}
+
+pub fn diff_outline(old: &Outline, new: &Outline) -> OutlineDiff {
+    let mut diff = OutlineDiff::default();
+    for (name, body) in &new.functions {
+        match old.functions.get(name) {
+            None                     => diff.added_functions.push(name.clone()),
+            Some(prev) if prev != body => diff.changed_functions.push(name.clone()),
+            Some(_)                  => {}
+        }
+    }
+    diff.removed_functions = old.functions.keys().filter(|n| !new.functions.contains_key(*n)).cloned().collect();
+    diff.added_imports     = new.imports.difference(&old.imports).cloned().collect();
+    diff.removed_imports   = old.imports.difference(&new.imports).cloned().collect();
+    if old.main_statements != new.main_statements {
+        diff.main_statements = Some((old.main_statements, new.main_statements));
+    }
+    diff
+}
+
+impl OutlineDiff {
+    pub fn is_empty(&self) -> bool { *self == OutlineDiff::default() }
+
+    /// Raport linia po linii: `+` dodane, `-` usunięte, `~` zmienione
+    pub fn render(&self) -> String {
+        if self.is_empty() { return "bez zmian\n".into(); }
+        let mut out = String::new();
+        for f in &self.added_functions   { out.push_str(&format!("+ funkcja {}\n", f)); }
+        for f in &self.removed_functions { out.push_str(&format!("- funkcja {}\n", f)); }
+        for f in &self.changed_functions { out.push_str(&format!("~ funkcja {}\n", f)); }
+        for i in &self.added_imports     { out.push_str(&format!("+ import {}\n", i)); }
+        for i in &self.removed_imports   { out.push_str(&format!("- import {}\n", i)); }
+        if let Some((old, new)) = self.main_statements {
+            out.push_str(&format!("~ blok główny: {} → {} instrukcji\n", old, new));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outline_of(src: &str) -> Outline {
+        outline(&hl_parser::parse_source(src).unwrap())
+    }
+
+    #[test]
+    fn test_added_and_changed_functions() {
+        let old = outline_of("# <main/colors>\n: a def\n    ~> a\ndone\n: b def\n    ~> b\ndone\n: a\n");
+        let new = outline_of("# <main/colors>\n: a def\n    ~> inne a\ndone\n: b def\n    ;; komentarz\n    ~> b\ndone\n\
+                              : c def\n    ~> c\ndone\n: a\n");
+        let diff = diff_outline(&old, &new);
+        assert_eq!(diff, OutlineDiff {
+            added_functions:   vec!["c".into()],
+            changed_functions: vec!["a".into()],
+            ..OutlineDiff::default()
+        });
+        assert_eq!(diff.render(), "+ funkcja c\n~ funkcja a\n");
+        assert!(diff_outline(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_function_moved_down_is_unchanged() {
+        let body = ": f def\n    @ x in a b\n        ? ok\n            ?~ @x == a\n                % x = b\n            done\n        done\n    done\ndone\n";
+        let old = outline_of(body);
+        let new = outline_of(&format!(": nowa def\n    ~> n\ndone\n\n{}", body));
+        assert_eq!(diff_outline(&old, &new), OutlineDiff { added_functions: vec!["nowa".into()], ..OutlineDiff::default() });
+    }
+}
@@ -0,0 +1,59 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, IsTerminal, Write};
+use std::sync::Mutex;
+use colored::Colorize;
+
+/// Pytanie "czy na pewno?" przed akcją, której skrypt nie powinien zrobić po cichu
+/// (np. `sudo apt-get install` dla brakującej zależności `// narzędzie`).
+///
+/// Wszystkie takie bramki idą przez `Env::prompter`, więc testy podstawiają
+/// `ScriptedPrompter` zamiast terminala.
+pub trait Prompter: Send + Sync {
+    fn confirm(&self, msg: &str) -> bool;
+}
+
+/// Pyta na stderr i czyta odpowiedź ze stdin. Bez terminala nie pyta:
+/// odpowiedź bierze z HL_ASSUME_YES (1/true — tak, inaczej nie).
+pub struct TtyPrompter;
+
+impl Prompter for TtyPrompter {
+    fn confirm(&self, msg: &str) -> bool {
+        if let Ok(v) = std::env::var("HL_ASSUME_YES") {
+            return matches!(v.as_str(), "1" | "true");
+        }
+        if !std::io::stdin().is_terminal() {
+            eprintln!("{} {} — brak terminala, pomijam (HL_ASSUME_YES=1 zgadza się bez pytania)",
+                      "[hl]".yellow(), msg);
+            return false;
+        }
+        eprint!("{} {} [t/N] ", "[hl]".yellow(), msg);
+        let _ = std::io::stderr().flush();
+        let mut answer = String::new();
+        if std::io::stdin().lock().read_line(&mut answer).is_err() { return false; }
+        matches!(answer.trim().to_lowercase().as_str(), "t" | "tak" | "y" | "yes")
+    }
+}
+
+/// Odpowiedzi z góry, po kolei; po ich wyczerpaniu — nie. Zapamiętuje zadane pytania.
+#[derive(Default)]
+pub struct ScriptedPrompter {
+    answers: Mutex<VecDeque<bool>>,
+    asked:   Mutex<Vec<String>>,
+}
+
+impl ScriptedPrompter {
+    pub fn new(answers: impl IntoIterator<Item = bool>) -> Self {
+        Self { answers: Mutex::new(answers.into_iter().collect()), asked: Mutex::default() }
+    }
+
+    pub fn asked(&self) -> Vec<String> {
+        self.asked.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl Prompter for ScriptedPrompter {
+    fn confirm(&self, msg: &str) -> bool {
+        self.asked.lock().unwrap_or_else(|e| e.into_inner()).push(msg.to_string());
+        self.answers.lock().unwrap_or_else(|e| e.into_inner()).pop_front().unwrap_or(false)
+    }
+}
@@ -1,7 +1,9 @@
+use colored::Colorize;
 use anyhow::{bail, Result};
 use hl_parser::ast::StringPart;
 use crate::env::{Env, Value};
-use crate::executor::ExecResult;
+use crate::executor::{call_func_value, wait_background, ExecResult};
+use crate::fault::{raise, FaultKind};
 
 pub fn exec_quick(name: &str, args: &[StringPart], env: &mut Env) -> Result<ExecResult> {
     let arg_str = env.resolve_string_parts(args);
@@ -41,7 +43,7 @@ pub fn exec_quick(name: &str, args: &[StringPart], env: &mut Env) -> Result<Exec
             println!("{}", (seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407)) % 100);
             Ok(ExecResult::ok())
         }
-        "env"  => { match std::env::var(arg_str) { Ok(v) => { println!("{}", v); Ok(ExecResult::ok()) } Err(_) => { println!(); Ok(ExecResult::err(1)) } } }
+        "env"  => { match env.env_var(arg_str) { Some(v) => { println!("{}", v); Ok(ExecResult::ok()) } None => { println!(); Ok(ExecResult::err(1)) } } }
         // ::env-path — ścieżka aktywnego env z config.hk, zero subprocess
         "env-path" => {
             use crate::config::get_active_env;
@@ -52,9 +54,9 @@ pub fn exec_quick(name: &str, args: &[StringPart], env: &mut Env) -> Result<Exec
         }
         // ::getenv VAR — pobierz zmienną środowiskową (alias do env, dla czytelności)
         "getenv" => {
-            match std::env::var(arg_str) {
-                Ok(v)  => { print!("{}", v); Ok(ExecResult::ok()) }
-                Err(_) => { Ok(ExecResult::err(1)) }
+            match env.env_var(arg_str) {
+                Some(v) => { print!("{}", v); Ok(ExecResult::ok()) }
+                None    => { Ok(ExecResult::err(1)) }
             }
         }
         "date" => { if let Ok(o) = std::process::Command::new("date").arg("+%Y-%m-%d").output() { print!("{}", String::from_utf8_lossy(&o.stdout)); } Ok(ExecResult::ok()) }
@@ -75,22 +77,124 @@ pub fn exec_quick(name: &str, args: &[StringPart], env: &mut Env) -> Result<Exec
                 Value::Number(_) => "number",
                 Value::Bool(_)   => "bool",
                 Value::List(_)   => "list",
+                Value::Func { .. } => "func",
                 Value::Nil       => "nil",
             };
             println!("{}", t); Ok(ExecResult::ok())
         }
         "unset" => { env.vars.remove(arg_str); Ok(ExecResult::ok()) }
+        // :: map @f a b c — wywołaj funkcję (referencję lub nazwę) dla każdego elementu
+        "map" => {
+            let (func, items) = resolve_func_arg(args, env)?;
+            let mut last = ExecResult::ok();
+            for item in items.split_whitespace() {
+                last = call_func_value(&func, &[item.to_string()], env)?;
+                env.last_exit = last.exit_code;
+            }
+            Ok(last)
+        }
+        "wait"   => { let code = wait_quick(arg_str, env)?; Ok(ExecResult::err_or_ok(code)) }
+        "args"   => { println!("{}", env.get_var("args").to_string_val()); Ok(ExecResult::ok()) }
+        "at"     => { println!("{}", list_at(arg_str, env)?); Ok(ExecResult::ok()) }
+        "shquote"=> { println!("{}", hl_parser::cmd_template::shell_quote(arg_str)); Ok(ExecResult::ok()) }
+        "serialize"   => { println!("{}", serialize_var(arg_str, env)?); Ok(ExecResult::ok()) }
+        "deserialize" => { deserialize_into(arg_str, env)?; Ok(ExecResult::ok()) }
+        "arg"    => match script_arg(arg_str, env)? {
+            Some(a) => { println!("{}", a); Ok(ExecResult::ok()) }
+            None    => { println!(); Ok(ExecResult::err(1)) }
+        },
         "nl"     => { println!(); Ok(ExecResult::ok()) }
         "hr"     => { let w: usize = arg_str.parse().unwrap_or(60); println!("{}", "─".repeat(w)); Ok(ExecResult::ok()) }
-        "bold"   => { println!("\x1b[1m{}\x1b[0m", arg_str); Ok(ExecResult::ok()) }
-        "red"    => { println!("\x1b[31m{}\x1b[0m", arg_str); Ok(ExecResult::ok()) }
-        "green"  => { println!("\x1b[32m{}\x1b[0m", arg_str); Ok(ExecResult::ok()) }
-        "yellow" => { println!("\x1b[33m{}\x1b[0m", arg_str); Ok(ExecResult::ok()) }
-        "cyan"   => { println!("\x1b[36m{}\x1b[0m", arg_str); Ok(ExecResult::ok()) }
+        "bold"   => { println!("{}", arg_str.bold()); Ok(ExecResult::ok()) }
+        "red"    => { println!("{}", arg_str.red()); Ok(ExecResult::ok()) }
+        "green"  => { println!("{}", arg_str.green()); Ok(ExecResult::ok()) }
+        "yellow" => { println!("{}", arg_str.yellow()); Ok(ExecResult::ok()) }
+        "cyan"   => { println!("{}", arg_str.cyan()); Ok(ExecResult::ok()) }
         other    => bail!("Nieznana quick-funkcja '::{}'. Zdefiniuj ją jako arena function: :: {} <4k> def ... done", other, other),
     }
 }
 
+/// `:: at lista N` — element N (od 0) listy albo słowo N tekstu; ujemne N liczy od końca
+/// (-1 to ostatni), poza zakresem w obie strony IndexError
+fn list_at(arg_str: &str, env: &Env) -> Result<String> {
+    let (name, idx) = split_last(arg_str);
+    let name = name.trim().trim_start_matches('@');
+    let Ok(idx) = idx.trim().parse::<i64>() else {
+        return Err(raise(FaultKind::Type, format!(":: at {}: indeks '{}' nie jest liczbą całkowitą", name, idx.trim())));
+    };
+    let items: Vec<String> = match env.get_var(name) {
+        Value::List(items) => items.iter().map(|v| v.to_string_val()).collect(),
+        other => other.to_string_val().split_whitespace().map(str::to_string).collect(),
+    };
+    let pos = if idx < 0 { (items.len() as i64).checked_add(idx) } else { Some(idx) };
+    match pos.and_then(|p| usize::try_from(p).ok()).and_then(|p| items.get(p)) {
+        Some(item) => Ok(item.clone()),
+        None => Err(raise(FaultKind::Index, format!(
+            ":: at {}: indeks {} poza zakresem (elementów: {})", name, idx, items.len()))),
+    }
+}
+
+/// Pierwszy argument higher-order quick-funkcji: `@f` z referencją albo goła nazwa
+/// zdefiniowanej funkcji (o ile nie przesłania jej zmienna). Zwraca funkcję i resztę argumentów.
+fn resolve_func_arg(args: &[StringPart], env: &mut Env) -> Result<(Value, String)> {
+    let first = args.iter().position(|p| !matches!(p, StringPart::Literal(s) if s.trim().is_empty()));
+    let Some(idx) = first else { bail!(":: map wymaga: :: map <funkcja|@ref> <elementy...>") };
+    let (func, head) = match &args[idx] {
+        StringPart::Var(v) => match env.get_var(v) {
+            f @ Value::Func { .. } => (f.clone(), String::new()),
+            _ => bail!("'@{}' nie jest referencją do funkcji (użyj: % {} = &nazwa)", v, v),
+        },
+        StringPart::Literal(s) => {
+            let (name, tail) = split_first(s.trim_start());
+            let func = match env.vars.get(name) {
+                Some(f @ Value::Func { .. }) => f.clone(),
+                _ if env.resolve_function(name)?.is_some() => Value::Func { name: name.to_string(), bound: vec![] },
+                _ => bail!("'{}' nie jest funkcją ani referencją do funkcji", name),
+            };
+            (func, format!("{} ", tail))
+        }
+        StringPart::DynVar(_) => bail!(":: map: dynamiczna referencja nie jest obsługiwana jako funkcja"),
+    };
+    let rest = env.resolve_string_parts(&args[idx + 1..]);
+    Ok((func, head + &rest))
+}
+
+/// `:: wait [pid]` — bez argumentu czeka na ostatni `& cmd` (@_bg_pid). Ustawia @_wait_exit.
+/// :: arg N — N-ty argument skryptu; None gdy poza zakresem
+fn script_arg(arg_str: &str, env: &Env) -> Result<Option<String>> {
+    let Ok(i) = arg_str.parse::<usize>() else { bail!(":: arg: '{}' nie jest indeksem argumentu", arg_str) };
+    match env.get_var("args") {
+        Value::List(items) => Ok(items.get(i).map(|v| v.to_string_val())),
+        _ => Ok(None),
+    }
+}
+
+/// `:: serialize nazwa` — nazwa zmiennej bez `@`, żeby lista nie spłaszczyła się do tekstu
+fn serialize_var(arg_str: &str, env: &Env) -> Result<String> {
+    let name = arg_str.trim_start_matches('@');
+    if name.is_empty() { bail!(":: serialize wymaga nazwy zmiennej: :: serialize lista |> @blob"); }
+    crate::wire::encode(&crate::wire::WireValue::from(env.get_var(name)))
+}
+
+/// `:: deserialize @blob cel` — odtwórz wartość (z listami) do zmiennej `cel`
+fn deserialize_into(arg_str: &str, env: &mut Env) -> Result<()> {
+    let (blob, target) = split_last(arg_str);
+    if blob.is_empty() || target.is_empty() {
+        bail!(":: deserialize wymaga: :: deserialize @blob <zmienna>");
+    }
+    let value = crate::wire::decode(blob).map_err(|e| anyhow::anyhow!(":: deserialize: {}", e))?;
+    env.set_var(target.trim_start_matches('@'), Value::from(value));
+    Ok(())
+}
+
+fn wait_quick(arg_str: &str, env: &mut Env) -> Result<i32> {
+    let pid_str = if arg_str.is_empty() { env.get_var("_bg_pid").to_string_val() } else { arg_str.to_string() };
+    let Ok(pid) = pid_str.trim().parse::<u32>() else { bail!(":: wait: '{}' nie jest PID-em procesu tła", pid_str) };
+    let code = wait_background(pid)?;
+    env.set_var("_wait_exit", Value::Number(code as f64));
+    Ok(code)
+}
+
 #[inline] fn split_last(s: &str) -> (&str, &str) {
 match s.rsplit_once(' ') { Some((a,b)) => (a.trim(), b.trim()), None => (s, "") }
 }
@@ -113,7 +217,14 @@ pub fn exec_quick_capture(name: &str, args: &[StringPart], env: &mut Env) -> Res
         "len"      => return Ok(arg_str_t.len().to_string()),
         "trim"     => return Ok(arg_str_t.trim().to_string()),
         "rev"      => return Ok(arg_str_t.chars().rev().collect::<String>()),
-        "env" | "getenv" => return Ok(std::env::var(arg_str_t).unwrap_or_default()),
+        // Jak wersja wypisująca: jedna część na linię (`@dane |> :: split , |> @czesci`)
+        "split"    => { let (t, s) = split_last(arg_str_t); return Ok(t.split(s).collect::<Vec<_>>().join("\n")); }
+        "replace"  => {
+            let parts: Vec<&str> = arg_str_t.splitn(3, ' ').collect();
+            if parts.len() < 3 { bail!(":: replace wymaga: :: replace <text> <from> <to>"); }
+            return Ok(parts[0].replace(parts[1], parts[2]));
+        }
+        "env" | "getenv" => return Ok(env.env_var(arg_str_t).unwrap_or_default()),
         "pid"      => return Ok(std::process::id().to_string()),
         "basename" => return Ok(std::path::Path::new(arg_str_t)
                           .file_name().and_then(|n| n.to_str())
@@ -133,6 +244,13 @@ pub fn exec_quick_capture(name: &str, args: &[StringPart], env: &mut Env) -> Res
                 .unwrap_or_default());
         }
         "which"  => return Ok(which::which(arg_str_t).map(|p| p.display().to_string()).unwrap_or_default()),
+        "wait"   => return Ok(wait_quick(arg_str_t, env)?.to_string()),
+        "args"   => return Ok(env.get_var("args").to_string_val()),
+        "at"     => return list_at(arg_str_t, env),
+        "shquote"=> return Ok(hl_parser::cmd_template::shell_quote(arg_str_t)),
+        "serialize"   => return serialize_var(arg_str_t, env),
+        "deserialize" => { deserialize_into(arg_str_t, env)?; return Ok(String::new()) }
+        "arg"    => return Ok(script_arg(arg_str_t, env)?.unwrap_or_default()),
         _ => {}
     }
 
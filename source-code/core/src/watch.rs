@@ -0,0 +1,164 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use hl_parser::{parse_source, Node};
+
+/// Tryb `hl run --watch` — obserwuje skrypt i jego importy, odpala ponownie po zmianie.
+///
+/// Watcher jest pollingowy (porównanie mtime i długości) — bez inotify, więc działa
+/// tak samo na każdym systemie plików i da się go sterować ręcznie w testach.
+pub const WATCH_POLL: Duration = Duration::from_millis(250);
+/// Edytory zapisują plik seriami (truncate + write + rename) — czekamy aż się uspokoi
+pub const WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Stan pliku w chwili ostatniego sprawdzenia; `None` = plik nie istnieje
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// Skrypt + pliki z `<<` i `<*` (rekurencyjnie) — rozwiązane jak w executorze (crate::import_path:
+/// katalog importującego pliku, cwd, HL_IMPORT_PATH).
+/// Błąd parsowania nie przerywa: wtedy obserwujemy tylko to, co da się ustalić,
+/// żeby poprawka pliku i tak wywołała ponowne uruchomienie.
+pub fn watched_paths(script: &Path) -> Vec<PathBuf> {
+    import_paths(script, None)
+}
+
+/// Jak `watched_paths`, ale importy najwyżej `max_depth` poziomów w głąb
+/// (0 = sam skrypt, 1 = + jego bezpośrednie importy, None = bez limitu)
+pub fn import_paths(script: &Path, max_depth: Option<usize>) -> Vec<PathBuf> {
+    // Wszerz: plik skanowany jest na najmniejszej głębokości, na której występuje.
+    // W głąb plik odwiedzony najpierw długą ścieżką (main → a → b) byłby już odcięty
+    // limitem i krótsza ścieżka (main → b) nie dodałaby jego importów.
+    let roots = crate::import_path::import_roots_from_env();
+    let mut out   = vec![script.to_path_buf()];
+    let mut queue = VecDeque::from([(script.to_path_buf(), 0)]);
+    while let Some((path, depth)) = queue.pop_front() {
+        if max_depth.is_some_and(|max| depth >= max) { continue; }
+        for import in direct_imports(&path, &roots) {
+            if out.contains(&import) { continue; }
+            out.push(import.clone());
+            queue.push_back((import, depth + 1));
+        }
+    }
+    out
+}
+
+/// Pliki z `<<` i `<*` jednego skryptu
+fn direct_imports(path: &Path, roots: &[PathBuf]) -> Vec<PathBuf> {
+    let Ok(src)   = std::fs::read_to_string(path) else { return Vec::new() };
+    let Ok(nodes) = parse_source(&src) else { return Vec::new() };
+    // Te same miejsca co executor; nieznaleziony import zostaje pod ścieżką ze źródła
+    let importer = path.parent();
+    let find     = |p: &str| crate::import_path::resolve(p, importer, roots).unwrap_or_else(|| PathBuf::from(p));
+    nodes.iter().filter_map(|node| match node {
+        // Ścieżki z @zmienną znane są dopiero w runtime — pomijamy
+        Node::FileImport { path, .. } if !path.contains('@') => {
+            let wanted = if !path.contains('.') { format!("{}.hl", path) } else { path.clone() };
+            Some(find(&wanted))
+        }
+        Node::DirImport { path } if !path.contains('@') => Some(find(path).join("imports.hl")),
+        _ => None,
+    }).collect()
+}
+
+pub struct FileWatcher {
+    script: PathBuf,
+    stamps: BTreeMap<PathBuf, Stamp>,
+    debounce: Duration,
+}
+
+impl FileWatcher {
+    pub fn new(script: &Path) -> Self {
+        let mut w = FileWatcher { script: script.to_path_buf(), stamps: BTreeMap::new(), debounce: WATCH_DEBOUNCE };
+        w.rescan();
+        w
+    }
+
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        self.stamps.keys()
+    }
+
+    /// Odśwież listę plików i ich stan — po każdym uruchomieniu, bo importy mogły się zmienić
+    pub fn rescan(&mut self) {
+        self.stamps = watched_paths(&self.script).into_iter()
+        .map(|p| { let s = stamp(&p); (p, s) })
+        .collect();
+    }
+
+    /// Pliki zmienione od ostatniego sprawdzenia (i zapamiętanie nowego stanu)
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for (path, old) in self.stamps.iter_mut() {
+            let now = stamp(path);
+            if now != *old {
+                *old = now;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+
+    /// Jeden krok pętli: jeśli coś się zmieniło — odczekaj debounce, wchłoń dalsze zapisy,
+    /// wywołaj `rerun` ze zmienionymi plikami i przeskanuj importy. Zwraca, czy był rerun.
+    pub fn step(&mut self, rerun: &mut dyn FnMut(&[PathBuf])) -> bool {
+        let mut changed = self.poll();
+        if changed.is_empty() { return false; }
+        loop {
+            std::thread::sleep(self.debounce);
+            let more = self.poll();
+            if more.is_empty() { break; }
+            for p in more { if !changed.contains(&p) { changed.push(p); } }
+        }
+        rerun(&changed);
+        self.rescan();
+        true
+    }
+
+    /// Pętla bez końca — kończy ją dopiero Ctrl+C
+    pub fn run(&mut self, rerun: &mut dyn FnMut(&[PathBuf])) -> ! {
+        loop {
+            if !self.step(rerun) {
+                std::thread::sleep(WATCH_POLL);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hl_test_support::TestDir;
+
+    #[test]
+    fn test_import_change_triggers_rerun() {
+        let dir = TestDir::new("watch");
+        let lib    = dir.join("lib.hl");
+        let script = dir.join("main.hl");
+        std::fs::write(&lib, "~> lib\n").unwrap();
+        std::fs::write(&script, format!("<< {}\n~> main\n", lib.display())).unwrap();
+
+        let mut w = FileWatcher::new(&script).with_debounce(Duration::from_millis(1));
+        assert!(w.paths().any(|p| p == &lib), "import nieobserwowany: {:?}", w.paths().collect::<Vec<_>>());
+
+        let mut runs = Vec::new();
+        assert!(!w.step(&mut |c| runs.push(c.to_vec())));
+
+        // Symulowana zmiana: inna treść i mtime przesunięty do przodu (rozdzielczość FS)
+        std::fs::write(&lib, "~> lib v2\n").unwrap();
+        let f = std::fs::File::options().write(true).open(&lib).unwrap();
+        f.set_modified(SystemTime::now() + Duration::from_secs(5)).unwrap();
+
+        assert!(w.step(&mut |c| runs.push(c.to_vec())));
+        assert_eq!(runs, vec![vec![lib.clone()]]);
+        assert!(!w.step(&mut |c| runs.push(c.to_vec())));
+    }
+}
@@ -0,0 +1,96 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use crate::env::Value;
+
+/// Przenośny format wartości HL — `:: serialize` / `:: deserialize`.
+///
+/// Schemat jest stały i niezależny od `Value`: tree-walk i VM kodują przez ten sam typ,
+/// więc bajty z jednego runtime'u czyta drugi. Nowe warianty tylko na końcu enuma
+/// (bincode zapisuje indeks wariantu) i z podbiciem `WIRE_VERSION`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WireValue {
+    Nil,
+    Bool(bool),
+    /// Liczby HL są f64 — inty mieszczą się dokładnie do 2^53
+    Num(f64),
+    Str(String),
+    List(Vec<WireValue>),
+}
+
+pub const WIRE_MAGIC: &[u8; 3] = b"HLV";
+pub const WIRE_VERSION: u8 = 1;
+
+impl From<&Value> for WireValue {
+    fn from(v: &Value) -> Self {
+        match v {
+            Value::Nil        => WireValue::Nil,
+            Value::Bool(b)    => WireValue::Bool(*b),
+            Value::Number(n)  => WireValue::Num(*n),
+            Value::String(s)  => WireValue::Str(s.clone()),
+            Value::List(xs)   => WireValue::List(xs.iter().map(WireValue::from).collect()),
+            // Referencja do funkcji nie przeżyje procesu — zostaje jej zapis tekstowy
+            Value::Func { .. } => WireValue::Str(v.to_string_val()),
+        }
+    }
+}
+
+impl From<WireValue> for Value {
+    fn from(w: WireValue) -> Self {
+        match w {
+            WireValue::Nil     => Value::Nil,
+            WireValue::Bool(b) => Value::Bool(b),
+            WireValue::Num(n)  => Value::Number(n),
+            WireValue::Str(s)  => Value::String(s),
+            WireValue::List(xs) => Value::List(xs.into_iter().map(Value::from).collect()),
+        }
+    }
+}
+
+/// magic + wersja + bincode, zapisane jako hex — zmienne HL to tekst,
+/// więc surowe bajty (NUL, niepoprawny UTF-8) nie przetrwałyby `|> @var` ani argv
+pub fn encode(v: &WireValue) -> Result<String> {
+    let body = bincode::serialize(v).context("Serializacja wartości")?;
+    let mut out = String::with_capacity((body.len() + 4) * 2);
+    for b in WIRE_MAGIC.iter().chain(std::iter::once(&WIRE_VERSION)).chain(body.iter()) {
+        out.push_str(&format!("{:02x}", b));
+    }
+    Ok(out)
+}
+
+pub fn decode(s: &str) -> Result<WireValue> {
+    let s = s.trim();
+    if !s.len().is_multiple_of(2) || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        bail!("To nie jest zserializowana wartość HL (oczekiwano hex)");
+    }
+    let bytes: Vec<u8> = (0..s.len()).step_by(2)
+    .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap_or(0))
+    .collect();
+    if bytes.len() < 4 || &bytes[..3] != WIRE_MAGIC {
+        bail!("Brak nagłówka HLV — to nie jest zserializowana wartość HL");
+    }
+    if bytes[3] != WIRE_VERSION {
+        bail!("Nieobsługiwana wersja formatu wartości: {} (obsługiwana: {})", bytes[3], WIRE_VERSION);
+    }
+    bincode::deserialize(&bytes[4..]).context("Deserializacja wartości")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_list_roundtrip() {
+        let v = Value::List(vec![
+            Value::String("ala ma kota".into()),
+            Value::Number(42.0),
+            Value::Number(-0.5),
+            Value::List(vec![Value::Bool(true), Value::Nil, Value::String(String::new())]),
+        ]);
+        let blob = encode(&WireValue::from(&v)).unwrap();
+        assert!(blob.starts_with("484c5601"), "{}", blob);
+        let back = Value::from(decode(&blob).unwrap());
+        assert_eq!(WireValue::from(&back), WireValue::from(&v));
+        assert!(decode("zzz").is_err());
+        assert!(decode("00112233").is_err());
+    }
+}
@@ -1,6 +1,13 @@
-use anyhow::{bail, Result};
+use anyhow::Result;
 use hl_compiler::bytecode::*;
+use colored::Colorize;
 use crate::runtime::{RuntimeState, NanVal};
+use hl_core::deny::DenyList;
+use hl_parser::cmd_template::split_words;
+use hl_core::executor::{capture_output, register_background, reject_echo, status_code, try_builtin_exit, wait_background, CaptureLimit, OutputLimit, TempDirGuard};
+use hl_core::fault::{self, FaultKind};
+use hl_core::wire::{self, WireValue};
+use std::io::Write;
 use std::process::{Command, Stdio};
 
 // ── Dispatch signal ───────────────────────────────────────────────────────────
@@ -11,6 +18,8 @@ pub enum ExecSignal {
     Jump(u32),
     Return,
     FuncCall(u32),   // name_idx
+    /// `-- f` tuż przed powrotem z funkcji — wywołujący uruchamia `f` w tej samej ramce
+    TailCall(u32),   // name_idx
     Exit(i32),
 }
 
@@ -27,6 +36,63 @@ pub struct BytecodeInterpreter<'a> {
     exec_counts:     Vec<u32>,
     /// Skompilowane trasy (offset → native fn ptr)
     compiled_traces: rustc_hash::FxHashMap<u32, CompiledTrace>,
+    /// Limit wyjścia Print (HL_MAX_OUTPUT) — ten sam co w tree-walk
+    output_limit:    Option<OutputLimit>,
+    /// HL_MAX_CAPTURE / HL_CAPTURE_OVERFLOW — limit `>> cmd |> @var` i `@dane |> cmd |> @var`, jak w tree-walk
+    capture_limit:   CaptureLimit,
+    /// Otwarte bloki `? tempdir` — Drop sprząta też po błędzie wykonania
+    tempdirs:        Vec<TempDirGuard>,
+    /// Katalog, w którym `? tempdir` tworzy katalogi — jak Env::tempdir_base
+    tempdir_base:    std::path::PathBuf,
+    /// Otwarte bloki `? try`, najgłębszy na końcu
+    try_stack:       Vec<TryFrame>,
+    /// Złapany błąd czekający na dopasowanie ramienia `CatchArm` (albo `Rethrow`)
+    pending_fault:   Option<anyhow::Error>,
+    /// HL_CHECKED=1 — przepełnienie i dzielenie przez zero to ArithmeticError
+    checked:         bool,
+    /// HL_MAX_LOOP — limit iteracji pętli (LoopGuard); przy limicie trace JIT jest wyłączony
+    loop_limit:      Option<u64>,
+    /// HL_DENY — zabronione komendy, sprawdzane przed spawnem jak w tree-walk
+    deny:            DenyList,
+    /// Ile razy tekst komendy był dzielony na argv w runtime (ExecCmd);
+    /// stałe komendy `>` (ExecArgv) mają argv z kompilacji i tego licznika nie ruszają
+    cmd_splits:      u64,
+    /// HL_TRACE — listing każdej instrukcji przed wykonaniem (`hl run --trace` / `--step`)
+    tracer:          Option<Tracer>,
+    /// Indeks stałej z nazwą zmiennej → klucz zmiennej (indeks w interner). Zmienne są
+    /// kluczowane nazwą z internera, tak samo dla GetVar/SetVar i dla nazw z runtime.
+    var_keys:        Vec<u32>,
+    /// Kod z `exit [N]` — ustawiony kończy wszystkie ramki aż do `run`
+    exiting:         Option<i32>,
+}
+
+/// Śledzenie VM: przed każdą instrukcją linia disasm, żywe rejestry i zmienne instrukcji
+pub struct Tracer {
+    out:  Box<dyn std::io::Write>,
+    /// Czekaj na Enter przed każdą instrukcją; `c` wyłącza pauzy do końca
+    step: bool,
+}
+
+impl Tracer {
+    pub fn new(out: Box<dyn std::io::Write>, step: bool) -> Self { Self { out, step } }
+
+    /// HL_TRACE=1 — śledzenie na stderr, HL_TRACE=step — z pauzą po każdej instrukcji
+    pub fn from_env() -> Option<Self> {
+        match std::env::var("HL_TRACE").ok()?.as_str() {
+            "1" | "true" => Some(Self::new(Box::new(std::io::stderr()), false)),
+            "step"       => Some(Self::new(Box::new(std::io::stderr()), true)),
+            _            => None,
+        }
+    }
+}
+
+/// Handler z `TryEnter`: łapie tylko błędy z tej samej głębokości wywołań
+/// (błąd z funkcji wraca do wołającego jako Err i tam trafia na handler)
+struct TryFrame {
+    handler:  u32,
+    depth:    u32,
+    /// Ile `? tempdir` było otwartych przy wejściu — reszta jest zamykana przy błędzie
+    tempdirs: usize,
 }
 
 /// Skompilowana trasa (wynik trace JIT)
@@ -40,14 +106,80 @@ pub struct CompiledTrace {
 impl<'a> BytecodeInterpreter<'a> {
     pub fn new(module: &'a HlModule) -> Self {
         let n = module.instructions.len();
+        let mut state = RuntimeState::new(module.main_regs as usize);
+        let var_keys = module.consts.strings.iter().map(|s| state.interner.intern(s)).collect();
         Self {
             module,
-            state:           RuntimeState::new(module.main_regs as usize),
+            state,
             exec_counts:     vec![0u32; n],
             compiled_traces: rustc_hash::FxHashMap::default(),
+            output_limit:    OutputLimit::from_env(),
+            capture_limit:   CaptureLimit::from_env(),
+            tempdirs:        Vec::new(),
+            tempdir_base:    std::env::temp_dir(),
+            try_stack:       Vec::new(),
+            pending_fault:   None,
+            checked:         hl_core::env::checked_from_env(),
+            loop_limit:      hl_core::env::loop_limit_from_env(),
+            deny:            hl_core::deny::deny_from_env(),
+            cmd_splits:      0,
+            tracer:          Tracer::from_env(),
+            var_keys,
+            exiting:         None,
         }
     }
 
+    pub fn with_output_limit(mut self, max_bytes: usize) -> Self {
+        self.output_limit = Some(OutputLimit::new(max_bytes));
+        self
+    }
+
+    pub fn with_capture_limit(mut self, limit: CaptureLimit) -> Self {
+        self.capture_limit = limit;
+        self
+    }
+
+    pub fn with_checked(mut self, checked: bool) -> Self {
+        self.checked = checked;
+        self
+    }
+
+    pub fn with_loop_limit(mut self, limit: Option<u64>) -> Self {
+        self.loop_limit = limit;
+        self
+    }
+
+    pub fn with_deny(mut self, deny: DenyList) -> Self {
+        self.deny = deny;
+        self
+    }
+
+    pub fn with_tempdir_base(mut self, base: std::path::PathBuf) -> Self {
+        self.tempdir_base = base;
+        self
+    }
+
+    pub fn with_trace(mut self, tracer: Option<Tracer>) -> Self {
+        self.tracer = tracer;
+        self
+    }
+
+    /// Argumenty skryptu — @argc / @arg0.. / @args jak Env::set_script_args w tree-walk
+    pub fn with_args(mut self, args: &[String]) -> Self {
+        let argc = self.var_key("argc");
+        self.state.set_var(argc, NanVal::num(args.len() as f64));
+        for (i, a) in args.iter().enumerate() {
+            let key = self.var_key(&format!("arg{}", i));
+            let val = self.state.intern_str(a);
+            self.state.set_var(key, val);
+        }
+        let key = self.var_key("args");
+        let val = self.state.intern_str_owned(args.join(" "));
+        self.state.set_var(key, val);
+        self.state.script_args = args.to_vec();
+        self
+    }
+
     /// Inicjalizuj zmienne HL_VERSION itp.
     pub fn init_hl_vars(&mut self) {
         let k = self.state.interner.intern("HL_VERSION");
@@ -61,10 +193,8 @@ impl<'a> BytecodeInterpreter<'a> {
     /// Uruchom główny blok
     pub fn run(&mut self) -> Result<i32> {
         self.init_hl_vars();
-        let main_end = self.module.funcs.entries.first()
-        .map(|f| f.start_insn as usize)
-        .unwrap_or(self.module.instructions.len());
-        self.exec_block(0, main_end)?;
+        // Ciała funkcji są przeskakiwane przez Jump, więc kod główny to cały moduł
+        self.exec_block(0, self.module.instructions.len())?;
         Ok(self.state.last_exit)
     }
 
@@ -81,7 +211,11 @@ impl<'a> BytecodeInterpreter<'a> {
                     // Guard: kompiluj tylko małe pętle (<= 64 instrukcji)
                     let loop_size = pc - target;
                     let count = self.exec_counts.get_mut(pc).map(|c| { *c += 1; *c }).unwrap_or(0);
-                    if count == TRACE_THRESHOLD && loop_size <= 64 {
+                    // Skompilowana trasa pomija LoopGuard i nie zgłasza ArithmeticError —
+                    // z limitem pętli albo w trybie checked pętle zostają w interpreterze
+                    // Przy --trace też: trasa natywna nie przechodzi przez listing instrukcji
+                    if count == TRACE_THRESHOLD && loop_size <= 64 && self.loop_limit.is_none() && !self.checked
+                        && self.tracer.is_none() {
                         // Próbuj skompilować pętlę [target..pc+1]
                         if let Ok(trace) = self.try_compile_trace(target as u32, pc as u32) {
                             self.compiled_traces.insert(target as u32, trace);
@@ -97,20 +231,111 @@ impl<'a> BytecodeInterpreter<'a> {
                 }
             }
 
-            match self.exec_insn(pc)? {
-                ExecSignal::Next          => pc += 1,
-                ExecSignal::Jump(off)     => pc = off as usize,
-                ExecSignal::Return        => return Ok(ExecSignal::Return),
-                ExecSignal::Exit(code)    => { self.state.last_exit = code; return Ok(ExecSignal::Return); }
-                ExecSignal::FuncCall(ni)  => {
-                    self.exec_func_by_name_idx(ni)?;
-                    pc += 1;
+            if self.tracer.is_some() { self.trace_insn(pc)?; }
+            let signal = match self.exec_insn(pc) {
+                Ok(ExecSignal::FuncCall(ni)) if self.is_tail_call(pc, end) => return Ok(ExecSignal::TailCall(ni)),
+                Ok(ExecSignal::FuncCall(ni)) => self.exec_func_by_name_idx(ni).map(|_| ExecSignal::Next),
+                other => other,
+            };
+            // `exit` w wywołanej funkcji kończy cały skrypt, nie tylko jej ramkę
+            let signal = match (signal, self.exiting) {
+                (Ok(_), Some(code)) => Ok(ExecSignal::Exit(code)),
+                (signal, _)         => signal,
+            };
+            match signal {
+                Ok(ExecSignal::Next)       => pc += 1,
+                Ok(ExecSignal::Jump(off))  => pc = off as usize,
+                Ok(ExecSignal::Return)     => return Ok(ExecSignal::Return),
+                Ok(ExecSignal::Exit(code)) => {
+                    self.state.last_exit = code;
+                    self.exiting = Some(code);
+                    return Ok(ExecSignal::Exit(code));
                 }
+                Ok(ExecSignal::FuncCall(_)) | Ok(ExecSignal::TailCall(_)) => unreachable!(),
+                Err(e) => pc = self.catch_fault(e, start..end)?,
             }
         }
         Ok(ExecSignal::Next)
     }
 
+    /// Linia śladu dla instrukcji pod `pc`: głębokość wywołań, disasm, 4 ostatnie niepuste
+    /// rejestry (wierzchołek „stosu” — lowering przydziela je rosnąco) i bieżące wartości zmiennych, których dotyka instrukcja
+    fn trace_insn(&mut self, pc: usize) -> Result<()> {
+        let mut line = format!("[trace] d{} {}", self.state.call_depth, hl_compiler::disassemble_insn(self.module, pc));
+        let shown = |v: NanVal, interner: &crate::runtime::StringInterner| {
+            if v.is_nil() { "nil".to_string() }
+            else if v.is_str() { format!("{:?}", v.to_str_val(interner)) }
+            else { v.to_str_val(interner) }
+        };
+        let mut regs: Vec<String> = self.state.regs.iter().enumerate().rev()
+            .filter(|(_, v)| !v.is_nil())
+            .take(4)
+            .map(|(i, v)| format!("r{}={}", i, shown(*v, &self.state.interner)))
+            .collect();
+        regs.reverse();
+        if !regs.is_empty() { line.push_str(&format!("  | {}", regs.join(" "))); }
+        if let Some(Instruction::GetVar { name, .. } | Instruction::SetVar { name, .. } | Instruction::SetEnv { name, .. })
+            = self.module.instructions.get(pc) {
+            let val = self.state.get_var(self.var_keys[*name as usize]);
+            line.push_str(&format!("  | @{}={}", self.const_str(*name), shown(val, &self.state.interner)));
+        }
+        let Some(tracer) = self.tracer.as_mut() else { return Ok(()) };
+        writeln!(tracer.out, "{}", line)?;
+        if tracer.step {
+            tracer.out.flush()?;
+            let mut input = String::new();
+            std::io::stdin().read_line(&mut input)?;
+            if input.trim() == "c" { tracer.step = false; }
+        }
+        Ok(())
+    }
+
+    /// Błąd w bloku: skok do najgłębszego `? try` tej samej ramki albo dalej jako Err
+    fn catch_fault(&mut self, err: anyhow::Error, block: std::ops::Range<usize>) -> Result<usize> {
+        let depth = self.state.call_depth;
+        match self.try_stack.last() {
+            Some(f) if f.depth == depth && block.contains(&(f.handler as usize)) => {}
+            _ => return Err(err),
+        }
+        let frame = self.try_stack.pop().expect("sprawdzone wyżej");
+        self.tempdirs.truncate(frame.tempdirs);
+        let (kind, message) = fault::classify(&err);
+        tracing::debug!("[hl jit] ? try: {}: {}", kind.tag(), message);
+        let kind_key = self.var_key("_error_kind");
+        let kind_val = self.state.intern_str(kind.tag());
+        self.state.set_var(kind_key, kind_val);
+        let msg_key = self.var_key("_error");
+        let msg_val = self.state.intern_str_owned(message);
+        self.state.set_var(msg_key, msg_val);
+        self.pending_fault = Some(err);
+        Ok(frame.handler as usize)
+    }
+
+    /// Operandy Add/Sub/Mul/Div; None gdy któryś jest nilem (błąd dziedziny, np. `sqrt(-1)`),
+    /// który — jak NaN w tree-walk — przechodzi przez całe wyrażenie
+    fn arith_operands(&self, a: u32, b: u32) -> Option<(f64, f64)> {
+        let (va, vb) = (self.state.get_reg(a), self.state.get_reg(b));
+        (!va.is_nil() && !vb.is_nil()).then(|| (va.as_f64(), vb.as_f64()))
+    }
+
+    /// Wynik Add/Sub/Mul/Div/Mod; w trybie checked poza zakresem i64 → ArithmeticError
+    fn arith_result(&self, op: &str, r: f64) -> Result<NanVal> {
+        if self.checked && !(r.is_finite() && r.abs() <= i64::MAX as f64) {
+            return Err(fault::raise(FaultKind::Arithmetic, format!("{}: przepełnienie albo dzielenie przez zero", op)));
+        }
+        // NaN (`inf - inf`) to nil, jak w tree-walk i dla funkcji matematycznych
+        Ok(if r.is_nan() { NanVal::nil() } else { NanVal::num(r) })
+    }
+
+    /// `& | ^ << >>` — wspólna semantyka z tree-walk (`hl_parser::math::eval_bitwise`)
+    fn exec_bitwise(&mut self, op: &str, dst: u32, a: u32, b: u32) -> Result<ExecSignal> {
+        let (va, vb) = (self.state.get_reg(a).as_f64(), self.state.get_reg(b).as_f64());
+        let r = hl_parser::math::eval_bitwise(op, va, vb).ok_or_else(|| fault::raise(FaultKind::Type,
+            format!("{} {} {}: operator {} wymaga liczb całkowitych (przesunięcie 0..63)", va, op, vb, op)))?;
+        self.state.set_reg(dst, NanVal::num(r));
+        Ok(ExecSignal::Next)
+    }
+
     /// Wykonaj skompilowaną trasę — przekaż rejestry i zmienne jako raw pointers
     fn exec_native_trace(&mut self, trace_start: u32) -> Result<u32> {
         let trace = match self.compiled_traces.get(&trace_start) {
@@ -185,7 +410,7 @@ impl<'a> BytecodeInterpreter<'a> {
             // ── Zmienne ───────────────────────────────────────────────────────
             Instruction::GetVar { dst, name } => {
                 // Inline cache hot path — O(1)
-                let val = self.state.get_var(name);
+                let val = self.state.get_var(self.var_keys[name as usize]);
                 self.state.set_reg(dst, val);
                 Ok(ExecSignal::Next)
             }
@@ -207,52 +432,67 @@ impl<'a> BytecodeInterpreter<'a> {
             }
             Instruction::SetVar { name, src } => {
                 let val = self.state.get_reg(src);
-                self.state.set_var(name, val);
+                let key = self.var_keys[name as usize];
+                self.state.set_var(key, val);
                 // Synchronizuj last_exit jeśli to _last_exit_code
                 let le_name = self.const_str_idx("_last_exit_code");
-                if name == le_name {
+                if key == le_name {
                     self.state.last_exit = val.as_f64() as i32;
                 }
                 Ok(ExecSignal::Next)
             }
             Instruction::SetEnv { name, src } => {
                 let val = self.state.get_reg(src);
-                self.state.export_var(name, val);
+                self.state.export_var(self.var_keys[name as usize], val);
                 Ok(ExecSignal::Next)
             }
 
             // ── Arytmetyka — bezpośrednio na f64, zero alokacji ───────────────
             Instruction::Add { dst, a, b } => {
-                let r = self.state.get_reg(a).as_f64() + self.state.get_reg(b).as_f64();
-                self.state.set_reg(dst, NanVal::num(r));
+                let r = self.arith_operands(a, b).map_or(f64::NAN, |(va, vb)| va + vb);
+                let v = self.arith_result("+", r)?;
+                self.state.set_reg(dst, v);
                 Ok(ExecSignal::Next)
             }
             Instruction::Sub { dst, a, b } => {
-                let r = self.state.get_reg(a).as_f64() - self.state.get_reg(b).as_f64();
-                self.state.set_reg(dst, NanVal::num(r));
+                let r = self.arith_operands(a, b).map_or(f64::NAN, |(va, vb)| va - vb);
+                let v = self.arith_result("-", r)?;
+                self.state.set_reg(dst, v);
                 Ok(ExecSignal::Next)
             }
             Instruction::Mul { dst, a, b } => {
-                let r = self.state.get_reg(a).as_f64() * self.state.get_reg(b).as_f64();
-                self.state.set_reg(dst, NanVal::num(r));
+                let r = self.arith_operands(a, b).map_or(f64::NAN, |(va, vb)| va * vb);
+                let v = self.arith_result("*", r)?;
+                self.state.set_reg(dst, v);
                 Ok(ExecSignal::Next)
             }
             Instruction::Div { dst, a, b } => {
-                let va = self.state.get_reg(a).as_f64();
-                let vb = self.state.get_reg(b).as_f64();
-                self.state.set_reg(dst, NanVal::num(if vb == 0.0 { 0.0 } else { va / vb }));
+                let zero = if self.checked { f64::INFINITY } else { 0.0 };
+                let r = match self.arith_operands(a, b) {
+                    Some((_, 0.0)) => zero,
+                    Some((va, vb)) => va / vb,
+                    None => f64::NAN,
+                };
+                let v = self.arith_result("/", r)?;
+                self.state.set_reg(dst, v);
                 Ok(ExecSignal::Next)
             }
             Instruction::Mod { dst, a, b } => {
                 let va = self.state.get_reg(a).as_f64() as i64;
                 let vb = self.state.get_reg(b).as_f64() as i64;
-                let r  = if vb == 0 { 0 } else { va % vb };
-                self.state.set_reg(dst, NanVal::num(r as f64));
+                let zero = if self.checked { f64::INFINITY } else { 0.0 };
+                let v = self.arith_result("%", if vb == 0 { zero } else { (va % vb) as f64 })?;
+                self.state.set_reg(dst, v);
                 Ok(ExecSignal::Next)
             }
+            Instruction::BitAnd { dst, a, b } => self.exec_bitwise("&", dst, a, b),
+            Instruction::BitOr { dst, a, b }  => self.exec_bitwise("|", dst, a, b),
+            Instruction::BitXor { dst, a, b } => self.exec_bitwise("^", dst, a, b),
+            Instruction::Shl { dst, a, b }    => self.exec_bitwise("<<", dst, a, b),
+            Instruction::Shr { dst, a, b }    => self.exec_bitwise(">>", dst, a, b),
             Instruction::Neg { dst, src } => {
-                let r = -self.state.get_reg(src).as_f64();
-                self.state.set_reg(dst, NanVal::num(r));
+                let val = self.state.get_reg(src);
+                self.state.set_reg(dst, if val.is_nil() { NanVal::nil() } else { NanVal::num(-val.as_f64()) });
                 Ok(ExecSignal::Next)
             }
 
@@ -298,7 +538,12 @@ impl<'a> BytecodeInterpreter<'a> {
                 Ok(ExecSignal::Next)
             }
             Instruction::ToNumber { dst, src } => {
-                let n = self.state.get_reg(src).as_f64();
+                // Tekst liczby (argument funkcji, wynik komendy) jak w tree-walk po interpolacji
+                let val = self.state.get_reg(src);
+                let n = match val.as_str_idx() {
+                    Some(idx) => self.state.interner.get(idx).trim().parse::<f64>().unwrap_or(0.0),
+                    None      => val.as_f64(),
+                };
                 self.state.set_reg(dst, NanVal::num(n));
                 Ok(ExecSignal::Next)
             }
@@ -308,7 +553,7 @@ impl<'a> BytecodeInterpreter<'a> {
                     Some(idx) => {
                         // Warunek while — ewaluuj wyrażenie porównania
                         let s = self.state.interner.get(idx).to_string();
-                        eval_condition_str(&s, &mut self.state)
+                        eval_condition_str(&s, &mut self.state, &self.deny)?
                     }
                     None => val.is_truthy(&self.state.interner),
                 };
@@ -330,7 +575,13 @@ impl<'a> BytecodeInterpreter<'a> {
 
             // ── Output ────────────────────────────────────────────────────────
             Instruction::Print { src } => {
-                println!("{}", self.state.get_reg(src).to_str_val(&self.state.interner));
+                let line = self.state.get_reg(src).to_str_val(&self.state.interner);
+                if self.output_limit.as_ref().is_some_and(|l| !l.admit(line.len())) {
+                    let k = self.state.interner.intern("_output_truncated");
+                    self.state.set_var(k, NanVal::bool(true));
+                } else {
+                    println!("{}", line);
+                }
                 Ok(ExecSignal::Next)
             }
 
@@ -358,15 +609,37 @@ impl<'a> BytecodeInterpreter<'a> {
             Instruction::CallQuick { name, arg, dst } => {
                 let arg_str  = self.state.get_reg(arg).to_str_val(&self.state.interner);
                 let name_str = self.const_str(name);
-                let result   = exec_quick_fn(&name_str, &arg_str, &mut self.state);
+                let result   = match name_str.as_str() {
+                    "serialize"   => self.quick_serialize(&arg_str),
+                    // bez przycinania argumentu — wartość `{zmiennej}` z komendy ma zostać dokładnie taka
+                    "shquote"     => hl_parser::cmd_template::shell_quote(&arg_str),
+                    "deserialize" => { self.quick_deserialize(&arg_str); String::new() }
+                    "call"        => { self.quick_call(&arg_str)?; String::new() }
+                    "map"         => { self.quick_map(&arg_str)?; String::new() }
+                    "at"          => self.quick_at(&arg_str)?,
+                    "wait"        => self.quick_wait(&arg_str)?,
+                    _ => exec_quick_fn(&name_str, &arg_str, &mut self.state),
+                };
                 let val      = self.state.intern_str_owned(result);
                 self.state.set_reg(dst, val);
                 Ok(ExecSignal::Next)
             }
 
+            Instruction::MathCall { name, args, dst } => {
+                let args: Vec<f64> = args.iter().map(|r| self.state.get_reg(*r).as_f64()).collect();
+                let r = hl_parser::math::eval_math(&self.const_str(name), &args);
+                // NanVal::num zamieniłby NaN na 0 — błąd dziedziny ma być nilem
+                self.state.set_reg(dst, if r.is_nan() { NanVal::nil() } else { NanVal::num(r) });
+                Ok(ExecSignal::Next)
+            }
+
             // ── Komendy systemowe ─────────────────────────────────────────────
             Instruction::ExecCmd { cmd, mode, dst } => {
                 let cmd_str   = self.state.get_reg(cmd).to_str_val(&self.state.interner);
+                if let Some(code) = try_builtin_exit(&cmd_str) { return Ok(ExecSignal::Exit(code)); }
+                reject_echo(&cmd_str)?;
+                self.deny.check(&cmd_str)?;
+                self.cmd_splits += 1;
                 let exit_code = exec_system_cmd(&cmd_str, mode, &mut self.state)?;
                 self.state.set_reg(dst, NanVal::num(exit_code as f64));
                 self.state.last_exit = exit_code;
@@ -376,23 +649,112 @@ impl<'a> BytecodeInterpreter<'a> {
                 Ok(ExecSignal::Next)
             }
 
+            Instruction::ExecArgv { cmd, argv, dst } => {
+                if let Some(code) = try_builtin_exit(&self.const_str(cmd)) { return Ok(ExecSignal::Exit(code)); }
+                reject_echo(&self.const_str(cmd))?;
+                self.deny.check(&self.const_str(cmd))?;
+                let strings = &self.module.consts.strings;
+                let word = |i: &ConstIdx| strings.get(*i as usize).map(String::as_str).unwrap_or("");
+                let exit_code = match argv.split_first() {
+                    Some((prog, args)) => spawn_argv(word(prog), args.iter().map(word), &self.state),
+                    None               => 0,
+                };
+                self.state.set_reg(dst, NanVal::num(exit_code as f64));
+                self.state.last_exit = exit_code;
+                let le_idx = self.state.interner.intern("_last_exit_code");
+                self.state.set_var(le_idx, NanVal::num(exit_code as f64));
+                Ok(ExecSignal::Next)
+            }
+
             Instruction::ExecCapture { cmd, mode, dst_ec, dst_out } => {
                 let cmd_str = self.state.get_reg(cmd).to_str_val(&self.state.interner);
-                let (exit_code, stdout) = exec_system_cmd_capture(&cmd_str, mode)?;
+                self.deny.check(&cmd_str)?;
+                let (exit_code, stdout, truncated) = exec_system_cmd_capture(&cmd_str, mode, &self.state, &self.capture_limit)?;
                 self.state.set_reg(dst_ec, NanVal::num(exit_code as f64));
                 let out_val = self.state.intern_str_owned(stdout);
                 self.state.set_reg(dst_out, out_val);
+                let tr_key = self.var_key("_capture_truncated");
+                self.state.set_var(tr_key, NanVal::bool(truncated));
+                self.state.last_exit = exit_code;
+                // Jak ExecCmd — `&&`/`||` i `? ok` czytają _last_exit_code
+                let le_idx = self.state.interner.intern("_last_exit_code");
+                self.state.set_var(le_idx, NanVal::num(exit_code as f64));
+                Ok(ExecSignal::Next)
+            }
+
+            Instruction::ExecFeed { cmd, input, dst_ec, dst_out } => {
+                let cmd_str = self.state.get_reg(cmd).to_str_val(&self.state.interner);
+                let input_str = self.state.get_reg(input).to_str_val(&self.state.interner);
+                self.deny.check(&cmd_str)?;
+                let limit = dst_out.is_some().then_some(&self.capture_limit);
+                let (exit_code, stdout, truncated) = exec_system_cmd_feed(&cmd_str, input_str, limit, &self.state);
+                self.state.set_reg(dst_ec, NanVal::num(exit_code as f64));
+                if let Some(dst_out) = dst_out {
+                    let out_val = self.state.intern_str_owned(stdout);
+                    self.state.set_reg(dst_out, out_val);
+                    let tr_key = self.var_key("_capture_truncated");
+                    self.state.set_var(tr_key, NanVal::bool(truncated));
+                }
                 self.state.last_exit = exit_code;
+                let le_idx = self.state.interner.intern("_last_exit_code");
+                self.state.set_var(le_idx, NanVal::num(exit_code as f64));
+                Ok(ExecSignal::Next)
+            }
+
+            // ── Katalog tymczasowy ────────────────────────────────────────────
+            Instruction::TempDirEnter { name } => {
+                let dir = TempDirGuard::enter(&self.tempdir_base)?;
+                let path = self.state.intern_str_owned(dir.path().display().to_string());
+                self.state.set_var(self.var_keys[name as usize], path);
+                self.tempdirs.push(dir);
+                Ok(ExecSignal::Next)
+            }
+            Instruction::TempDirLeave => {
+                self.tempdirs.pop();
                 Ok(ExecSignal::Next)
             }
 
+            // ── Try/catch ─────────────────────────────────────────────────────
+            Instruction::TryEnter { handler } => {
+                self.try_stack.push(TryFrame { handler, depth: self.state.call_depth, tempdirs: self.tempdirs.len() });
+                Ok(ExecSignal::Next)
+            }
+            Instruction::TryLeave => {
+                self.try_stack.pop();
+                Ok(ExecSignal::Next)
+            }
+            Instruction::CatchArm { kind, var, next } => {
+                let Some(err) = &self.pending_fault else { return Ok(ExecSignal::Jump(next)); };
+                let (fault_kind, message) = fault::classify(err);
+                let kind = kind.map(|k| self.const_str(k));
+                if !fault::catches(kind.as_deref(), fault_kind) {
+                    return Ok(ExecSignal::Jump(next));
+                }
+                self.pending_fault = None;
+                if let Some(var) = var {
+                    let val = self.state.intern_str_owned(message);
+                    self.state.set_var(self.var_keys[var as usize], val);
+                }
+                self.state.last_exit = 1;
+                let le_idx = self.var_key("_last_exit_code");
+                self.state.set_var(le_idx, NanVal::num(1.0));
+                Ok(ExecSignal::Next)
+            }
+            Instruction::Rethrow => match self.pending_fault.take() {
+                Some(err) => Err(err),
+                None      => Ok(ExecSignal::Next),
+            },
+
             // ── For-in ────────────────────────────────────────────────────────
-            Instruction::ForInStart { iter_reg, src } => {
+            Instruction::ForInStart { iter_reg, src, chars } => {
                 let src_str = self.state.get_reg(src).to_str_val(&self.state.interner);
                 // Intern każde słowo — szybsze porównania w pętli
-                let words: Vec<u32> = src_str.split_whitespace()
-                .map(|w| self.state.interner.intern(w))
-                .collect();
+                let words: Vec<u32> = if chars {
+                    let mut buf = [0u8; 4];
+                    src_str.chars().map(|c| self.state.interner.intern(c.encode_utf8(&mut buf))).collect()
+                } else {
+                    src_str.split_whitespace().map(|w| self.state.interner.intern(w)).collect()
+                };
                 self.state.iters.insert(iter_reg, (words, 0));
                 Ok(ExecSignal::Next)
             }
@@ -417,6 +779,16 @@ impl<'a> BytecodeInterpreter<'a> {
                 }
             }
 
+            Instruction::LoopGuard { counter, line } => {
+                let Some(limit) = self.loop_limit else { return Ok(ExecSignal::Next) };
+                let done = self.state.get_reg(counter).as_f64() as u64;
+                if done >= limit {
+                    return Err(hl_core::env::loop_limit_exceeded(line as usize, limit));
+                }
+                self.state.set_reg(counter, NanVal::num((done + 1) as f64));
+                Ok(ExecSignal::Next)
+            }
+
             // ── HackerOS API ──────────────────────────────────────────────────
             Instruction::HackerOsCall { tool, args, dst } => {
                 let tool_str = self.const_str(tool);
@@ -427,9 +799,10 @@ impl<'a> BytecodeInterpreter<'a> {
                     format!("{} {}", tool_str, args_str)
                 };
                 if which::which(&tool_str).is_err() {
-                    eprintln!("\x1b[33m[hl ||]\x1b[0m Narzędzie '{}' nie jest zainstalowane.", tool_str);
+                    eprintln!("{} Narzędzie '{}' nie jest zainstalowane.", "[hl ||]".yellow(), tool_str);
                     self.state.set_reg(dst, NanVal::num(127.0));
                 } else {
+                    self.deny.check(&cmd)?;
                     let ec = exec_system_cmd(&cmd, CmdMode::Plain, &mut self.state)?;
                     self.state.set_reg(dst, NanVal::num(ec as f64));
                 }
@@ -439,28 +812,52 @@ impl<'a> BytecodeInterpreter<'a> {
     }
 
     fn exec_func_by_name_idx(&mut self, name_idx: u32) -> Result<()> {
+        self.exec_func(self.const_str(name_idx))
+    }
+
+    fn exec_func(&mut self, name: String) -> Result<()> {
         self.state.check_call_depth()?;
-        let name = self.const_str(name_idx);
-        let entry = match self.module.funcs.find(&name) {
-            Some(e) => e.clone(),
-            None    => bail!("Niezdefiniowana funkcja: '{}'", name),
-        };
         self.state.call_depth += 1;
-        let start = entry.start_insn as usize;
-        let end   = start + entry.insn_count as usize;
-        let mut pc = start;
-        loop {
-            if pc >= end { break; }
-            match self.exec_insn(pc)? {
-                ExecSignal::Next             => pc += 1,
-                ExecSignal::Jump(off)        => pc = off as usize,
-                ExecSignal::Return           => break,
-                ExecSignal::Exit(code)       => { self.state.last_exit = code; break; }
-                ExecSignal::FuncCall(ni)     => { self.exec_func_by_name_idx(ni)?; pc += 1; }
+        let mut name = name;
+        let result = loop {
+            let entry = match self.module.funcs.find(&name) {
+                Some(e) => e.clone(),
+                None    => break Err(fault::raise(FaultKind::Name, format!("Niezdefiniowana funkcja: '{}'", name))),
+            };
+            let start = entry.start_insn as usize;
+            let end   = start + entry.insn_count as usize;
+            match self.exec_block(start, end) {
+                // Wywołanie ogonowe: ramka zostaje, głębokość się nie zmienia — rekurencja
+                // ogonowa działa jak pętla i nie dochodzi do MAX_CALL_DEPTH
+                Ok(ExecSignal::TailCall(next)) => name = self.const_str(next),
+                other => break other,
             }
-        }
+        };
         self.state.call_depth -= 1;
-        Ok(())
+        // Handlery funkcji, która wyszła (błędem albo Return wewnątrz `? try`), są martwe
+        let depth = self.state.call_depth;
+        self.try_stack.retain(|f| f.depth <= depth);
+        result.map(|_| ())
+    }
+
+    /// `CallFunc` pod `pc` w ciele funkcji, po którym zostaje już tylko powrót: same
+    /// skoki bezwarunkowe i markery aż do `Return` albo końca ciała. Nie wewnątrz `? try`
+    /// tej ramki — handler potrzebuje ramki, która go założyła.
+    fn is_tail_call(&self, pc: usize, end: usize) -> bool {
+        let depth = self.state.call_depth;
+        if depth == 0 || self.try_stack.last().is_some_and(|f| f.depth == depth) { return false; }
+        let mut at = pc + 1;
+        // Limit kroków — cykl samych skoków nie może zawiesić sprawdzania
+        for _ in 0..end.saturating_sub(pc) + 1 {
+            if at >= end { return true; }
+            match &self.module.instructions[at] {
+                Instruction::Return { .. } => return true,
+                Instruction::Jump { offset } => at = *offset as usize,
+                Instruction::Nop | Instruction::SourceLine { .. } => at += 1,
+                _ => return false,
+            }
+        }
+        false
     }
 
     #[inline]
@@ -472,11 +869,145 @@ impl<'a> BytecodeInterpreter<'a> {
     fn const_str_idx(&mut self, s: &str) -> u32 {
         self.state.interner.intern(s)
     }
+
+    /// Klucz zmiennej o nazwie z runtime — ten sam, którego używają GetVar/SetVar
+    fn var_key(&mut self, name: &str) -> u32 {
+        self.state.interner.intern(name)
+    }
+
+    /// `-- @f args` — lowering przekazuje nazwę zmiennej i argumenty. Referencja w VM to jej
+    /// tekst (`&nazwa` / `&nazwa(1 _)`), jak `Value::Func::to_string_val` w tree-walk.
+    fn quick_call(&mut self, arg: &str) -> Result<()> {
+        let (var, rest) = arg.trim_start().split_once(' ').unwrap_or((arg.trim(), ""));
+        let key = self.var_key(var);
+        let text = self.state.get_var(key).to_str_val(&self.state.interner);
+        let Some((name, bound)) = parse_func_ref(&text) else {
+            return Err(fault::raise(FaultKind::Type, format!("Wartość '{}' nie jest referencją do funkcji", text)));
+        };
+        let call_args: Vec<String> = rest.split_whitespace().map(str::to_string).collect();
+        self.call_with_args(name, &bound, &call_args)
+    }
+
+    /// `:: map <funkcja|@ref> elementy...` — funkcja wywoływana dla każdego elementu (@_arg0)
+    fn quick_map(&mut self, arg: &str) -> Result<()> {
+        let arg = arg.trim_start();
+        if arg.is_empty() { anyhow::bail!(":: map wymaga: :: map <funkcja|@ref> <elementy...>") }
+        // `&nazwa(a _)` ze spacjami w nawiasie to jedno słowo
+        let head_end = match arg.starts_with('&').then(|| arg.find('(')).flatten() {
+            Some(open) if !arg[..open].contains(char::is_whitespace) => arg.find(')').map_or(arg.len(), |c| c + 1),
+            _ => arg.find(char::is_whitespace).unwrap_or(arg.len()),
+        };
+        let (first, rest) = arg.split_at(head_end);
+        let words = rest.split_whitespace();
+        let (name, bound) = match parse_func_ref(first) {
+            Some(found) => found,
+            None => {
+                // Goła nazwa: zmienna z referencją przesłania funkcję o tej nazwie
+                let key = self.var_key(first);
+                let text = self.state.get_var(key).to_str_val(&self.state.interner);
+                match parse_func_ref(&text) {
+                    Some(found) => found,
+                    None if self.module.funcs.find(first).is_some() => (first.to_string(), Vec::new()),
+                    None => anyhow::bail!("'{}' nie jest funkcją ani referencją do funkcji", first),
+                }
+            }
+        };
+        for item in words {
+            self.call_with_args(name.clone(), &bound, &[item.to_string()])?;
+        }
+        Ok(())
+    }
+
+    /// `:: wait [PID]` — domyślnie ostatni `& cmd`; kod procesu w @_wait_exit i jako kod wyjścia
+    fn quick_wait(&mut self, arg: &str) -> Result<String> {
+        let pid_str = match arg.trim() {
+            "" => { let key = self.var_key("_bg_pid"); self.state.get_var(key).to_str_val(&self.state.interner) }
+            pid => pid.to_string(),
+        };
+        let Ok(pid) = pid_str.trim().parse::<u32>() else {
+            anyhow::bail!(":: wait: '{}' nie jest PID-em procesu tła", pid_str)
+        };
+        let code = wait_background(pid)?;
+        let key = self.var_key("_wait_exit");
+        self.state.set_var(key, NanVal::num(code as f64));
+        self.state.last_exit = code;
+        let le_key = self.var_key("_last_exit_code");
+        self.state.set_var(le_key, NanVal::num(code as f64));
+        Ok(code.to_string())
+    }
+
+    /// `:: at nazwa N` — słowo N (od 0) zmiennej; ujemne N liczy od końca, jak tree-walk
+    fn quick_at(&mut self, arg: &str) -> Result<String> {
+        let (name, idx) = arg.trim().rsplit_once(char::is_whitespace).unwrap_or((arg.trim(), ""));
+        let name = name.trim().trim_start_matches('@');
+        let Ok(idx) = idx.trim().parse::<i64>() else {
+            return Err(fault::raise(FaultKind::Type, format!(":: at {}: indeks '{}' nie jest liczbą całkowitą", name, idx.trim())));
+        };
+        let key   = self.var_key(name);
+        let text  = self.state.get_var(key).to_str_val(&self.state.interner);
+        let items: Vec<&str> = text.split_whitespace().collect();
+        let pos = if idx < 0 { (items.len() as i64).checked_add(idx) } else { Some(idx) };
+        match pos.and_then(|p| usize::try_from(p).ok()).and_then(|p| items.get(p)) {
+            Some(item) => Ok(item.to_string()),
+            None => Err(fault::raise(FaultKind::Index, format!(
+                ":: at {}: indeks {} poza zakresem (elementów: {})", name, idx, items.len()))),
+        }
+    }
+
+    /// Ustaw @_argc / @_arg0.. (argumenty związane najpierw, jak w tree-walk) i wywołaj funkcję
+    fn call_with_args(&mut self, name: String, bound: &[String], call_args: &[String]) -> Result<()> {
+        if self.module.funcs.find(&name).is_none() {
+            return Err(fault::raise(FaultKind::Name, format!("Niezdefiniowana funkcja: '{}'", name)));
+        }
+        let args = hl_core::executor::apply_partial(bound, call_args);
+        let argc = self.var_key("_argc");
+        self.state.set_var(argc, NanVal::num(args.len() as f64));
+        for (i, a) in args.into_iter().enumerate() {
+            let key = self.var_key(&format!("_arg{}", i));
+            let val = self.state.intern_str_owned(a);
+            self.state.set_var(key, val);
+        }
+        self.exec_func(name)
+    }
+
+    /// `:: serialize nazwa` — ten sam format co tree-walk (hl_core::wire)
+    fn quick_serialize(&mut self, arg: &str) -> String {
+        let key = self.var_key(arg.trim().trim_start_matches('@'));
+        let val = self.state.get_var(key);
+        let w = if val.is_nil() { WireValue::Nil }
+            else if val.is_bool() { WireValue::Bool(val.is_truthy(&self.state.interner)) }
+            else if val.is_num() || val.is_int() { WireValue::Num(val.as_f64()) }
+            else { WireValue::Str(val.to_str_val(&self.state.interner)) };
+        wire::encode(&w).unwrap_or_else(|e| { eprintln!("{} :: serialize: {}", "[hl jit]".red(), e); String::new() })
+    }
+
+    /// `:: deserialize @blob cel` — VM nie ma list, więc lista trafia do `cel` jako tekst
+    fn quick_deserialize(&mut self, arg: &str) {
+        let Some((blob, target)) = arg.trim().rsplit_once(' ') else {
+            eprintln!("{} :: deserialize wymaga: :: deserialize @blob <zmienna>", "[hl jit]".red());
+            return;
+        };
+        let w = match wire::decode(blob) {
+            Ok(w)  => w,
+            Err(e) => { eprintln!("{} :: deserialize: {}", "[hl jit]".red(), e); return; }
+        };
+        let val = match w {
+            WireValue::Nil     => NanVal::nil(),
+            WireValue::Bool(b) => NanVal::bool(b),
+            WireValue::Num(n)  => NanVal::num(n),
+            other => {
+                let text = hl_core::Value::from(other).to_string_val();
+                self.state.intern_str_owned(text)
+            }
+        };
+        let key = self.var_key(target.trim().trim_start_matches('@'));
+        self.state.set_var(key, val);
+    }
 }
 
 // ── Komendy systemowe ─────────────────────────────────────────────────────────
 
-fn exec_system_cmd(cmd: &str, mode: CmdMode, _state: &mut RuntimeState) -> Result<i32> {
+fn exec_system_cmd(cmd: &str, mode: CmdMode, state: &mut RuntimeState) -> Result<i32> {
     // Specjalne prefiksy z lowera
     if let Some(path) = cmd.strip_prefix("__hl_import__ ") {
         // Import w czasie wykonania — placeholder, obsługiwany przez tree-walk executor
@@ -485,46 +1016,88 @@ fn exec_system_cmd(cmd: &str, mode: CmdMode, _state: &mut RuntimeState) -> Resul
         return Ok(0);
     }
     if let Some(rest) = cmd.strip_prefix("& ") {
-        let _ = Command::new("sh").args(["-c", rest])
+        // Jak tree-walk: PID w @_bg_pid, proces we wspólnym rejestrze dla `:: wait`
+        let child = state.apply_env(&mut Command::new("sh")).args(["-c", rest.trim()])
         .stdin(Stdio::null()).stdout(Stdio::inherit()).stderr(Stdio::inherit())
-        .spawn();
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Błąd tła: {}", e))?;
+        let pid = register_background(child);
+        let key = state.interner.intern("_bg_pid");
+        state.set_var(key, NanVal::num(pid as f64));
+        eprintln!("{}", format!("[hl &] PID={}", pid).bright_black());
         return Ok(0);
     }
 
     let (prog, args, needs_sh) = build_cmd_parts(cmd, mode);
-    let status = if needs_sh {
-        Command::new("sh").args(["-c", cmd])
-        .stdin(Stdio::inherit()).stdout(Stdio::inherit()).stderr(Stdio::inherit())
-        .status()
-    } else {
-        Command::new(&prog).args(&args)
-        .stdin(Stdio::inherit()).stdout(Stdio::inherit()).stderr(Stdio::inherit())
-        .status()
-    };
-
+    if !needs_sh { return Ok(spawn_argv(&prog, &args, state)); }
+    let status = state.apply_env(&mut Command::new("sh")).args(["-c", cmd])
+    .stdin(Stdio::inherit()).stdout(Stdio::inherit()).stderr(Stdio::inherit())
+    .status();
     match status {
-        Ok(s)  => Ok(s.code().unwrap_or(1)),
+        Ok(s)  => Ok(status_code(s)),
         Err(e) => {
-            eprintln!("\x1b[31m[hl jit]\x1b[0m Błąd komendy: {}", e);
+            eprintln!("{} Błąd komendy: {}", "[hl jit]".red(), e);
             Ok(1)
         }
     }
 }
 
-fn exec_system_cmd_capture(cmd: &str, mode: CmdMode) -> Result<(i32, String)> {
+/// Uruchom program z gotowym argv (bez powłoki), stdio odziedziczone; kod wyjścia
+fn spawn_argv<S: AsRef<std::ffi::OsStr>>(prog: &str, args: impl IntoIterator<Item = S>, state: &RuntimeState) -> i32 {
+    let status = state.apply_env(&mut Command::new(prog)).args(args)
+    .stdin(Stdio::inherit()).stdout(Stdio::inherit()).stderr(Stdio::inherit())
+    .status();
+    match status {
+        Ok(s)  => status_code(s),
+        Err(e) => {
+            eprintln!("{} Błąd komendy: {}", "[hl jit]".red(), e);
+            1
+        }
+    }
+}
+
+/// `>> cmd |> @var` — stdout przez `capture_output` z tree-walk, z tym samym limitem;
+/// (kod, przycięte wyjście, czy obcięte)
+fn exec_system_cmd_capture(cmd: &str, mode: CmdMode, state: &RuntimeState, limit: &CaptureLimit) -> Result<(i32, String, bool)> {
     let (prog, args, needs_sh) = build_cmd_parts(cmd, mode);
-    let out = if needs_sh {
-        Command::new("sh").args(["-c", cmd])
-        .stdin(Stdio::inherit()).stdout(Stdio::piped()).stderr(Stdio::piped())
-        .output()
-    } else {
-        Command::new(&prog).args(&args)
-        .stdin(Stdio::inherit()).stdout(Stdio::piped()).stderr(Stdio::piped())
-        .output()
+    let mut command = if needs_sh { Command::new("sh") } else { Command::new(&prog) };
+    if needs_sh { command.args(["-c", cmd]); } else { command.args(&args); }
+    state.apply_env(&mut command);
+    command.stdin(Stdio::inherit()).stdout(Stdio::piped()).stderr(Stdio::inherit());
+    match capture_output(command, limit, None) {
+        Ok(r)  => Ok((r.exit_code, r.stdout.unwrap_or_default().trim().to_string(), r.truncated)),
+        Err(e) => { eprintln!("{} Capture error: {}", "[hl jit]".red(), e); Ok((1, String::new(), false)) }
+    }
+}
+
+/// `@dane |> cmd` — `input` na stdin (piped, pisane z osobnego wątku); stdout przechwycony
+/// z limitem gdy podany `limit`
+fn exec_system_cmd_feed(cmd: &str, input: String, limit: Option<&CaptureLimit>, state: &RuntimeState) -> (i32, String, bool) {
+    use std::io::Write;
+    let (prog, args, needs_sh) = build_cmd_parts(cmd, CmdMode::WithVars);
+    let mut command = if needs_sh { Command::new("sh") } else { Command::new(&prog) };
+    if needs_sh { command.args(["-c", cmd]); } else { command.args(&args); }
+    state.apply_env(&mut command);
+    command.stdin(Stdio::piped()).stderr(Stdio::inherit());
+    if let Some(limit) = limit {
+        command.stdout(Stdio::piped());
+        return match capture_output(command, limit, Some(input)) {
+            Ok(r)  => (r.exit_code, r.stdout.unwrap_or_default().trim().to_string(), r.truncated),
+            Err(e) => { eprintln!("{} Błąd komendy: {}", "[hl jit]".red(), e); (1, String::new(), false) }
+        };
+    }
+    command.stdout(Stdio::inherit());
+    let mut child = match command.spawn() {
+        Ok(c)  => c,
+        Err(e) => { eprintln!("{} Błąd komendy: {}", "[hl jit]".red(), e); return (1, String::new(), false); }
     };
-    match out {
-        Ok(o)  => Ok((o.status.code().unwrap_or(1), String::from_utf8_lossy(&o.stdout).trim().to_string())),
-        Err(e) => { eprintln!("\x1b[31m[hl jit]\x1b[0m Capture error: {}", e); Ok((1, String::new())) }
+    let mut stdin = child.stdin.take().expect("stdin ustawione na piped");
+    let writer = std::thread::spawn(move || { let _ = stdin.write_all(input.as_bytes()); });
+    let status = child.wait();
+    let _ = writer.join();
+    match status {
+        Ok(s)  => (status_code(s), String::new(), false),
+        Err(e) => { eprintln!("{} Błąd komendy: {}", "[hl jit]".red(), e); (1, String::new(), false) }
     }
 }
 
@@ -538,7 +1111,7 @@ fn build_cmd_parts(cmd: &str, mode: CmdMode) -> (String, Vec<String>, bool) {
             if needs_sh {
                 ("sudo".into(), vec!["sh".into(), "-c".into(), cmd.into()], false)
             } else {
-                let parts = split_cmd(cmd);
+                let parts = split_words(cmd);
                 ("sudo".into(), parts, false)
             }
         }
@@ -547,6 +1120,11 @@ fn build_cmd_parts(cmd: &str, mode: CmdMode) -> (String, Vec<String>, bool) {
             .into_iter().map(|s| s.to_string()).collect();
             ("unshare".into(), a, false)
         }
+        CmdMode::Raw => {
+            let mut parts = split_words(cmd);
+            let prog = if parts.is_empty() { String::new() } else { parts.remove(0) };
+            (prog, parts, false)
+        }
         CmdMode::IsolatedSudo => {
             let a = vec!["unshare","--mount","--pid","--net","--fork","--","sh","-c",cmd]
             .into_iter().map(|s| s.to_string()).collect();
@@ -556,7 +1134,7 @@ fn build_cmd_parts(cmd: &str, mode: CmdMode) -> (String, Vec<String>, bool) {
             if needs_sh {
                 (String::new(), vec![], true) // caller uses sh -c
             } else {
-                let mut parts = split_cmd(cmd);
+                let mut parts = split_words(cmd);
                 let prog = if parts.is_empty() { String::new() } else { parts.remove(0) };
                 (prog, parts, false)
             }
@@ -564,25 +1142,6 @@ fn build_cmd_parts(cmd: &str, mode: CmdMode) -> (String, Vec<String>, bool) {
     }
 }
 
-fn split_cmd(cmd: &str) -> Vec<String> {
-    let mut parts = Vec::new();
-    let mut cur = String::new();
-    let mut in_sq = false;
-    let mut in_dq = false;
-    for c in cmd.chars() {
-        match c {
-            '\'' if !in_dq => in_sq = !in_sq,
-            '"'  if !in_sq => in_dq = !in_dq,
-            ' ' | '\t' if !in_sq && !in_dq => {
-                if !cur.is_empty() { parts.push(std::mem::take(&mut cur)); }
-            }
-            _ => cur.push(c),
-        }
-    }
-    if !cur.is_empty() { parts.push(cur); }
-    parts
-}
-
 // ── Quick functions ───────────────────────────────────────────────────────────
 
 fn exec_quick_fn(name: &str, arg: &str, state: &mut RuntimeState) -> String {
@@ -592,6 +1151,15 @@ fn exec_quick_fn(name: &str, arg: &str, state: &mut RuntimeState) -> String {
         "len"      => arg.len().to_string(),
         "trim"     => arg.trim().to_string(),
         "rev"      => arg.chars().rev().collect(),
+        // Jak tree-walk (hl_core::quick w trybie przechwycenia): separator to ostatnie słowo
+        "split"    => {
+            let (text, sep) = arg.trim().rsplit_once(' ').map_or((arg.trim(), ""), |(t, s)| (t.trim(), s.trim()));
+            text.split(sep).collect::<Vec<_>>().join("\n")
+        }
+        "replace"  => match arg.trim().splitn(3, ' ').collect::<Vec<_>>().as_slice() {
+            [text, from, to] => text.replace(from, to),
+            _ => { eprintln!("{} :: replace wymaga: :: replace <text> <from> <to>", "[hl jit]".red()); String::new() }
+        },
         "abs"      => arg.parse::<f64>().unwrap_or(0.0).abs().to_string(),
         "ceil"     => arg.parse::<f64>().unwrap_or(0.0).ceil().to_string(),
         "floor"    => arg.parse::<f64>().unwrap_or(0.0).floor().to_string(),
@@ -604,11 +1172,11 @@ fn exec_quick_fn(name: &str, arg: &str, state: &mut RuntimeState) -> String {
             let w: usize = arg.parse().unwrap_or(60);
             println!("{}", "─".repeat(w)); String::new()
         }
-        "bold"     => { println!("\x1b[1m{}\x1b[0m", arg); String::new() }
-        "red"      => { println!("\x1b[31m{}\x1b[0m", arg); String::new() }
-        "green"    => { println!("\x1b[32m{}\x1b[0m", arg); String::new() }
-        "yellow"   => { println!("\x1b[33m{}\x1b[0m", arg); String::new() }
-        "cyan"     => { println!("\x1b[36m{}\x1b[0m", arg); String::new() }
+        "bold"     => { println!("{}", arg.bold()); String::new() }
+        "red"      => { println!("{}", arg.red()); String::new() }
+        "green"    => { println!("{}", arg.green()); String::new() }
+        "yellow"   => { println!("{}", arg.yellow()); String::new() }
+        "cyan"     => { println!("{}", arg.cyan()); String::new() }
         "exists"   => {
             let e = std::path::Path::new(arg).exists();
             let k = state.interner.intern("_last_bool");
@@ -618,7 +1186,12 @@ fn exec_quick_fn(name: &str, arg: &str, state: &mut RuntimeState) -> String {
         "isdir"    => { let e = std::path::Path::new(arg).is_dir();  e.to_string() }
         "isfile"   => { let e = std::path::Path::new(arg).is_file(); e.to_string() }
         "which"    => which::which(arg).map(|p| p.display().to_string()).unwrap_or_default(),
-        "env" | "getenv" => std::env::var(arg).unwrap_or_default(),
+        "env" | "getenv" => state.env_var(arg).unwrap_or_default(),
+        // Argumenty skryptu — z RuntimeState (BytecodeInterpreter::with_args)
+        "args"     => state.script_args.join(" "),
+        "arg"      => arg.trim().parse::<usize>().ok()
+            .and_then(|i| state.script_args.get(i).cloned())
+            .unwrap_or_default(),
         // ::env-path — ścieżka aktywnego środowiska z config.hk, zero subprocess
         "env-path" => {
             use hl_core::config::get_active_env;
@@ -651,65 +1224,557 @@ fn exec_quick_fn(name: &str, arg: &str, state: &mut RuntimeState) -> String {
             let r = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407) % 100;
             r.to_string()
         }
-        "date"     => { let o = Command::new("date").arg("+%Y-%m-%d").output().ok(); o.map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string()).unwrap_or_default() }
-        "time"     => { let o = Command::new("date").arg("+%H:%M:%S").output().ok(); o.map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string()).unwrap_or_default() }
+        "date"     => { let o = state.apply_env(&mut Command::new("date")).arg("+%Y-%m-%d").output().ok(); o.map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string()).unwrap_or_default() }
+        "time"     => { let o = state.apply_env(&mut Command::new("date")).arg("+%H:%M:%S").output().ok(); o.map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string()).unwrap_or_default() }
         _          => {
-            eprintln!("\x1b[31m[hl jit]\x1b[0m Nieznana quick-funkcja '::{}'", name);
+            eprintln!("{} Nieznana quick-funkcja '::{}'", "[hl jit]".red(), name);
             String::new()
         }
     }
 }
 
+/// Tekst referencji `&nazwa` / `&nazwa(a _ b)` → (nazwa, argumenty związane)
+fn parse_func_ref(text: &str) -> Option<(String, Vec<String>)> {
+    let body = text.trim().strip_prefix('&')?;
+    let (name, bound) = match body.split_once('(') {
+        Some((name, rest)) => (name, rest.strip_suffix(')')?.split_whitespace().map(str::to_string).collect()),
+        None               => (body, Vec::new()),
+    };
+    (!name.is_empty() && !name.contains(char::is_whitespace)).then(|| (name.to_string(), bound))
+}
+
 // ── Ewaluacja warunków while ──────────────────────────────────────────────────
 
-fn eval_condition_str(cond: &str, state: &mut RuntimeState) -> bool {
+fn eval_condition_str(cond: &str, state: &mut RuntimeState, deny: &DenyList) -> Result<bool> {
     let cond = cond.trim();
-    if cond.is_empty() { return false; }
-    if cond == "true"  { return true;  }
-    if cond == "false" { return false; }
+    if let Some(b) = hl_parser::truth::literal_condition(cond) { return Ok(b); }
 
-    const OPS: &[&str] = &["==", "!=", ">=", "<=", ">", "<"];
-    for op in OPS {
-        if let Some(pos) = find_op(cond, op) {
-            let left_raw  = cond[..pos].trim();
-            let right_raw = cond[pos + op.len()..].trim().trim_matches('"');
-
-            let lv = if let Some(name) = left_raw.strip_prefix('@') {
+    // `a < b < c` = `a < b && b < c` (hl_parser::truth::comparison_chain)
+    if let Some((operands, ops)) = hl_parser::truth::comparison_chain(cond) {
+        let mut lv = match operands[0].strip_prefix('@') {
+            Some(name) => {
                 let k = state.interner.intern(name);
                 state.get_var(k).to_str_val(&state.interner)
-            } else {
-                left_raw.to_string()
-            };
-
-            return match *op {
-                "==" => lv == right_raw,
-                "!=" => lv != right_raw,
-                ">=" => lv.parse::<f64>().unwrap_or(0.0) >= right_raw.parse::<f64>().unwrap_or(0.0),
-                "<=" => lv.parse::<f64>().unwrap_or(0.0) <= right_raw.parse::<f64>().unwrap_or(0.0),
-                ">"  => lv.parse::<f64>().unwrap_or(0.0) >  right_raw.parse::<f64>().unwrap_or(0.0),
-                "<"  => lv.parse::<f64>().unwrap_or(0.0) <  right_raw.parse::<f64>().unwrap_or(0.0),
-                _    => false,
-            };
+            }
+            None => operands[0].to_string(),
+        };
+        for (op, right) in ops.iter().zip(&operands[1..]) {
+            let rv = right.trim_matches('"');
+            if !hl_parser::truth::compare_text(&lv, op, rv) { return Ok(false); }
+            lv = rv.to_string();
         }
+        return Ok(true);
     }
 
     // Fallback shell
-    Command::new("sh").args(["-c", cond]).status().map(|s| s.success()).unwrap_or(false)
+    deny.check(cond)?;
+    Ok(state.apply_env(&mut Command::new("sh")).args(["-c", cond]).status().map(|s| s.success()).unwrap_or(false))
 }
 
-fn find_op(s: &str, op: &str) -> Option<usize> {
-    let b = s.as_bytes(); let ob = op.as_bytes(); let ol = ob.len();
-    let mut i = 0;
-    while i + ol <= b.len() {
-        if &b[i..i+ol] == ob {
-            let ok = match op {
-                ">" => i + 1 >= b.len() || b[i+1] != b'=',
-                "<" => i + 1 >= b.len() || b[i+1] != b'=',
-                _   => true,
-            };
-            if ok { return Some(i); }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hl_test_support::TestDir;
+
+    fn run(src: &str) -> i32 {
+        let nodes = hl_parser::parse_source(src).unwrap();
+        let module = hl_compiler::lower_ast(&nodes, std::path::Path::new("test.hl"), 2);
+        BytecodeInterpreter::new(&module).run().unwrap()
+    }
+
+    #[test]
+    fn test_and_chain_skips_after_failure() {
+        let dir = TestDir::new("chain");
+        let marker = dir.join("marker");
+        let code = run(&format!("> false\n&& > touch {}\n", marker.display()));
+        assert!(!marker.exists(), "Druga komenda nie powinna się wykonać");
+        assert_eq!(code, 1, "Pominięta komenda zachowuje kod wyjścia `false`");
+    }
+
+    #[test]
+    fn test_or_chain_runs_after_skipped_and() {
+        // false && a || b — jak w bash, `b` się wykonuje
+        assert_eq!(run("> false\n&& > false\n|| > true\n"), 0);
+    }
+
+    #[test]
+    fn test_capture_limit_truncates_vm_capture() {
+        let src = ">> yes hl |> @out\n% dane = ala ma kota\n@dane |> cat |> @feed\n";
+        let nodes = hl_parser::parse_source(src).unwrap();
+        let module = hl_compiler::lower_ast(&nodes, std::path::Path::new("test.hl"), 2);
+        let limit = CaptureLimit { max_bytes: 8, overflow: hl_core::executor::CaptureOverflow::Truncate };
+        let mut interp = BytecodeInterpreter::new(&module).with_capture_limit(limit);
+        assert_eq!(interp.run().unwrap(), 0);
+        let get = |interp: &mut BytecodeInterpreter, name: &str| {
+            let key = interp.var_key(name);
+            interp.state.get_var(key).to_str_val(&interp.state.interner)
+        };
+        assert_eq!(get(&mut interp, "out"), format!("hl\nhl\nhl{}", hl_core::executor::truncation_marker(8)).trim());
+        // Drugie przechwycenie (feed) też obcięte: "ala ma k" + znacznik
+        assert!(get(&mut interp, "feed").starts_with("ala ma k\n[hl:"), "{}", get(&mut interp, "feed"));
+        assert_eq!(get(&mut interp, "_capture_truncated"), "true");
+    }
+
+    #[test]
+    fn test_output_limit_suppresses_print() {
+        let nodes = hl_parser::parse_source("_1000 ~> linia\n").unwrap();
+        let module = hl_compiler::lower_ast(&nodes, std::path::Path::new("test.hl"), 2);
+        let mut interp = BytecodeInterpreter::new(&module).with_output_limit(32);
+        assert_eq!(interp.run().unwrap(), 0);
+        assert!(interp.output_limit.as_ref().is_some_and(|l| l.truncated()));
+    }
+
+    #[test]
+    fn test_deny_blocks_shell_condition() {
+        let dir = TestDir::new("deny-cond");
+        let marker = dir.join("marker");
+        let nodes = hl_parser::parse_source(&format!("?~ touch {} && false\n  > true\ndone\n", marker.display())).unwrap();
+        let module = hl_compiler::lower_ast(&nodes, std::path::Path::new("test.hl"), 2);
+        let err = BytecodeInterpreter::new(&module).with_deny(DenyList::new(["touch"])).run().unwrap_err();
+        assert!(err.to_string().contains("--deny 'touch'"), "{}", err);
+        assert!(!marker.exists(), "Zabroniona komenda w warunku nie może się wykonać");
+    }
+
+    #[test]
+    fn test_exit_ends_script_from_function() {
+        let src = ": f def\n    > exit 3\n    % po = tak\ndone\n-- f\n> true\n";
+        assert_eq!(run(src), 3);
+        assert_eq!(run("> exit\n> false\n"), 0);
+        assert_eq!(vm_var(src, "po"), "");
+    }
+
+    #[test]
+    fn test_frozen_env_export_does_not_leak_to_next_script() {
+        let run_frozen = |src: &str, var: &str| {
+            let nodes = hl_parser::parse_source(src).unwrap();
+            let module = hl_compiler::lower_ast(&nodes, std::path::Path::new("test.hl"), 2);
+            let mut interp = BytecodeInterpreter::new(&module);
+            interp.state.frozen_env = Some(std::env::vars().collect());
+            interp.run().unwrap();
+            let key = interp.var_key(var);
+            interp.state.get_var(key).to_str_val(&interp.state.interner)
+        };
+        // Komenda potomna widzi eksport, proces hl — nie
+        assert_eq!(run_frozen("=> HL_FROZEN_VM_TEST = tak\n> printenv HL_FROZEN_VM_TEST |> @seen\n", "seen"), "tak");
+        assert!(std::env::var("HL_FROZEN_VM_TEST").is_err());
+        assert_eq!(run_frozen("> printenv HL_FROZEN_VM_TEST |> @seen\n", "seen"), "");
+    }
+
+    #[test]
+    fn test_wait_background_exit_code() {
+        let src = "& exit 3\n:: wait @_bg_pid\n? err\n    % w = @_wait_exit\ndone\n";
+        assert_eq!(vm_var(src, "w"), "3");
+        assert_eq!(run("& exit 5\n:: wait\n"), 5);
+    }
+
+    #[test]
+    fn test_script_args_keep_boundaries_and_stay_out_of_process_env() {
+        let src = ":: arg 0 |> @a\n:: arg 1 |> @b\n:: arg 5 |> @missing\n:: args |> @all\n";
+        let module = hl_compiler::lower_ast(&hl_parser::parse_source(src).unwrap(), std::path::Path::new("test.hl"), 2);
+        let mut vm = BytecodeInterpreter::new(&module).with_args(&["a b".into(), "c".into()]);
+        vm.run().unwrap();
+        let mut get = |name: &str| { let k = vm.var_key(name); let v = vm.state.get_var(k); vm.state.val_to_str(v) };
+        assert_eq!(get("a"), "a b");
+        assert_eq!(get("b"), "c");
+        assert_eq!(get("missing"), "");
+        assert_eq!(get("all"), "a b c");
+        assert_eq!(get("argc"), "2");
+        assert!(std::env::var("arg0").is_err());
+    }
+
+    #[test]
+    fn test_echo_rejected_like_tree_walk() {
+        for src in ["> echo hi\n", ">> echo @x\n"] {
+            let module = hl_compiler::lower_ast(&hl_parser::parse_source(src).unwrap(), std::path::Path::new("test.hl"), 2);
+            let err = BytecodeInterpreter::new(&module).run().unwrap_err();
+            assert!(err.to_string().contains("'echo' jest zabroniony"), "{}: {}", src, err);
+            assert!(hl_core::run_source(src, &mut hl_core::env::Env::new()).is_err(), "{}", src);
         }
-        i += 1;
     }
-    None
+
+    #[test]
+    fn test_raw_mode_passes_dollar_literally() {
+        // Bez powłoki oba argumenty to dosłowne "$HOME"
+        assert_eq!(run("> test \"$HOME\" = '$HOME'\n"), 0);
+        assert_ne!(run(">> test \"$HOME\" = '$HOME'\n"), 0);
+    }
+
+    #[test]
+    fn test_tree_walk_serialized_value_deserializes_in_vm() {
+        use hl_core::{env::Env, Value};
+        let mut env = Env::new();
+        env.set_var("lista", Value::List(vec![
+            Value::String("ala".into()), Value::Number(2.5), Value::List(vec![Value::Bool(true)]),
+        ]));
+        hl_core::run_source(":: serialize lista |> @blob", &mut env).unwrap();
+        let blob = env.get_var("blob").to_string_val();
+        assert_eq!(run(&format!(":: deserialize {} x\n> test \"@x\" = \"ala 2.5 true\"\n", blob)), 0);
+        // I w drugą stronę: bajty z VM czyta tree-walk
+        let vm_blob = wire::encode(&WireValue::Num(7.0)).unwrap();
+        hl_core::run_source(&format!(":: deserialize {} n", vm_blob), &mut env).unwrap();
+        assert!(matches!(env.get_var("n"), Value::Number(n) if *n == 7.0));
+    }
+
+    #[test]
+    fn test_feed_writes_value_to_stdin() {
+        assert_eq!(run("% dane = ala ma kota\n@dane |> grep -q kota\n"), 0);
+        assert_ne!(run("% dane = ala ma kota\n@dane |> grep -q psa\n"), 0);
+    }
+
+    #[test]
+    fn test_tempdir_is_cwd_and_removed() {
+        let dir = TestDir::new("tempdir-vm");
+        let base = dir.path().to_path_buf();
+        let src = "? tempdir @d\n> touch plik\n> test -f @d/plik\n% kod = @_last_exit_code\ndone\n";
+        let module = hl_compiler::lower_ast(&hl_parser::parse_source(src).unwrap(), std::path::Path::new("test.hl"), 2);
+        let mut vm = BytecodeInterpreter::new(&module).with_tempdir_base(base.clone());
+        assert_eq!(vm.run().unwrap(), 0);
+        let mut get = |name: &str| { let k = vm.var_key(name); let v = vm.state.get_var(k); vm.state.val_to_str(v) };
+        assert_eq!(get("kod"), "0");
+        assert!(std::path::Path::new(&get("d")).starts_with(&base));
+        assert!(std::fs::read_dir(&base).unwrap().next().is_none(), "katalog tymczasowy nie usunięty");
+    }
+
+    #[test]
+    fn test_env_scope_ignores_local_shadow() {
+        // HOME procesu jest niepusty, lokalny pusty — `test -n` sprawdza, z którego czytamy
+        assert_eq!(run("% HOME =\n> test -n \"@env:HOME\"\n"), 0);
+        assert_ne!(run("% HOME =\n> test -n \"@local:HOME\"\n"), 0);
+    }
+
+    #[test]
+    fn test_math_builtins() {
+        assert_eq!(run("$( sqrt(9.0) ) -> @a\n> test @a = 3\n"), 0);
+        assert_eq!(run("$( abs(-3) + max(2, 5) ) -> @b\n> test @b = 8\n"), 0);
+        // Błąd dziedziny — nil, wypisywany jako pusty tekst
+        assert_eq!(run("$( sqrt(-1) ) -> @c\n> test -z \"@c\"\n"), 0);
+    }
+
+    #[test]
+    fn test_while_condition_truthiness() {
+        let src = "% n = 3\n% i = 0\n?~ @n\n    $( @n - 1 ) -> @n\n    $( @i + 1 ) -> @i\ndone\n\
+                   % zero = nie\n?~ 0\n    % zero = tak\ndone\n% s = x\n?~ @s\n    % s = 0\n    % napis = tak\ndone\n\
+                   > test @i = 3\n&& > test @zero = nie\n&& > test @napis = tak\n";
+        assert_eq!(run(src), 0);
+    }
+
+    #[test]
+    fn test_trace_lists_ops_and_registers() {
+        #[derive(Clone, Default)]
+        struct Buf(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+        impl std::io::Write for Buf {
+            fn write(&mut self, b: &[u8]) -> std::io::Result<usize> { self.0.lock().unwrap().extend_from_slice(b); Ok(b.len()) }
+            fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+        }
+        let buf = Buf::default();
+        let nodes = hl_parser::parse_source("% x = 2\n$( @x + 3 ) -> @y\n").unwrap();
+        let module = hl_compiler::lower_ast(&nodes, std::path::Path::new("test.hl"), 2);
+        BytecodeInterpreter::new(&module)
+            .with_trace(Some(Tracer::new(Box::new(buf.clone()), false)))
+            .run().unwrap();
+        let trace = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        let ops: Vec<&str> = trace.lines()
+            .filter_map(|l| l.split_whitespace().nth(3))
+            .map(|op| op.trim_end_matches(|c: char| !c.is_alphanumeric()))
+            .collect();
+        let add = ops.iter().position(|op| *op == "Add").expect(&trace);
+        assert!(ops[..add].contains(&"GetVar"), "{}", trace);
+        assert_eq!(ops.get(add + 1), Some(&"SetVar"), "{}", trace);
+        // Przed Add oba argumenty leżą w rejestrach, przed SetVar — także wynik
+        let line = |op: &str| trace.lines().find(|l| l.contains(&format!(" {} ", op))).unwrap();
+        assert!(line("Add").contains("r2=2 r3=3"), "{}", trace);
+        assert!(trace.lines().any(|l| l.contains(" SetVar ") && l.contains("r4=5") && l.ends_with("@y=nil")), "{}", trace);
+    }
+
+    #[test]
+    fn test_modulo_and_bitwise_operators() {
+        for (expr, want) in [("7 % 3", 1.0), ("6 & 3", 2.0), ("1 << 4", 16.0), ("8 | 6 & 3", 10.0), ("1 << 1 + 1", 4.0)] {
+            let nodes = hl_parser::parse_source(&format!("% a = 6\n$( {} ) -> @w\n", expr.replacen('6', "@a", 1))).unwrap();
+            let mut module = hl_compiler::lower_ast(&nodes, std::path::Path::new("test.hl"), 2);
+            for optimized in [false, true] {
+                if optimized { hl_compiler::optimize_module(&mut module); }
+                let mut vm = BytecodeInterpreter::new(&module);
+                vm.run().unwrap();
+                let w = vm.var_key("w");
+                assert_eq!(vm.state.get_var(w).as_f64(), want, "{} (optimized: {})", expr, optimized);
+            }
+        }
+        let nodes = hl_parser::parse_source("% a = 1.5\n$( @a & 1 ) -> @w\n").unwrap();
+        let module = hl_compiler::lower_ast(&nodes, std::path::Path::new("test.hl"), 2);
+        let err = BytecodeInterpreter::new(&module).run().unwrap_err();
+        assert_eq!(fault::classify(&err).0, FaultKind::Type, "{}", err);
+    }
+
+    /// Wartość zmiennej po wykonaniu skryptu w VM
+    fn vm_var(src: &str, name: &str) -> String {
+        let module = hl_compiler::lower_ast(&hl_parser::parse_source(src).unwrap(), std::path::Path::new("test.hl"), 2);
+        let mut vm = BytecodeInterpreter::new(&module);
+        vm.run().unwrap();
+        let key = vm.var_key(name);
+        let val = vm.state.get_var(key);
+        vm.state.val_to_str(val)
+    }
+
+    #[test]
+    fn test_split_and_replace_quick_functions() {
+        assert_eq!(vm_var("% csv = a,b\n@csv |> :: split , |> @czesci", "czesci"), "a\nb");
+        assert_eq!(vm_var("% sep = ,\n@sep |> :: split x,y _ |> @czesci", "czesci"), "x\ny");
+        assert_eq!(vm_var(":: replace hello l L |> @r", "r"), "heLLo");
+    }
+
+    #[test]
+    fn test_func_ref_call_and_map() {
+        // Definicja przed użyciem, jak w tree-walk — kod główny przeskakuje ciało
+        let src = ": add def\n$( @_arg0 + @_arg1 ) -> @sum\ndone\n% inc = &add(1 _)\n-- @inc 41";
+        assert_eq!(vm_var(src, "sum"), "42");
+        let src = ": collect def\n% out = \"@out @_arg0\"\ndone\n% f = &collect\n:: map @f a b c";
+        assert_eq!(vm_var(src, "out").trim(), "a b c");
+        let src = ":: map collect x y\n: collect def\n% out = \"@out @_arg0\"\ndone\n";
+        assert_eq!(vm_var(src, "out").trim(), "x y");
+        let src = "% tag = &pair(_ x)\n:: map @tag 1 2\n: pair def\n% out = \"@out @_arg0-@_arg1\"\ndone\n";
+        assert_eq!(vm_var(src, "out").trim(), "1-x 2-x");
+
+        let module = hl_compiler::lower_ast(&hl_parser::parse_source("% f = tekst\n-- @f").unwrap(), std::path::Path::new("test.hl"), 2);
+        let err = BytecodeInterpreter::new(&module).run().unwrap_err();
+        assert_eq!(fault::classify(&err).0, FaultKind::Type, "{}", err);
+    }
+
+    #[test]
+    fn test_tail_recursion_does_not_grow_call_depth() {
+        let src = "% n = 5000\n-- odliczaj\n\
+                   : odliczaj def\n    $( @n - 1 ) -> @n\n    ? switch @n\n    | 0\n        % koniec = tak\n    | *\n        -- odliczaj\n    done\ndone\n";
+        let module = hl_compiler::lower_ast(&hl_parser::parse_source(src).unwrap(), std::path::Path::new("test.hl"), 2);
+        let mut vm = BytecodeInterpreter::new(&module);
+        vm.run().unwrap();
+        let n = vm.var_key("n");
+        assert_eq!(vm.state.get_var(n).as_f64(), 0.0);
+        assert_eq!(vm.state.call_depth, 0);
+
+        // Wywołanie, po którym funkcja jeszcze coś robi, nie jest ogonowe — limit głębokości działa
+        let src = "-- f\n: f def\n    -- f\n    % po = tak\ndone\n";
+        let module = hl_compiler::lower_ast(&hl_parser::parse_source(src).unwrap(), std::path::Path::new("test.hl"), 2);
+        assert!(BytecodeInterpreter::new(&module).run().is_err());
+    }
+
+    #[test]
+    fn test_exit_status_binding() {
+        assert_eq!(run("% a = ? > false\n> test @a = 1\n"), 0);
+        assert_eq!(run("% a = ? > true\n> test @a = 0\n"), 0);
+    }
+
+    #[test]
+    fn test_exit_status_binding_matches_tree_walk() {
+        // `>` to argv bez powłoki — glob trafia do `test` dosłownie; przypisany kod nie jest kodem skryptu
+        for src in ["% c = ? > test -e /tm*\n", "% d = ? > false\n"] {
+            let mut env = hl_core::env::Env::new();
+            let tree = hl_core::run_source(src, &mut env).unwrap();
+            let var = if src.contains("% c") { "c" } else { "d" };
+            assert_eq!(vm_var(src, var), env.get_var(var).to_string_val(), "{}", src);
+            assert_eq!(run(src), tree.exit_code, "{}", src);
+        }
+        // Jak każda deklaracja w tree-walk: po przypisaniu `? ok` jest prawdą
+        let src = "> false\n% d = ? > false\n? ok\n    % r = ok\ndone\n? err\n    % r = err\ndone\n";
+        let mut env = hl_core::env::Env::new();
+        hl_core::run_source(src, &mut env).unwrap();
+        assert_eq!(env.get_var("r").to_string_val(), "ok");
+        assert_eq!(vm_var(src, "r"), "ok");
+    }
+
+    #[test]
+    fn test_nil_propagates_through_arithmetic() {
+        // Błąd dziedziny daje nil w całym wyrażeniu; niezdefiniowana zmienna to nadal 0
+        for expr in ["sqrt(-1) + 1", "1 - sqrt(-1)", "sqrt(-1) * 2", "2 / sqrt(-1)", "-sqrt(-1)", "sqrt(-1) % 2", "@u + 1", "-@n - 2"] {
+            let src = format!("% n = 3\n$( {} ) -> @r\n", expr);
+            let mut env = hl_core::env::Env::new();
+            hl_core::run_source(&src, &mut env).unwrap();
+            assert_eq!(vm_var(&src, "r"), env.get_var("r").to_string_val(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_chained_comparison_means_and() {
+        // `0 <= @i < 3` to `0 <= @i && @i < 3`; `(0 <= @i) < 3` dałoby pętlę bez końca
+        assert_eq!(run("% i = 0\n?~ 0 <= @i < 3\n    $( @i + 1 ) -> @i\ndone\n> test @i = 3\n"), 0);
+        let src = "? switch x\n| x when 1 < 2 < 3\n    % r = tak\n| *\n    % r = nie\ndone\n> test @r = tak\n";
+        assert_eq!(run(src), 0);
+    }
+
+    #[test]
+    fn test_placeholder_is_shell_quoted() {
+        let dir = TestDir::new("jit-quote");
+        let marker = dir.join("marker");
+        let src = format!("% v = \"it's; touch {}\"\n>> printf '%s' {{v}} |> @out\n> test {{out}} = {{v}}\n", marker.display());
+        assert_eq!(run(&src), 0);
+        assert!(!marker.exists(), "Wartość {{v}} nie może wykonać się jako osobna komenda");
+    }
+
+    #[test]
+    fn test_try_catch_name_error() {
+        let src = ": f def\n    -- brak\n    % po = tak\ndone\n\
+                   ? try\n    -- f\ndone\n? catch IndexError\n    % arm = index\ndone\n? catch NameError @e\n    % arm = name\ndone\n\
+                   > test @arm = name\n&& > test @_error_kind = NameError\n&& > test -z \"@po\"\n";
+        assert_eq!(run(src), 0);
+
+        let nodes = hl_parser::parse_source("? try\n    -- brak\ndone\n? catch TypeError\n    % x = 1\ndone\n").unwrap();
+        let module = hl_compiler::lower_ast(&nodes, std::path::Path::new("test.hl"), 2);
+        let err = BytecodeInterpreter::new(&module).run().expect_err("NameError powinien przejść dalej");
+        assert_eq!(fault::classify(&err).0, FaultKind::Name);
+    }
+
+    #[test]
+    fn test_index_out_of_range_fault() {
+        let src = "% xs = \"1 2 3\"\n:: at xs -1 |> @last\n? try\n    :: at xs 5\n    % po = tak\ndone\n\
+                   ? catch NameError\n    % arm = name\ndone\n? catch IndexError @e\n    % arm = index\ndone\n\
+                   > test {last}{arm} = 3index\n&& > test @_error_kind = IndexError\n&& > test -z \"@po\"\n";
+        assert_eq!(run(src), 0);
+    }
+
+    #[test]
+    fn test_negative_index_matches_tree_walk() {
+        // -1 to ostatni element; -5 przy trzech elementach to IndexError w obu runtime'ach
+        let src = "% xs = \"1 2 3\"\n:: at xs -1 |> @last\n? try\n    :: at xs -5\ndone\n? catch IndexError\n    % poza = tak\ndone\n";
+        let mut env = hl_core::env::Env::new();
+        hl_core::run_source(src, &mut env).unwrap();
+        for var in ["last", "poza"] {
+            assert_eq!(vm_var(src, var), env.get_var(var).to_string_val(), "{}", var);
+        }
+        assert_eq!(vm_var(src, "last"), "3");
+    }
+
+    #[test]
+    fn test_checked_arithmetic_fault() {
+        let src = "% n = 9223372036854775807\n? try\n    $( @n * 4 ) -> @x\ndone\n? catch ArithmeticError\n    % x = blad\ndone\n> test @x = blad\n";
+        let nodes = hl_parser::parse_source(src).unwrap();
+        let mut module = hl_compiler::lower_ast(&nodes, std::path::Path::new("test.hl"), 2);
+        hl_compiler::optimize_module(&mut module);
+        assert_eq!(BytecodeInterpreter::new(&module).with_checked(true).run().unwrap(), 0);
+        assert_eq!(BytecodeInterpreter::new(&module).with_checked(false).run().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_numeric_equality_ignores_notation() {
+        let src = "% n = 3\n% wynik = nie\n?~ @n == 3.0\n    % wynik = tak\n    % n = 0\ndone\n\
+                   $( 6 / 2 ) -> @x\n% sw = nie\n? switch @x\n| 3.0\n    % sw = tak\n| *\n    % sw = inne\ndone\n";
+        assert_eq!(run(&format!("{}> test {{wynik}}{{sw}} = taktak\n", src)), 0);
+    }
+
+    #[test]
+    fn test_switch_when_guard() {
+        let src = "? switch @kod\n| 0 when @verbose\n    % r = szczegoly\n| 0\n    % r = ok\n\
+                   | * when @kod > 100\n    % r = krytyczny\n| *\n    % r = blad\n| *\n    % r = drugi\ndone\n";
+        for (pre, want) in [("% kod = 0\n% verbose = true\n", "szczegoly"), ("% kod = 0\n% verbose = false\n", "ok"),
+                            ("% kod = 500\n% verbose = true\n", "krytyczny"), ("% kod = 5\n% verbose = true\n", "blad")] {
+            assert_eq!(run(&format!("{}{}> test {{r}} = {}\n", pre, src, want)), 0, "{}", want);
+        }
+    }
+
+    #[test]
+    fn test_nan_and_infinity_stay_numbers() {
+        for n in [f64::NAN, -f64::NAN, f64::INFINITY - f64::INFINITY, f64::from_bits(0x7FF8_0000_0000_0002)] {
+            let v = NanVal::num(n);
+            assert!(v.is_num() && !v.is_nil() && !v.is_str() && !v.is_int() && !v.is_bool(), "{:?}", v);
+            assert!(v.as_f64().is_nan());
+        }
+        for n in [f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(NanVal::num(n).is_num());
+            assert_eq!(NanVal::num(n).as_f64(), n);
+        }
+
+        // inf - inf w skompilowanej trasie — NaN prosto z FPU, z pominięciem NanVal::num
+        let regs = run_trace(f64::INFINITY, f64::INFINITY, |dst, a, b| Instruction::Sub { dst, a, b });
+        assert!(regs[2].is_num() && regs[2].as_f64().is_nan(), "{:?}", regs[2]);
+    }
+
+    /// Trasa `r0 = a; r1 = b; r2 = op(r0, r1)` wywołana bezpośrednio, bez pętli i progu
+    fn run_trace(a: f64, b: f64, op: impl Fn(u32, u32, u32) -> Instruction) -> [NanVal; 3] {
+        let mut module = hl_compiler::bytecode::HlModule::new("test.hl", 2);
+        let (ia, ib) = (module.consts.add_num(a), module.consts.add_num(b));
+        module.instructions = vec![
+            Instruction::LoadNum { dst: 0, idx: ia },
+            Instruction::LoadNum { dst: 1, idx: ib },
+            op(2, 0, 1),
+        ];
+        let entry = hl_compiler::bytecode::FuncEntry { name: "trasa".into(), start_insn: 0, insn_count: 3 };
+        let trace = crate::jit_engine::compile_trace_entry(&module, &entry).unwrap();
+        let mut regs = [NanVal::nil(); 3];
+        // SAFETY: trasa dotyka tylko rejestrów 0..3, zmiennych nie używa
+        unsafe { (trace.fn_ptr)(regs.as_mut_ptr() as *mut u64, std::ptr::null_mut(), 3, 0) };
+        regs
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        let src = "% n = 0\n$( 10 / @n ) -> @a\n$( 10 / 0.0 ) -> @b\n$( 2.5 / 0.0 ) -> @c\n$( 7 % @n ) -> @d\n";
+        let nodes = hl_parser::parse_source(&format!("{}> test {{a}}{{b}}{{c}}{{d}} = 0000\n", src)).unwrap();
+        let module = hl_compiler::lower_ast(&nodes, std::path::Path::new("test.hl"), 2);
+        assert_eq!(BytecodeInterpreter::new(&module).with_checked(false).run().unwrap(), 0);
+
+        // checked: każdy przypadek to osobny ArithmeticError
+        for expr in ["10 / @n", "10 / 0.0", "2.5 / 0.0", "7 % @n"] {
+            let src = format!("% n = 0\n% x = ok\n? try\n    $( {} ) -> @x\ndone\n? catch ArithmeticError\n    % x = blad\ndone\n> test @x = blad\n", expr);
+            let module = hl_compiler::lower_ast(&hl_parser::parse_source(&src).unwrap(), std::path::Path::new("test.hl"), 2);
+            assert_eq!(BytecodeInterpreter::new(&module).with_checked(true).run().unwrap(), 0, "{}", expr);
+        }
+
+        for (a, b) in [(10.0, 0.0), (2.5, 0.0), (-1.0, -0.0)] {
+            let regs = run_trace(a, b, |dst, a, b| Instruction::Div { dst, a, b });
+            assert_eq!(regs[2].as_f64(), 0.0, "{} / {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_inlined_function_matches_call() {
+        let src = "% x = 1\n-- inc\n?~ @x < 5\n    -- inc\ndone\n> test @x = 5\n: inc def\n    $( @x + 1 ) -> @x\ndone\n";
+        let nodes = hl_parser::parse_source(src).unwrap();
+        let module = hl_compiler::lower_ast(&nodes, std::path::Path::new("test.hl"), 2);
+        let mut inlined = module.clone();
+        hl_compiler::optimize_module(&mut inlined);
+        assert!(!inlined.instructions.iter().any(|i| matches!(i, Instruction::CallFunc { .. })));
+        for m in [&module, &inlined] {
+            let mut vm = BytecodeInterpreter::new(m);
+            assert_eq!(vm.run().unwrap(), 0);
+            let x = vm.var_key("x");
+            assert_eq!(vm.state.get_var(x).as_f64(), 5.0);
+        }
+    }
+
+    #[test]
+    fn test_inlined_function_still_callable_by_reference() {
+        let src = "% x = 1\n-- inc\n% f = &inc\n-- @f\n: inc def\n    $( @x + 1 ) -> @x\ndone\n";
+        let mut module = hl_compiler::lower_ast(&hl_parser::parse_source(src).unwrap(), std::path::Path::new("test.hl"), 2);
+        hl_compiler::optimize_module(&mut module);
+        assert!(!module.instructions.iter().any(|i| matches!(i, Instruction::CallFunc { .. })));
+        let mut vm = BytecodeInterpreter::new(&module);
+        assert_eq!(vm.run().unwrap(), 0);
+        let x = vm.var_key("x");
+        assert_eq!(vm.state.get_var(x).as_f64(), 3.0);
+    }
+
+    #[test]
+    fn test_constant_command_split_once() {
+        let module = |src: &str| hl_compiler::lower_ast(&hl_parser::parse_source(src).unwrap(), std::path::Path::new("test.hl"), 2);
+        let constant = module("@ i in a b c d e\n    > test x = x\ndone\n");
+        assert!(constant.instructions.iter().any(|i| matches!(i, Instruction::ExecArgv { .. })));
+        assert!(!constant.instructions.iter().any(|i| matches!(i, Instruction::ExecCmd { .. })));
+        let mut vm = BytecodeInterpreter::new(&constant);
+        assert_eq!(vm.run().unwrap(), 0);
+        assert_eq!(vm.cmd_splits, 0);
+
+        // Ze zmienną tekst komendy znany jest dopiero w runtime — dzielony w każdej iteracji
+        let dynamic = module("@ i in a b c d e\n    > test @i = @i\ndone\n");
+        let mut vm = BytecodeInterpreter::new(&dynamic);
+        assert_eq!(vm.run().unwrap(), 0);
+        assert_eq!(vm.cmd_splits, 5);
+    }
+
+    #[test]
+    fn test_loop_limit_aborts_runaway_loop() {
+        let src = "% n = 0\n@ w in a b c\n    % ok = {w}\ndone\n?~ true\n    $( @n + 1 ) -> @n\ndone\n";
+        let nodes = hl_parser::parse_source(src).unwrap();
+        let mut module = hl_compiler::lower_ast(&nodes, std::path::Path::new("test.hl"), 2);
+        hl_compiler::optimize_module(&mut module);
+        let err = BytecodeInterpreter::new(&module).with_loop_limit(Some(100)).run()
+            .expect_err("pętla bez końca musi się przerwać");
+        assert!(err.to_string().contains("Pętla w linii 5: przekroczono limit 100 iteracji"), "{}", err);
+    }
 }
@@ -34,7 +34,7 @@ impl JitEngine {
         Self {
             call_counts: HashMap::new(),
             compiled:    HashMap::new(),
-            enabled:     !std::env::var("HL_NO_JIT").is_ok(),
+            enabled:     std::env::var("HL_NO_JIT").is_err(),
         }
     }
 
@@ -188,7 +188,7 @@ fn is_jit_eligible(module: &HlModule, entry: &FuncEntry) -> bool {
             Instruction::Sub { .. } |
             Instruction::Mul { .. } |
             Instruction::Div { .. } |
-            Instruction::Mod { .. } |
+            // Mod zostaje w interpreterze — reszta z i64 i `% 0` bez odpowiednika tutaj
             Instruction::Neg { .. } |
             Instruction::CmpEq { .. } |
             Instruction::CmpNe { .. } |
@@ -201,6 +201,8 @@ fn is_jit_eligible(module: &HlModule, entry: &FuncEntry) -> bool {
             Instruction::JumpIfFalse { .. } |
             Instruction::JumpIfTrue { .. } |
             Instruction::Return { .. } |
+            // bez limitu pętli no-op; z limitem interpreter nie kompiluje tras
+            Instruction::LoopGuard { .. } |
             Instruction::Nop => {}
 
             // Niedozwolone — fallback do interpretera
@@ -299,6 +301,15 @@ fn compile_insn(
             }
         };
     }
+    // Wynik arytmetyki: NaN z FPU → CANONICAL_NAN, inaczej rejestr czytałby się jako nil/str
+    macro_rules! canon {
+        ($val:expr) => {{
+            let val   = $val;
+            let isnan = builder.ins().fcmp(cranelift_codegen::ir::condcodes::FloatCC::Unordered, val, val);
+            let nan   = builder.ins().f64const(f64::from_bits(crate::runtime::CANONICAL_NAN));
+            builder.ins().select(isnan, nan, val)
+        }};
+    }
 
     match insn {
         Instruction::LoadNum { dst, idx } => {
@@ -322,26 +333,35 @@ fn compile_insn(
         Instruction::Add { dst, a, b } => {
             let va = gv!(*a); let vb = gv!(*b);
             let r  = builder.ins().fadd(va, vb);
+            let r  = canon!(r);
             dv!(*dst, r);
         }
         Instruction::Sub { dst, a, b } => {
             let va = gv!(*a); let vb = gv!(*b);
             let r  = builder.ins().fsub(va, vb);
+            let r  = canon!(r);
             dv!(*dst, r);
         }
         Instruction::Mul { dst, a, b } => {
             let va = gv!(*a); let vb = gv!(*b);
             let r  = builder.ins().fmul(va, vb);
+            let r  = canon!(r);
             dv!(*dst, r);
         }
         Instruction::Div { dst, a, b } => {
             let va = gv!(*a); let vb = gv!(*b);
             let r  = builder.ins().fdiv(va, vb);
+            let r  = canon!(r);
+            // `x / 0` → 0 jak w interpreterze (tryb checked nie kompiluje tras)
+            let zero    = builder.ins().f64const(0.0);
+            let by_zero = builder.ins().fcmp(cranelift_codegen::ir::condcodes::FloatCC::Equal, vb, zero);
+            let r       = builder.ins().select(by_zero, zero, r);
             dv!(*dst, r);
         }
         Instruction::Neg { dst, src } => {
             let v = gv!(*src);
             let r = builder.ins().fneg(v);
+            let r = canon!(r);
             dv!(*dst, r);
         }
         Instruction::CmpLt { dst, a, b } => {
@@ -380,6 +400,11 @@ fn insn_regs(insn: &Instruction) -> Vec<u32> {
         Instruction::Mul { dst, a, b } |
         Instruction::Div { dst, a, b } |
         Instruction::Mod { dst, a, b } |
+        Instruction::BitAnd { dst, a, b } |
+        Instruction::BitOr { dst, a, b } |
+        Instruction::BitXor { dst, a, b } |
+        Instruction::Shl { dst, a, b } |
+        Instruction::Shr { dst, a, b } |
         Instruction::CmpEq { dst, a, b } |
         Instruction::CmpNe { dst, a, b } |
         Instruction::CmpLt { dst, a, b } |
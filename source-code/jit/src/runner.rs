@@ -1,7 +1,7 @@
 use anyhow::Result;
 use colored::Colorize;
 use hl_compiler::{compile_to_cache, read_bc_file, HlModule};
-use hl_core::{env::Env, Value};
+use hl_core::env::Env;
 use crate::interpreter::BytecodeInterpreter;
 use std::path::Path;
 
@@ -36,9 +36,6 @@ pub fn run_hl_file(path: &Path, args: &[String]) -> Result<i32> {
 pub fn run_hl_source(source: &str, source_path: &Path, args: &[String]) -> Result<i32> {
     tracing::debug!("run_hl_source: {:?}", source_path);
 
-    // Zawsze ustawiamy zmienne procesu (dla kompatybilności z BytecodeInterpreter)
-    inject_args_to_env(args);
-
     let line_count = source.lines().count();
 
     if line_count > BC_LINE_THRESHOLD {
@@ -51,10 +48,17 @@ pub fn run_hl_source(source: &str, source_path: &Path, args: &[String]) -> Resul
 
     // Mały plik — kompiluj do .bc z timeoutem
     match compile_with_timeout(source, source_path, std::time::Duration::from_secs(30)) {
-        Ok(bc_path) => {
-            let module = read_bc_file(&bc_path)?;
-            run_bc_module(&module, args)
-        }
+        Ok(bc_path) => match read_bc_file(&bc_path) {
+            Ok(module) => run_bc_module(&module, args),
+            Err(e) => {
+                // Np. brakująca wersja puli stringów — zgłoś, usuń wpis (następne
+                // uruchomienie skompiluje od nowa) i wykonaj przez AST executor
+                eprintln!("{} Odczyt .bc z cache nieudany ({:#}), używam interpretera tree-walk",
+                          "[hl jit]".yellow(), e);
+                let _ = hl_compiler::cache::remove_entry(&bc_path);
+                run_via_ast(source, source_path, args)
+            }
+        },
         Err(e) => {
             tracing::warn!("BC compile failed ({}), fallback do AST executor", e);
             run_via_ast(source, source_path, args)
@@ -73,10 +77,8 @@ fn run_via_ast(source: &str, _source_path: &Path, args: &[String]) -> Result<i32
     let mut env = Env::new();
 
     // Wstrzyknij argumenty bezpośrednio do Env — niezawodne, nie zależy od process env
-    env.set_var("argc", Value::Number(args.len() as f64));
-    for (i, arg) in args.iter().enumerate() {
-        env.set_var(&format!("arg{}", i), Value::String(arg.clone()));
-    }
+    env.set_script_args(args);
+    env.output_limit = hl_core::executor::OutputLimit::from_env().map(std::sync::Arc::new);
 
     match run_source(source, &mut env) {
         Ok(result) => Ok(result.exit_code),
@@ -128,23 +130,14 @@ pub fn run_bc_file(path: &Path, args: &[String]) -> Result<i32> {
 
 /// Uruchom załadowany moduł bytecode przez interpreter + JIT
 pub fn run_bc_module(module: &HlModule, args: &[String]) -> Result<i32> {
-    inject_args_to_env(args);
-    let mut interp = BytecodeInterpreter::new(module);
+    let mut interp = BytecodeInterpreter::new(module).with_args(args);
     let exit_code = interp.run()?;
     Ok(exit_code)
 }
 
-/// Ustaw zmienne procesu dla BytecodeInterpreter (który czyta std::env::var)
-fn inject_args_to_env(args: &[String]) {
-    std::env::set_var("argc", args.len().to_string());
-    for (i, arg) in args.iter().enumerate() {
-        std::env::set_var(format!("arg{}", i), arg);
-    }
-}
-
 /// Wypisz statystyki cache (dla `hl cache-info`)
 pub fn print_cache_stats() {
-    use hl_compiler::cache::{cache_dir, cache_list, CACHE_MAX_FILES};
+    use hl_compiler::cache::{cache_dir, cache_list, format_age, CacheKind, CACHE_MAX_FILES};
     let dir = cache_dir();
     println!("{}", "=== Cache bytecode ===".bright_cyan().bold());
     println!("  Katalog: {}", dir.display().to_string().bright_white());
@@ -158,7 +151,9 @@ pub fn print_cache_stats() {
                 return;
             }
             let total_size: u64 = entries.iter().map(|e| e.size).sum();
-            println!("  Pliki: {}", entries.len().to_string().bright_white());
+            let count = |kind| entries.iter().filter(|e| e.kind == kind).count();
+            println!("  Pliki: {} (.bc: {}, .ast: {}, wersje puli: {})", entries.len().to_string().bright_white(),
+                     count(CacheKind::Bytecode), count(CacheKind::Ast), count(CacheKind::PoolVersion));
             println!("  Rozmiar łączny: {} KB",
                      (total_size / 1024).to_string().bright_yellow());
             println!();
@@ -166,7 +161,8 @@ pub fn print_cache_stats() {
                 let name = entry.path.file_name()
                 .and_then(|n| n.to_str())
                 .unwrap_or("?");
-                println!("  {} ({} B)", name.bright_white(), entry.size);
+                println!("  {} ({} B, {} temu) {}", name.bright_white(), entry.size,
+                         format_age(entry.age()), entry.source.as_deref().unwrap_or("?").bright_black());
             }
             if entries.len() > 10 {
                 println!("  {} ... i {} więcej",
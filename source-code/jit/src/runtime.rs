@@ -11,6 +11,11 @@ const TAG_BOOL: u64 = 0x0001;
 const TAG_STR:  u64 = 0x0002;
 const TAG_INT:  u64 = 0x0003;
 
+/// Jedyny NaN przechowywany jako liczba: sygnalizujący (bit quiet = 0), więc nie pasuje
+/// do NAN_BASE. Każdy inny NaN (`0.0/0.0`, `inf - inf`, ujemny NaN z FPU) ma bit quiet
+/// i bez kanonizacji zostałby odczytany jako nil/bool/str/int.
+pub const CANONICAL_NAN: u64 = 0x7FF0_0000_0000_0001;
+
 /// Wartość jako NaN-boxed u64 — 8 bajtów, zero alokacji dla liczb/boolów/intów
 #[derive(Clone, Copy)]
 #[repr(transparent)]
@@ -24,15 +29,10 @@ impl NanVal {
         NanVal(NAN_BASE | TAG_BOOL | ((b as u64) << PAYLOAD_SHIFT))
     }
 
+    /// Nieskończoności mają zerową mantysę i zostają bez zmian; NaN → CANONICAL_NAN
     #[inline(always)]
     pub fn num(n: f64) -> Self {
-        let bits = n.to_bits();
-        // Unikaj kolizji z NaN sentinel
-        if (bits & NAN_BASE) == NAN_BASE {
-            NanVal(0.0f64.to_bits())
-        } else {
-            NanVal(bits)
-        }
+        if n.is_nan() { NanVal(CANONICAL_NAN) } else { NanVal(n.to_bits()) }
     }
 
     #[inline(always)]
@@ -77,7 +77,7 @@ impl NanVal {
         if self.is_int()  { return self.payload() != 0; }
         if self.is_str()  {
             let s = interner.get(self.payload() as u32);
-            return !s.is_empty() && s != "false" && s != "0";
+            return hl_parser::truth::text_is_truthy(s);
         }
         false
     }
@@ -99,16 +99,18 @@ impl NanVal {
         String::new()
     }
 
-    /// Równość — fast path dla stringów przez idx
+    /// Równość — fast path dla stringów przez idx; liczby (także zapisane
+    /// jako tekst) wartością, jak `hl_parser::truth::text_equals` w tree-walk
     #[inline]
     pub fn eq_val(&self, other: &NanVal, interner: &StringInterner) -> bool {
-        if self.is_num() && other.is_num() {
+        let numeric = |v: &NanVal| v.is_num() || v.is_int();
+        if numeric(self) && numeric(other) {
             return self.as_f64() == other.as_f64();
         }
-        if self.is_str() && other.is_str() {
-            return self.payload() == other.payload(); // u32 porównanie!
+        if self.is_str() && other.is_str() && self.payload() == other.payload() {
+            return true; // u32 porównanie!
         }
-        self.to_str_val(interner) == other.to_str_val(interner)
+        hl_parser::truth::text_equals(&self.to_str_val(interner), &other.to_str_val(interner))
     }
 }
 
@@ -236,6 +238,10 @@ pub struct RuntimeState {
     pub call_depth: u32,
     /// Iterator state: iter_reg → (interned word idxs, current pos)
     pub iters: FxHashMap<u32, (Vec<u32>, usize)>,
+    /// Prywatne środowisko (hl run --frozen-env) — jak Env::frozen_env w tree-walk
+    pub frozen_env: Option<FxHashMap<String, String>>,
+    /// Argumenty skryptu (:: arg N / :: args) — osobno, bez łączenia spacjami
+    pub script_args: Vec<String>,
 }
 
 const MAX_CALL_DEPTH: u32 = 512;
@@ -251,6 +257,8 @@ impl RuntimeState {
             last_exit: 0,
             call_depth: 0,
             iters:     FxHashMap::default(),
+            frozen_env: hl_core::env::frozen_env_from_env(),
+            script_args: Vec::new(),
         }
     }
 
@@ -280,7 +288,7 @@ impl RuntimeState {
         }
         // 3. Fallback: std::env
         let name = self.interner.get(name_idx).to_string();
-        if let Ok(val) = std::env::var(&name) {
+        if let Some(val) = self.env_var(&name) {
             let idx = self.interner.intern_owned(val);
             return NanVal::str_interned(idx);
         }
@@ -314,10 +322,29 @@ impl RuntimeState {
     pub fn export_var(&mut self, name_idx: u32, val: NanVal) {
         let name    = self.interner.get(name_idx).to_string();
         let val_str = val.to_str_val(&self.interner);
-        std::env::set_var(&name, &val_str);
+        match &mut self.frozen_env {
+            Some(map) => { map.insert(name, val_str); }
+            None      => std::env::set_var(&name, &val_str),
+        }
         self.set_var(name_idx, val);
     }
 
+    /// Zmienna środowiskowa — z prywatnej kopii w trybie --frozen-env, inaczej z procesu
+    pub fn env_var(&self, name: &str) -> Option<String> {
+        match &self.frozen_env {
+            Some(map) => map.get(name).cloned(),
+            None      => std::env::var(name).ok(),
+        }
+    }
+
+    /// Ustaw środowisko procesu potomnego: w trybie --frozen-env tylko prywatna kopia
+    pub fn apply_env<'c>(&self, cmd: &'c mut std::process::Command) -> &'c mut std::process::Command {
+        if let Some(map) = &self.frozen_env {
+            cmd.env_clear().envs(map);
+        }
+        cmd
+    }
+
     pub fn val_to_str(&self, val: NanVal) -> String { val.to_str_val(&self.interner) }
 
     #[inline]
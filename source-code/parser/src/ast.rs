@@ -8,6 +8,7 @@ pub enum VarType {
 }
 
 impl VarType {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         match s.trim() {
             "str" | "string" => VarType::String,
@@ -24,6 +25,8 @@ impl VarType {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatchArm {
     pub pattern: String,
+    /// `| wzorzec when warunek` — ramię wybierane tylko, gdy warunek (jak w `?~`) jest prawdziwy
+    pub guard:   Option<Vec<StringPart>>,
     pub body:    Vec<Node>,
 }
 
@@ -35,6 +38,7 @@ pub enum HackerOsTool {
 }
 
 impl HackerOsTool {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         match s.trim() {
             "H#"               => HackerOsTool::HHash,
@@ -140,8 +144,8 @@ impl ArenaSize {
 impl std::fmt::Display for ArenaSize {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let b = self.0;
-        if b >= 1024*1024 && b%(1024*1024)==0 { write!(f, "{}m", b/(1024*1024)) }
-        else if b >= 1024 && b%1024==0         { write!(f, "{}k", b/1024) }
+        if b >= 1024*1024 && b.is_multiple_of(1024 * 1024) { write!(f, "{}m", b/(1024*1024)) }
+        else if b >= 1024 && b.is_multiple_of(1024)         { write!(f, "{}k", b/1024) }
         else                                   { write!(f, "{}", b) }
     }
 }
@@ -170,6 +174,21 @@ pub enum Node {
     HshCommand  { raw: String },
     Background  { raw: String },
     RepeatN     { count: u64, body: Vec<Node> },
+    /// ? retry N [opóźnienie] ... done — ponawiaj ciało aż ostatnia instrukcja
+    /// zakończy się kodem 0, najwyżej `count` prób, z `delay_ms` przerwy między nimi
+    Retry       { count: u32, delay_ms: u64, body: Vec<Node> },
+    /// ? bench nazwa ... done — mikrobenchmark; `hl run` go pomija, `hl bench` mierzy
+    Bench       { name: String, body: Vec<Node> },
+    /// ? tempdir @d ... done — ciało z nowym pustym katalogiem jako cwd, ścieżka w @d;
+    /// katalog znika po bloku, także gdy ciało zakończy się błędem
+    TempDir     { var: String, body: Vec<Node> },
+    /// ? try ... done, po nim jeden lub więcej `? catch [Rodzaj] [@e] ... done`.
+    /// Łapie błędy wykonania (nie kody wyjścia — od nich jest `? err`);
+    /// pierwszy pasujący `catch` wykonuje się, bez pasującego błąd leci dalej
+    Try         { body: Vec<Node>, catches: Vec<CatchArm> },
+    /// && instrukcja / || instrukcja — jak w bash: pominięta instrukcja
+    /// nie zmienia ostatniego kodu wyjścia, więc łańcuch `&&`/`||` działa dalej
+    Chain       { op: ChainOp, node: Box<Node> },
     VarDecl     { name: String, typ: VarType, value: VarValue },
     Export      { name: String, value: ExportValue },
     VarRef      (String),
@@ -186,8 +205,11 @@ pub enum Node {
     DirImport   { path: String },
 
     // Zwykła funkcja (gen 1+2): : nazwa def ... done
-    FuncDef     { name: String, body: Vec<Node> },
+    // `public` — `: pub nazwa def`; w module z choć jednym `pub` tylko one są eksportowane
+    FuncDef     { name: String, public: bool, body: Vec<Node> },
     FuncCall    { name: String },
+    /// -- @f args — wywołanie funkcji przez referencję trzymaną w zmiennej
+    FuncRefCall { var: String, args: Vec<StringPart> },
 
     // Arena function (gen 2): :: nazwa <rozmiar> def ... done
     //
@@ -199,11 +221,14 @@ pub enum Node {
     ArenaFuncCall { name: String, args: Vec<StringPart> },
 
     Conditional { condition: ConditionKind, body: Vec<Node> },
-    ForIn       { var: String, iterable: Vec<StringPart>, body: Vec<Node> },
-    WhileLoop   { condition: Vec<StringPart>, body: Vec<Node> },
+    /// `line` — linia nagłówka pętli (0 bez pozycji), do komunikatu limitu iteracji
+    ForIn       { var: String, iterable: Vec<StringPart>, each: ForEach, body: Vec<Node>, line: usize },
+    WhileLoop   { condition: Vec<StringPart>, body: Vec<Node>, line: usize },
     MatchExpr   { subject: Vec<StringPart>, arms: Vec<MatchArm> },
     Arithmetic  { expr: String, assign_to: Option<String> },
     PipeToVar   { command: String, mode: CommandMode, var_name: String },
+    /// @dane |> cmd [|> @var] — string wartości @dane trafia na stdin komendy (jak `>>`)
+    Feed        { source: String, command: String, var_name: Option<String> },
     HackerOsApi { tool: HackerOsTool, args: Vec<StringPart> },
     Goroutine   { name: Option<String>, body: Vec<Node> },
     ChannelOp   { name: String, value: Option<Vec<StringPart>> },
@@ -212,6 +237,8 @@ pub enum Node {
     DocComment  (String),
     LineComment  (String),
     Block       (Vec<Node>),
+    /// Marker linii źródła przed instrukcją — tylko z parse_source_with_lines (coverage)
+    SourceLine  (usize),
 
     // ── extern system (gen 2+) ────────────────────────────────────────────────
     // _> plik [runtime] def ... done
@@ -226,6 +253,9 @@ pub enum Node {
     },
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ChainOp { And, Or }
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ExportValue {
     Single(Vec<StringPart>),
@@ -241,6 +271,16 @@ pub enum CommandMode {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ConditionKind { Ok, Err }
 
+/// Po czym iteruje for-in
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum ForEach {
+    /// `@ x in ...` — słowa rozdzielone białymi znakami
+    #[default]
+    Words,
+    /// `@ x chars ...` — pojedyncze znaki Unicode (char, nie bajty), łącznie ze spacjami
+    Chars,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StringPart {
     Literal(String),
@@ -251,6 +291,12 @@ pub enum StringPart {
     DynVar(Vec<StringPart>),
 }
 
+impl StringPart {
+    /// Czy część wymaga rozwiązania w runtime (Var lub DynVar)
+    #[inline]
+    pub fn is_ref(&self) -> bool { !matches!(self, StringPart::Literal(_)) }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum VarValue {
     String(String),
@@ -263,6 +309,29 @@ pub enum VarValue {
     List(Vec<VarValue>),
     Map(Vec<(String, VarValue)>),
     Arithmetic(String),
+    /// `% kod = ? > cmd` — tylko kod wyjścia komendy w trybie `>` (jej stdout idzie na terminal)
+    ExitStatus(String),
+    /// &nazwa lub &nazwa(a _ c) — referencja do funkcji z opcjonalnie
+    /// związanymi argumentami; `_` oznacza miejsce na argument wywołania
+    FuncRef { name: String, bound: Vec<String> },
+}
+
+impl VarValue {
+    /// Parsuj `&nazwa` / `&nazwa(a _ c)` — None gdy to nie referencja do funkcji
+    pub fn parse_func_ref(value: &str) -> Option<VarValue> {
+        let rest = value.trim().strip_prefix('&')?;
+        let (name, bound) = match rest.find('(') {
+            Some(open) => {
+                let inner = rest[open + 1..].strip_suffix(')')?;
+                (&rest[..open], inner.split_whitespace().map(str::to_string).collect())
+            }
+            None => (rest, Vec::new()),
+        };
+        let valid = !name.is_empty()
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+        if !valid { return None; }
+        Some(VarValue::FuncRef { name: name.to_string(), bound })
+    }
 }
 
 impl Node {
@@ -278,6 +347,24 @@ impl Node {
 ///   @{arg@_i}       → StringPart::DynVar([Var("arg"), Var("_i")]) → get_var("arg" + get_var("_i"))
 ///   @arg@_i         → StringPart::DynVar([Var("arg"), Var("_i")]) — compound ref (bez spacji/literału między)
 ///   "tekst @var ok" → [Literal("tekst "), Var("var"), Literal(" ok")]
+/// Prefiksy zasięgu w `@env:NAZWA` (tylko zmienna procesu) i `@local:NAZWA` (tylko zmienna HL).
+/// Gołe `@NAZWA` — najpierw zmienna HL, potem środowisko procesu.
+pub const VAR_SCOPES: &[&str] = &["env:", "local:"];
+
+/// Ramię `? catch`: `kind` None łapie każdy błąd, `var` dostaje jego komunikat
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatchArm {
+    pub kind: Option<String>,
+    pub var:  Option<String>,
+    pub body: Vec<Node>,
+}
+
+/// Rodzaje błędów wykonania, które można wskazać w `? catch Rodzaj` (hl_core::fault).
+/// Błąd bez rodzaju to RuntimeError.
+pub const FAULT_KINDS: &[&str] = &[
+    "NameError", "TypeError", "IndexError", "ArithmeticError", "ImportError", "RuntimeError",
+];
+
 pub fn parse_string_parts(s: &str) -> Vec<StringPart> {
     let mut parts = Vec::with_capacity(4);
     let mut lit   = String::with_capacity(s.len());
@@ -285,6 +372,12 @@ pub fn parse_string_parts(s: &str) -> Vec<StringPart> {
     let mut i = 0;
 
     while i < bytes.len() {
+        // @@ — dosłowne @ (np. user@@host), nigdy początek referencji
+        if bytes[i] == b'@' && bytes.get(i + 1) == Some(&b'@') {
+            lit.push('@');
+            i += 2;
+            continue;
+        }
         if bytes[i] == b'@' && i + 1 < bytes.len() {
             // @{...} — dynamiczna referencja
             if bytes[i+1] == b'{' {
@@ -303,6 +396,13 @@ pub fn parse_string_parts(s: &str) -> Vec<StringPart> {
                 if !lit.is_empty() { parts.push(StringPart::Literal(std::mem::take(&mut lit))); }
                 i += 1;
                 let start = i;
+                // @env:NAZWA / @local:NAZWA — jawny zasięg, prefiks zostaje w nazwie (VAR_SCOPES)
+                if let Some(scope) = VAR_SCOPES.iter().find(|p| s[i..].starts_with(*p)) {
+                    let after = i + scope.len();
+                    if bytes.get(after).is_some_and(|b| b.is_ascii_alphabetic() || *b == b'_') {
+                        i = after;
+                    }
+                }
                 while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') { i += 1; }
                 let var_name = s[start..i].to_string();
 
@@ -330,8 +430,10 @@ pub fn parse_string_parts(s: &str) -> Vec<StringPart> {
                 continue;
             }
         }
-        lit.push(bytes[i] as char);
-        i += 1;
+        // Cały znak UTF-8 — `bytes[i] as char` rozbijał wielobajtowe znaki na śmieci
+        let ch = s[i..].chars().next().unwrap_or('\u{FFFD}');
+        lit.push(ch);
+        i += ch.len_utf8();
     }
     if !lit.is_empty() { parts.push(StringPart::Literal(lit)); }
     parts
@@ -0,0 +1,117 @@
+/// `{nazwa}` w komendzie — wartość zmiennej wstawiana jako jedno słowo powłoki.
+///
+/// `@nazwa` wkleja tekst tak, jak jest, więc spacja albo `;` w wartości zmienia komendę.
+/// `{nazwa}` cytuje wartość (`'...'`), dzięki czemu `> rm {plik}` z `plik = "a; rm -rf /"`
+/// usuwa jeden plik o dziwnej nazwie. Placeholder działa tylko poza cudzysłowami i tylko
+/// dla nazw zmiennych — `${HOME}`, `@{arg@_i}`, `find -exec {} \;`, `'{print}'`
+/// i `{a,b}` zostają tekstem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmdSegment<'a> {
+    /// Zwykły tekst komendy (z `@zmiennymi` do interpolacji)
+    Text(&'a str),
+    /// `{nazwa}` — nazwa zmiennej do zacytowania
+    Quoted(&'a str),
+}
+
+/// Podziel komendę na tekst i placeholdery; bez `{` — jeden segment Text
+pub fn split_placeholders(raw: &str) -> Vec<CmdSegment<'_>> {
+    let b = raw.as_bytes();
+    let mut out = Vec::new();
+    let (mut in_s, mut in_d) = (false, false);
+    let (mut start, mut i) = (0, 0);
+    while i < b.len() {
+        match b[i] {
+            b'\\' if !in_s => { i += 2; continue; }
+            b'\'' if !in_d => in_s = !in_s,
+            b'"'  if !in_s => in_d = !in_d,
+            b'{' if !in_s && !in_d && (i == 0 || !matches!(b[i - 1], b'$' | b'@')) => {
+                if let Some(len) = raw[i + 1..].find('}') {
+                    let name = &raw[i + 1..i + 1 + len];
+                    if is_ident(name) {
+                        if start < i { out.push(CmdSegment::Text(&raw[start..i])); }
+                        out.push(CmdSegment::Quoted(name));
+                        i += len + 2;
+                        start = i;
+                        continue;
+                    }
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    if start < raw.len() || out.is_empty() { out.push(CmdSegment::Text(&raw[start.min(raw.len())..])); }
+    out
+}
+
+/// Słowo powłoki o wartości dokładnie `value` — także dla `>` (argv), bo jego podział
+/// na słowa rozumie te same cudzysłowy co sh
+pub fn shell_quote(value: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_-./:=+,%".contains(c);
+    if !value.is_empty() && value.chars().all(safe) {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Słowa argv jak w sh: cudzysłowy `'...'` / `"..."` łączą, `\` zabezpiecza następny znak
+/// (w `"..."` tylko `$`, `` ` ``, `"`, `\` i nową linię — inny backslash zostaje dosłownie).
+/// Wspólne dla VM (`>` w runtime) i kompilatora (stałe komendy dzielone raz, `ExecArgv`).
+pub fn split_words(cmd: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut cur = String::new();
+    let mut in_sq = false;
+    let mut in_dq = false;
+    // "" to pusty argument, nie brak argumentu
+    let mut had_quote = false;
+    let mut chars = cmd.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_dq => match chars.next() {
+                Some('\n') => {}
+                Some(n) if "$`\"\\".contains(n) => cur.push(n),
+                Some(n) => { cur.push('\\'); cur.push(n); }
+                None    => cur.push('\\'),
+            },
+            '\\' if !in_sq => { if let Some(n) = chars.next() { cur.push(n); } }
+            '\'' if !in_dq => { in_sq = !in_sq; had_quote = true; }
+            '"'  if !in_sq => { in_dq = !in_dq; had_quote = true; }
+            ' ' | '\t' if !in_sq && !in_dq => {
+                if !cur.is_empty() || had_quote { parts.push(std::mem::take(&mut cur)); }
+                had_quote = false;
+            }
+            _ => cur.push(c),
+        }
+    }
+    if !cur.is_empty() || had_quote { parts.push(cur); }
+    parts
+}
+
+fn is_ident(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use CmdSegment::*;
+
+    #[test]
+    fn test_split_placeholders() {
+        assert_eq!(split_placeholders("ls {path} -l"), vec![Text("ls "), Quoted("path"), Text(" -l")]);
+        assert_eq!(split_placeholders("awk '{print}' ${HOME} {} {a,b}"), vec![Text("awk '{print}' ${HOME} {} {a,b}")]);
+        assert_eq!(split_placeholders("{x}"), vec![Quoted("x")]);
+        assert_eq!(shell_quote("a; rm -rf /"), "'a; rm -rf /'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote("/tmp/plik.txt"), "/tmp/plik.txt");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn test_split_words_backslash_in_double_quotes() {
+        assert_eq!(split_words(r#"bash -c "printf 'x\ny\n'""#), vec!["bash", "-c", r"printf 'x\ny\n'"]);
+        assert_eq!(split_words(r#"echo "a\"b" "\$HOME" c\ d"#), vec!["echo", "a\"b", "$HOME", "c d"]);
+    }
+}
@@ -10,6 +10,7 @@ pub enum ExternRuntime {
 }
 
 impl ExternRuntime {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.trim().to_lowercase().as_str() {
             "shell" | "sh" | "bash"   => Some(ExternRuntime::Shell),
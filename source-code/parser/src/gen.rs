@@ -18,6 +18,7 @@ impl Gen {
         Ok(Gen(n))
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Gen { Gen(HL_DEFAULT_GEN) }
 
     /// Tryb ROLLING — wszystkie funkcje włączone
@@ -103,8 +104,8 @@ pub enum GenError {
 
 pub fn parse_gen_declaration(line: &str) -> Result<Gen, GenError> {
     let line = line.trim();
-    let inner = if line.starts_with("using") {
-        let rest = line["using".len()..].trim();
+    let inner = if let Some(rest) = line.strip_prefix("using") {
+        let rest = rest.trim();
         rest.trim_start_matches('<').trim_end_matches('>').trim()
     } else {
         return Err(GenError::ParseError(line.to_string()));
@@ -10,8 +10,8 @@ pub fn parse_import_line(line: &str) -> Option<ImportDecl> {
     let close1 = line.find('>')?;
     let spec_raw = line[1..close1].trim().to_string();
     let rest = line[close1 + 1..].trim();
-    let detail = if rest.starts_with('|') {
-        let after_pipe = rest[1..].trim();
+    let detail = if let Some(after_pipe) = rest.strip_prefix('|') {
+        let after_pipe = after_pipe.trim();
         if after_pipe.starts_with('<') && after_pipe.ends_with('>') {
             Some(after_pipe[1..after_pipe.len()-1].trim().to_string())
         } else if !after_pipe.is_empty() {
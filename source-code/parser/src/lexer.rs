@@ -23,6 +23,8 @@ pub enum Token {
     HshCmd(String),
     Background(String),
     CmdPipeToVar { cmd: String, mode: PipeCmdMode, var_name: String },
+    /// @dane |> cmd [|> @var] — wartość zmiennej na stdin komendy
+    FeedCmd { source: String, cmd: String, var_name: Option<String> },
     HackerOsApi { tool: String, args: String },
     VarDecl { name: String, typ: String, value: String },
     VarRef(String),
@@ -37,14 +39,18 @@ pub enum Token {
     FileImport { path: String, detail: Option<String> },
     // <* katalog — import katalogu (gen 2)
     DirImport  { path: String },
-    FuncDef(String),
+    /// `: nazwa def` albo `: pub nazwa def` (eksport z modułu)
+    FuncDef { name: String, public: bool },
     FuncCall(String),
+    /// -- @zmienna args — wywołanie przez referencję do funkcji
+    FuncRefCall { var: String, args: String },
     IfOk,
     IfErr,
     WhileStart(String),
     SwitchStart(String),
     SwitchArm { pattern: String },
-    ForIn { var: String, iterable: String },
+    /// @ x in lista (słowa) / @ x chars tekst (znaki)
+    ForIn { var: String, iterable: String, chars: bool },
     Arithmetic { expr: String, assign_to: Option<String> },
     Done,
     Using(String),
@@ -54,6 +60,19 @@ pub enum Token {
     // _> plik [runtime] — extern system
     ExternStart { file: String, runtime: String },
     RepeatN(u64),
+    /// ? retry N [opóźnienie] — blok ponawiany do skutku
+    RetryStart(String),
+    /// ? bench nazwa — blok mierzony przez `hl bench`
+    BenchStart(String),
+    /// ? tempdir @d — blok w tymczasowym katalogu roboczym
+    TempDirStart(String),
+    /// ? try — blok, którego błędy wykonania łapią następujące po nim `? catch`
+    TryStart,
+    /// ? catch [Rodzaj] [@e]
+    CatchStart(String),
+    /// `&& instrukcja` / `|| instrukcja` — wykonaj tylko gdy poprzedni kod wyjścia był 0 / ≠ 0
+    ChainAnd,
+    ChainOr,
     Comments(CommentKind, String),
     Ident(String),
     StringLit(String),
@@ -63,6 +82,22 @@ pub enum Token {
     Eof,
 }
 
+impl Token {
+    /// Krótki opis tokenu do komunikatów błędów
+    pub fn describe(&self) -> String {
+        match self {
+            Token::Done       => "`done`".into(),
+            Token::Eof        => "koniec pliku".into(),
+            Token::Newline    => "koniec linii".into(),
+            Token::Ident(s)   => format!("identyfikator `{}`", s),
+            Token::StringLit(s) => format!("string \"{}\"", s),
+            Token::Number(n)  => format!("liczba `{}`", n),
+            Token::Bool(b)    => format!("wartość `{}`", b),
+            other             => format!("token {:?}", other),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum PipeCmdMode { Plain, Sudo, WithVars }
 
@@ -252,9 +287,27 @@ impl Lexer {
     }
 
     pub fn tokenize(&mut self) -> Result<Vec<Token>, LexError> {
+        self.tokenize_impl(None)
+    }
+
+    /// Tokenizacja z pozycjami — każdy token z (linia, kolumna) początku.
+    /// Używane przez `hl ast --tokens` przy debugowaniu gramatyki.
+    pub fn tokenize_spanned(&mut self) -> Result<Vec<(usize, usize, Token)>, LexError> {
+        let mut spans = Vec::new();
+        let tokens = self.tokenize_impl(Some(&mut spans))?;
+        Ok(spans.into_iter().zip(tokens).map(|((l, c), t)| (l, c, t)).collect())
+    }
+
+    fn tokenize_impl(&mut self, mut spans: Option<&mut Vec<(usize, usize)>>) -> Result<Vec<Token>, LexError> {
         let mut tokens = Vec::with_capacity(self.source.len() / 8 + 16);
+        let mut start = (self.line, self.col);
 
         while self.pos < self.source.len() {
+            // Tokeny z poprzedniej iteracji dostają pozycję jej początku
+            if let Some(sp) = spans.as_deref_mut() {
+                sp.resize(tokens.len(), start);
+                start = (self.line, self.col);
+            }
             let ch = match self.peek() { None => break, Some(c) => c };
 
             // ── Export list mode ─────────────────────────────────────────────
@@ -325,6 +378,11 @@ impl Lexer {
                 // ── || HackerOS API ───────────────────────────────────────────
                 '|' if self.peek_at(1) == Some('|') => {
                     self.skip_n(2); self.skip_ws();
+                    // `|| > cmd` — łańcuch "or"; nazwa narzędzia zaczyna się od litery/cyfry
+                    if matches!(self.peek(), Some(c) if !c.is_alphanumeric() && c != '\n') {
+                        tokens.push(Token::ChainOr);
+                        continue;
+                    }
                     let mut tool = String::new();
                     while let Some(c) = self.peek() {
                         if c.is_alphanumeric() || c == '-' || c == '#' { tool.push(c); self.advance(); }
@@ -358,8 +416,8 @@ impl Lexer {
                     self.skip_n(2); self.skip_ws();
                     let rest = self.read_line();
                     let rest = rest.trim();
-                    let name = if rest.ends_with("def") {
-                        let n = rest[..rest.len()-3].trim().to_string();
+                    let name = if let Some(n) = rest.strip_suffix("def") {
+                        let n = n.trim().to_string();
                         if n.is_empty() { None } else { Some(n) }
                     } else if rest.is_empty() { None }
                     else { Some(rest.to_string()) };
@@ -426,9 +484,12 @@ impl Lexer {
 
                 ':' => {
                     self.advance(); self.skip_ws();
-                    let name = self.read_ident_full(); self.skip_ws();
-                    let kw = self.read_ident();
-                    if kw == "def" { tokens.push(Token::FuncDef(name)); self.read_line(); }
+                    let mut name = self.read_ident_full(); self.skip_ws();
+                    let mut kw = self.read_ident();
+                    // `: pub nazwa def` — ale `: pub def` to funkcja o nazwie "pub"
+                    let public = name == "pub" && kw != "def";
+                    if public { name = kw; self.skip_ws(); kw = self.read_ident(); }
+                    if kw == "def" { tokens.push(Token::FuncDef { name, public }); self.read_line(); }
                     else { tokens.push(Token::Ident(format!(":{} {}", name, kw))); }
                 }
 
@@ -492,8 +553,15 @@ impl Lexer {
                 // ── -- func call ──────────────────────────────────────────────
                 '-' if self.matches_seq(&['-', '-']) => {
                     self.skip_n(2); self.skip_ws();
-                    tokens.push(Token::FuncCall(self.read_ident_full()));
-                    self.read_line();
+                    if self.peek() == Some('@') {
+                        self.advance();
+                        let var = self.read_ident_full();
+                        self.skip_ws();
+                        tokens.push(Token::FuncRefCall { var, args: self.read_line() });
+                    } else {
+                        tokens.push(Token::FuncCall(self.read_ident_full()));
+                        self.read_line();
+                    }
                 }
 
                 // ── => export ─────────────────────────────────────────────────
@@ -548,6 +616,7 @@ impl Lexer {
                     } else { tokens.push(Token::Cmd(line)); }
                 }
 
+                '&' if self.peek_at(1) == Some('&') => { self.skip_n(2); tokens.push(Token::ChainAnd); }
                 '&' => { self.advance(); self.skip_ws(); tokens.push(Token::Background(self.read_line())); }
 
                 // ── _> extern ─────────────────────────────────────────────────
@@ -634,6 +703,8 @@ impl Lexer {
 
                 '@' => {
                     self.advance();
+                    // "@ item in lista" — dopuszczalna spacja po @ w nagłówku for-in
+                    if matches!(self.peek(), Some(' ') | Some('\t')) { self.skip_ws(); }
                     let name = self.read_ident_full();
                     self.skip_ws();
                     let looks_like_for = {
@@ -643,12 +714,20 @@ impl Lexer {
                             kw.push(self.source[tmp]);
                             tmp += 1;
                         }
-                        kw == "in"
+                        kw == "in" || kw == "chars"
                     };
                     if looks_like_for {
-                        let _in_kw = self.read_ident();
+                        let kw = self.read_ident();
                         self.skip_ws();
-                        tokens.push(Token::ForIn { var: name, iterable: self.read_line() });
+                        tokens.push(Token::ForIn { var: name, iterable: self.read_line(), chars: kw == "chars" });
+                    } else if self.matches_seq(&['|', '>']) {
+                        self.skip_n(2); self.skip_ws();
+                        let line = self.read_line();
+                        let (cmd, var_name) = match Self::split_pipe_to_var(&line) {
+                            Some((cmd, var)) => (cmd, Some(var)),
+                            None             => (line.trim().to_string(), None),
+                        };
+                        tokens.push(Token::FeedCmd { source: name, cmd, var_name });
                     } else {
                         tokens.push(Token::VarRef(name));
                     }
@@ -681,6 +760,11 @@ impl Lexer {
                             "ok"     => { tokens.push(Token::IfOk);  self.read_line(); }
                             "err"    => { tokens.push(Token::IfErr); self.read_line(); }
                             "switch" => { self.skip_ws(); tokens.push(Token::SwitchStart(self.read_line())); }
+                            "retry"  => { self.skip_ws(); tokens.push(Token::RetryStart(self.read_line())); }
+                            "bench"  => { self.skip_ws(); tokens.push(Token::BenchStart(self.read_line())); }
+                            "tempdir" => { self.skip_ws(); tokens.push(Token::TempDirStart(self.read_line())); }
+                            "try"    => { tokens.push(Token::TryStart); self.read_line(); }
+                            "catch"  => { self.skip_ws(); tokens.push(Token::CatchStart(self.read_line())); }
                             _        => tokens.push(Token::Ident(format!("?{}", kw))),
                         }
                     }
@@ -748,7 +832,27 @@ impl Lexer {
             }
         }
 
+        if let Some(sp) = spans {
+            sp.resize(tokens.len(), start);
+            sp.push((self.line, self.col));
+        }
         tokens.push(Token::Eof);
         Ok(tokens)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_spanned_positions() {
+        let toks = Lexer::new("~> a\n  > ls\n").tokenize_spanned().unwrap();
+        assert_eq!(toks[0], (1, 1, Token::Print("a".into())));
+        assert_eq!(toks[1], (1, 5, Token::Newline));
+        assert_eq!(toks[2], (2, 3, Token::Cmd("ls".into())));
+        assert_eq!(toks.last().unwrap().2, Token::Eof);
+        let plain = Lexer::new("~> a\n  > ls\n").tokenize().unwrap();
+        assert_eq!(toks.len(), plain.len());
+    }
+}
@@ -5,18 +5,24 @@ pub mod parser;
 pub mod shebang;
 pub mod import_spec;
 pub mod extern_spec;
+pub mod math;
+pub mod truth;
+pub mod cmd_template;
 
 pub use ast::*;
 pub use gen::{Gen, GenError, GenFeature, extract_gen, parse_gen_declaration, HL_MAX_GEN, HL_DEFAULT_GEN};
 pub use shebang::{ShebangInfo, PreprocessResult, preprocess};
 pub use lexer::{Lexer, Token, LexError};
-pub use parser::{Parser, ParseError, parse_source, parse_source_with_meta};
+pub use parser::{Parser, ParseError, parse_source, parse_source_with_meta, parse_source_with_lines};
 pub use import_spec::{parse_import_line, ImportDecl};
 pub use extern_spec::{ExternRuntime};
 
 // ArenaSize jest częścią ast — re-export dla wygody
 pub use ast::ArenaSize;
 
+/// Wersja parsera — część klucza cache AST (nowy parser = nowe wpisy)
+pub const PARSER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
 #[derive(Debug)]
 pub struct ParseMeta {
     pub nodes:   Vec<Node>,
@@ -0,0 +1,114 @@
+/// Funkcje matematyczne i operatory bitowe w `$( ... )` — wspólne dla tree-walk executora i VM.
+///
+/// `sqrt(9.0)`, `abs(-3)`, `max(2, 5)`: argumenty to dowolne wyrażenia arytmetyczne.
+/// Błąd dziedziny (`sqrt(-1)`, `pow(-8, 0.5)`) daje NaN, który runtime zamienia na nil.
+pub const MATH_FUNCS: &[(&str, usize)] = &[
+    ("sqrt", 1), ("abs", 1), ("floor", 1),
+    ("min", 2), ("max", 2), ("pow", 2),
+];
+
+/// `nazwa(a, b)` → (nazwa, [a, b]) dla znanej funkcji z poprawną liczbą argumentów.
+/// Przecinki wewnątrz zagnieżdżonych nawiasów nie dzielą argumentów.
+pub fn split_math_call(expr: &str) -> Option<(&'static str, Vec<&str>)> {
+    let expr = expr.trim();
+    let open = expr.find('(')?;
+    let inner = expr[open + 1..].strip_suffix(')')?;
+    let &(name, arity) = MATH_FUNCS.iter().find(|(n, _)| *n == expr[..open].trim())?;
+
+    let mut args = Vec::new();
+    let (mut depth, mut start) = (0i32, 0);
+    for (i, b) in inner.bytes().enumerate() {
+        match b {
+            b'(' => depth += 1,
+            // Nawias zamykający wywołanie przed końcem: `f(1) + g(2)` to nie jedno wywołanie
+            b')' => { depth -= 1; if depth < 0 { return None; } }
+            b',' if depth == 0 => { args.push(inner[start..i].trim()); start = i + 1; }
+            _ => {}
+        }
+    }
+    args.push(inner[start..].trim());
+    (args.len() == arity && args.iter().all(|a| !a.is_empty())).then_some((name, args))
+}
+
+/// Wartość funkcji `name` (z `MATH_FUNCS`) dla już policzonych argumentów
+pub fn eval_math(name: &str, args: &[f64]) -> f64 {
+    match (name, args) {
+        ("sqrt",  [x])    => x.sqrt(),
+        ("abs",   [x])    => x.abs(),
+        ("floor", [x])    => x.floor(),
+        ("min",   [a, b]) => a.min(*b),
+        ("max",   [a, b]) => a.max(*b),
+        ("pow",   [a, b]) => a.powf(*b),
+        _                 => f64::NAN,
+    }
+}
+
+/// Poziomy operatorów bitowych od najniższego priorytetu (jak w C): `|`, `^`, `&`, `<<` / `>>`.
+/// Wszystkie wiążą słabiej niż `+ -` — `1 << 2 + 1` to `1 << 3`.
+const BIT_LEVELS: &[&[&str]] = &[&["|"], &["^"], &["&"], &["<<", ">>"]];
+
+/// Ostatni (lewostronna łączność) operator bitowy najniższego poziomu poza nawiasami:
+/// `6 & 3 | 8` → ("6 & 3", "|", "8"). None gdy wyrażenie nie ma operatorów bitowych.
+pub fn split_bitwise(expr: &str) -> Option<(&str, &'static str, &str)> {
+    let bytes = expr.as_bytes();
+    for level in BIT_LEVELS {
+        let mut depth = 0i32;
+        for i in (0..bytes.len()).rev() {
+            match bytes[i] {
+                b')' => depth += 1,
+                b'(' => depth -= 1,
+                _ if depth == 0 => {
+                    if let Some(op) = level.iter().find(|op| expr[..=i].ends_with(**op)) {
+                        let start = i + 1 - op.len();
+                        return Some((&expr[..start], op, &expr[i + 1..]));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+/// Wynik operatora bitowego — operandy muszą być liczbami całkowitymi, przesunięcie w 0..=63.
+/// None przy złym operandzie: runtime zgłasza TypeError.
+pub fn eval_bitwise(op: &str, a: f64, b: f64) -> Option<f64> {
+    let int = |v: f64| (v.fract() == 0.0 && v.abs() <= i64::MAX as f64).then_some(v as i64);
+    let (a, b) = (int(a)?, int(b)?);
+    let shift = || u32::try_from(b).ok().filter(|s| *s < 64);
+    Some(match op {
+        "&"  => a & b,
+        "|"  => a | b,
+        "^"  => a ^ b,
+        "<<" => a.wrapping_shl(shift()?),
+        ">>" => a >> shift()?,
+        _    => return None,
+    } as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bitwise_split_and_eval() {
+        assert_eq!(split_bitwise("6 & 3 | 8"), Some(("6 & 3 ", "|", " 8")));
+        assert_eq!(split_bitwise("1 << 2 + 1"), Some(("1 ", "<<", " 2 + 1")));
+        assert_eq!(split_bitwise("(1 | 2) + 3"), None);
+        assert_eq!(eval_bitwise("&", 6.0, 3.0), Some(2.0));
+        assert_eq!(eval_bitwise("<<", 1.0, 4.0), Some(16.0));
+        assert_eq!(eval_bitwise(">>", -16.0, 2.0), Some(-4.0));
+        assert_eq!(eval_bitwise("&", 1.5, 1.0), None);
+        assert_eq!(eval_bitwise("<<", 1.0, 64.0), None);
+    }
+
+    #[test]
+    fn test_split_math_call() {
+        assert_eq!(split_math_call("max(2, 5)"), Some(("max", vec!["2", "5"])));
+        assert_eq!(split_math_call("pow(max(1, 2), 3)"), Some(("pow", vec!["max(1, 2)", "3"])));
+        assert_eq!(split_math_call("sqrt(1) + sqrt(4)"), None);
+        assert_eq!(split_math_call("max(1)"), None);
+        assert_eq!(split_math_call("nieznana(1)"), None);
+        assert!(eval_math("sqrt", &[-1.0]).is_nan());
+    }
+}
@@ -10,8 +10,9 @@ use thiserror::Error;
 pub enum ParseError {
     #[error("Błąd leksera: {0}")]
     Lex(#[from] LexError),
-    #[error("Nieoczekiwany token na pozycji {0}: {1}")]
-    UnexpectedToken(usize, String),
+    /// `line`/`col` = 0 gdy parser dostał tokeny bez pozycji (`Parser::new`)
+    #[error("Nieoczekiwany {found} w linii {line}:{col}, oczekiwano: {expected}")]
+    UnexpectedToken { line: usize, col: usize, found: String, expected: &'static str },
     #[error("Brakujące 'done' — blok nie jest zamknięty")]
     MissingDone,
     #[error("Brakujące 'def' po nazwie funkcji")]
@@ -24,7 +25,12 @@ pub enum ParseError {
 
 pub struct Parser {
     tokens: Vec<Token>,
+    /// (linia, kolumna) każdego tokenu — liczone raz przez lekser, puste bez pozycji
+    spans:  Vec<(usize, usize)>,
     pos:    usize,
+    /// Wstawiaj Node::SourceLine przed instrukcjami (coverage) — wymaga spans
+    line_markers: bool,
+    marked_lines: Vec<usize>,
     /// Nazwy zdefiniowanych arena functions — do rozróżnienia wywołań `:: nazwa`
     arena_funcs: std::collections::HashSet<String>,
 }
@@ -33,11 +39,50 @@ impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
         Self {
             tokens,
+            spans: Vec::new(),
             pos: 0,
+            line_markers: false,
+            marked_lines: Vec::new(),
             arena_funcs: std::collections::HashSet::new(),
         }
     }
 
+    /// Parser z pozycjami tokenów z `Lexer::tokenize_spanned` — błędy dostają linię i kolumnę
+    pub fn with_spans(spanned: Vec<(usize, usize, Token)>) -> Self {
+        let (spans, tokens) = spanned.into_iter().map(|(l, c, t)| ((l, c), t)).unzip();
+        Self { spans, ..Self::new(tokens) }
+    }
+
+    /// Włącz markery linii źródła przed każdą instrukcją (dla `hl run --coverage`)
+    pub fn with_line_markers(mut self) -> Self {
+        self.line_markers = true;
+        self
+    }
+
+    /// Linie, przed którymi parser wstawił marker — czyli linie wykonywalne
+    pub fn marked_lines(&self) -> &[usize] { &self.marked_lines }
+
+    /// parse_node + ewentualny marker linii — wspólne dla pętli bloków
+    fn push_stmt(&mut self, out: &mut Vec<Node>) -> Result<(), ParseError> {
+        let line = self.spans.get(self.pos).map(|s| s.0);
+        if let Some(n) = self.parse_node()? {
+            if let (true, Some(line), false) = (self.line_markers, line, n.is_comment()) {
+                out.push(Node::SourceLine(line));
+                self.marked_lines.push(line);
+            }
+            out.push(n);
+        }
+        Ok(())
+    }
+
+    fn unexpected(&self, pos: usize, found: String, expected: &'static str) -> ParseError {
+        let (line, col) = self.spans.get(pos).copied().unwrap_or((0, 0));
+        ParseError::UnexpectedToken { line, col, found, expected }
+    }
+
+    /// Linia bieżącego tokenu (0, gdy parser nie ma pozycji)
+    fn current_line(&self) -> usize { self.spans.get(self.pos).map_or(0, |s| s.0) }
+
     #[inline] fn peek(&self) -> &Token { self.tokens.get(self.pos).unwrap_or(&Token::Eof) }
 
     fn advance(&mut self) -> Token {
@@ -62,14 +107,20 @@ impl Parser {
             "str" | "string" => { return VarValue::String(value.trim_matches('"').to_string()); }
             _ => {}
         }
+        if let Some(func_ref) = VarValue::parse_func_ref(value) {
+            return func_ref;
+        }
         if value.starts_with('"') && value.ends_with('"') && value.len() >= 2 {
             let inner = &value[1..value.len()-1];
             let parts = parse_string_parts(inner);
-            if parts.iter().any(|p| matches!(p, StringPart::Var(_))) {
+            if parts.iter().any(StringPart::is_ref) {
                 return VarValue::Interpolated(parts);
             }
             return VarValue::String(inner.to_string());
         }
+        if let Some(cmd) = value.strip_prefix('?').map(str::trim_start).and_then(|v| v.strip_prefix('>')) {
+            if !cmd.starts_with('>') { return VarValue::ExitStatus(cmd.trim().to_string()); }
+        }
         if value.starts_with("$(") && value.ends_with(')') {
             return VarValue::Arithmetic(value[2..value.len()-1].trim().to_string());
         }
@@ -78,7 +129,7 @@ impl Parser {
         if value == "true"  { return VarValue::Bool(true);  }
         if value == "false" { return VarValue::Bool(false); }
         let parts = parse_string_parts(value);
-        if parts.iter().any(|p| matches!(p, StringPart::Var(_))) {
+        if parts.iter().any(StringPart::is_ref) {
             VarValue::Interpolated(parts)
         } else {
             VarValue::String(value.to_string())
@@ -108,15 +159,19 @@ impl Parser {
                 Token::Eof  => return Err(ParseError::MissingDone),
                 Token::SwitchArm { pattern } => {
                     self.advance();
+                    let (pattern, guard) = match pattern.split_once(" when ") {
+                        Some((p, g)) => (p.trim().to_string(), Some(parse_string_parts(g.trim()))),
+                        None         => (pattern, None),
+                    };
                     let mut body = Vec::new();
                     loop {
                         self.skip_newlines();
                         match self.peek() {
                             Token::SwitchArm { .. } | Token::Done | Token::Eof => break,
-                            _ => { if let Some(n) = self.parse_node()? { body.push(n); } }
+                            _ => self.push_stmt(&mut body)?,
                         }
                     }
-                    arms.push(MatchArm { pattern, body });
+                    arms.push(MatchArm { pattern, guard, body });
                 }
                 _ => { self.advance(); }
             }
@@ -201,21 +256,109 @@ impl Parser {
             Token::ChannelDecl(name) => { self.advance(); Ok(Some(Node::Channel { name })) }
             Token::ChannelOp(name)   => { self.advance(); Ok(Some(Node::ChannelOp { name, value: None })) }
 
-            Token::ForIn { var, iterable } => {
+            Token::ForIn { var, iterable, chars } => {
+                let line = self.current_line();
                 self.advance();
-                Ok(Some(Node::ForIn { var, iterable: parse_string_parts(&iterable), body: self.parse_block()? }))
+                let each = if chars { ForEach::Chars } else { ForEach::Words };
+                Ok(Some(Node::ForIn { var, iterable: parse_string_parts(&iterable), each, body: self.parse_block()?, line }))
             }
             Token::WhileStart(condition) => {
+                let line = self.current_line();
                 self.advance();
-                Ok(Some(Node::WhileLoop { condition: parse_string_parts(&condition), body: self.parse_block()? }))
+                Ok(Some(Node::WhileLoop { condition: parse_string_parts(&condition), body: self.parse_block()?, line }))
             }
             Token::SwitchStart(subject) => {
                 self.advance();
                 Ok(Some(Node::MatchExpr { subject: parse_string_parts(&subject), arms: self.parse_switch_arms()? }))
             }
 
+            Token::RetryStart(spec) => {
+                let pos = self.pos;
+                self.advance();
+                let Some((count, delay_ms)) = parse_retry_spec(&spec) else {
+                    return Err(self.unexpected(pos, format!("`? retry {}`", spec.trim()),
+                        "liczba prób > 0 i opcjonalne opóźnienie, np. `? retry 3 500ms`"));
+                };
+                Ok(Some(Node::Retry { count, delay_ms, body: self.parse_block()? }))
+            }
+
+            Token::BenchStart(name) => {
+                let pos = self.pos;
+                self.advance();
+                let name = name.trim().trim_matches('"').to_string();
+                if name.is_empty() {
+                    return Err(self.unexpected(pos, "`? bench`".into(), "nazwa benchmarku, np. `? bench sortowanie`"));
+                }
+                Ok(Some(Node::Bench { name, body: self.parse_block()? }))
+            }
+
+            Token::TempDirStart(spec) => {
+                let pos = self.pos;
+                self.advance();
+                let var = spec.trim().trim_start_matches('@').to_string();
+                if var.is_empty() || !var.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                    return Err(self.unexpected(pos, format!("`? tempdir {}`", spec.trim()),
+                        "nazwa zmiennej na ścieżkę, np. `? tempdir @tmp`"));
+                }
+                Ok(Some(Node::TempDir { var, body: self.parse_block()? }))
+            }
+
+            Token::TryStart => {
+                let pos = self.pos;
+                self.advance();
+                let body = self.parse_block()?;
+                let mut catches = Vec::new();
+                loop {
+                    self.skip_newlines();
+                    let Token::CatchStart(spec) = self.peek().clone() else { break };
+                    let cpos = self.pos;
+                    self.advance();
+                    let (mut kind, mut var) = (None, None);
+                    for word in spec.split_whitespace() {
+                        if let Some(v) = word.strip_prefix('@') {
+                            var = Some(v.to_string());
+                        } else if FAULT_KINDS.contains(&word) && kind.is_none() {
+                            kind = Some(word.to_string());
+                        } else {
+                            return Err(self.unexpected(cpos, format!("`? catch {}`", spec.trim()),
+                                "rodzaj błędu (NameError, TypeError, IndexError, ArithmeticError, ImportError, RuntimeError) i/lub @zmienna"));
+                        }
+                    }
+                    catches.push(CatchArm { kind, var, body: self.parse_block()? });
+                }
+                if catches.is_empty() {
+                    return Err(self.unexpected(pos, "`? try` bez `? catch`".into(), "blok `? catch [Rodzaj] [@e] ... done` po `? try ... done`"));
+                }
+                Ok(Some(Node::Try { body, catches }))
+            }
+            Token::CatchStart(_) => Err(self.unexpected(self.pos, "`? catch`".into(), "`? catch` zaraz po bloku `? try ... done`")),
+
+            Token::ChainAnd => self.parse_chain(ChainOp::And),
+            Token::ChainOr  => self.parse_chain(ChainOp::Or),
+
             Token::Arithmetic { expr, assign_to } => { self.advance(); Ok(Some(Node::Arithmetic { expr, assign_to })) }
 
+            Token::FeedCmd { source, cmd, var_name } => {
+                let pos = self.pos;
+                self.advance();
+                if cmd.is_empty() {
+                    return Err(self.unexpected(pos, format!("`@{} |>`", source), "komenda, np. `@dane |> jq .pole |> @wynik`"));
+                }
+                // @dane |> :: nazwa args — wartość jako argument quick function, nie stdin
+                if let Some(call) = cmd.strip_prefix("::") {
+                    let (name, rest) = call.trim().split_once(char::is_whitespace).unwrap_or((call.trim(), ""));
+                    if name.is_empty() {
+                        return Err(self.unexpected(pos, format!("`@{} |> ::`", source), "nazwa funkcji, np. `@dane |> :: upper`"));
+                    }
+                    let args = parse_string_parts(&pipe_args(&source, rest));
+                    let name = name.to_string();
+                    return Ok(Some(match var_name {
+                        Some(var_name) => Node::QuickPipeToVar { name, args, var_name },
+                        None           => Node::QuickCall { name, args },
+                    }));
+                }
+                Ok(Some(Node::Feed { source, command: cmd, var_name }))
+            }
             Token::CmdPipeToVar { cmd, mode, var_name } => {
                 self.advance();
                 let cmd_mode = match mode {
@@ -262,11 +405,15 @@ impl Parser {
             Token::Dependency(name, apt_package) => { self.advance(); Ok(Some(Node::Dependency { name, apt_package })) }
             Token::Import { lib, detail } => { self.advance(); Ok(Some(Node::Import { lib, detail })) }
 
-            Token::FuncDef(name) => {
+            Token::FuncDef { name, public } => {
                 self.advance();
-                Ok(Some(Node::FuncDef { name, body: self.parse_block()? }))
+                Ok(Some(Node::FuncDef { name, public, body: self.parse_block()? }))
             }
             Token::FuncCall(name) => { self.advance(); Ok(Some(Node::FuncCall { name })) }
+            Token::FuncRefCall { var, args } => {
+                self.advance();
+                Ok(Some(Node::FuncRefCall { var, args: parse_string_parts(&args) }))
+            }
 
             Token::IfOk  => { self.advance(); Ok(Some(Node::Conditional { condition: ConditionKind::Ok,  body: self.parse_block()? })) }
             Token::IfErr => { self.advance(); Ok(Some(Node::Conditional { condition: ConditionKind::Err, body: self.parse_block()? })) }
@@ -289,11 +436,22 @@ impl Parser {
             #[allow(unreachable_patterns)]
             tok => {
                 let pos = self.pos; self.advance();
-                Err(ParseError::UnexpectedToken(pos, format!("{:?}", tok)))
+                Err(self.unexpected(pos, tok.describe(), "instrukcja"))
             }
         }
     }
 
+    /// `&& instrukcja` / `|| instrukcja` — instrukcja musi być w tej samej linii
+    fn parse_chain(&mut self, op: ChainOp) -> Result<Option<Node>, ParseError> {
+        let pos = self.pos;
+        self.advance();
+        if matches!(self.peek(), Token::Newline | Token::Eof | Token::Done) {
+            let found = if op == ChainOp::And { "`&&` na końcu linii" } else { "`||` na końcu linii" };
+            return Err(self.unexpected(pos, found.into(), "instrukcja w tej samej linii, np. `&& > make install`"));
+        }
+        Ok(self.parse_node()?.map(|node| Node::Chain { op, node: Box::new(node) }))
+    }
+
     fn parse_block(&mut self) -> Result<Vec<Node>, ParseError> {
         let mut nodes = Vec::with_capacity(8);
         loop {
@@ -301,7 +459,7 @@ impl Parser {
             match self.peek() {
                 Token::Done => { self.advance(); break; }
                 Token::Eof  => return Err(ParseError::MissingDone),
-                _           => { if let Some(n) = self.parse_node()? { nodes.push(n); } }
+                _           => self.push_stmt(&mut nodes)?,
             }
         }
         Ok(nodes)
@@ -311,32 +469,186 @@ impl Parser {
         let mut nodes = Vec::with_capacity(32);
         loop {
             self.skip_newlines();
-            if matches!(self.peek(), Token::Eof) { break; }
-            if let Some(n) = self.parse_node()? { nodes.push(n); }
+            match self.peek() {
+                Token::Eof  => break,
+                // Osierocone `done` na najwyższym poziomie — parse_node go nie konsumuje
+                Token::Done => return Err(self.unexpected(self.pos, Token::Done.describe(),
+                    "instrukcja (`done` bez otwartego bloku)")),
+                _           => self.push_stmt(&mut nodes)?,
+            }
         }
         Ok(nodes)
     }
 }
 
+/// Argumenty `@dane |> :: f args`: każde słowo `_` to miejsce na `@dane`;
+/// bez `_` wartość idzie jako pierwszy argument (`:: f @dane args`)
+fn pipe_args(source: &str, rest: &str) -> String {
+    let value = format!("@{}", source);
+    let words: Vec<&str> = rest.split_whitespace().collect();
+    if words.contains(&"_") {
+        words.iter().map(|w| if *w == "_" { value.as_str() } else { w }).collect::<Vec<_>>().join(" ")
+    } else if words.is_empty() {
+        value
+    } else {
+        format!("{} {}", value, words.join(" "))
+    }
+}
+
+/// "3" / "3 500ms" / "5 2s" → (liczba prób, opóźnienie w ms).
+/// Sama liczba jako opóźnienie oznacza sekundy (jak `sleep`).
+fn parse_retry_spec(spec: &str) -> Option<(u32, u64)> {
+    let mut it = spec.split_whitespace();
+    let count: u32 = it.next()?.parse().ok().filter(|&n| n > 0)?;
+    let delay_ms = match it.next() {
+        None => 0,
+        Some(d) => {
+            if let Some(ms) = d.strip_suffix("ms") { ms.parse().ok()? }
+            else if let Some(s) = d.strip_suffix('s') { s.parse::<u64>().ok()?.checked_mul(1000)? }
+            else { d.parse::<u64>().ok()?.checked_mul(1000)? }
+        }
+    };
+    if it.next().is_some() { return None; }
+    Some((count, delay_ms))
+}
+
 pub fn parse_source(source: &str) -> Result<Vec<Node>, ParseError> {
     Ok(parse_source_with_meta(source)?.nodes)
 }
 
 pub fn parse_source_with_meta(source: &str) -> Result<ParseMeta, ParseError> {
+    Ok(parse_impl(source, false)?.0)
+}
+
+/// Jak parse_source, ale z Node::SourceLine przed instrukcjami.
+/// Zwraca też listę linii wykonywalnych (do raportu pokrycia).
+pub fn parse_source_with_lines(source: &str) -> Result<(Vec<Node>, Vec<usize>), ParseError> {
+    let (meta, lines) = parse_impl(source, true)?;
+    Ok((meta.nodes, lines))
+}
+
+fn parse_impl(source: &str, line_markers: bool) -> Result<(ParseMeta, Vec<usize>), ParseError> {
     let preprocessed = preprocess(source);
     let (gen, gen_err) = extract_gen(&preprocessed.source);
     if let Some(err) = gen_err { return Err(ParseError::Gen(err)); }
     let mut lexer  = Lexer::new(&preprocessed.source);
-    let tokens     = lexer.tokenize()?;
-    let mut parser = Parser::new(tokens);
+    let tokens     = lexer.tokenize_spanned()?;
+    let mut parser = Parser::with_spans(tokens);
+    if line_markers { parser = parser.with_line_markers(); }
     let nodes      = parser.parse()?;
-    Ok(ParseMeta { nodes, gen, shebang: preprocessed.shebang })
+    let lines      = std::mem::take(&mut parser.marked_lines);
+    Ok((ParseMeta { nodes, gen, shebang: preprocessed.shebang }, lines))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_dyn_value_only_is_interpolated() {
+        let nodes = parse_source("% x = @{arg@_i}").unwrap();
+        assert!(matches!(&nodes[0], Node::VarDecl { value: VarValue::Interpolated(p), .. }
+            if matches!(p.as_slice(), [StringPart::DynVar(_)])));
+    }
+
+    #[test]
+    fn test_stray_done_is_error() {
+        assert!(matches!(parse_source("~> a\ndone"), Err(ParseError::UnexpectedToken { found, .. }) if found == "`done`"));
+        assert!(parse_source("@item in a b\ndone\ndone").is_err());
+    }
+
+    #[test]
+    fn test_double_at_is_literal() {
+        use crate::ast::parse_string_parts;
+        match parse_string_parts("@@HOME i @HOME").as_slice() {
+            [StringPart::Literal(a), StringPart::Var(v)] => {
+                assert_eq!(a, "@HOME i ");
+                assert_eq!(v, "HOME");
+            }
+            other => panic!("Nieoczekiwane części: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_line_markers_only_when_requested() {
+        let src = "~> a\n? ok\n  > ls\ndone\n";
+        assert!(!parse_source(src).unwrap().iter().any(|n| matches!(n, Node::SourceLine(_))));
+        let (nodes, lines) = parse_source_with_lines(src).unwrap();
+        assert_eq!(lines, vec![1, 3, 2]);
+        assert!(matches!(nodes[0], Node::SourceLine(1)));
+        match &nodes[3] {
+            Node::Conditional { body, .. } => assert!(matches!(body[0], Node::SourceLine(3))),
+            n => panic!("Oczekiwano Conditional, jest {:?}", n),
+        }
+    }
+
+    #[test]
+    fn test_unexpected_token_position_and_expectation() {
+        let err = parse_source("~> a\n  > ls\n  done\n").unwrap_err();
+        match &err {
+            ParseError::UnexpectedToken { line, col, found, expected } => {
+                assert_eq!((*line, *col), (3, 3));
+                assert_eq!(found, "`done`");
+                assert!(expected.contains("instrukcja"));
+            }
+            e => panic!("Oczekiwano UnexpectedToken, jest {:?}", e),
+        }
+        let msg = err.to_string();
+        assert!(msg.contains("`done`") && msg.contains("oczekiwano") && msg.contains("3:3"), "{}", msg);
+    }
+
+    #[test]
+    fn test_retry_block() {
+        let nodes = parse_source("? retry 3 250ms\n> curl -f http://x\ndone").unwrap();
+        match &nodes[0] {
+            Node::Retry { count, delay_ms, body } => {
+                assert_eq!((*count, *delay_ms), (3, 250));
+                assert_eq!(body.len(), 1);
+            }
+            n => panic!("Oczekiwano Retry, jest {:?}", n),
+        }
+        assert!(matches!(parse_source("? retry 2 1\n> ls\ndone").unwrap()[0], Node::Retry { count: 2, delay_ms: 1000, .. }));
+        assert!(parse_source("? retry zero\n> ls\ndone").is_err());
+        assert!(parse_source("? retry 0\n> ls\ndone").is_err());
+        assert!(parse_source("? retry 2 18446744073709552s\n> ls\ndone").is_err());
+        assert!(parse_source("? retry 2 18446744073709552\n> ls\ndone").is_err());
+    }
+
+    #[test]
+    fn test_pipe_into_quick_call_placeholder() {
+        let nodes = parse_source("@s |> :: split ,\n@sep |> :: split a,b _ |> @out\n").unwrap();
+        assert!(matches!(&nodes[0], Node::QuickCall { name, args } if name == "split"
+            && matches!(args.as_slice(), [StringPart::Var(v), StringPart::Literal(l)] if v == "s" && l == " ,")));
+        assert!(matches!(&nodes[1], Node::QuickPipeToVar { name, args, var_name } if name == "split" && var_name == "out"
+            && matches!(args.as_slice(), [StringPart::Literal(l), StringPart::Var(v)] if l == "a,b " && v == "sep")));
+    }
+
+    #[test]
+    fn test_tempdir_block() {
+        assert!(matches!(&parse_source("? tempdir @tmp\n> ls\ndone").unwrap()[0],
+            Node::TempDir { var, body } if var == "tmp" && body.len() == 1));
+        assert!(parse_source("? tempdir\n> ls\ndone").is_err());
+    }
+
+    #[test]
+    fn test_bench_block() {
+        match &parse_source("? bench \"petla\"\n  $( 1 + 1 ) -> @x\ndone").unwrap()[0] {
+            Node::Bench { name, body } => {
+                assert_eq!(name, "petla");
+                assert_eq!(body.len(), 1);
+            }
+            n => panic!("Oczekiwano Bench, jest {:?}", n),
+        }
+        assert!(parse_source("? bench\ndone").is_err());
+    }
+
+    #[test]
+    fn test_unresolved_import_kept_as_node() {
+        // Parser nie rozwiązuje importów — `hl ast` pokazuje je dosłownie
+        let nodes = parse_source("# <main/nie-istnieje>\n~> ok").unwrap();
+        assert!(nodes.iter().any(|n| matches!(n, Node::Import { lib, .. } if lib.contains("nie-istnieje"))));
+    }
+
     #[test]
     fn test_pipe_to_var_simple() {
         let src = "> hostname |> @myhost";
@@ -344,6 +656,15 @@ mod tests {
         assert!(nodes.iter().any(|n| matches!(n, Node::PipeToVar { .. })));
     }
 
+    #[test]
+    fn test_feed_to_cmd() {
+        let nodes = parse_source("@dane |> jq '.a | .b' |> @wynik\n@dane |> wc -c").unwrap();
+        assert!(matches!(&nodes[0], Node::Feed { source, command, var_name: Some(v) }
+            if source == "dane" && command == "jq '.a | .b'" && v == "wynik"));
+        assert!(matches!(&nodes[1], Node::Feed { var_name: None, .. }));
+        assert!(parse_source("@dane |>").is_err());
+    }
+
     #[test]
     fn test_arena_func_def_and_call() {
         let src = ":: my_fn <4k> def\n~> hello\ndone\n:: my_fn";
@@ -394,9 +715,66 @@ mod tests {
         assert!(parse_source(src).is_ok());
     }
 
+    #[test]
+    fn test_for_chars() {
+        let nodes = parse_source("@ c chars żółw\n~> @c\ndone").unwrap();
+        assert!(matches!(&nodes[0], Node::ForIn { var, each: ForEach::Chars, iterable, .. }
+            if var == "c" && matches!(iterable.as_slice(), [StringPart::Literal(l)] if l == "żółw")));
+        let nodes = parse_source("@ w in a b\ndone").unwrap();
+        assert!(matches!(&nodes[0], Node::ForIn { each: ForEach::Words, line: 1, .. }));
+        let nodes = parse_source("~> x\n\n?~ true\ndone").unwrap();
+        assert!(matches!(&nodes[1], Node::WhileLoop { line: 3, .. }));
+    }
+
+    #[test]
+    fn test_scoped_var_refs() {
+        use crate::ast::parse_string_parts;
+        let parts = parse_string_parts("@env:HOME/x @local:HOME @env: y");
+        assert!(matches!(parts.as_slice(), [
+            StringPart::Var(a), StringPart::Literal(l1), StringPart::Var(b), StringPart::Literal(l2),
+            StringPart::Var(c), StringPart::Literal(l3),
+        ] if a == "env:HOME" && l1 == "/x " && b == "local:HOME" && l2 == " " && c == "env" && l3 == ": y"),
+            "{:?}", parts);
+    }
+
+    #[test]
+    fn test_func_ref_and_partial() {
+        let src = "% f = &double\n% inc = &add(1 _)\n-- @inc 41";
+        let nodes = parse_source(src).unwrap();
+        assert!(matches!(&nodes[0], Node::VarDecl { value: VarValue::FuncRef { name, bound }, .. }
+            if name == "double" && bound.is_empty()));
+        assert!(matches!(&nodes[1], Node::VarDecl { value: VarValue::FuncRef { name, bound }, .. }
+            if name == "add" && bound == &["1", "_"]));
+        assert!(matches!(&nodes[2], Node::FuncRefCall { var, .. } if var == "inc"));
+    }
+
     #[test]
     fn test_switch() {
         let src = "? switch @x\n| a\n~> A\n| *\n~> other\ndone";
         assert!(parse_source(src).is_ok());
     }
+
+    #[test]
+    fn test_pub_func_def() {
+        let nodes = parse_source(": pub api def\n    ~> a\ndone\n: pub def\n    ~> b\ndone\n").unwrap();
+        assert!(matches!(&nodes[0], Node::FuncDef { name, public: true, .. } if name == "api"));
+        // `: pub def` to zwykła funkcja o nazwie "pub"
+        assert!(matches!(&nodes[1], Node::FuncDef { name, public: false, .. } if name == "pub"));
+    }
+
+    #[test]
+    fn test_try_catch_arms() {
+        let src = "? try\n    -- brak\ndone\n? catch NameError @e\n    ~> @e\ndone\n? catch\n    ~> inne\ndone\n";
+        match &parse_source(src).unwrap()[0] {
+            Node::Try { body, catches } => {
+                assert_eq!(body.len(), 1);
+                assert_eq!(catches.len(), 2);
+                assert_eq!((catches[0].kind.as_deref(), catches[0].var.as_deref()), (Some("NameError"), Some("e")));
+                assert!(catches[1].kind.is_none() && catches[1].var.is_none());
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(parse_source("? try\n~> a\ndone\n").is_err());
+        assert!(parse_source("? try\n~> a\ndone\n? catch Literowka\n~> b\ndone\n").is_err());
+    }
 }
@@ -49,8 +49,8 @@ pub fn preprocess(source: &str) -> PreprocessResult {
         .lines()
         .map(|line| {
             let t = line.trim();
-            if t.starts_with("using") {
-                let after = t["using".len()..].trim();
+            if let Some(after) = t.strip_prefix("using") {
+                let after = after.trim();
                 // using <gen N>  lub  using <ROLLING>  lub  using <gen N+future>
                 if after.starts_with('<') && after.ends_with('>') {
                     return "";  // Zastąp pustą linią — numer linii zachowany
@@ -0,0 +1,141 @@
+/// Prawdziwość wartości zapisanej jako tekst — jedna reguła dla tree-walk
+/// (`Value::is_truthy`), VM (`NanVal::is_truthy`) i stałych warunków `?~`.
+///
+/// Fałsz: pusty tekst, `false` i liczba równa zero (`0`, `0.0`, `-0`); reszta to prawda.
+pub fn text_is_truthy(s: &str) -> bool {
+    !(s.is_empty() || s == "false" || s.parse::<f64>().is_ok_and(|n| n == 0.0))
+}
+
+/// Równość `==` / `!=`, ramion `? switch` i CmpEq w VM: dwie skończone liczby
+/// porównujemy wartością (`3 == 3.0`, `-0 == 0`), wszystko inne — jako tekst.
+pub fn text_equals(a: &str, b: &str) -> bool {
+    if a == b { return true; }
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(x), Ok(y)) => x.is_finite() && y.is_finite() && x == y,
+        _ => false,
+    }
+}
+
+/// Operatory porównania warunków; dłuższe przed krótszymi, żeby `<=` nie było `<`
+const CMP_OPS: [&str; 6] = ["==", "!=", ">=", "<=", ">", "<"];
+
+/// Porównanie w warunku `?~` / `when` rozbite na operandy i operatory (poza cudzysłowami).
+///
+/// Łańcuch `a < b < c` znaczy `a < b && b < c` — jak w matematyce, nie `(a < b) < c`;
+/// każdy środkowy operand jest prawą stroną jednego porównania i lewą następnego.
+/// Operatorem łańcucha jest tylko znak otoczony białymi znakami, więc `@tag == <none>`
+/// porównuje z tekstem `<none>`. Bez takich operatorów (`@x==5`) warunek to jedno
+/// porównanie na pierwszym operatorze o najwyższym priorytecie, jak przed łańcuchami.
+/// None — w warunku nie ma operatora porównania.
+pub fn comparison_chain(cond: &str) -> Option<(Vec<&str>, Vec<&'static str>)> {
+    let (mut operands, mut ops) = (Vec::new(), Vec::new());
+    let mut start = 0;
+    for (i, op) in operator_positions(cond) {
+        let spaced_before = cond[..i].ends_with(char::is_whitespace);
+        let spaced_after  = cond[i + op.len()..].starts_with(char::is_whitespace);
+        if spaced_before && spaced_after {
+            operands.push(cond[start..i].trim());
+            ops.push(op);
+            start = i + op.len();
+        }
+    }
+    if ops.is_empty() {
+        let (i, op) = CMP_OPS.iter()
+            .find_map(|op| operator_positions(cond).find(|(_, o)| o == op))?;
+        return Some((vec![cond[..i].trim(), cond[i + op.len()..].trim()], vec![op]));
+    }
+    operands.push(cond[start..].trim());
+    Some((operands, ops))
+}
+
+/// Pozycje operatorów porównania poza cudzysłowami
+fn operator_positions(cond: &str) -> impl Iterator<Item = (usize, &'static str)> + '_ {
+    let (mut i, mut quoted) = (0, false);
+    std::iter::from_fn(move || {
+        while i < cond.len() {
+            let rest = &cond[i..];
+            if rest.starts_with('"') { quoted = !quoted; }
+            if !quoted {
+                if let Some(op) = CMP_OPS.iter().find(|op| rest.starts_with(**op)) {
+                    let at = i;
+                    i += op.len();
+                    return Some((at, *op));
+                }
+            }
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+        None
+    })
+}
+
+/// Jedno porównanie z warunku: `==` / `!=` jak `text_equals`, reszta liczbowo (nie-liczba = 0)
+pub fn compare_text(left: &str, op: &str, right: &str) -> bool {
+    let num = |s: &str| s.parse::<f64>().unwrap_or(0.0);
+    match op {
+        "==" => text_equals(left, right),
+        "!=" => !text_equals(left, right),
+        ">=" => num(left) >= num(right),
+        "<=" => num(left) <= num(right),
+        ">"  => num(left) >  num(right),
+        "<"  => num(left) <  num(right),
+        _    => false,
+    }
+}
+
+/// Warunek `?~` (już po interpolacji) rozstrzygalny bez uruchamiania czegokolwiek:
+/// `true`/`false`, pusty, liczba albo tekst w cudzysłowie (`?~ "x"`).
+/// None — porównanie albo komenda powłoki (`?~ test -f plik`).
+pub fn literal_condition(cond: &str) -> Option<bool> {
+    let cond = cond.trim();
+    if let Some(inner) = cond.strip_prefix('"').and_then(|c| c.strip_suffix('"')) {
+        return (!inner.contains('"')).then(|| text_is_truthy(inner));
+    }
+    match cond {
+        "" | "true" | "false" => Some(text_is_truthy(cond)),
+        _ if cond.parse::<f64>().is_ok_and(f64::is_finite) => Some(text_is_truthy(cond)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literal_condition() {
+        assert_eq!(literal_condition("0"), Some(false));
+        assert_eq!(literal_condition("0.0"), Some(false));
+        assert_eq!(literal_condition("3"), Some(true));
+        assert_eq!(literal_condition("\"x\""), Some(true));
+        assert_eq!(literal_condition("\"\""), Some(false));
+        assert_eq!(literal_condition("\"a\" == \"b\""), None);
+        assert_eq!(literal_condition("test -f /etc/passwd"), None);
+        assert_eq!(literal_condition("nan"), None);
+    }
+
+    #[test]
+    fn test_comparison_chain() {
+        let (operands, ops) = comparison_chain("1 < 2 < 3").unwrap();
+        assert_eq!((operands, ops), (vec!["1", "2", "3"], vec!["<", "<"]));
+        let (operands, ops) = comparison_chain("@x <= 10").unwrap();
+        assert_eq!((operands, ops), (vec!["@x", "10"], vec!["<="]));
+        // Operator w cudzysłowie to tekst, nie porównanie
+        let (operands, ops) = comparison_chain("@n == \"<brak>\"").unwrap();
+        assert_eq!((operands, ops), (vec!["@n", "\"<brak>\""], vec!["=="]));
+        assert_eq!(comparison_chain("test -f plik"), None);
+        // `<` / `>` przyklejone do tekstu nie dzielą łańcucha
+        let (operands, ops) = comparison_chain("@tag == <none>").unwrap();
+        assert_eq!((operands, ops), (vec!["@tag", "<none>"], vec!["=="]));
+        let (operands, ops) = comparison_chain("@x==5").unwrap();
+        assert_eq!((operands, ops), (vec!["@x", "5"], vec!["=="]));
+        let (operands, ops) = comparison_chain("@a<=@b").unwrap();
+        assert_eq!((operands, ops), (vec!["@a", "@b"], vec!["<="]));
+    }
+
+    #[test]
+    fn test_text_equals_numeric() {
+        assert!(text_equals("3", "3.0") && text_equals("-0", "0") && text_equals("1e3", "1000"));
+        assert!(!text_equals("3", "3.5") && !text_equals("abc", "ABC") && !text_equals("nan", "NaN"));
+        assert!(text_equals("tak", "tak") && !text_equals("inf", "infinity"));
+    }
+}
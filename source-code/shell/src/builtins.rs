@@ -49,14 +49,18 @@ fn print_help() {
   Hacker Lang gen 2 — Referencia skladni
 
   ── GEN 1 ────────────────────────────────────────────────────
-  PRINT:     ~> tekst          -- wypisz tekst (@var interpolacja)
-  CMD:       >  komenda         -- uruchom komende
+  PRINT:     ~> tekst          -- wypisz tekst (@var interpolacja, @@ = @)
+  SCOPE:     @env:X / @local:X -- tylko srodowisko procesu / tylko zmienna HL (@X: HL, potem env)
+  CMD:       >  komenda         -- uruchom komende bez powloki ($VAR, |, glob doslownie)
   SUDO:      ^> komenda         -- uruchom z sudo
   ISO:       -> komenda         -- izolacja namespace
   ISO+SU:    ^-> komenda        -- sudo + izolacja
-  VARS:      >> komenda         -- komenda z @zmiennymi
+  VARS:      >> komenda         -- przez powloke, z @zmiennymi
   HSH:       *> komenda         -- uruchom przez hsh -c
-  BG:        & komenda          -- uruchom w tle
+  BG:        & komenda          -- uruchom w tle (PID w @_bg_pid)
+  WAIT:      :: wait [pid]      -- czekaj na proces tla, kod w @_wait_exit
+  ARGS:      :: arg N / :: args -- argumenty skryptu (tez @arg0, @argc, @args)
+  SERIAL:    :: serialize lista |> @blob / :: deserialize @blob cel -- wartosc <-> hex (IPC)
 
   VAR:       % n = val          -- zmienna lokalna
   REF:       @nazwa             -- odwolanie do zmiennej
@@ -71,13 +75,25 @@ fn print_help() {
   TYPED VAR: % n: int = 42      -- typowana zmienna (int/float/str/bool)
   ARITH:     $( expr ) -> @var  -- arytmetyka natywna
   PIPE:      > cmd |> @var      -- pipe wyniku do zmiennej
+  FEED:      @dane |> cmd [|> @var] -- wartosc zmiennej na stdin komendy
+             @dane |> :: f a _ b  -- @dane jako argument (w miejscu `_`, domyslnie pierwszy)
   FOR-IN:    @ item in lista    -- for-in loop (done)
+             @ c chars tekst    -- po znakach (Unicode, nie bajty)
   WHILE:     ?~ warunek         -- while loop (done)
   SWITCH:    ? switch @var      -- switch/case
              | "pattern"        -- case arm
              | *                -- wildcard
              done
+  CHAIN:     && > cmd / || > cmd -- wykonaj gdy poprzedni kod = 0 / != 0
+  RETRY:     ? retry N [500ms]  -- ponawiaj blok az do kodu 0 (done)
+  BENCH:     ? bench nazwa      -- blok mierzony przez hl bench (done)
+  TEMPDIR:   ? tempdir @d       -- blok z tymczasowym cwd w @d, usuwany po done
+  TRY:       ? try              -- blok (done), ktorego blad przejmuje ? catch
+             ? catch IndexError @e -- ramie dla rodzaju bledu (bez rodzaju: kazdy), done
   HACKEROS:  || narzedzie args  -- HackerOS API (hacker/hsh/lpm/...)
+  FN REF:    % f = &nazwa       -- referencja do funkcji (&nazwa(a _) = partial)
+             -- @f args         -- wywolanie przez referencje (@_arg0.. @_argc)
+             :: map @f a b c    -- wywolaj funkcje dla kazdego elementu
 
   ── WSPOLNE ──────────────────────────────────────────────────
   FUNC DEF:  : nazwa def ... done
@@ -14,7 +14,7 @@ const HL_KEYWORDS: &[&str] = &[
     "::repeat", "::abs", "::ceil", "::floor", "::round", "::max", "::min", "::rand",
     "::env", "::date", "::time", "::pid", "::which", "::exists", "::isdir",
     "::isfile", "::basename", "::dirname", "::read", "::set", "::get", "::type",
-    "::unset", "::nl", "::hr", "::bold", "::red", "::green", "::yellow", "::cyan",
+    "::unset", "::map", "::wait", "::arg", "::args", "::serialize", "::deserialize", "::nl", "::hr", "::bold", "::red", "::green", "::yellow", "::cyan",
     // Commands gen 1
     ">", "^>", "->", "^->", ">>", "^>>", "->>",
     // Gen 1 new
@@ -25,10 +25,16 @@ const HL_KEYWORDS: &[&str] = &[
     "$(", "$(( ))",
     // Gen 2 — for-in
     "@ item in",
+    "@ c chars",
     // Gen 2 — while
     "?~",
     // Gen 2 — switch
     "? switch",
+    // Gen 2 — retry / bench
+    "? retry",
+    "? bench",
+    "? tempdir",
+    "? try", "? catch",
     // Gen 2 — HackerOS API
     "||",
     "|| hacker", "|| hco", "|| hsh", "|| hpkg", "|| lpm",
@@ -79,6 +85,7 @@ impl Completer for HlCompleter {
 }
 
 impl Highlighter for HlCompleter {
+    #[allow(clippy::if_same_then_else)]
     fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
         let (color, reset) = if line.starts_with("~>")                                              { ("\x1b[32m", "\x1b[0m") }
             else if line.starts_with("::")                                                           { ("\x1b[35m", "\x1b[0m") }
@@ -88,15 +95,19 @@ impl Highlighter for HlCompleter {
             else if line.starts_with("||")                                                          { ("\x1b[95m", "\x1b[0m") } // HackerOS API
             else if line.starts_with("?~")                                                          { ("\x1b[36m", "\x1b[0m") } // while
             else if line.starts_with("? switch")                                                    { ("\x1b[36m", "\x1b[0m") } // switch
+            else if line.starts_with("? retry")                                                     { ("\x1b[36m", "\x1b[0m") } // retry
+            else if line.starts_with("? bench")                                                     { ("\x1b[36m", "\x1b[0m") } // bench
+            else if line.starts_with("? tempdir")                                                   { ("\x1b[36m", "\x1b[0m") } // tempdir
+            else if line.starts_with("? try") || line.starts_with("? catch")                        { ("\x1b[36m", "\x1b[0m") } // try/catch
             else if line.starts_with('|')                                                           { ("\x1b[36m", "\x1b[0m") } // case arm
-            else if line.starts_with('@') && line.contains(" in ")                                  { ("\x1b[33m", "\x1b[0m") } // for-in
+            else if line.starts_with('@') && (line.contains(" in ") || line.contains(" chars ")) { ("\x1b[33m", "\x1b[0m") } // for-in
             // Gen 1
             else if line.starts_with(":*") || line.starts_with(":**")                               { ("\x1b[35m", "\x1b[0m") }
             else if line.starts_with("*>")                                                          { ("\x1b[33m", "\x1b[0m") }
             else if line.starts_with("*--")                                                         { ("\x1b[35m", "\x1b[0m") }
             else if line.starts_with('&')                                                           { ("\x1b[36m", "\x1b[0m") }
             else if line.starts_with("<<")                                                          { ("\x1b[36m", "\x1b[0m") }
-            else if line.starts_with('_') && line.chars().nth(1).map_or(false,|c| c.is_ascii_digit()) { ("\x1b[33m", "\x1b[0m") }
+            else if line.starts_with('_') && line.chars().nth(1).is_some_and(|c| c.is_ascii_digit()) { ("\x1b[33m", "\x1b[0m") }
             else if line.starts_with("^->") || line.starts_with("->")                               { ("\x1b[35m", "\x1b[0m") }
             else if line.starts_with("^>") || line.starts_with('>')                                 { ("\x1b[34m", "\x1b[0m") }
             else if line.starts_with("=>")                                                          { ("\x1b[33m", "\x1b[0m") }
@@ -133,9 +144,14 @@ impl Hinter for HlCompleter {
             "<<"     => Some(" <plik.hl>  -- importuj plik".into()),
             "$("     => Some(" expr )  -- arytmetyka  |  $( expr ) -> @var".into()),
             "||"     => Some(" <narzedzie> [args]  -- HackerOS API".into()),
-            "@"      => Some(" <var> in <lista>  -- for-in loop".into()),
+            "@"      => Some(" <var> in <lista> | <var> chars <tekst>  -- for-in loop  |  @dane |> cmd  -- stdin".into()),
             "?~"     => Some(" <warunek>  -- while loop".into()),
             "? switch" => Some(" <@var>  -- switch/case".into()),
+            "? retry"  => Some(" N [500ms|2s]  -- ponawiaj blok do skutku".into()),
+            "? bench"  => Some(" nazwa  -- mikrobenchmark, uruchom przez hl bench".into()),
+            "? tempdir" => Some(" @var  -- blok w tymczasowym katalogu, usuwanym po done".into()),
+            "? try"    => Some("  -- blok do done, po nim ? catch [Rodzaj] [@e] ... done".into()),
+            "? catch"  => Some(" [NameError|TypeError|IndexError|ArithmeticError|ImportError|RuntimeError] [@e]".into()),
             "|"      => Some(" <pattern>  -- case arm (w switch)".into()),
             _ => {
                 if t.starts_with('_') && t.len() > 1 && t[1..].chars().all(|c| c.is_ascii_digit()) {
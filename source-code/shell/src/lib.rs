@@ -164,6 +164,8 @@ pub fn run_file(path: &Path, env: &mut Env) -> Result<i32> {
         renderer.emit(&parse_error_to_diag(&e)); return Ok(2);
     }
 
+    // Import `<< lib` szuka najpierw obok skryptu, nie w katalogu, z którego go uruchomiono
+    env.source_dir = std::fs::canonicalize(path).ok().and_then(|p| p.parent().map(|d| d.to_path_buf()));
     match run_source(&source, env) {
         Ok(r)  => Ok(r.exit_code),
         Err(e) => {
@@ -178,10 +180,12 @@ fn is_block_start(line: &str) -> bool {
     let is_func_def = line.starts_with(':') && !line.starts_with("::") && !line.starts_with(":*") && line.ends_with("def");
     let is_goroutine = line.starts_with(":*");
     let is_cond = line.starts_with("? ok") || line.starts_with("? err");
-    let is_for_in  = line.starts_with('@') && line.contains(" in ");
+    let is_for_in  = line.starts_with('@') && (line.contains(" in ") || line.contains(" chars "));
     let is_while   = line.starts_with("?~");
     let is_switch  = line.starts_with("? switch");
-    is_func_def || is_goroutine || is_cond || is_for_in || is_while || is_switch
+    let is_keyword_block = line.starts_with("? retry") || line.starts_with("? bench") || line.starts_with("? tempdir")
+        || line.starts_with("? try") || line.starts_with("? catch");
+    is_func_def || is_goroutine || is_cond || is_for_in || is_while || is_switch || is_keyword_block
 }
 
 fn print_banner() {
@@ -0,0 +1,59 @@
+//! Pomocnicze narzędzia dla testów crate'ów workspace'u (tylko dev-dependencies)
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Katalog tymczasowy testu — usuwany w `Drop`, także gdy asercja się nie powiedzie.
+///
+/// Nazwa zawiera PID i licznik, więc równoległe testy w jednym procesie
+/// (i równoległe `cargo test`) nie dzielą katalogu.
+pub struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    pub fn new(name: &str) -> Self {
+        static SEQ: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "hl-test-{}-{}-{}", name, std::process::id(), SEQ.fetch_add(1, Ordering::Relaxed)));
+        // Pozostałość po przerwanym procesie z tym samym PID
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path)
+        .unwrap_or_else(|e| panic!("Nie można utworzyć {:?}: {}", path, e));
+        TestDir { path }
+    }
+
+    pub fn path(&self) -> &Path { &self.path }
+}
+
+impl std::ops::Deref for TestDir {
+    type Target = Path;
+    fn deref(&self) -> &Path { &self.path }
+}
+
+impl AsRef<Path> for TestDir {
+    fn as_ref(&self) -> &Path { &self.path }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dirs_are_unique_and_removed_on_drop() {
+        let a = TestDir::new("x");
+        let b = TestDir::new("x");
+        assert_ne!(a.path(), b.path());
+        std::fs::write(a.join("plik"), b"1").unwrap();
+        let path = a.path().to_path_buf();
+        drop(a);
+        assert!(!path.exists());
+        assert!(b.path().is_dir());
+    }
+}